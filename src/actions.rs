@@ -0,0 +1,165 @@
+use crate::encoding::normalize_pdf_string;
+use crate::resolve;
+use lopdf::{Dictionary, Document, Object};
+use serde::{Deserialize, Serialize};
+
+const MAX_ACTION_DEPTH: usize = 32;
+
+/// Action types that reach outside the document.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum ActionKind {
+    /// Runs a program or opens a file with its default application.
+    Launch,
+    /// Opens another PDF.
+    GoToR,
+    /// Opens a PDF embedded in this one.
+    GoToE,
+    /// Sends form data to a URL.
+    SubmitForm,
+    /// Loads form data from a file.
+    ImportData,
+}
+
+impl ActionKind {
+    fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"Launch" => Some(ActionKind::Launch),
+            b"GoToR" => Some(ActionKind::GoToR),
+            b"GoToE" => Some(ActionKind::GoToE),
+            b"SubmitForm" => Some(ActionKind::SubmitForm),
+            b"ImportData" => Some(ActionKind::ImportData),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ActionKind::Launch => "Launch",
+            ActionKind::GoToR => "GoToR",
+            ActionKind::GoToE => "GoToE",
+            ActionKind::SubmitForm => "SubmitForm",
+            ActionKind::ImportData => "ImportData",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ActionFinding {
+    /// The object holding the action, which may be written inline in it.
+    pub object_id: u32,
+    pub kind: ActionKind,
+    /// The file, URL or command line the action points at, if it names one.
+    pub target: Option<String>,
+}
+
+/// Finds every `/Launch`, `/GoToR`, `/GoToE`, `/SubmitForm` and `/ImportData`
+/// action, whether it is an object of its own or written inline in another
+/// one (an annotation's `/A`, an `/AA` entry, a `/Next` array), and extracts
+/// what it points at.
+pub fn find_external_actions(doc: &Document) -> Vec<ActionFinding> {
+    let mut findings = Vec::new();
+    for (id, object) in doc.objects.iter() {
+        collect_actions(doc, id.0, object, 0, &mut findings);
+    }
+    findings
+}
+
+fn collect_actions(
+    doc: &Document,
+    holder: u32,
+    object: &Object,
+    depth: usize,
+    findings: &mut Vec<ActionFinding>,
+) {
+    if depth > MAX_ACTION_DEPTH {
+        return;
+    }
+    let dict = match object {
+        Object::Dictionary(dict) => dict,
+        Object::Stream(stream) => &stream.dict,
+        Object::Array(items) => {
+            for item in items {
+                collect_actions(doc, holder, item, depth + 1, findings);
+            }
+            return;
+        }
+        _ => return,
+    };
+
+    let kind = dict
+        .get(b"S")
+        .ok()
+        .and_then(|kind| resolve(doc, kind))
+        .and_then(|kind| kind.as_name().ok())
+        .and_then(ActionKind::from_name);
+    if let Some(kind) = kind {
+        findings.push(ActionFinding {
+            object_id: holder,
+            kind,
+            target: action_target(doc, kind, dict),
+        });
+    }
+
+    for (_, value) in dict.iter() {
+        collect_actions(doc, holder, value, depth + 1, findings);
+    }
+}
+
+fn action_target(doc: &Document, kind: ActionKind, action: &Dictionary) -> Option<String> {
+    if kind == ActionKind::Launch {
+        // Platform-specific parameters take precedence, as in viewers that honour them
+        let windows = action
+            .get(b"Win")
+            .ok()
+            .and_then(|win| resolve(doc, win))
+            .and_then(|win| win.as_dict().ok());
+        if let Some(command) = windows.and_then(|win| windows_command(doc, win)) {
+            return Some(command);
+        }
+    }
+
+    let file = action
+        .get(b"F")
+        .ok()
+        .and_then(|file| file_specification(doc, file));
+    if file.is_some() || kind != ActionKind::GoToE {
+        return file;
+    }
+
+    // An embedded target names the attachment in /T /N instead
+    action
+        .get(b"T")
+        .ok()
+        .and_then(|target| resolve(doc, target))
+        .and_then(|target| target.as_dict().ok())
+        .and_then(|target| string_entry(doc, target, b"N"))
+}
+
+/// `/F` plus `/P` of a Windows launch dictionary, e.g. `cmd.exe /c ...`.
+fn windows_command(doc: &Document, win: &Dictionary) -> Option<String> {
+    let file = string_entry(doc, win, b"F")?;
+    match string_entry(doc, win, b"P") {
+        Some(parameters) => Some(format!("{} {}", file, parameters)),
+        None => Some(file),
+    }
+}
+
+/// A file specification is either a plain string or a dictionary whose
+/// `/UF` or `/F` entry holds the name. URLs use the same forms.
+fn file_specification(doc: &Document, spec: &Object) -> Option<String> {
+    match resolve(doc, spec)? {
+        Object::String(bytes, _) => Some(normalize_pdf_string(bytes)),
+        Object::Dictionary(dict) => file_specification_name(doc, dict),
+        _ => None,
+    }
+}
+
+/// The name in a file specification dictionary: `/UF`, or failing that `/F`.
+pub(crate) fn file_specification_name(doc: &Document, spec: &Dictionary) -> Option<String> {
+    string_entry(doc, spec, b"UF").or_else(|| string_entry(doc, spec, b"F"))
+}
+
+fn string_entry(doc: &Document, dict: &Dictionary, key: &[u8]) -> Option<String> {
+    let value = resolve(doc, dict.get(key).ok()?)?;
+    Some(normalize_pdf_string(value.as_str().ok()?))
+}
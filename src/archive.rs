@@ -0,0 +1,228 @@
+//! PDFs inside ZIP archives, including archives inside archives, which is
+//! how phishing campaigns and malware feeds tend to ship them. Entries
+//! encrypted with one of the configured passwords (`infected` by the usual
+//! convention for samples) are opened as well.
+
+use crate::batch::{has_pdf_marker, SNIFF_WINDOW};
+use pdf_sentinel::SentinelError;
+use std::fs::File;
+use std::io::{self, Cursor, Read};
+use std::path::Path;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// Whether `data` starts like a ZIP archive.
+pub fn is_zip(data: &[u8]) -> bool {
+    data.starts_with(ZIP_MAGIC)
+}
+
+/// Whether the entry whose central directory record starts at `offset` in
+/// `data` is encrypted: bit 0 of its general purpose flags, which zip 0.6
+/// reads but doesn't expose.
+fn is_encrypted(data: &[u8], offset: u64) -> bool {
+    usize::try_from(offset)
+        .ok()
+        .and_then(|offset| data.get(offset.checked_add(8)?))
+        .is_some_and(|flags| flags & 1 == 1)
+}
+
+/// Whether the file at `path` starts like a ZIP archive. A file that can't
+/// be read isn't one, and is left to fail when it is analyzed.
+pub fn is_zip_file(path: &Path) -> bool {
+    let mut head = Vec::new();
+    let read =
+        File::open(path).and_then(|file| file.take(ZIP_MAGIC.len() as u64).read_to_end(&mut head));
+    read.is_ok() && is_zip(&head)
+}
+
+/// A PDF found in an archive, or an entry that may be one but couldn't be
+/// extracted.
+pub struct Entry {
+    /// The archive's name, then the path of each archive inside it and of
+    /// the PDF, separated by colons, e.g. `mail.zip:docs.zip:invoice.pdf`.
+    pub path: String,
+    pub data: Result<Vec<u8>, SentinelError>,
+}
+
+pub struct Extractor {
+    max_depth: usize,
+    max_size: u64,
+    passwords: Vec<String>,
+}
+
+/// How much of an archive a single [`Extractor::pdf_entries`] call has
+/// used up.
+struct Walk {
+    entries: Vec<Entry>,
+    /// Bytes that may still be decompressed.
+    budget: u64,
+    exhausted: bool,
+}
+
+impl Extractor {
+    /// Opens archives nested up to `max_depth` deep, 1 being only the
+    /// archive given, and decompresses at most `max_size` bytes of each, so
+    /// that a ZIP bomb runs out of budget rather than memory. Encrypted
+    /// entries are tried with each of `passwords` in turn.
+    pub fn new(max_depth: usize, max_size: u64, passwords: Vec<String>) -> Self {
+        Extractor {
+            max_depth,
+            max_size,
+            passwords,
+        }
+    }
+
+    /// The PDFs in the ZIP archive `data`, in the order the archive lists
+    /// them, named after `archive`. Entries are PDFs when their name ends in
+    /// `.pdf` or they start with a PDF header, and archives when their name
+    /// ends in `.zip` or they start like one; anything else is skipped.
+    ///
+    /// Fails only when `data` is not a readable archive. Entries that can't
+    /// be extracted, nested archives included, are listed with their error.
+    pub fn pdf_entries(&self, archive: &str, data: &[u8]) -> Result<Vec<Entry>, SentinelError> {
+        let mut walk = Walk {
+            entries: Vec::new(),
+            budget: self.max_size,
+            exhausted: false,
+        };
+        self.walk(archive, data, 1, &mut walk)?;
+        Ok(walk.entries)
+    }
+
+    fn walk(
+        &self,
+        name: &str,
+        data: &[u8],
+        depth: usize,
+        walk: &mut Walk,
+    ) -> Result<(), SentinelError> {
+        let mut zip = ZipArchive::new(Cursor::new(data)).map_err(zip_error)?;
+        for index in 0..zip.len() {
+            if walk.exhausted {
+                break;
+            }
+            let (entry_name, encrypted) = match zip.by_index_raw(index) {
+                Ok(file) if file.is_dir() => continue,
+                Ok(file) => (
+                    file.name().to_string(),
+                    is_encrypted(data, file.central_header_start()),
+                ),
+                Err(err) => {
+                    walk.entries.push(Entry {
+                        path: format!("{}:entry {}", name, index + 1),
+                        data: Err(zip_error(err)),
+                    });
+                    continue;
+                }
+            };
+            let path = format!("{}:{}", name, entry_name);
+            let lowercase = entry_name.to_ascii_lowercase();
+            let named_pdf = lowercase.ends_with(".pdf");
+            let named_zip = lowercase.ends_with(".zip");
+
+            let data = match self.extract(&mut zip, index, encrypted, named_pdf || named_zip, walk)
+            {
+                Ok(Some(data)) => data,
+                // Neither a PDF nor an archive
+                Ok(None) => continue,
+                Err(err) => {
+                    walk.entries.push(Entry {
+                        path,
+                        data: Err(err),
+                    });
+                    continue;
+                }
+            };
+
+            if named_zip || is_zip(&data) {
+                if depth >= self.max_depth {
+                    let message = format!("archive nested deeper than {} levels", self.max_depth);
+                    walk.entries.push(Entry {
+                        path,
+                        data: Err(io::Error::new(io::ErrorKind::InvalidData, message).into()),
+                    });
+                } else if let Err(err) = self.walk(&path, &data, depth + 1, walk) {
+                    walk.entries.push(Entry {
+                        path,
+                        data: Err(err),
+                    });
+                }
+            } else {
+                walk.entries.push(Entry {
+                    path,
+                    data: Ok(data),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Decompresses entry `index`, unless its first bytes show it is
+    /// neither a PDF nor an archive and `wanted` doesn't say otherwise.
+    fn extract(
+        &self,
+        zip: &mut ZipArchive<Cursor<&[u8]>>,
+        index: usize,
+        encrypted: bool,
+        wanted: bool,
+        walk: &mut Walk,
+    ) -> Result<Option<Vec<u8>>, SentinelError> {
+        let mut file = if encrypted {
+            let unknown_password = || {
+                io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "encrypted with none of the configured passwords",
+                )
+            };
+            // Trying a password keeps no borrow of the archive, unlike
+            // opening the entry with it
+            let password = self
+                .passwords
+                .iter()
+                .find(|password| {
+                    matches!(zip.by_index_decrypt(index, password.as_bytes()), Ok(Ok(_)))
+                })
+                .ok_or_else(unknown_password)?;
+            match zip
+                .by_index_decrypt(index, password.as_bytes())
+                .map_err(zip_error)?
+            {
+                Ok(file) => file,
+                Err(_) => return Err(unknown_password().into()),
+            }
+        } else {
+            zip.by_index(index).map_err(zip_error)?
+        };
+
+        // One byte past the budget tells an entry that is too large from
+        // one that uses it up exactly
+        let mut data = Vec::new();
+        let window = SNIFF_WINDOW.min(walk.budget + 1);
+        (&mut file).take(window).read_to_end(&mut data)?;
+        if !wanted && !has_pdf_marker(&data) && !is_zip(&data) {
+            walk.budget = walk.budget.saturating_sub(data.len() as u64);
+            return Ok(None);
+        }
+        let rest = (walk.budget + 1).saturating_sub(data.len() as u64);
+        (&mut file).take(rest).read_to_end(&mut data)?;
+        if data.len() as u64 > walk.budget {
+            walk.exhausted = true;
+            let message = format!(
+                "the archive holds more than {} bytes; entries past this one were skipped",
+                self.max_size
+            );
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message).into());
+        }
+        walk.budget -= data.len() as u64;
+        Ok(Some(data))
+    }
+}
+
+fn zip_error(err: ZipError) -> SentinelError {
+    match err {
+        ZipError::Io(err) => err.into(),
+        err => io::Error::new(io::ErrorKind::InvalidData, err.to_string()).into(),
+    }
+}
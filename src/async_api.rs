@@ -0,0 +1,38 @@
+use crate::error::SentinelError;
+use crate::{analyze_pdf_bytes, AnalysisResult, CompiledConfig};
+use futures::stream::{self, StreamExt};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Runs the synchronous analysis on tokio's blocking pool so async handlers
+/// don't stall the runtime while a PDF is parsed.
+pub async fn analyze_pdf_async(
+    data: Vec<u8>,
+    config: Arc<CompiledConfig>,
+) -> Result<AnalysisResult, SentinelError> {
+    tokio::task::spawn_blocking(move || analyze_pdf_bytes(&data, &config)).await?
+}
+
+/// Analyzes the files concurrently, as many at a time as there are CPUs, so
+/// that a long list doesn't hold every file in memory at once. Results are
+/// returned in completion order, each paired with its path.
+pub async fn analyze_multiple_pdfs_async(
+    paths: Vec<PathBuf>,
+    config: Arc<CompiledConfig>,
+) -> Vec<(PathBuf, Result<AnalysisResult, SentinelError>)> {
+    let limit = std::thread::available_parallelism().map_or(1, |workers| workers.get());
+    stream::iter(paths)
+        .map(|path| {
+            let config = Arc::clone(&config);
+            async move {
+                let result = match tokio::fs::read(&path).await {
+                    Ok(data) => analyze_pdf_async(data, config).await,
+                    Err(err) => Err(err.into()),
+                };
+                (path, result)
+            }
+        })
+        .buffer_unordered(limit)
+        .collect()
+        .await
+}
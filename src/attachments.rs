@@ -0,0 +1,167 @@
+use crate::actions::file_specification_name;
+use crate::error::SentinelError;
+use crate::filters::{decode_stream_bounded, StreamCache};
+use crate::{resolve, to_hex};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Extensions of files Windows will run or script hosts will interpret.
+const EXECUTABLE_EXTENSIONS: [&str; 14] = [
+    "exe", "dll", "scr", "com", "bat", "cmd", "ps1", "vbs", "vbe", "js", "jse", "wsf", "hta", "msi",
+];
+
+#[derive(Deserialize, Serialize)]
+pub struct EmbeddedFile {
+    /// The `/EmbeddedFile` stream.
+    pub object_id: u32,
+    /// The name its file specification gives it, if one refers to it.
+    pub name: Option<String>,
+    /// Decoded size; `None` when the stream could not be decoded.
+    pub size: Option<u64>,
+    /// SHA-256 of the decoded content, in lowercase hex.
+    pub sha256: Option<String>,
+    /// Whether the name has an extension Windows runs or interprets.
+    pub executable: bool,
+}
+
+/// Lists every `/Type /EmbeddedFile` stream, named after the file
+/// specification that refers to it through `/EF`. Streams no specification
+/// refers to are listed too, since a viewer never shows them but a script
+/// can still reach them.
+pub fn find_embedded_files(doc: &Document, streams: &StreamCache) -> Vec<EmbeddedFile> {
+    let names = embedded_file_names(doc);
+
+    embedded_file_streams(doc)
+        .map(|(id, _)| {
+            let name = names.get(&id).cloned();
+            let content = streams.get(id.0);
+            EmbeddedFile {
+                object_id: id.0,
+                executable: name.as_deref().is_some_and(is_executable_name),
+                name,
+                size: content.map(|content| content.len() as u64),
+                sha256: content.map(|content| to_hex(&Sha256::digest(content))),
+            }
+        })
+        .collect()
+}
+
+/// Writes the decoded content of every embedded file to `dir`, creating it
+/// if needed, and returns the paths written. Files are named
+/// `<object id>-<name>`, with the name reduced to its last path component and
+/// anything outside `[A-Za-z0-9._-]` replaced, so that a crafted name cannot
+/// escape `dir`. Streams that can't be decoded within `max_size` bytes are
+/// skipped.
+pub fn extract_embedded_files(
+    doc: &Document,
+    dir: &Path,
+    max_size: u64,
+) -> Result<Vec<PathBuf>, SentinelError> {
+    std::fs::create_dir_all(dir)?;
+    let names = embedded_file_names(doc);
+    let mut written = Vec::new();
+
+    for (id, stream) in embedded_file_streams(doc) {
+        let Some(Ok(content)) = decode_stream_bounded(stream, max_size) else {
+            tracing::warn!(
+                object_id = id.0,
+                "embedded file could not be decoded, skipping"
+            );
+            continue;
+        };
+        let name = names.get(&id).map_or("attachment", String::as_str);
+        let path = dir.join(format!("{}-{}", id.0, safe_file_name(name)));
+        std::fs::write(&path, content)?;
+        written.push(path);
+    }
+
+    Ok(written)
+}
+
+fn embedded_file_streams(doc: &Document) -> impl Iterator<Item = (ObjectId, &Stream)> {
+    doc.objects.iter().filter_map(|(id, object)| {
+        let stream = object.as_stream().ok()?;
+        let is_embedded_file = stream
+            .dict
+            .get(b"Type")
+            .and_then(|kind| kind.as_name())
+            .is_ok_and(|kind| kind == b"EmbeddedFile");
+        is_embedded_file.then_some((*id, stream))
+    })
+}
+
+/// Maps embedded file streams to the names their file specifications give
+/// them. File specifications are found wherever they are, not only in the
+/// `/EmbeddedFiles` name tree: annotations and `/GoToE` actions carry them
+/// too.
+fn embedded_file_names(doc: &Document) -> HashMap<ObjectId, String> {
+    let mut names = HashMap::new();
+    for object in doc.objects.values() {
+        if let Ok(spec) = object.as_dict() {
+            collect_file_specification(doc, spec, 0, &mut names);
+        }
+    }
+    names
+}
+
+fn collect_file_specification(
+    doc: &Document,
+    dict: &Dictionary,
+    depth: usize,
+    names: &mut HashMap<ObjectId, String>,
+) {
+    // File specifications sit at most a couple of levels inside an object
+    if depth > 4 {
+        return;
+    }
+
+    let embedded = dict
+        .get(b"EF")
+        .ok()
+        .and_then(|ef| resolve(doc, ef))
+        .and_then(|ef| ef.as_dict().ok());
+    if let Some(embedded) = embedded {
+        let name = file_specification_name(doc, dict);
+        for (_, stream) in embedded.iter() {
+            if let (Ok(stream_id), Some(name)) = (stream.as_reference(), &name) {
+                names.entry(stream_id).or_insert_with(|| name.clone());
+            }
+        }
+    }
+
+    for (_, value) in dict.iter() {
+        if let Object::Dictionary(nested) = value {
+            collect_file_specification(doc, nested, depth + 1, names);
+        }
+    }
+}
+
+fn is_executable_name(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            EXECUTABLE_EXTENSIONS
+                .iter()
+                .any(|executable| extension.eq_ignore_ascii_case(executable))
+        })
+}
+
+pub(crate) fn safe_file_name(name: &str) -> String {
+    // Both separators, whatever the platform the PDF was made on
+    let base = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let safe: String = base
+        .chars()
+        .map(|c| match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '.' | '_' | '-' => c,
+            _ => '_',
+        })
+        .collect();
+    match safe.trim_start_matches('.') {
+        "" => "attachment".to_string(),
+        safe => safe.to_string(),
+    }
+}
@@ -0,0 +1,88 @@
+//! Documents approved as they are, such as a company's own templates, so
+//! that scanning them again costs a hash lookup rather than an analysis. An
+//! entry is keyed by the file's SHA-256 and keeps the `/Producer` and
+//! `/Creator` the file was made with, as a fingerprint of where it came
+//! from.
+
+use crate::encoding::normalize_pdf_string;
+use crate::{
+    compute_sha256, info_dictionary, load_config_file, resolve, AnalysisResult, SentinelError,
+};
+use lopdf::Document;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A baseline database: `[[approved]]` tables in TOML, or an `approved`
+/// array in JSON.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Baseline {
+    pub approved: Vec<BaselineEntry>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct BaselineEntry {
+    /// In lowercase hex.
+    pub sha256: String,
+    /// The name of the file when it was added.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub producer: Option<String>,
+    #[serde(default)]
+    pub creator: Option<String>,
+}
+
+impl BaselineEntry {
+    /// The entry for the file `data`, named `name`. A file that doesn't
+    /// parse gets no fingerprint.
+    pub fn new(name: &str, data: &[u8]) -> Self {
+        let doc = Document::load_mem(data).ok();
+        let info = |key: &[u8]| {
+            let doc = doc.as_ref()?;
+            let value = resolve(doc, info_dictionary(doc)?.get(key).ok()?)?;
+            Some(normalize_pdf_string(value.as_str().ok()?))
+        };
+        BaselineEntry {
+            sha256: compute_sha256(data),
+            name: Some(name.to_string()),
+            producer: info(b"Producer"),
+            creator: info(b"Creator"),
+        }
+    }
+}
+
+impl Baseline {
+    /// Reads a database written by [`Baseline::save`] or by hand.
+    pub fn load(path: &Path) -> Result<Self, SentinelError> {
+        load_config_file(path)
+    }
+
+    /// Writes the database as TOML, or as JSON when its extension is
+    /// `.json`, as [`Baseline::load`] reads it.
+    pub fn save(&self, path: &Path) -> Result<(), SentinelError> {
+        let contents = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => serde_json::to_string_pretty(self)?,
+            _ => toml::to_string(self)
+                .map_err(|err| SentinelError::Config(path.to_path_buf(), err.to_string()))?,
+        };
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// The entry approving the file with hash `sha256`, if any.
+    pub fn find(&self, sha256: &str) -> Option<&BaselineEntry> {
+        self.approved
+            .iter()
+            .find(|entry| entry.sha256.eq_ignore_ascii_case(sha256))
+    }
+}
+
+/// The result for a file in the baseline, which is not analyzed at all.
+pub(crate) fn baselined_result(sha256: String, entry: &BaselineEntry) -> AnalysisResult {
+    AnalysisResult {
+        sha256: Some(sha256),
+        baselined: Some(entry.clone()),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,143 @@
+use crate::OutputFormat;
+use pdf_sentinel::AnalysisResult;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// How many bytes of a file `--sniff` looks through for the PDF header.
+/// Readers accept it anywhere in the first kilobyte, after junk or a mail
+/// header, and so does malware.
+pub const SNIFF_WINDOW: u64 = 1024;
+
+/// Which files under a `--recursive` directory to analyze.
+pub enum FileFilter {
+    /// Files whose extension is one of these, compared ignoring case.
+    Extensions(Vec<String>),
+    /// Files with a `%PDF-` header, whatever they are called.
+    Sniff,
+}
+
+impl FileFilter {
+    fn matches(&self, path: &Path) -> bool {
+        match self {
+            FileFilter::Extensions(extensions) => path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| {
+                    extensions.iter().any(|wanted| {
+                        wanted
+                            .trim_start_matches('.')
+                            .eq_ignore_ascii_case(extension)
+                    })
+                }),
+            FileFilter::Sniff => has_pdf_header(path),
+        }
+    }
+}
+
+fn has_pdf_header(path: &Path) -> bool {
+    let mut head = Vec::new();
+    let read = File::open(path).and_then(|file| file.take(SNIFF_WINDOW).read_to_end(&mut head));
+    read.is_ok() && has_pdf_marker(&head)
+}
+
+/// Whether the first [`SNIFF_WINDOW`] bytes of `data` hold a PDF header.
+pub fn has_pdf_marker(data: &[u8]) -> bool {
+    data[..data.len().min(SNIFF_WINDOW as usize)]
+        .windows(5)
+        .any(|window| window == b"%PDF-")
+}
+
+/// Lists the files under `dir` that `filter` accepts, sorted so that batches
+/// report in a stable order. Symbolic links are not followed, so a link back
+/// up the tree can't loop. Directories that can't be read are reported and
+/// skipped rather than failing the whole batch.
+pub fn collect_files(dir: &Path, filter: &FileFilter) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                eprintln!("{}: {}", dir.display(), err);
+                continue;
+            }
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() => pending.push(path),
+                Ok(kind) if kind.is_file() && filter.matches(&path) => {
+                    files.push(path.to_string_lossy().into_owned())
+                }
+                _ => {}
+            }
+        }
+    }
+
+    files.sort();
+    files
+}
+
+/// Totals over a batch, printed once it is done.
+#[derive(Default)]
+pub struct BatchSummary {
+    analyzed: usize,
+    pub skipped: usize,
+    failed: usize,
+    /// Files per classification label, in the order labels were first seen.
+    classifications: Vec<(&'static str, usize)>,
+    highest: Option<(String, u32)>,
+}
+
+impl BatchSummary {
+    pub fn record(&mut self, file: &str, result: &AnalysisResult) {
+        self.analyzed += 1;
+        let label = result.classification.label();
+        match self
+            .classifications
+            .iter_mut()
+            .find(|(seen, _)| *seen == label)
+        {
+            Some((_, count)) => *count += 1,
+            None => self.classifications.push((label, 1)),
+        }
+        if self
+            .highest
+            .as_ref()
+            .is_none_or(|(_, score)| result.severity_score > *score)
+        {
+            self.highest = Some((file.to_string(), result.severity_score));
+        }
+    }
+
+    pub fn record_failure(&mut self) {
+        self.failed += 1;
+    }
+
+    /// Text output gets the summary at its end; the other formats are meant
+    /// for machines, so it goes to stderr instead of breaking them.
+    pub fn print(&self, format: OutputFormat) {
+        let mut lines = vec![format!(
+            "Summary: {} files, {} analyzed, {} skipped, {} failed",
+            self.analyzed + self.skipped + self.failed,
+            self.analyzed,
+            self.skipped,
+            self.failed
+        )];
+        for (label, count) in &self.classifications {
+            lines.push(format!("  {}: {}", label, count));
+        }
+        if let Some((file, score)) = &self.highest {
+            lines.push(format!("  Highest severity score: {} ({})", score, file));
+        }
+
+        for line in lines {
+            match format {
+                OutputFormat::Text => println!("{}", line),
+                _ => eprintln!("{}", line),
+            }
+        }
+    }
+}
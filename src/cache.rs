@@ -0,0 +1,134 @@
+//! Results of earlier analyses, so that a file scanned again with the same
+//! settings costs a hash and a read instead of an analysis. Results are kept
+//! as JSON files named after the file's SHA-256, in a directory named after
+//! a fingerprint of everything else that decides the result: the config,
+//! the password, the revision, the files given to
+//! [`ResultCache::depends_on`] and the version of this crate. Changing any
+//! of them starts an empty directory next to the old one, which can be
+//! deleted.
+//!
+//! Only whole documents read into memory are cached; streaming analysis
+//! and nested PDFs are not.
+
+use crate::{to_hex, AnalysisResult, CompiledConfig, SentinelError};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone)]
+pub struct ResultCache {
+    dir: PathBuf,
+    /// The files of `depends_on`, hashed together.
+    dependencies: Sha256,
+    /// Where this analyzer's results go, under `dir`.
+    results: PathBuf,
+}
+
+impl ResultCache {
+    /// Opens the cache in `dir`, creating it if need be.
+    pub fn open(dir: &Path) -> Result<Self, SentinelError> {
+        std::fs::create_dir_all(dir)?;
+        Ok(ResultCache {
+            dir: dir.to_path_buf(),
+            dependencies: Sha256::new(),
+            results: dir.to_path_buf(),
+        })
+    }
+
+    /// Makes the contents of `path`, a file or a directory of files, part
+    /// of the fingerprint, for rules that are loaded from files, such as a
+    /// rules library or a directory of YARA rules.
+    pub fn depends_on(mut self, path: &Path) -> Result<Self, SentinelError> {
+        let mut files = if path.is_dir() {
+            std::fs::read_dir(path)?
+                .map(|entry| Ok(entry?.path()))
+                .collect::<Result<Vec<_>, std::io::Error>>()?
+        } else {
+            vec![path.to_path_buf()]
+        };
+        files.sort();
+        for file in files.iter().filter(|file| file.is_file()) {
+            self.dependencies
+                .update(file.file_name().unwrap_or_default().as_encoded_bytes());
+            self.dependencies
+                .update(Sha256::digest(std::fs::read(file)?));
+        }
+        Ok(self)
+    }
+
+    /// Takes the fingerprint of `config`, which the results from then on are
+    /// filed under.
+    pub(crate) fn configure(&mut self, config: &CompiledConfig) {
+        let mut analysis =
+            serde_json::to_value(&config.config).expect("a config always serializes");
+        // Only decides the exit code, not the result
+        analysis["fail_threshold"] = Value::Null;
+        // A set, which serializes in no particular order
+        if let Some(Value::Array(hashes)) = analysis.get_mut("known_bad_hashes") {
+            hashes.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+        }
+        #[cfg(feature = "yara")]
+        let yara = config.yara.is_some();
+        #[cfg(not(feature = "yara"))]
+        let yara = false;
+        // Lookups are cached with the rest, so detections VirusTotal adds
+        // later are not seen until the cache is cleared
+        #[cfg(feature = "virustotal")]
+        let virustotal = config.virustotal.is_some();
+        #[cfg(not(feature = "virustotal"))]
+        let virustotal = false;
+        let settings = json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "config": analysis,
+            "password": config.password.as_deref(),
+            "revision": config.revision,
+            "rules": config.rules.is_some(),
+            "yara": yara,
+            "virustotal": virustotal,
+        });
+        let mut fingerprint = self.dependencies.clone();
+        fingerprint.update(settings.to_string());
+        self.results = self.dir.join(to_hex(&fingerprint.finalize()));
+    }
+
+    /// The result cached for the file with hash `sha256`. A result that
+    /// can't be read is logged and treated as missing.
+    pub(crate) fn get(&self, sha256: &str) -> Option<AnalysisResult> {
+        let path = self.path(sha256);
+        let contents = std::fs::read(&path).ok()?;
+        match serde_json::from_slice(&contents) {
+            Ok(result) => Some(result),
+            Err(err) => {
+                tracing::warn!(path = %path.display(), error = %err, "unreadable cached result, analyzing again");
+                None
+            }
+        }
+    }
+
+    /// Caches `result` for the file with hash `sha256`. Failing to is only
+    /// logged, since the analysis itself went fine.
+    pub(crate) fn put(&self, sha256: &str, result: &AnalysisResult) {
+        if let Err(err) = self.write(sha256, result) {
+            tracing::warn!(%sha256, error = %err, "failed to cache result");
+        }
+    }
+
+    fn write(&self, sha256: &str, result: &AnalysisResult) -> Result<(), SentinelError> {
+        std::fs::create_dir_all(&self.results)?;
+        // Written aside and renamed into place, so that a run reading the
+        // cache meanwhile never sees half a result
+        let partial = self.results.join(format!(
+            "{}.{}.{:?}.partial",
+            sha256,
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        std::fs::write(&partial, serde_json::to_vec(result)?)?;
+        std::fs::rename(&partial, self.path(sha256))?;
+        Ok(())
+    }
+
+    fn path(&self, sha256: &str) -> PathBuf {
+        self.results.join(format!("{}.json", sha256))
+    }
+}
@@ -0,0 +1,429 @@
+use flate2::read::ZlibDecoder;
+use regex::bytes::Regex;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::sync::OnceLock;
+
+use crate::classification::classify;
+use crate::encoding::{name_pattern, normalize_pdf_string};
+use crate::encryption::EncryptionInfo;
+use crate::filters::{stream_limit, BombLimit, CompressionBombHint};
+use crate::findings::collect_findings;
+use crate::framing::check_framing;
+use crate::names::find_escaped_names;
+use crate::nested::analyze_embedded_pdfs;
+use crate::patterns::PatternTarget;
+use crate::revisions::check_revisions;
+use crate::scoring::calculate_severity_score;
+use crate::shellcode::check_shellcode_heuristics;
+use crate::signatures::find_embedded_signatures;
+use crate::streaming::name_regex;
+use crate::suppressions::apply_suppressions;
+use crate::xref::count_xref_sections;
+use crate::{
+    check_file_size, check_object_ratio_heuristics, check_script_shellcode, find_dangerous_js_apis,
+    find_hidden_matches, push_javascript, push_stream_javascript, push_stream_match,
+    AnalysisResult, CompiledConfig, JsSource,
+};
+
+/// One `N G obj ... endobj` block cut out of the raw file.
+pub struct CarvedObject<'a> {
+    pub id: u32,
+    /// Byte offset of the object header in the file.
+    pub offset: usize,
+    /// The object's text up to its `stream` keyword, or to `endobj` when it
+    /// has no stream.
+    pub body: &'a [u8],
+    /// Stream content as stored in the file, up to `endstream`.
+    pub stream: Option<&'a [u8]>,
+}
+
+fn object_header() -> &'static Regex {
+    static OBJECT_HEADER: OnceLock<Regex> = OnceLock::new();
+    OBJECT_HEADER.get_or_init(|| {
+        Regex::new(r"(?-u)(?:^|[^0-9])(\d+)[\x00\t\n\x0c\r ]+\d+[\x00\t\n\x0c\r ]+obj\b").unwrap()
+    })
+}
+
+fn stream_keyword() -> &'static Regex {
+    static STREAM_KEYWORD: OnceLock<Regex> = OnceLock::new();
+    STREAM_KEYWORD.get_or_init(|| Regex::new(r"(?-u)(?:^|[^d])stream(?:\r\n|\r|\n)").unwrap())
+}
+
+fn js_entry() -> &'static Regex {
+    static JS_ENTRY: OnceLock<Regex> = OnceLock::new();
+    JS_ENTRY.get_or_init(|| {
+        Regex::new(&format!(
+            r"(?-u){}[\x00\t\n\x0c\r ]*(?:(\d+)[\x00\t\n\x0c\r ]+\d+[\x00\t\n\x0c\r ]+R|[(<])",
+            name_pattern("JS")
+        ))
+        .unwrap()
+    })
+}
+
+/// Cuts every object out of `data` without the cross-reference table, the
+/// way a viewer repairing a broken file would. Objects run to their `endobj`,
+/// or to the next object header when it is missing, and stream content to
+/// `endstream`, or likewise. Each looks no further than the next object
+/// header, so that files missing the keywords take linear time.
+pub fn carve_objects(data: &[u8]) -> Vec<CarvedObject<'_>> {
+    let mut objects = Vec::new();
+    let mut at = 0;
+
+    while let Some(header) = object_header().captures(&data[at..]) {
+        let (Some(whole), Some(id)) = (header.get(0), header.get(1)) else {
+            break;
+        };
+        let offset = at + id.start();
+        let rest = &data[at + whole.end()..];
+        let next_header = object_header()
+            .find(rest)
+            .map_or(rest.len(), |next| next.start());
+        let rest = &rest[..next_header];
+        let body_end = find(rest, b"endobj").unwrap_or(next_header);
+
+        let (body, stream, end) = match stream_keyword().find(&rest[..body_end]) {
+            Some(keyword) => {
+                let content = &rest[keyword.end()..];
+                let content_end = find(content, b"endstream").unwrap_or(body_end - keyword.end());
+                let end = keyword.end() + content_end;
+                let end = end + find(&rest[end..], b"endobj").unwrap_or(0);
+                (&rest[..keyword.start()], Some(&content[..content_end]), end)
+            }
+            None => (&rest[..body_end], None, body_end),
+        };
+
+        if let Some(id) = std::str::from_utf8(id.as_bytes())
+            .ok()
+            .and_then(|id| id.parse().ok())
+        {
+            objects.push(CarvedObject {
+                id,
+                offset,
+                body,
+                stream,
+            });
+        }
+        at += whole.end() + end;
+    }
+
+    objects
+}
+
+/// Analyzes a file lopdf rejected from the `objects` carved out of it. Broken
+/// structure is a common way for samples to slip past parsers that viewers
+/// are more forgiving than, so `parse_error` is reported as a finding of its
+/// own. References can't be followed beyond a script's `/JS` entry, and
+/// streams are inflated whatever filter they declare, keeping what decodes
+/// before any damage.
+pub(crate) fn analyze_pdf_carved(
+    data: &[u8],
+    objects: &[CarvedObject],
+    sha256: String,
+    parse_error: String,
+    encryption: Option<EncryptionInfo>,
+    config: &CompiledConfig,
+) -> AnalysisResult {
+    tracing::debug!(objects = objects.len(), "carved objects from the raw file");
+    let (streams, compression_bombs) = inflate_streams(objects, config);
+    let bodies: HashMap<u32, &[u8]> = objects
+        .iter()
+        .map(|object| (object.id, object.body))
+        .collect();
+
+    let javascript = name_regex(&["JavaScript", "JS"]);
+    let auto_action = name_regex(&["OpenAction", "AA"]);
+    let launch = name_regex(&["Launch"]);
+    let obj_stm = name_regex(&["ObjStm"]);
+    let image = name_regex(&["Image"]);
+
+    let mut result = AnalysisResult {
+        encryption,
+        parse_error: Some(parse_error),
+        compression_bomb_hints: compression_bombs,
+        ..Default::default()
+    };
+    let mut ids = HashSet::new();
+
+    for object in objects {
+        ids.insert(object.id);
+        let body = object.body;
+        let stream = streams.get(&object.id);
+
+        result.has_auto_action |= auto_action.is_match(body);
+        if launch.is_match(body) {
+            result.launch_actions.push(object.id);
+        }
+        if obj_stm.is_match(body) {
+            result.has_obj_stm = true;
+            result.object_statistics.obj_stm_objects += 1;
+            // Object streams hide action dictionaries behind compression
+            if let Some(content) = stream {
+                result.has_javascript |= javascript.is_match(content);
+                result.has_auto_action |= auto_action.is_match(content);
+            }
+        }
+        if javascript.is_match(body) {
+            result.has_javascript = true;
+            result.object_statistics.js_objects += 1;
+            carve_javascript(object, &bodies, &streams, &mut result);
+        }
+
+        let Some(content) = stream else {
+            continue;
+        };
+        result.object_statistics.stream_objects += 1;
+        let text = String::from_utf8_lossy(content);
+        if let Some(hit) = config
+            .suspicious_patterns
+            .find(PatternTarget::Streams, &text)
+        {
+            push_stream_match(&mut result, object.id, hit);
+        }
+        if !image.is_match(body) {
+            let hints = check_shellcode_heuristics(content);
+            if hints.is_detected() {
+                result.shellcode_hints.push((object.id, hints));
+            }
+        }
+    }
+
+    for js_obj in &mut result.javascript_objects {
+        js_obj.dangerous_apis = find_dangerous_js_apis(js_obj.decoded(), config);
+        let hidden = find_hidden_matches(js_obj, config);
+        js_obj.hidden_matches = hidden.iter().map(|found| found.evidence.clone()).collect();
+        result.pattern_matches.extend(hidden);
+    }
+    result.embedded_signatures =
+        find_embedded_signatures(streams.iter().map(|(&id, content)| (id, content.as_ref())));
+    result.embedded_pdfs = analyze_embedded_pdfs(
+        streams.iter().map(|(&id, content)| (id, content.as_ref())),
+        &result.embedded_files,
+        config,
+    );
+    let script_shellcode = check_script_shellcode(&result.javascript_objects);
+    result.shellcode_hints.extend(script_shellcode);
+    result.object_statistics.total_objects = ids.len();
+    result.ratio_findings = check_object_ratio_heuristics(&result.object_statistics, config);
+    result.large_file_size = check_file_size(data, config);
+    result.xref_sections = count_xref_sections(data);
+    result.escaped_names = find_escaped_names(data);
+    result.framing = check_framing(data);
+    result.revisions = check_revisions(data);
+
+    result.sha256 = Some(sha256);
+    #[cfg(feature = "virustotal")]
+    if let Some(virustotal) = &config.virustotal {
+        result.virustotal = virustotal.look_up(&result);
+    }
+
+    let config = &config.config;
+    (result.severity_score, result.explanation) =
+        calculate_severity_score(&result, &config.scoring);
+    result.severity = config.scoring.bands.severity(result.severity_score);
+    result.classification = classify(&result);
+    result.findings = collect_findings(&result, None);
+    apply_suppressions(&mut result, config);
+    result
+}
+
+/// Reads the script an action's `/JS` entry holds or references, or the
+/// object's own stream when it is the script.
+fn carve_javascript(
+    object: &CarvedObject,
+    bodies: &HashMap<u32, &[u8]>,
+    streams: &HashMap<u32, Cow<[u8]>>,
+    result: &mut AnalysisResult,
+) {
+    let scripts = &mut result.javascript_objects;
+    let Some(entry) = js_entry().captures(object.body) else {
+        if let Some(content) = streams.get(&object.id) {
+            push_stream_javascript(scripts, object.id, content);
+        }
+        return;
+    };
+
+    let target = entry
+        .get(1)
+        .and_then(|id| std::str::from_utf8(id.as_bytes()).ok())
+        .and_then(|id| id.parse::<u32>().ok());
+    match target {
+        Some(target) => {
+            if let Some(content) = streams.get(&target) {
+                push_stream_javascript(scripts, target, content);
+            } else if let Some(script) = bodies
+                .get(&target)
+                .and_then(|body| read_string(body.trim_ascii_start()))
+            {
+                push_javascript(
+                    scripts,
+                    object.id,
+                    normalize_pdf_string(&script),
+                    JsSource::IndirectRef(target),
+                );
+            }
+        }
+        None => {
+            let start = entry.get(0).map_or(0, |entry| entry.end() - 1);
+            if let Some(script) = read_string(&object.body[start..]) {
+                push_javascript(
+                    scripts,
+                    object.id,
+                    normalize_pdf_string(&script),
+                    JsSource::InlineString,
+                );
+            }
+        }
+    }
+}
+
+/// Inflates the streams of `objects` within the same limits as
+/// `populate_stream_cache`, which the parsed document's streams are decoded
+/// within. Streams that run into one are left out and reported.
+fn inflate_streams<'a>(
+    objects: &[CarvedObject<'a>],
+    config: &CompiledConfig,
+) -> (HashMap<u32, Cow<'a, [u8]>>, Vec<CompressionBombHint>) {
+    let mut streams = HashMap::new();
+    let mut compression_bombs = Vec::new();
+    let mut budget = config.config.max_total_decompressed_size;
+
+    for object in objects {
+        let Some(content) = object.stream else {
+            continue;
+        };
+        if budget == 0 {
+            tracing::warn!("decompression budget exhausted, streams left undecoded");
+            break;
+        }
+        let compressed_size = content.len() as u64;
+        // Any stream may be inflated, so the ratio applies to all
+        let (limit, kind) = stream_limit(true, compressed_size, budget, config);
+        match inflate(content, limit) {
+            Ok(inflated) => {
+                if let Cow::Owned(inflated) = &inflated {
+                    budget -= inflated.len() as u64;
+                }
+                streams.insert(object.id, inflated);
+            }
+            Err(bytes_before_abort) => {
+                tracing::warn!(
+                    object_id = object.id,
+                    limit,
+                    "stream decompresses past the {}, skipping",
+                    kind.description()
+                );
+                if kind == BombLimit::DocumentBudget {
+                    budget = 0;
+                }
+                compression_bombs.push(CompressionBombHint {
+                    object_id: object.id,
+                    compressed_size,
+                    bytes_before_abort,
+                    limit: kind,
+                });
+            }
+        }
+    }
+    (streams, compression_bombs)
+}
+
+/// Inflates `content` as if it were Flate-encoded, falling back to the bytes
+/// as stored when nothing decodes. Truncated or corrupt streams still give up
+/// what decoded before the damage. Past `limit` bytes of output, the error
+/// holds how many were produced before giving up.
+fn inflate(content: &[u8], limit: u64) -> Result<Cow<'_, [u8]>, u64> {
+    // One byte past the limit shows it was reached
+    let mut inflated = Vec::new();
+    let _ = ZlibDecoder::new(content)
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut inflated);
+    if inflated.len() as u64 > limit {
+        Err(limit)
+    } else if inflated.is_empty() {
+        Ok(Cow::Borrowed(content))
+    } else {
+        Ok(Cow::Owned(inflated))
+    }
+}
+
+/// Reads the literal `(...)` or hex `<...>` string `text` starts with. An
+/// unterminated string runs to the end of `text`.
+fn read_string(text: &[u8]) -> Option<Vec<u8>> {
+    match text.first()? {
+        b'(' => Some(read_literal(&text[1..])),
+        b'<' => {
+            let end = text.iter().position(|&b| b == b'>').unwrap_or(text.len());
+            let digits: Vec<u8> = text[1..end]
+                .iter()
+                .filter_map(|&b| (b as char).to_digit(16))
+                .map(|digit| digit as u8)
+                .collect();
+            Some(
+                digits
+                    .chunks(2)
+                    .map(|pair| pair[0] << 4 | pair.get(1).copied().unwrap_or(0))
+                    .collect(),
+            )
+        }
+        _ => None,
+    }
+}
+
+fn read_literal(text: &[u8]) -> Vec<u8> {
+    let mut value = Vec::new();
+    let mut depth = 1;
+    let mut bytes = text.iter().copied().peekable();
+
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            b'\\' => {
+                let Some(escaped) = bytes.next() else {
+                    break;
+                };
+                match escaped {
+                    b'n' => value.push(b'\n'),
+                    b'r' => value.push(b'\r'),
+                    b't' => value.push(b'\t'),
+                    b'b' => value.push(0x08),
+                    b'f' => value.push(0x0c),
+                    b'0'..=b'7' => {
+                        let mut code = u32::from(escaped - b'0');
+                        for _ in 0..2 {
+                            match bytes.next_if(|b| (b'0'..=b'7').contains(b)) {
+                                Some(digit) => code = code * 8 + u32::from(digit - b'0'),
+                                None => break,
+                            }
+                        }
+                        value.push(code as u8);
+                    }
+                    // A line continuation
+                    b'\r' => {
+                        bytes.next_if_eq(&b'\n');
+                    }
+                    b'\n' => {}
+                    _ => value.push(escaped),
+                }
+                continue;
+            }
+            _ => {}
+        }
+        value.push(byte);
+    }
+
+    value
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
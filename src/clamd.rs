@@ -0,0 +1,420 @@
+use crate::batch::{self, has_pdf_marker, FileFilter};
+use pdf_sentinel::findings::{rule, Finding};
+use pdf_sentinel::scoring::Severity;
+use pdf_sentinel::{AnalysisResult, Analyzer, SentinelError};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often idle connections and the listener check whether Ctrl-C was
+/// pressed.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// A command that has started must keep arriving at least this fast.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longest command accepted, paths included.
+const MAX_COMMAND: usize = 8 * 1024;
+
+/// Prefixes the names reported as `FOUND`, as ClamAV's own do with their
+/// platform, e.g. `PdfSentinel.OpenActionJavaScript`.
+const SIGNATURE_PREFIX: &str = "PdfSentinel";
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Listener {
+    /// A path, or `unix:PATH`, is a Unix socket; anything else is HOST:PORT.
+    /// A socket left behind by an earlier run is replaced.
+    fn bind(address: &str) -> io::Result<Self> {
+        let path = address
+            .strip_prefix("unix:")
+            .or_else(|| address.contains('/').then_some(address));
+        let Some(path) = path else {
+            return Ok(Listener::Tcp(TcpListener::bind(address)?));
+        };
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+
+            let stale = std::fs::symlink_metadata(path)
+                .is_ok_and(|metadata| metadata.file_type().is_socket());
+            if stale {
+                std::fs::remove_file(path)?;
+            }
+            Ok(Listener::Unix(UnixListener::bind(path)?))
+        }
+        #[cfg(not(unix))]
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} is a Unix socket, which this platform lacks", path),
+        ))
+    }
+
+    fn set_nonblocking(&self) -> io::Result<()> {
+        match self {
+            Listener::Tcp(listener) => listener.set_nonblocking(true),
+            #[cfg(unix)]
+            Listener::Unix(listener) => listener.set_nonblocking(true),
+        }
+    }
+
+    fn accept(&self) -> io::Result<Stream> {
+        let stream = match self {
+            Listener::Tcp(listener) => Stream::Tcp(listener.accept()?.0),
+            #[cfg(unix)]
+            Listener::Unix(listener) => Stream::Unix(listener.accept()?.0),
+        };
+        stream.set_blocking()?;
+        Ok(stream)
+    }
+
+    /// Removes the socket file, if any.
+    fn close(self) {
+        #[cfg(unix)]
+        if let Listener::Unix(listener) = self {
+            if let Some(path) = listener
+                .local_addr()
+                .ok()
+                .and_then(|address| address.as_pathname().map(Path::to_path_buf))
+            {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    fn set_blocking(&self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_nonblocking(false),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.set_nonblocking(false),
+        }
+    }
+
+    fn set_read_timeout(&self, timeout: Duration) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_read_timeout(Some(timeout)),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.set_read_timeout(Some(timeout)),
+        }
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(match self {
+            Stream::Tcp(stream) => Stream::Tcp(stream.try_clone()?),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Stream::Unix(stream.try_clone()?),
+        })
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buffer),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.read(buffer),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buffer),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.write(buffer),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Speaks enough of clamd's protocol for mail filters such as amavis and
+/// rspamd to use pdf-sentinel as a scanner: `PING`, `VERSION`, `INSTREAM`,
+/// `SCAN`, `CONTSCAN`, `MULTISCAN`, `IDSESSION` and `END`, with the `z` and
+/// `n` command prefixes. PDFs at or above the severity are reported as
+/// `FOUND`, named after their most severe finding.
+///
+/// Anything that is not a PDF is `OK`, as are PDFs that fail to parse, so
+/// that mail with a broken attachment isn't deferred over and over.
+pub struct ClamdService<'a> {
+    analyzer: &'a Analyzer,
+    severity: Severity,
+    max_stream_size: u64,
+}
+
+impl<'a> ClamdService<'a> {
+    /// `INSTREAM` uploads past `max_stream_size` are refused, as clamd does
+    /// past its `StreamMaxLength`.
+    pub fn new(analyzer: &'a Analyzer, severity: Severity, max_stream_size: u64) -> Self {
+        ClamdService {
+            analyzer,
+            severity,
+            max_stream_size,
+        }
+    }
+
+    /// Serves on `address` until Ctrl-C, a thread per connection. Commands
+    /// already being handled when the signal arrives are answered before
+    /// returning.
+    pub fn serve(&self, address: &str) -> Result<(), SentinelError> {
+        let listener = Listener::bind(address)?;
+        listener.set_nonblocking()?;
+        let running = Arc::new(AtomicBool::new(true));
+        let handler_flag = Arc::clone(&running);
+        ctrlc::set_handler(move || handler_flag.store(false, Ordering::SeqCst))
+            .map_err(|err| SentinelError::Io(io::Error::other(err)))?;
+        eprintln!("clamd service listening on {}", address);
+
+        let running: &AtomicBool = &running;
+        std::thread::scope(|scope| {
+            while running.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    Ok(stream) => {
+                        scope.spawn(move || {
+                            if let Err(err) = self.handle_connection(stream, running) {
+                                tracing::warn!(error = %err, "clamd connection failed");
+                            }
+                        });
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(POLL_INTERVAL)
+                    }
+                    Err(err) => tracing::error!(error = %err, "failed to accept a connection"),
+                }
+            }
+        });
+        listener.close();
+        Ok(())
+    }
+
+    /// Answers one command, or every command of an `IDSESSION` up to `END`.
+    fn handle_connection(&self, stream: Stream, running: &AtomicBool) -> io::Result<()> {
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+        // Replies within a session are numbered after their command
+        let mut session: Option<u32> = None;
+        loop {
+            reader.get_ref().set_read_timeout(POLL_INTERVAL)?;
+            loop {
+                match reader.fill_buf() {
+                    Ok([]) => return Ok(()),
+                    Ok(_) => break,
+                    Err(err)
+                        if matches!(
+                            err.kind(),
+                            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                        ) =>
+                    {
+                        if !running.load(Ordering::SeqCst) {
+                            return Ok(());
+                        }
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            reader.get_ref().set_read_timeout(REQUEST_TIMEOUT)?;
+
+            let terminator = match reader.fill_buf()?.first() {
+                Some(b'z') => b'\0',
+                _ => b'\n',
+            };
+            let command = match read_command(&mut reader, terminator) {
+                Ok(command) => command,
+                Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                    // The rest of an overlong command can't be told from the
+                    // next, so the connection can't carry on
+                    writer.write_all(format!("{}. ERROR", err).as_bytes())?;
+                    writer.write_all(&[terminator])?;
+                    return writer.flush();
+                }
+                Err(err) => return Err(err),
+            };
+            let (name, argument) = command.split_once(' ').unwrap_or((&command, ""));
+            let prefix = match &mut session {
+                Some(id) => {
+                    *id += 1;
+                    format!("{}: ", id)
+                }
+                None => String::new(),
+            };
+            let mut reply = |line: &str| -> io::Result<()> {
+                writer.write_all(prefix.as_bytes())?;
+                writer.write_all(line.as_bytes())?;
+                writer.write_all(&[terminator])?;
+                writer.flush()
+            };
+
+            match name {
+                "PING" => reply("PONG")?,
+                "VERSION" => reply(&version())?,
+                "VERSIONCOMMANDS" => reply(&format!(
+                    "{}| COMMANDS: SCAN CONTSCAN MULTISCAN INSTREAM PING VERSION IDSESSION END",
+                    version()
+                ))?,
+                "IDSESSION" if session.is_none() => {
+                    session = Some(0);
+                    continue;
+                }
+                "END" => return Ok(()),
+                "INSTREAM" => match read_stream(&mut reader, self.max_stream_size)? {
+                    Some(data) => reply(&format!("stream: {}", self.verdict(&data)))?,
+                    None => {
+                        // The rest of the upload is still coming, so the
+                        // connection can't carry on
+                        reply("INSTREAM size limit exceeded. ERROR")?;
+                        return Ok(());
+                    }
+                },
+                "SCAN" | "CONTSCAN" | "MULTISCAN" if !argument.is_empty() => {
+                    for line in self.scan_path(Path::new(argument), name == "SCAN") {
+                        reply(&line)?;
+                    }
+                }
+                _ => reply("UNKNOWN COMMAND")?,
+            }
+            if session.is_none() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// `OK`, or the name of what was found followed by `FOUND`.
+    fn verdict(&self, data: &[u8]) -> String {
+        if !has_pdf_marker(data) {
+            return "OK".to_string();
+        }
+        match self.analyzer.analyze_bytes(data) {
+            Ok(result) => self.verdict_for(&result),
+            Err(err) => {
+                tracing::warn!(error = %err, "PDF not analyzed, reported as OK");
+                "OK".to_string()
+            }
+        }
+    }
+
+    fn verdict_for(&self, result: &AnalysisResult) -> String {
+        if result.severity < self.severity {
+            return "OK".to_string();
+        }
+        let worst = result
+            .findings
+            .iter()
+            .fold(None::<&Finding>, |worst, finding| match worst {
+                Some(worst) if worst.severity >= finding.severity => Some(worst),
+                _ => Some(finding),
+            });
+        let name = worst.map_or(result.classification.id(), |finding| {
+            rule(&finding.rule_id).name
+        });
+        format!("{}.{} FOUND", SIGNATURE_PREFIX, name)
+    }
+
+    /// The reply lines for a `SCAN` of `path`, a file or a directory
+    /// searched for PDFs: one per file found or failed, or a single `OK`.
+    /// `SCAN` stops at the first file found.
+    fn scan_path(&self, path: &Path, stop_at_first: bool) -> Vec<String> {
+        let files = if path.is_dir() {
+            batch::collect_files(path, &FileFilter::Sniff)
+        } else {
+            vec![path.to_string_lossy().into_owned()]
+        };
+        let mut lines = Vec::new();
+        for (file, result) in self.analyzer.analyze_files(&files, true) {
+            let verdict = match result {
+                Ok(result) => self.verdict_for(&result),
+                Err(SentinelError::Io(err)) => format!("{}. ERROR", err),
+                Err(err) => {
+                    tracing::warn!(%file, error = %err, "PDF not analyzed, reported as OK");
+                    continue;
+                }
+            };
+            if verdict != "OK" {
+                lines.push(format!("{}: {}", file, verdict));
+                if stop_at_first && verdict.ends_with(" FOUND") {
+                    break;
+                }
+            }
+        }
+        if lines.is_empty() {
+            lines.push(format!("{}: OK", path.display()));
+        }
+        lines
+    }
+}
+
+fn version() -> String {
+    format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
+/// Reads a command ending with `terminator`, which is also the byte its
+/// replies end with: `zCOMMAND\0` is answered with a NUL, `nCOMMAND\n` and
+/// the older bare `COMMAND\n` with a newline. Commands longer than
+/// `MAX_COMMAND` are invalid.
+fn read_command(reader: &mut impl BufRead, terminator: u8) -> io::Result<String> {
+    let mut command = Vec::new();
+    reader
+        .take(MAX_COMMAND as u64)
+        .read_until(terminator, &mut command)?;
+    if command.last() != Some(&terminator) {
+        if command.len() == MAX_COMMAND {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Command too long",
+            ));
+        }
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    command.pop();
+    if command
+        .first()
+        .is_some_and(|&first| first == b'z' || first == b'n')
+    {
+        command.remove(0);
+    }
+    let command = String::from_utf8(command)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Command is not UTF-8"))?;
+    Ok(command.trim_end_matches('\r').to_string())
+}
+
+/// Reads the chunks of an `INSTREAM` upload, each a 4-byte big-endian
+/// length and that many bytes, up to a zero length. `None` once the upload
+/// passes `limit`.
+fn read_stream(reader: &mut impl Read, limit: u64) -> io::Result<Option<Vec<u8>>> {
+    let mut data = Vec::new();
+    loop {
+        let mut length = [0; 4];
+        reader.read_exact(&mut length)?;
+        let length = u32::from_be_bytes(length) as usize;
+        if length == 0 {
+            return Ok(Some(data));
+        }
+        if (data.len() + length) as u64 > limit {
+            return Ok(None);
+        }
+        let start = data.len();
+        data.resize(start + length, 0);
+        reader.read_exact(&mut data[start..])?;
+    }
+}
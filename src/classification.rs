@@ -0,0 +1,304 @@
+use serde::{Deserialize, Serialize};
+
+use crate::scoring::Severity;
+use crate::virustotal::VtSubject;
+use crate::AnalysisResult;
+
+/// Triage class for a document. Each non-clean class carries the indicators
+/// that put it there, most specific first.
+#[derive(Default, Deserialize, Serialize)]
+pub enum ThreatClassification {
+    #[default]
+    Clean,
+    Suspicious(Vec<String>),
+    ProbableExploit(Vec<String>),
+    ConfirmedMalicious(Vec<String>),
+}
+
+impl ThreatClassification {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ThreatClassification::Clean => "Clean",
+            ThreatClassification::Suspicious(_) => "Suspicious",
+            ThreatClassification::ProbableExploit(_) => "Probable exploit",
+            ThreatClassification::ConfirmedMalicious(_) => "Confirmed malicious",
+        }
+    }
+
+    /// The label in a form fit for event ids and metric labels, e.g.
+    /// `probable-exploit`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            ThreatClassification::Clean => "clean",
+            ThreatClassification::Suspicious(_) => "suspicious",
+            ThreatClassification::ProbableExploit(_) => "probable-exploit",
+            ThreatClassification::ConfirmedMalicious(_) => "confirmed-malicious",
+        }
+    }
+
+    pub fn indicators(&self) -> &[String] {
+        match self {
+            ThreatClassification::Clean => &[],
+            ThreatClassification::Suspicious(indicators)
+            | ThreatClassification::ProbableExploit(indicators)
+            | ThreatClassification::ConfirmedMalicious(indicators) => indicators,
+        }
+    }
+}
+
+/// Classifies on combinations of findings rather than on the score alone: a
+/// lone JavaScript object is merely suspicious, but JavaScript that runs
+/// without user interaction is treated as confirmed.
+pub fn classify(result: &AnalysisResult) -> ThreatClassification {
+    if result.known_malicious {
+        return ThreatClassification::ConfirmedMalicious(vec![
+            "matches a known-malicious hash".to_string()
+        ]);
+    }
+    let detections: Vec<String> = result
+        .virustotal
+        .iter()
+        .filter(|lookup| lookup.is_detected())
+        .map(|lookup| {
+            let subject = match lookup.subject {
+                VtSubject::File => "the file".to_string(),
+                VtSubject::Payload(id) => format!("object {}", id),
+            };
+            format!(
+                "VirusTotal engines flag {} ({})",
+                subject,
+                lookup.ratio().unwrap_or_default()
+            )
+        })
+        .collect();
+    if !detections.is_empty() {
+        return ThreatClassification::ConfirmedMalicious(detections);
+    }
+    // A document is at least as bad as the worst PDF inside it
+    let nested = |pick: fn(&ThreatClassification) -> bool| -> Vec<String> {
+        result
+            .embedded_pdfs
+            .iter()
+            .filter(|pdf| pick(&pdf.result.classification))
+            .map(|pdf| {
+                format!(
+                    "embedded PDF in object {}: {}",
+                    pdf.object_id,
+                    pdf.result.classification.indicators().join(", ")
+                )
+            })
+            .collect()
+    };
+    let malicious_pdfs =
+        nested(|class| matches!(class, ThreatClassification::ConfirmedMalicious(_)));
+    if !malicious_pdfs.is_empty() {
+        return ThreatClassification::ConfirmedMalicious(malicious_pdfs);
+    }
+
+    let launches = !result.launch_actions.is_empty();
+    if result.has_javascript && (result.has_auto_action || launches) {
+        let mut indicators = Vec::new();
+        if let Some(open_action) = &result.open_action_js {
+            indicators.push(format!(
+                "JavaScript runs on open (object {})",
+                open_action.action_object_id
+            ));
+        }
+        indicators.push("JavaScript".to_string());
+        if result.has_auto_action {
+            indicators.push("automatic action".to_string());
+        }
+        if launches {
+            indicators.push("Launch action".to_string());
+        }
+        return ThreatClassification::ConfirmedMalicious(indicators);
+    }
+
+    let mut exploit_indicators: Vec<String> = result
+        .shellcode_hints
+        .iter()
+        .map(|(id, _)| format!("shellcode in object {}", id))
+        .collect();
+    for candidate in &result.heap_spray_candidates {
+        exploit_indicators.push(format!(
+            "heap spray array in object {}",
+            candidate.object_id
+        ));
+    }
+    if result.named_destinations.is_suspicious() {
+        exploit_indicators.push("heap spray addresses in named destinations".to_string());
+    }
+    exploit_indicators.extend(nested(|class| {
+        matches!(class, ThreatClassification::ProbableExploit(_))
+    }));
+    if !exploit_indicators.is_empty() {
+        return ThreatClassification::ProbableExploit(exploit_indicators);
+    }
+
+    if result.severity_score == 0 {
+        return ThreatClassification::Clean;
+    }
+    ThreatClassification::Suspicious(suspicious_indicators(result))
+}
+
+fn suspicious_indicators(result: &AnalysisResult) -> Vec<String> {
+    let mut indicators = Vec::new();
+    if let Some(creator) = &result.malicious_creator {
+        indicators.push(format!("built with {}", creator));
+    }
+    if result.has_javascript {
+        indicators.push("JavaScript".to_string());
+    }
+    if result.has_auto_action {
+        indicators.push("automatic action".to_string());
+    }
+    if !result.launch_actions.is_empty() {
+        indicators.push("Launch action".to_string());
+    }
+    for js in &result.javascript_objects {
+        if !js.dangerous_apis.is_empty() {
+            indicators.push(format!(
+                "{} in object {}",
+                js.dangerous_apis.join(", "),
+                js.id
+            ));
+        }
+        if !js.hidden_matches.is_empty() {
+            indicators.push(format!("obfuscated JavaScript in object {}", js.id));
+        }
+        for call in &js.ast_findings.acrobat_apis {
+            if call.severity == Severity::Critical {
+                indicators.push(format!("{} in object {}", call.api.name(), js.id));
+            }
+        }
+    }
+    if !result.split_js_candidates.is_empty() {
+        indicators.push("JavaScript split across string fragments".to_string());
+    }
+    if !result.form_field_actions.is_empty() {
+        indicators.push("form field actions".to_string());
+    }
+    for annotation in result
+        .media
+        .iter()
+        .filter(|annotation| annotation.has_exploitable_payload())
+    {
+        indicators.push(format!(
+            "{} payload in object {}",
+            annotation.kind.description(),
+            annotation.object_id
+        ));
+    }
+    if let Some(xfa) = &result.xfa {
+        if xfa.scripts > 0 {
+            indicators.push("JavaScript in XFA form".to_string());
+        }
+        for submit in xfa.submit_targets.iter().filter(|submit| submit.external) {
+            indicators.push(format!("XFA form submits to {}", submit.target));
+        }
+    }
+    if !result.compression_bomb_hints.is_empty() {
+        indicators.push("compression bomb streams".to_string());
+    }
+    if !result.high_entropy_streams.is_empty() {
+        indicators.push("high-entropy streams".to_string());
+    }
+    for signature in &result.embedded_signatures {
+        indicators.push(format!(
+            "{} in stream {}",
+            signature.kind.description(),
+            signature.object_id
+        ));
+    }
+    for pdf in &result.embedded_pdfs {
+        indicators.push(format!("PDF in object {}", pdf.object_id));
+    }
+    if !result.steganography_hints.is_empty() {
+        indicators.push("LSB steganography in images".to_string());
+    }
+    if let Some(report) = result
+        .pdfa_conformance
+        .as_ref()
+        .filter(|report| !report.violations.is_empty())
+    {
+        indicators.push(format!(
+            "{} claim with {}",
+            report.claimed_level,
+            report.violations.join(", ")
+        ));
+    }
+    if !result.structure_violations.is_empty() {
+        indicators.push(format!(
+            "{} page tree violations",
+            result.structure_violations.len()
+        ));
+    }
+    if !result.xref_anomalies.is_empty() {
+        indicators.push("cross-reference offset mismatches".to_string());
+    }
+    let consistency = &result.xref_consistency;
+    if !consistency.missing_objects.is_empty() || !consistency.unlisted_objects.is_empty() {
+        indicators.push("objects missing from or outside the cross-reference data".to_string());
+    } else if consistency.size_mismatch.is_some() {
+        indicators.push("trailer /Size mismatch".to_string());
+    }
+    let shadowed = &result.revisions.shadowed_objects;
+    if shadowed.iter().any(|object| object.is_sensitive()) {
+        indicators.push("catalog or actions replaced by an update".to_string());
+    } else if !shadowed.is_empty() {
+        indicators.push(format!("{} objects redefined by updates", shadowed.len()));
+    }
+    for anomaly in &result.framing.anomalies {
+        indicators.push(anomaly.kind.description().to_string());
+    }
+    for polyglot in &result.framing.polyglots {
+        indicators.push(format!("also {}", polyglot.format.description()));
+    }
+    if result.parse_error.is_some() {
+        indicators.push("unparseable structure".to_string());
+    }
+    if let Some(encryption) = &result.encryption {
+        indicators.push(if encryption.raw_scan_only {
+            "encrypted, not decrypted".to_string()
+        } else {
+            "encrypted".to_string()
+        });
+    }
+    if !result.escaped_names.is_empty() {
+        indicators.push("hex-escaped names".to_string());
+    }
+    for url in result.urls.iter().filter(|url| !url.indicators.is_empty()) {
+        indicators.push(format!("suspicious link to {}", url.url));
+    }
+    if !result.suspicious_names.is_empty() {
+        indicators.push("suspicious names".to_string());
+    }
+    for found in &result.suspicious_stream_content {
+        indicators.push(format!(
+            "{:?} in stream {}",
+            found.evidence, found.object_id
+        ));
+    }
+    if result.suspicious_metadata {
+        indicators.push("suspicious metadata".to_string());
+    }
+    if result.metadata_date_mismatch.is_some() {
+        indicators.push("Info and XMP creation dates disagree".to_string());
+    }
+    for anomaly in &result.metadata_anomalies {
+        indicators.push(anomaly.detail.clone());
+    }
+    if result.unknown_creator {
+        indicators.push("unknown creator tool".to_string());
+    }
+    for finding in &result.ratio_findings {
+        indicators.push(format!("high {}", finding.ratio_name));
+    }
+    for finding in &result.rule_findings {
+        indicators.push(format!("{}: {}", finding.rule, finding.description));
+    }
+    if indicators.is_empty() {
+        indicators.push(format!("severity score {}", result.severity_score));
+    }
+    indicators
+}
@@ -0,0 +1,158 @@
+use crate::filters::StreamCache;
+use crate::{cached_stream, resolve};
+use lopdf::{Document, Object};
+use serde::{Deserialize, Serialize};
+
+/// Colorant limit for DeviceN spaces in older PDF consumers.
+const MAX_DEVICEN_COLORANTS: usize = 8;
+
+#[derive(Deserialize, Serialize)]
+pub enum ColorspaceAnomalyKind {
+    TooManyColorants,
+    ZeroTintTransform,
+    UnusualIndexedHival,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ColorspaceAnomaly {
+    pub page_id: u32,
+    pub kind: ColorspaceAnomalyKind,
+    pub detail: String,
+}
+
+pub fn check_colorspace_anomalies(doc: &Document, streams: &StreamCache) -> Vec<ColorspaceAnomaly> {
+    let mut anomalies = Vec::new();
+
+    for (_, page_id) in doc.get_pages() {
+        let colorspaces = doc
+            .get_dictionary(page_id)
+            .ok()
+            .and_then(|page| page.get(b"Resources").ok())
+            .and_then(|resources| resolve(doc, resources))
+            .and_then(|resources| resources.as_dict().ok())
+            .and_then(|resources| resources.get(b"ColorSpace").ok())
+            .and_then(|colorspaces| resolve(doc, colorspaces))
+            .and_then(|colorspaces| colorspaces.as_dict().ok());
+
+        let Some(colorspaces) = colorspaces else {
+            continue;
+        };
+
+        for (name, colorspace) in colorspaces.iter() {
+            let Some(Object::Array(family)) = resolve(doc, colorspace) else {
+                continue;
+            };
+            let name = String::from_utf8_lossy(name);
+            if let Some((kind, detail)) = check_colorspace(doc, streams, &name, family) {
+                anomalies.push(ColorspaceAnomaly {
+                    page_id: page_id.0,
+                    kind,
+                    detail,
+                });
+            }
+        }
+    }
+
+    anomalies
+}
+
+fn check_colorspace(
+    doc: &Document,
+    streams: &StreamCache,
+    name: &str,
+    family: &[Object],
+) -> Option<(ColorspaceAnomalyKind, String)> {
+    match family.first()?.as_name().ok()? {
+        b"DeviceN" => {
+            let colorants = resolve(doc, family.get(1)?)?.as_array().ok()?.len();
+            (colorants > MAX_DEVICEN_COLORANTS).then(|| {
+                (
+                    ColorspaceAnomalyKind::TooManyColorants,
+                    format!("/{} is DeviceN with {} colorants", name, colorants),
+                )
+            })
+        }
+        b"Separation" => is_constant_zero_function(doc, streams, family.get(3)?).then(|| {
+            (
+                ColorspaceAnomalyKind::ZeroTintTransform,
+                format!(
+                    "/{} is a Separation whose tint transform is always zero",
+                    name
+                ),
+            )
+        }),
+        b"Indexed" => {
+            let hival = resolve(doc, family.get(2)?)?.as_i64().ok()?;
+            if !(0..=255).contains(&hival) {
+                return Some((
+                    ColorspaceAnomalyKind::UnusualIndexedHival,
+                    format!("/{} is Indexed with hival {} outside 0..=255", name, hival),
+                ));
+            }
+            let components = match resolve(doc, family.get(1)?)?.as_name().ok()? {
+                b"DeviceGray" => 1,
+                b"DeviceRGB" => 3,
+                b"DeviceCMYK" => 4,
+                _ => return None,
+            };
+            let lookup = family.get(3)?;
+            let lookup_len = match resolve(doc, lookup)? {
+                Object::String(bytes, _) => bytes.len(),
+                Object::Stream(_) => cached_stream(doc, streams, lookup)?.len(),
+                _ => return None,
+            };
+            let expected = (hival as usize + 1) * components;
+            (lookup_len < expected).then(|| {
+                (
+                    ColorspaceAnomalyKind::UnusualIndexedHival,
+                    format!(
+                        "/{} is Indexed with hival {} but a {}-byte lookup table (expected {})",
+                        name, hival, lookup_len, expected
+                    ),
+                )
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Recognises exponential functions with all-zero output bounds and
+/// PostScript functions that only pop their input and push zeros.
+fn is_constant_zero_function(doc: &Document, streams: &StreamCache, function: &Object) -> bool {
+    let dict = match resolve(doc, function) {
+        Some(Object::Dictionary(dict)) => dict,
+        Some(Object::Stream(stream)) => &stream.dict,
+        _ => return false,
+    };
+
+    match dict.get(b"FunctionType").and_then(|t| t.as_i64()) {
+        Ok(2) => [b"C0".as_slice(), b"C1".as_slice()].iter().all(|key| {
+            dict.get(key)
+                .and_then(|values| values.as_array())
+                .is_ok_and(|values| values.iter().all(is_zero))
+        }),
+        Ok(4) => {
+            let Some(program) = cached_stream(doc, streams, function) else {
+                return false;
+            };
+            let program = String::from_utf8_lossy(program);
+            let tokens: Vec<&str> = program
+                .split(|c: char| c.is_whitespace() || c == '{' || c == '}')
+                .filter(|token| !token.is_empty())
+                .collect();
+            tokens.contains(&"pop")
+                && tokens
+                    .iter()
+                    .all(|token| *token == "pop" || token.parse::<f64>() == Ok(0.0))
+        }
+        _ => false,
+    }
+}
+
+fn is_zero(value: &Object) -> bool {
+    match value {
+        Object::Integer(value) => *value == 0,
+        Object::Real(value) => *value == 0.0,
+        _ => false,
+    }
+}
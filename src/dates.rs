@@ -0,0 +1,105 @@
+use crate::encoding::normalize_pdf_string;
+use crate::filters::StreamCache;
+use crate::pdfa::{xmp_metadata, xmp_property};
+use crate::{info_dictionary, resolve};
+use chrono::{DateTime, FixedOffset, TimeZone};
+use lopdf::Document;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize)]
+pub struct DateMismatch {
+    pub info_date: String,
+    pub xmp_date: String,
+    /// XMP date minus Info date, so positive when XMP is later.
+    pub delta_days: i64,
+}
+
+/// Compares `/Info /CreationDate` with XMP's `xmp:CreateDate`. Tools that
+/// write metadata keep the two in step, so dates that disagree by days or
+/// years point at a document edited by hand or by a crafting tool. Returns
+/// `None` when either date is missing or unparseable, or when they agree to
+/// the day.
+pub fn detect_metadata_date_mismatch(
+    doc: &Document,
+    streams: &StreamCache,
+) -> Option<DateMismatch> {
+    let info_date = info_dictionary(doc)?.get(b"CreationDate").ok()?;
+    let info_date = normalize_pdf_string(resolve(doc, info_date)?.as_str().ok()?);
+    let xmp_date = xmp_property(&xmp_metadata(doc, streams)?, "xmp:CreateDate")?;
+
+    let delta_days = parse_iso_date(&xmp_date)?
+        .signed_duration_since(parse_pdf_date(&info_date)?)
+        .num_days();
+    if delta_days == 0 {
+        return None;
+    }
+
+    Some(DateMismatch {
+        info_date,
+        xmp_date,
+        delta_days,
+    })
+}
+
+/// Parses `D:YYYYMMDDHHmmSSOHH'mm'`. Everything after the year is optional,
+/// and so is the `D:` prefix, which many writers leave out.
+pub(crate) fn parse_pdf_date(date: &str) -> Option<DateTime<FixedOffset>> {
+    let re = Regex::new(
+        r"^(?:D:)?(\d{4})(\d{2})?(\d{2})?(\d{2})?(\d{2})?(\d{2})?(?:([+\-Z])(?:(\d{2})'?(?:(\d{2})'?)?)?)?",
+    )
+    .ok()?;
+    let captures = re.captures(date.trim())?;
+    let field = |index: usize| captures.get(index).map(|m| m.as_str());
+    to_datetime(
+        [field(1), field(2), field(3), field(4), field(5), field(6)],
+        field(7),
+        field(8),
+        field(9),
+    )
+}
+
+/// Parses the ISO 8601 subset XMP uses: `YYYY[-MM[-DD[Thh:mm[:ss[.s]]]]]`
+/// followed by an optional `Z` or `+hh:mm`.
+pub(crate) fn parse_iso_date(date: &str) -> Option<DateTime<FixedOffset>> {
+    let re = Regex::new(
+        r"^(\d{4})(?:-(\d{2})(?:-(\d{2})(?:T(\d{2}):(\d{2})(?::(\d{2})(?:\.\d+)?)?)?)?)?(?:([+\-Z])(?:(\d{2}):(\d{2}))?)?",
+    )
+    .ok()?;
+    let captures = re.captures(date.trim())?;
+    let field = |index: usize| captures.get(index).map(|m| m.as_str());
+    to_datetime(
+        [field(1), field(2), field(3), field(4), field(5), field(6)],
+        field(7),
+        field(8),
+        field(9),
+    )
+}
+
+/// Missing month and day default to 1, missing time fields and offsets to 0.
+fn to_datetime(
+    fields: [Option<&str>; 6],
+    offset_sign: Option<&str>,
+    offset_hours: Option<&str>,
+    offset_minutes: Option<&str>,
+) -> Option<DateTime<FixedOffset>> {
+    let [year, month, day, hour, minute, second] =
+        fields.map(|field| field.map_or(Some(0), |value| value.parse::<u32>().ok()));
+    let offset = offset_hours.map_or(Some(0), |h| h.parse::<i32>().ok())? * 3600
+        + offset_minutes.map_or(Some(0), |m| m.parse::<i32>().ok())? * 60;
+    let offset = match offset_sign {
+        Some("-") => -offset,
+        _ => offset,
+    };
+
+    FixedOffset::east_opt(offset)?
+        .with_ymd_and_hms(
+            year? as i32,
+            month?.max(1),
+            day?.max(1),
+            hour?,
+            minute?,
+            second?,
+        )
+        .single()
+}
@@ -0,0 +1,341 @@
+use regex::Regex;
+use std::iter::Peekable;
+use std::str::CharIndices;
+use std::sync::OnceLock;
+
+/// Rounds of rewriting before giving up on reaching a fixed point, so that
+/// nested `unescape(unescape(...))` chains unroll without a hostile script
+/// keeping the loop busy.
+const MAX_PASSES: usize = 8;
+
+enum Token {
+    /// Script text outside string literals.
+    Code(String),
+    /// A string literal's value, with its source text while it is unchanged.
+    Str { value: String, raw: Option<String> },
+}
+
+fn from_char_code() -> &'static Regex {
+    static FROM_CHAR_CODE: OnceLock<Regex> = OnceLock::new();
+    FROM_CHAR_CODE.get_or_init(|| {
+        Regex::new(
+            r"String\s*\.\s*fromCharCode\s*\(\s*((?:0[xX][0-9a-fA-F]+|\d+)(?:\s*,\s*(?:0[xX][0-9a-fA-F]+|\d+))*)\s*\)",
+        )
+        .unwrap()
+    })
+}
+
+fn unescape_call() -> &'static Regex {
+    static UNESCAPE_CALL: OnceLock<Regex> = OnceLock::new();
+    UNESCAPE_CALL.get_or_init(|| Regex::new(r"\bunescape\s*\(\s*$").unwrap())
+}
+
+fn call_end() -> &'static Regex {
+    static CALL_END: OnceLock<Regex> = OnceLock::new();
+    CALL_END.get_or_init(|| Regex::new(r"^\s*\)").unwrap())
+}
+
+fn concat_operator() -> &'static Regex {
+    static CONCAT_OPERATOR: OnceLock<Regex> = OnceLock::new();
+    CONCAT_OPERATOR.get_or_init(|| Regex::new(r"^\s*\+\s*$").unwrap())
+}
+
+/// Undoes the cheap obfuscation scripts use to keep `eval` and friends away
+/// from pattern matching: `\x65` and `\u0065` escapes, `"ev" + "al"`
+/// concatenation, `String.fromCharCode(101, 118, ...)` and `unescape("%65...")`,
+/// repeated until nothing more changes. Only literals are rewritten, never
+/// evaluated, so the result is for matching, not running. `None` when the
+/// script had nothing to undo.
+pub fn deobfuscate_js(source: &str) -> Option<String> {
+    let mut tokens = tokenize(source);
+    for _ in 0..MAX_PASSES {
+        let mut changed = decode_from_char_code(&mut tokens);
+        changed |= unroll_unescape(&mut tokens);
+        changed |= join_literals(&mut tokens);
+        if !changed {
+            break;
+        }
+    }
+
+    let decoded = render(&tokens);
+    (decoded != source).then_some(decoded)
+}
+
+/// Splits `source` into string literals and the code between them. Comments
+/// stay code, so quotes in them don't start strings. Identifier escapes in
+/// code, such as `\u0065val`, are decoded on the way.
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut code = String::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '"' | '\'' => {
+                let quote = c;
+                let mut value = String::new();
+                let mut escaped = false;
+                let mut end = source.len();
+                let mut unterminated = false;
+                while let Some((index, c)) = chars.next() {
+                    match c {
+                        '\\' => {
+                            let (decoded, obfuscating) = read_escape(&mut chars);
+                            value.extend(decoded);
+                            escaped |= obfuscating;
+                        }
+                        '\n' => {
+                            end = index;
+                            unterminated = true;
+                            break;
+                        }
+                        _ if c == quote => {
+                            end = index + 1;
+                            break;
+                        }
+                        _ => value.push(c),
+                    }
+                }
+                if !code.is_empty() {
+                    tokens.push(Token::Code(std::mem::take(&mut code)));
+                }
+                let raw = (!escaped).then(|| source[start..end].to_string());
+                tokens.push(Token::Str { value, raw });
+                if unterminated {
+                    code.push('\n');
+                }
+            }
+            '/' if matches!(chars.peek(), Some((_, '/'))) => {
+                code.push(c);
+                while let Some((_, c)) = chars.next_if(|(_, c)| *c != '\n') {
+                    code.push(c);
+                }
+            }
+            '/' if matches!(chars.peek(), Some((_, '*'))) => {
+                code.push(c);
+                let mut previous = ' ';
+                for (_, c) in chars.by_ref() {
+                    code.push(c);
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            '\\' if matches!(chars.peek(), Some((_, 'u'))) => {
+                let (decoded, _) = read_escape(&mut chars);
+                code.extend(decoded);
+            }
+            _ => code.push(c),
+        }
+    }
+    if !code.is_empty() {
+        tokens.push(Token::Code(code));
+    }
+    tokens
+}
+
+/// Reads the escape after a backslash. The flag is whether it was a hex or
+/// Unicode escape, which plain text has no need for.
+fn read_escape(chars: &mut Peekable<CharIndices>) -> (Option<char>, bool) {
+    let Some((_, c)) = chars.next() else {
+        return (Some('\\'), false);
+    };
+    match c {
+        'x' => (read_hex(chars, 2).and_then(char::from_u32), true),
+        'u' if matches!(chars.peek(), Some((_, '{'))) => {
+            chars.next();
+            let mut value = 0u32;
+            while let Some((_, digit)) = chars.next_if(|(_, c)| c.is_ascii_hexdigit()) {
+                value = value
+                    .saturating_mul(16)
+                    .saturating_add(digit.to_digit(16).unwrap_or(0));
+            }
+            chars.next_if(|(_, c)| *c == '}');
+            (char::from_u32(value), true)
+        }
+        'u' => (
+            Some(
+                read_hex(chars, 4).map_or(char::REPLACEMENT_CHARACTER, |unit| {
+                    char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER)
+                }),
+            ),
+            true,
+        ),
+        'n' => (Some('\n'), false),
+        'r' => (Some('\r'), false),
+        't' => (Some('\t'), false),
+        'b' => (Some('\u{8}'), false),
+        'f' => (Some('\u{c}'), false),
+        'v' => (Some('\u{b}'), false),
+        '0' => (Some('\0'), false),
+        // A line continuation
+        '\n' => (None, false),
+        _ => (Some(c), false),
+    }
+}
+
+fn read_hex(chars: &mut Peekable<CharIndices>, digits: usize) -> Option<u32> {
+    let mut value = 0;
+    for _ in 0..digits {
+        let (_, digit) = chars.next_if(|(_, c)| c.is_ascii_hexdigit())?;
+        value = value * 16 + digit.to_digit(16)?;
+    }
+    Some(value)
+}
+
+/// Replaces `String.fromCharCode(...)` calls with all-literal arguments by
+/// the string they build.
+fn decode_from_char_code(tokens: &mut Vec<Token>) -> bool {
+    let mut changed = false;
+    let mut rewritten = Vec::with_capacity(tokens.len());
+    for token in tokens.drain(..) {
+        let Token::Code(code) = token else {
+            rewritten.push(token);
+            continue;
+        };
+        let mut rest = 0;
+        for call in from_char_code().captures_iter(&code) {
+            let (Some(whole), Some(args)) = (call.get(0), call.get(1)) else {
+                continue;
+            };
+            let value: String = args
+                .as_str()
+                .split(',')
+                .map(|arg| parse_char_code(arg.trim()))
+                .collect();
+            rewritten.push(Token::Code(code[rest..whole.start()].to_string()));
+            rewritten.push(Token::Str { value, raw: None });
+            rest = whole.end();
+            changed = true;
+        }
+        rewritten.push(Token::Code(code[rest..].to_string()));
+    }
+    tokens.extend(
+        rewritten
+            .into_iter()
+            .filter(|token| !matches!(token, Token::Code(code) if code.is_empty())),
+    );
+    changed
+}
+
+fn parse_char_code(arg: &str) -> char {
+    let code = match arg.strip_prefix("0x").or_else(|| arg.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => arg.parse(),
+    };
+    // fromCharCode takes UTF-16 code units
+    code.ok()
+        .and_then(|code| char::from_u32(code & 0xFFFF))
+        .unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+/// Replaces `unescape("...")` of a single literal by the decoded literal.
+fn unroll_unescape(tokens: &mut [Token]) -> bool {
+    let mut changed = false;
+    let mut i = 1;
+    while i + 1 < tokens.len() {
+        let call = match (&tokens[i - 1], &tokens[i], &tokens[i + 1]) {
+            (Token::Code(before), Token::Str { value, .. }, Token::Code(after)) => {
+                match (unescape_call().find(before), call_end().find(after)) {
+                    (Some(open), Some(close)) => Some((open.start(), close.end(), unescape(value))),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        if let Some((open, close, value)) = call {
+            if let Token::Code(before) = &mut tokens[i - 1] {
+                before.truncate(open);
+            }
+            if let Token::Code(after) = &mut tokens[i + 1] {
+                after.drain(..close);
+            }
+            tokens[i] = Token::Str { value, raw: None };
+            changed = true;
+        }
+        i += 1;
+    }
+    changed
+}
+
+/// JavaScript's `unescape`: `%XX` and `%uXXXX` become the characters they
+/// name, anything else stays as it is.
+fn unescape(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(percent) = rest.find('%') {
+        decoded.push_str(&rest[..percent]);
+        rest = &rest[percent..];
+        let (digits, skip) = match rest.as_bytes().get(1) {
+            Some(b'u') => (rest.get(2..6), 6),
+            _ => (rest.get(1..3), 3),
+        };
+        match digits
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+        {
+            Some(unit) => {
+                decoded.push(char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER));
+                rest = &rest[skip..];
+            }
+            None => {
+                decoded.push('%');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Joins literals that are only separated by `+`.
+fn join_literals(tokens: &mut Vec<Token>) -> bool {
+    let mut changed = false;
+    let mut i = 0;
+    while i + 2 < tokens.len() {
+        let joinable = matches!(
+            (&tokens[i], &tokens[i + 1], &tokens[i + 2]),
+            (Token::Str { .. }, Token::Code(operator), Token::Str { .. })
+                if concat_operator().is_match(operator)
+        );
+        if !joinable {
+            i += 1;
+            continue;
+        }
+        let right = tokens.remove(i + 2);
+        tokens.remove(i + 1);
+        if let (Token::Str { value, raw }, Token::Str { value: tail, .. }) = (&mut tokens[i], right)
+        {
+            value.push_str(&tail);
+            *raw = None;
+        }
+        changed = true;
+    }
+    changed
+}
+
+fn render(tokens: &[Token]) -> String {
+    let mut rendered = String::new();
+    for token in tokens {
+        match token {
+            Token::Code(code) => rendered.push_str(code),
+            Token::Str { raw: Some(raw), .. } => rendered.push_str(raw),
+            Token::Str { value, raw: None } => {
+                rendered.push('"');
+                for c in value.chars() {
+                    match c {
+                        '"' | '\\' => {
+                            rendered.push('\\');
+                            rendered.push(c);
+                        }
+                        '\n' => rendered.push_str("\\n"),
+                        _ => rendered.push(c),
+                    }
+                }
+                rendered.push('"');
+            }
+        }
+    }
+    rendered
+}
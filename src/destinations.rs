@@ -0,0 +1,134 @@
+use crate::encoding::normalize_pdf_string;
+use crate::resolve;
+use lopdf::{Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+
+const MAX_NAME_TREE_DEPTH: usize = 32;
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct NamedDestinationAnalysis {
+    pub total_count: usize,
+    pub sequential_integer_names: usize,
+    pub suspicious_offsets: Vec<u64>,
+}
+
+impl NamedDestinationAnalysis {
+    pub fn is_suspicious(&self) -> bool {
+        self.sequential_integer_names > 100 || !self.suspicious_offsets.is_empty()
+    }
+}
+
+/// Collects named destinations from both the PDF 1.1 `/Dests` dictionary and the
+/// `/Names /Dests` name tree, then looks for long runs of consecutive integer
+/// names and coordinates that look like heap spray addresses.
+pub fn analyze_named_destinations(doc: &Document) -> NamedDestinationAnalysis {
+    let mut destinations: Vec<(String, &Object)> = Vec::new();
+
+    if let Ok(catalog) = doc.catalog() {
+        if let Some(Object::Dictionary(dests)) =
+            catalog.get(b"Dests").ok().and_then(|d| resolve(doc, d))
+        {
+            for (name, value) in dests.iter() {
+                destinations.push((String::from_utf8_lossy(name).into_owned(), value));
+            }
+        }
+
+        let tree_root = catalog
+            .get(b"Names")
+            .ok()
+            .and_then(|names| resolve(doc, names))
+            .and_then(|names| names.as_dict().ok())
+            .and_then(|names| names.get(b"Dests").ok());
+        if let Some(root) = tree_root {
+            let mut visited = HashSet::new();
+            collect_name_tree(doc, root, 0, &mut visited, &mut destinations);
+        }
+    }
+
+    let mut analysis = NamedDestinationAnalysis {
+        total_count: destinations.len(),
+        ..Default::default()
+    };
+
+    let integer_names: BTreeSet<i64> = destinations
+        .iter()
+        .filter_map(|(name, _)| name.trim().parse().ok())
+        .collect();
+    analysis.sequential_integer_names = integer_names
+        .iter()
+        .filter(|&&n| {
+            integer_names.contains(&n.wrapping_sub(1)) || integer_names.contains(&n.wrapping_add(1))
+        })
+        .count();
+
+    let mut offsets = BTreeSet::new();
+    for (_, destination) in &destinations {
+        collect_suspicious_offsets(doc, destination, &mut offsets);
+    }
+    analysis.suspicious_offsets = offsets.into_iter().collect();
+
+    analysis
+}
+
+fn collect_name_tree<'a>(
+    doc: &'a Document,
+    node: &'a Object,
+    depth: usize,
+    visited: &mut HashSet<ObjectId>,
+    destinations: &mut Vec<(String, &'a Object)>,
+) {
+    if depth > MAX_NAME_TREE_DEPTH {
+        tracing::warn!(depth, "name tree nested too deeply, not descending further");
+        return;
+    }
+    if let Object::Reference(id) = node {
+        if !visited.insert(*id) {
+            return;
+        }
+    }
+    let Some(Object::Dictionary(node)) = resolve(doc, node) else {
+        return;
+    };
+
+    if let Ok(names) = node.get(b"Names").and_then(|names| names.as_array()) {
+        for pair in names.chunks(2) {
+            if let [Object::String(name, _), value] = pair {
+                destinations.push((normalize_pdf_string(name), value));
+            }
+        }
+    }
+
+    if let Ok(kids) = node.get(b"Kids").and_then(|kids| kids.as_array()) {
+        for kid in kids {
+            collect_name_tree(doc, kid, depth + 1, visited, destinations);
+        }
+    }
+}
+
+fn collect_suspicious_offsets(doc: &Document, destination: &Object, offsets: &mut BTreeSet<u64>) {
+    let destination = match resolve(doc, destination) {
+        Some(Object::Dictionary(dict)) => dict.get(b"D").ok().and_then(|d| resolve(doc, d)),
+        other => other,
+    };
+    let Some(Object::Array(items)) = destination else {
+        return;
+    };
+
+    for item in items {
+        let value = match item {
+            Object::Integer(value) => *value as f64,
+            Object::Real(value) => *value as f64,
+            _ => continue,
+        };
+        if value >= 0.0 && value <= u32::MAX as f64 && is_spray_address(value as u32) {
+            offsets.insert(value as u64);
+        }
+    }
+}
+
+/// Spray targets such as 0x0c0c0c0c repeat one non-zero byte in every position.
+fn is_spray_address(value: u32) -> bool {
+    let bytes = value.to_le_bytes();
+    bytes[0] != 0 && bytes.iter().all(|&b| b == bytes[0])
+}
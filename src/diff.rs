@@ -0,0 +1,477 @@
+use crate::encoding::{decode_name, get_name_key};
+use crate::AnalysisResult;
+use lopdf::{Document, Object};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Action types of the PDF specification. Other dictionaries have an `/S`
+/// entry too, such as structure elements and transparency groups.
+const ACTION_TYPES: [&str; 19] = [
+    "GoTo",
+    "GoToR",
+    "GoToE",
+    "GoTo3DView",
+    "Launch",
+    "Thread",
+    "URI",
+    "Sound",
+    "Movie",
+    "Hide",
+    "Named",
+    "SubmitForm",
+    "ResetForm",
+    "ImportData",
+    "JavaScript",
+    "SetOCGState",
+    "Rendition",
+    "Trans",
+    "RichMediaExecute",
+];
+
+#[derive(Serialize)]
+pub struct ResultDiff {
+    pub added_findings: Vec<String>,
+    pub removed_findings: Vec<String>,
+    pub severity_delta: i32,
+    /// What each kind of finding added to the score before and after, for
+    /// the kinds whose share changed.
+    pub score_changes: Vec<ScoreChange>,
+    pub new_javascript_objects: Vec<u32>,
+    pub removed_javascript_objects: Vec<u32>,
+    /// How the objects themselves differ, when both documents parse.
+    pub objects: Option<ObjectDiff>,
+}
+
+#[derive(Serialize)]
+pub struct ScoreChange {
+    /// The name of the weight, as in `ScoreContribution::weight`.
+    pub weight: String,
+    pub before: u32,
+    pub after: u32,
+}
+
+/// Objects are matched by number, as an incremental update or a tool that
+/// strips objects out in place keeps them.
+#[derive(Default, Serialize)]
+pub struct ObjectDiff {
+    /// Objects only in the second document, streams included.
+    pub added_objects: Vec<u32>,
+    /// Objects only in the first document, streams included.
+    pub removed_objects: Vec<u32>,
+    /// Objects other than streams whose value changed.
+    pub modified_objects: Vec<u32>,
+    pub added_streams: Vec<u32>,
+    pub removed_streams: Vec<u32>,
+    /// Streams whose dictionary or encoded content changed.
+    pub modified_streams: Vec<u32>,
+    pub new_actions: Vec<DocumentAction>,
+    pub removed_actions: Vec<DocumentAction>,
+}
+
+/// An action dictionary, in the object that holds it or is it.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct DocumentAction {
+    pub object_id: u32,
+    /// The action's `/S` type, e.g. `JavaScript`.
+    pub action: String,
+}
+
+/// Compares two analyses finding by finding, e.g. to see what a packer added
+/// when a sample is re-submitted. Findings are matched on their description,
+/// which includes the object number where there is one.
+pub fn diff_results(before: &AnalysisResult, after: &AnalysisResult) -> ResultDiff {
+    let before_findings = findings(before);
+    let after_findings = findings(after);
+    let before_js: BTreeSet<u32> = before.javascript_objects.iter().map(|js| js.id).collect();
+    let after_js: BTreeSet<u32> = after.javascript_objects.iter().map(|js| js.id).collect();
+
+    ResultDiff {
+        added_findings: after_findings
+            .difference(&before_findings)
+            .cloned()
+            .collect(),
+        removed_findings: before_findings
+            .difference(&after_findings)
+            .cloned()
+            .collect(),
+        // Known-malicious results score u32::MAX, which doesn't fit an i32
+        severity_delta: (after.severity_score as i64 - before.severity_score as i64)
+            .clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+        score_changes: score_changes(before, after),
+        new_javascript_objects: after_js.difference(&before_js).copied().collect(),
+        removed_javascript_objects: before_js.difference(&after_js).copied().collect(),
+        objects: None,
+    }
+}
+
+/// Compares two documents object by object, e.g. to check that a sanitizer
+/// or a vendor's patch really removed the active content. Streams are
+/// compared as stored, so one that was only compressed differently counts
+/// as modified.
+pub fn diff_documents(before: &Document, after: &Document) -> ObjectDiff {
+    let before_objects = objects_by_number(before);
+    let after_objects = objects_by_number(after);
+    let mut diff = ObjectDiff::default();
+    for (&id, object) in &after_objects {
+        match before_objects.get(&id) {
+            None => {
+                diff.added_objects.push(id);
+                if matches!(object, Object::Stream(_)) {
+                    diff.added_streams.push(id);
+                }
+            }
+            Some(old) => match (old, object) {
+                (Object::Stream(old), Object::Stream(new)) => {
+                    let dict_changed = format!("{:?}", old.dict) != format!("{:?}", new.dict);
+                    if dict_changed || old.content != new.content {
+                        diff.modified_streams.push(id);
+                    }
+                }
+                // An object that stopped or started being a stream
+                (Object::Stream(_), _) => diff.removed_streams.push(id),
+                (_, Object::Stream(_)) => diff.added_streams.push(id),
+                (old, new) => {
+                    if format!("{:?}", old) != format!("{:?}", new) {
+                        diff.modified_objects.push(id);
+                    }
+                }
+            },
+        }
+    }
+    for (&id, object) in &before_objects {
+        if !after_objects.contains_key(&id) {
+            diff.removed_objects.push(id);
+            if matches!(object, Object::Stream(_)) {
+                diff.removed_streams.push(id);
+            }
+        }
+    }
+
+    let before_actions = actions(&before_objects);
+    let after_actions = actions(&after_objects);
+    diff.new_actions = after_actions.difference(&before_actions).cloned().collect();
+    diff.removed_actions = before_actions.difference(&after_actions).cloned().collect();
+    diff
+}
+
+/// A document's objects by number. Of the generations of one number, the
+/// last is kept.
+fn objects_by_number(doc: &Document) -> BTreeMap<u32, &Object> {
+    doc.objects
+        .iter()
+        .map(|(&(number, _), object)| (number, object))
+        .collect()
+}
+
+fn actions(objects: &BTreeMap<u32, &Object>) -> BTreeSet<DocumentAction> {
+    let mut actions = BTreeSet::new();
+    for (&id, object) in objects {
+        collect_actions(id, object, &mut actions);
+    }
+    actions
+}
+
+/// Finds action dictionaries in `object` and the direct objects inside it,
+/// such as an `/OpenAction` written into the catalog.
+fn collect_actions(object_id: u32, object: &Object, actions: &mut BTreeSet<DocumentAction>) {
+    let dict = match object {
+        Object::Dictionary(dict) => dict,
+        Object::Stream(stream) => &stream.dict,
+        Object::Array(items) => {
+            for item in items {
+                collect_actions(object_id, item, actions);
+            }
+            return;
+        }
+        _ => return,
+    };
+    if let Some(Object::Name(name)) = get_name_key(dict, b"S") {
+        let name = String::from_utf8_lossy(&decode_name(name)).into_owned();
+        if ACTION_TYPES.contains(&name.as_str()) {
+            actions.insert(DocumentAction {
+                object_id,
+                action: name,
+            });
+        }
+    }
+    for (_, value) in dict.iter() {
+        collect_actions(object_id, value, actions);
+    }
+}
+
+/// The kinds of finding whose share of the score differs, in the order of
+/// the second analysis's explanation and then the first's.
+fn score_changes(before: &AnalysisResult, after: &AnalysisResult) -> Vec<ScoreChange> {
+    let points = |result: &AnalysisResult, weight: &str| {
+        result
+            .explanation
+            .contributions
+            .iter()
+            .find(|contribution| contribution.weight == weight)
+            .map_or(0, |contribution| contribution.points)
+    };
+    let mut weights: Vec<&str> = Vec::new();
+    for contribution in after
+        .explanation
+        .contributions
+        .iter()
+        .chain(&before.explanation.contributions)
+    {
+        if !weights.contains(&contribution.weight.as_str()) {
+            weights.push(&contribution.weight);
+        }
+    }
+    weights
+        .into_iter()
+        .map(|weight| ScoreChange {
+            weight: weight.to_string(),
+            before: points(before, weight),
+            after: points(after, weight),
+        })
+        .filter(|change| change.before != change.after)
+        .collect()
+}
+
+fn findings(result: &AnalysisResult) -> BTreeSet<String> {
+    let mut findings = BTreeSet::new();
+    let flags = [
+        (result.known_malicious, "known-malicious hash"),
+        (result.has_javascript, "JavaScript"),
+        (result.has_auto_action, "automatic action"),
+        (result.has_obj_stm, "object streams"),
+        (result.hidden_content, "hidden content"),
+        (result.large_file_size, "large file size"),
+        (result.suspicious_metadata, "suspicious metadata"),
+        (result.unknown_creator, "unknown creator tool"),
+        (
+            result.named_destinations.is_suspicious(),
+            "suspicious named destinations",
+        ),
+    ];
+    for (present, finding) in flags {
+        if present {
+            findings.insert(finding.to_string());
+        }
+    }
+
+    if result.metadata_date_mismatch.is_some() {
+        findings.insert("Info and XMP creation dates disagree".to_string());
+    }
+    for anomaly in &result.metadata_anomalies {
+        findings.insert(anomaly.detail.clone());
+    }
+    if let Some(creator) = &result.malicious_creator {
+        findings.insert(format!("built with {}", creator));
+    }
+    for name in &result.suspicious_names {
+        findings.insert(format!("suspicious name {}", name));
+    }
+    for found in &result.suspicious_stream_content {
+        findings.insert(format!(
+            "suspicious content {:?} in object {}",
+            found.evidence, found.object_id
+        ));
+    }
+    for object in &result.unusual_objects {
+        findings.insert(format!("unusual object type {}", object));
+    }
+    for uri in &result.uris {
+        findings.insert(format!("URI {}", uri));
+    }
+    for url in &result.urls {
+        for indicator in &url.indicators {
+            findings.insert(format!("URI {} with {}", url.url, indicator.description()));
+        }
+    }
+    for id in &result.launch_actions {
+        findings.insert(format!("Launch action in object {}", id));
+    }
+    for file in &result.embedded_files {
+        findings.insert(format!("embedded file in object {}", file.object_id));
+    }
+    for annotation in &result.media {
+        findings.insert(format!(
+            "{} in object {}",
+            annotation.kind.description(),
+            annotation.object_id
+        ));
+        for payload in &annotation.payloads {
+            if let Some(sha256) = &payload.sha256 {
+                findings.insert(format!("media payload {}", sha256));
+            }
+        }
+    }
+    for action in &result.external_actions {
+        findings.insert(match &action.target {
+            Some(target) => format!(
+                "{} action in object {} targeting {}",
+                action.kind.name(),
+                action.object_id,
+                target
+            ),
+            None => format!(
+                "{} action in object {}",
+                action.kind.name(),
+                action.object_id
+            ),
+        });
+    }
+    for js in &result.javascript_objects {
+        for api in &js.dangerous_apis {
+            findings.insert(format!("{} call in object {}", api, js.id));
+        }
+        for hidden in &js.hidden_matches {
+            findings.insert(format!("{:?} hidden in object {}", hidden, js.id));
+        }
+        for call in &js.ast_findings.acrobat_apis {
+            findings.insert(format!("{} call in object {}", call.api.name(), js.id));
+        }
+    }
+    for action in &result.form_field_actions {
+        findings.insert(format!(
+            "/{} action on form field {}",
+            action.trigger, action.field_name
+        ));
+    }
+    if let Some(xfa) = &result.xfa {
+        findings.insert("XFA form".to_string());
+        for submit in &xfa.submit_targets {
+            findings.insert(format!("XFA submit to {}", submit.target));
+        }
+    }
+    for candidate in &result.split_js_candidates {
+        findings.insert(format!(
+            "split JavaScript in object {}",
+            candidate.parent_object_id
+        ));
+    }
+    for (id, _) in &result.shellcode_hints {
+        findings.insert(format!("shellcode in object {}", id));
+    }
+    for candidate in &result.heap_spray_candidates {
+        findings.insert(format!(
+            "heap spray array in object {}",
+            candidate.object_id
+        ));
+    }
+    for hint in &result.compression_bomb_hints {
+        findings.insert(format!("compression bomb in object {}", hint.object_id));
+    }
+    for stream in &result.high_entropy_streams {
+        findings.insert(format!(
+            "high-entropy stream in object {}",
+            stream.object_id
+        ));
+    }
+    for signature in &result.embedded_signatures {
+        findings.insert(format!(
+            "{} in object {}",
+            signature.kind.description(),
+            signature.object_id
+        ));
+    }
+    for pdf in &result.embedded_pdfs {
+        findings.insert(format!(
+            "PDF in object {} classified {}",
+            pdf.object_id,
+            pdf.result.classification.label()
+        ));
+    }
+    for (id, _) in &result.steganography_hints {
+        findings.insert(format!("LSB steganography in object {}", id));
+    }
+    for anomaly in &result.xref_anomalies {
+        findings.insert(format!(
+            "xref offset mismatch for object {}",
+            anomaly.object_id
+        ));
+    }
+    for shadowed in &result.revisions.shadowed_objects {
+        findings.insert(format!(
+            "object {} redefined in revisions {:?}",
+            shadowed.object_id, shadowed.revisions
+        ));
+    }
+    for anomaly in &result.framing.anomalies {
+        findings.insert(format!(
+            "{} ({} bytes)",
+            anomaly.kind.description(),
+            anomaly.length
+        ));
+    }
+    for polyglot in &result.framing.polyglots {
+        findings.insert(format!("also {}", polyglot.format.description()));
+    }
+    if result.parse_error.is_some() {
+        findings.insert("parse failure".to_string());
+    }
+    if let Some(encryption) = &result.encryption {
+        findings.insert(format!(
+            "encrypted with {}",
+            encryption
+                .algorithm
+                .as_deref()
+                .unwrap_or("an unknown algorithm")
+        ));
+        if encryption.raw_scan_only {
+            findings.insert("encrypted and not decrypted".to_string());
+        }
+    }
+    let consistency = &result.xref_consistency;
+    if let Some(mismatch) = &consistency.size_mismatch {
+        findings.insert(format!(
+            "trailer /Size {:?} instead of {}",
+            mismatch.declared, mismatch.expected
+        ));
+    }
+    for id in &consistency.missing_objects {
+        findings.insert(format!("xref entry for missing object {}", id));
+    }
+    for id in &consistency.unlisted_objects {
+        findings.insert(format!("object {} outside the xref", id));
+    }
+    for name in &result.escaped_names {
+        findings.insert(format!("escaped name {} for {}", name.raw, name.decoded));
+    }
+    for violation in &result.structure_violations {
+        findings.insert(format!(
+            "{} violation: {}",
+            violation.rule, violation.detail
+        ));
+    }
+    for anomaly in &result.colorspace_anomalies {
+        findings.insert(format!(
+            "colorspace anomaly on page {}: {}",
+            anomaly.page_id, anomaly.detail
+        ));
+    }
+    for finding in &result.ratio_findings {
+        findings.insert(format!("high {}", finding.ratio_name));
+    }
+    for cycle in &result.reference_cycles {
+        findings.insert(format!("reference cycle through {:?}", cycle));
+    }
+    for finding in &result.rule_findings {
+        findings.insert(format!("{}: {}", finding.rule, finding.description));
+    }
+    for found in &result.yara_matches {
+        findings.insert(format!(
+            "YARA rule {}:{} in object {}",
+            found.namespace, found.rule, found.object_id
+        ));
+    }
+    for lookup in result
+        .virustotal
+        .iter()
+        .filter(|lookup| lookup.is_detected())
+    {
+        findings.insert(format!("VirusTotal detection of {}", lookup.sha256));
+    }
+    if let Some(report) = &result.pdfa_conformance {
+        for violation in &report.violations {
+            findings.insert(format!("{} violation: {}", report.claimed_level, violation));
+        }
+    }
+
+    findings
+}
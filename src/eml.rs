@@ -0,0 +1,359 @@
+//! PDF attachments of RFC 822 email messages, for `--eml`. Multipart bodies
+//! and forwarded messages (`message/rfc822` parts) are walked down to
+//! [`MAX_DEPTH`] levels, and base64 and quoted-printable bodies are decoded.
+
+use crate::batch::has_pdf_marker;
+
+/// Deepest nesting of multiparts and forwarded messages that is walked; a
+/// message nested deeper than any mail client would produce is hiding
+/// something, and its inner parts are left out.
+const MAX_DEPTH: usize = 32;
+
+pub struct Attachment {
+    /// From the `filename` of Content-Disposition, else the `name` of
+    /// Content-Type.
+    pub filename: Option<String>,
+    /// Without the angle brackets.
+    pub content_id: Option<String>,
+    /// The decoded body.
+    pub data: Vec<u8>,
+}
+
+impl Attachment {
+    /// The name the attachment's result is reported under: the message's
+    /// name followed by the attachment's file name, its content id, or for
+    /// an attachment with neither its position among the PDFs, from 1.
+    pub fn report_name(&self, message: &str, position: usize) -> String {
+        match (&self.filename, &self.content_id) {
+            (Some(filename), Some(content_id)) => {
+                format!("{}:{} <{}>", message, filename, content_id)
+            }
+            (Some(filename), None) => format!("{}:{}", message, filename),
+            (None, Some(content_id)) => format!("{}:<{}>", message, content_id),
+            (None, None) => format!("{}:attachment {}", message, position),
+        }
+    }
+}
+
+/// The PDF parts of `message`, in the order they appear. A part counts as a
+/// PDF when it is declared `application/pdf`, its file name ends in `.pdf`,
+/// or, unless it is text, its decoded body starts with a PDF header.
+pub fn pdf_attachments(message: &[u8]) -> Vec<Attachment> {
+    let mut attachments = Vec::new();
+    collect(message, None, 0, &mut attachments);
+    attachments
+}
+
+fn collect(entity: &[u8], default_type: Option<&str>, depth: usize, out: &mut Vec<Attachment>) {
+    if depth > MAX_DEPTH {
+        tracing::warn!("MIME parts nested deeper than {} levels skipped", MAX_DEPTH);
+        return;
+    }
+    let (headers, body) = split_entity(entity);
+    let content_type = header(&headers, "Content-Type");
+    let mime = content_type
+        .as_deref()
+        .map(|value| value.split(';').next().unwrap_or_default().trim())
+        .filter(|mime| !mime.is_empty())
+        .or(default_type)
+        .unwrap_or("text/plain")
+        .to_ascii_lowercase();
+
+    if mime.starts_with("multipart/") {
+        let Some(boundary) = content_type
+            .as_deref()
+            .and_then(|value| parameter(value, "boundary"))
+        else {
+            return;
+        };
+        // Parts of a multipart/digest are forwarded messages by default
+        let default_type = (mime == "multipart/digest").then_some("message/rfc822");
+        for part in multipart_parts(body, &boundary) {
+            collect(part, default_type, depth + 1, out);
+        }
+        return;
+    }
+
+    let encoding = header(&headers, "Content-Transfer-Encoding").unwrap_or_default();
+    let data = decode_body(body, &encoding);
+    if mime == "message/rfc822" {
+        collect(&data, None, depth + 1, out);
+        return;
+    }
+
+    let disposition = header(&headers, "Content-Disposition");
+    let filename = disposition
+        .as_deref()
+        .and_then(|value| parameter(value, "filename"))
+        .or_else(|| {
+            content_type
+                .as_deref()
+                .and_then(|value| parameter(value, "name"))
+        });
+    let is_pdf = matches!(mime.as_str(), "application/pdf" | "application/x-pdf")
+        || filename
+            .as_deref()
+            .is_some_and(|name| name.to_ascii_lowercase().ends_with(".pdf"))
+        || (!mime.starts_with("text/") && has_pdf_marker(&data));
+    if !is_pdf {
+        return;
+    }
+    let content_id = header(&headers, "Content-ID").map(|id| {
+        id.trim()
+            .trim_start_matches('<')
+            .trim_end_matches('>')
+            .to_string()
+    });
+    out.push(Attachment {
+        filename,
+        content_id: content_id.filter(|id| !id.is_empty()),
+        data,
+    });
+}
+
+/// Splits an entity at the blank line after its headers, unfolding headers
+/// continued on indented lines. An entity without a blank line is all
+/// headers.
+fn split_entity(entity: &[u8]) -> (Vec<(String, String)>, &[u8]) {
+    let mut headers: Vec<(String, String)> = Vec::new();
+    let mut rest = entity;
+    while !rest.is_empty() {
+        let end = find(rest, b"\n").map_or(rest.len(), |end| end + 1);
+        let line = String::from_utf8_lossy(&rest[..end]);
+        rest = &rest[end..];
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    (headers, rest)
+}
+
+fn header(headers: &[(String, String)], name: &str) -> Option<String> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.clone())
+}
+
+/// A parameter of a structured header value such as `attachment;
+/// filename="a.pdf"`, with RFC 2231 (`filename*=utf-8''a%20b.pdf`) and RFC
+/// 2047 (`=?utf-8?B?...?=`) encodings undone.
+fn parameter(value: &str, name: &str) -> Option<String> {
+    let mut plain = None;
+    let mut extended = None;
+    for param in split_parameters(value).into_iter().skip(1) {
+        let Some((key, value)) = param.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+        if key == name {
+            plain = Some(decode_words(unquote(value)));
+        } else if key == format!("{}*", name) || key == format!("{}*0*", name) {
+            // charset'language'percent-encoded text
+            let text = value.splitn(3, '\'').nth(2).unwrap_or(value);
+            extended = Some(percent_decode(unquote(text)));
+        }
+    }
+    extended.or(plain).filter(|value| !value.is_empty())
+}
+
+/// Splits at the semicolons outside quoted strings.
+fn split_parameters(value: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (index, c) in value.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                params.push(&value[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    params.push(&value[start..]);
+    params
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = bytes.get(i + 1..i + 3).and_then(hex_byte) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Undoes RFC 2047 encoded words, taking every charset as UTF-8; a word that
+/// isn't well formed is kept as it is.
+fn decode_words(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    let mut after_word = false;
+    while let Some(start) = rest.find("=?") {
+        let Some((decoded, length)) = encoded_word(&rest[start..]) else {
+            out.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            after_word = false;
+            continue;
+        };
+        // Whitespace between two encoded words is dropped
+        let before = &rest[..start];
+        if !(after_word && before.trim().is_empty()) {
+            out.push_str(before);
+        }
+        out.push_str(&decoded);
+        rest = &rest[start + length..];
+        after_word = true;
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Decodes the `=?charset?encoding?text?=` word `text` starts with, and says
+/// how long it was.
+fn encoded_word(text: &str) -> Option<(String, usize)> {
+    let mut fields = text[2..].splitn(3, '?');
+    let charset = fields.next()?;
+    let encoding = fields.next()?;
+    let tail = fields.next()?;
+    let end = tail.find("?=")?;
+    let bytes = match encoding {
+        "B" | "b" => decode_base64(&tail.as_bytes()[..end]),
+        "Q" | "q" => decode_quoted_printable(tail[..end].replace('_', " ").as_bytes()),
+        _ => return None,
+    };
+    let length = 2 + charset.len() + 1 + encoding.len() + 1 + end + 2;
+    Some((String::from_utf8_lossy(&bytes).into_owned(), length))
+}
+
+/// The parts between the `--boundary` lines of a multipart body, up to the
+/// closing `--boundary--` or the end of the body if it is missing.
+fn multipart_parts<'b>(body: &'b [u8], boundary: &str) -> Vec<&'b [u8]> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let mut parts = Vec::new();
+    let mut start = None;
+    let mut offset = 0;
+    while offset < body.len() {
+        let end = find(&body[offset..], b"\n").map_or(body.len(), |end| offset + end + 1);
+        let line = &body[offset..end];
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if let Some(suffix) = line.strip_prefix(&delimiter[..]) {
+            if let Some(start) = start {
+                // The line break before a delimiter belongs to it
+                let part = &body[start..offset];
+                let part = part.strip_suffix(b"\n").unwrap_or(part);
+                parts.push(part.strip_suffix(b"\r").unwrap_or(part));
+            }
+            if suffix.starts_with(b"--") {
+                return parts;
+            }
+            start = Some(end);
+        }
+        offset = end;
+    }
+    if let Some(start) = start {
+        parts.push(&body[start..]);
+    }
+    parts
+}
+
+fn decode_body(body: &[u8], encoding: &str) -> Vec<u8> {
+    match encoding.trim().to_ascii_lowercase().as_str() {
+        "base64" => decode_base64(body),
+        "quoted-printable" => decode_quoted_printable(body),
+        // 7bit, 8bit and binary bodies are as they are
+        _ => body.to_vec(),
+    }
+}
+
+/// Skips anything outside the base64 alphabet, line breaks included, and
+/// stops at the padding.
+fn decode_base64(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() / 4 * 3);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &byte in data {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            _ => continue,
+        };
+        buffer = buffer << 6 | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    out
+}
+
+fn decode_quoted_printable(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        if data[i] == b'=' {
+            let rest = &data[i + 1..];
+            // An = at the end of a line is a soft line break
+            if rest.starts_with(b"\r\n") {
+                i += 3;
+                continue;
+            }
+            if rest.starts_with(b"\n") {
+                i += 2;
+                continue;
+            }
+            if let Some(byte) = rest.get(..2).and_then(hex_byte) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(data[i]);
+        i += 1;
+    }
+    out
+}
+
+fn hex_byte(hex: &[u8]) -> Option<u8> {
+    u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
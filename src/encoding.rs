@@ -0,0 +1,138 @@
+use lopdf::{Dictionary, Object};
+use std::borrow::Cow;
+
+/// PDFDocEncoding code points for 0x18..=0x1F, where it departs from Latin-1.
+const PDF_DOC_LOW: [char; 8] = [
+    '\u{02D8}', '\u{02C7}', '\u{02C6}', '\u{02D9}', '\u{02DD}', '\u{02DB}', '\u{02DA}', '\u{02DC}',
+];
+
+/// PDFDocEncoding code points for 0x80..=0xA0. 0x9F is undefined.
+const PDF_DOC_HIGH: [char; 33] = [
+    '\u{2022}', '\u{2020}', '\u{2021}', '\u{2026}', '\u{2014}', '\u{2013}', '\u{0192}', '\u{2044}',
+    '\u{2039}', '\u{203A}', '\u{2212}', '\u{2030}', '\u{201E}', '\u{201C}', '\u{201D}', '\u{2018}',
+    '\u{2019}', '\u{201A}', '\u{2122}', '\u{FB01}', '\u{FB02}', '\u{0141}', '\u{0152}', '\u{0160}',
+    '\u{0178}', '\u{017D}', '\u{0131}', '\u{0142}', '\u{0153}', '\u{0161}', '\u{017E}', '\u{FFFD}',
+    '\u{20AC}',
+];
+
+/// Decodes a raw PDF string into UTF-8 so that pattern matching sees the same
+/// text a viewer would. Strings starting with a UTF-16BE byte order mark are
+/// transcoded, a UTF-8 BOM (PDF 2.0) is stripped, and everything else is read
+/// as PDFDocEncoding. Only for string objects and names: stream content, such
+/// as a script, is not in PDFDocEncoding and is read as UTF-8.
+pub fn normalize_pdf_string(raw: &[u8]) -> String {
+    if let Some(utf16) = raw.strip_prefix(b"\xfe\xff") {
+        let units = utf16
+            .chunks(2)
+            .map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]));
+        return char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect();
+    }
+
+    if let Some(utf8) = raw.strip_prefix(b"\xef\xbb\xbf") {
+        return String::from_utf8_lossy(utf8).into_owned();
+    }
+
+    raw.iter().map(|&byte| pdf_doc_char(byte)).collect()
+}
+
+fn pdf_doc_char(byte: u8) -> char {
+    match byte {
+        0x18..=0x1F => PDF_DOC_LOW[(byte - 0x18) as usize],
+        0x80..=0xA0 => PDF_DOC_HIGH[(byte - 0x80) as usize],
+        0xAD => char::REPLACEMENT_CHARACTER,
+        _ => byte as char,
+    }
+}
+
+/// Decodes the `#xx` escapes in a name, so that `J#61vaScript` reads as
+/// `JavaScript`. Escapes that aren't two hex digits are left as they are.
+pub fn decode_name(name: &[u8]) -> Cow<'_, [u8]> {
+    if !name.contains(&b'#') {
+        return Cow::Borrowed(name);
+    }
+    let mut decoded = Vec::with_capacity(name.len());
+    let mut i = 0;
+    while i < name.len() {
+        let escape = name
+            .get(i + 1..i + 3)
+            .filter(|_| name[i] == b'#')
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok());
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(name[i]);
+                i += 1;
+            }
+        }
+    }
+    Cow::Owned(decoded)
+}
+
+/// Whether `dict` has `key`, however its name is escaped.
+pub fn has_name_key(dict: &Dictionary, key: &[u8]) -> bool {
+    get_name_key(dict, key).is_some()
+}
+
+/// `dict`'s entry for `key`, however its name is escaped.
+pub fn get_name_key<'a>(dict: &'a Dictionary, key: &[u8]) -> Option<&'a Object> {
+    dict.get(key).ok().or_else(|| {
+        dict.iter()
+            .find(|(name, _)| *decode_name(name) == *key)
+            .map(|(_, value)| value)
+    })
+}
+
+/// A byte regex matching `/name` with any of its characters written as `#xx`
+/// escapes, for scanning raw files where names are not decoded.
+pub fn name_pattern(name: &str) -> String {
+    let mut pattern = String::from("/");
+    for c in name.chars() {
+        pattern.push_str(&format!(
+            "(?:{}|#(?i:{:02x}))",
+            regex::escape(&c.to_string()),
+            c as u32
+        ));
+    }
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `text` as a PDF string in UTF-16BE with its byte order mark.
+    fn utf16be(text: &str) -> Vec<u8> {
+        let mut raw = b"\xfe\xff".to_vec();
+        raw.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+        raw
+    }
+
+    #[test]
+    fn utf16be_eval_is_decoded() {
+        assert_eq!(normalize_pdf_string(&utf16be("eval")), "eval");
+    }
+
+    #[test]
+    fn utf16be_javascript_is_decoded() {
+        assert_eq!(normalize_pdf_string(&utf16be("JavaScript")), "JavaScript");
+    }
+
+    #[test]
+    fn pdf_doc_encoding_departs_from_latin1() {
+        assert_eq!(
+            normalize_pdf_string(b"\x93\x80 caf\xe9"),
+            "\u{FB01}\u{2022} café"
+        );
+    }
+
+    #[test]
+    fn utf8_byte_order_mark_is_stripped() {
+        assert_eq!(normalize_pdf_string("\u{FEFF}café".as_bytes()), "café");
+    }
+}
@@ -0,0 +1,153 @@
+use crate::resolve;
+use lopdf::{Dictionary, Document, Object};
+use serde::{Deserialize, Serialize};
+
+/// Which password opened an encrypted document.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum PasswordUsed {
+    /// The empty user password, which most encrypted PDFs have: they open
+    /// without prompting and are encrypted only to restrict permissions, or
+    /// to keep scanners out.
+    Empty,
+    /// The password given with `--password`.
+    Supplied,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct EncryptionInfo {
+    /// The security handler, normally `Standard`.
+    pub filter: Option<String>,
+    /// `/V`, the algorithm version.
+    pub version: Option<i64>,
+    /// `/R`, the standard security handler's revision.
+    pub revision: Option<i64>,
+    /// Key length in bits.
+    pub key_length: Option<i64>,
+    /// RC4 or AES with its key length, when the dictionary says.
+    pub algorithm: Option<String>,
+    /// `/P`, the permission flags.
+    pub permissions: Option<i64>,
+    pub decrypted: bool,
+    pub password: Option<PasswordUsed>,
+    /// Decryption failed or the document didn't load, so only the raw
+    /// streaming scan ran: object structure and action names are still seen,
+    /// but strings and stream content are ciphertext.
+    pub raw_scan_only: bool,
+}
+
+/// Reads the trailer's `/Encrypt` dictionary, if the document has one.
+pub fn read_encryption(doc: &Document) -> Option<EncryptionInfo> {
+    let encrypt = doc.trailer.get(b"Encrypt").ok()?;
+    let Some(dict) = resolve(doc, encrypt).and_then(|encrypt| encrypt.as_dict().ok()) else {
+        return Some(EncryptionInfo::default());
+    };
+
+    let integer = |key: &[u8]| dict.get(key).ok().and_then(|value| value.as_i64().ok());
+    let version = integer(b"V");
+    let key_length = integer(b"Length").or(match version {
+        Some(1) | None => Some(40),
+        Some(5) => Some(256),
+        _ => None,
+    });
+    Some(EncryptionInfo {
+        filter: dict
+            .get(b"Filter")
+            .and_then(Object::as_name)
+            .ok()
+            .map(|filter| String::from_utf8_lossy(filter).into_owned()),
+        version,
+        revision: integer(b"R"),
+        key_length,
+        algorithm: algorithm(dict, version, key_length),
+        permissions: integer(b"P"),
+        ..Default::default()
+    })
+}
+
+/// Versions 1 to 3 are RC4 throughout. Version 4 names its cipher in the
+/// crypt filter that strings and streams use, and version 5 is AES-256.
+fn algorithm(dict: &Dictionary, version: Option<i64>, key_length: Option<i64>) -> Option<String> {
+    match version? {
+        1..=3 => Some(format!("RC4-{}", key_length.unwrap_or(40))),
+        4 => {
+            let filter_name = dict.get(b"StmF").and_then(Object::as_name).ok()?;
+            let method = dict
+                .get(b"CF")
+                .and_then(Object::as_dict)
+                .and_then(|filters| filters.get(filter_name))
+                .and_then(Object::as_dict)
+                .and_then(|filter| filter.get(b"CFM"))
+                .and_then(Object::as_name)
+                .ok()?;
+            match method {
+                b"V2" => Some(format!("RC4-{}", key_length.unwrap_or(128))),
+                b"AESV2" => Some("AES-128".to_string()),
+                _ => None,
+            }
+        }
+        5 => Some("AES-256".to_string()),
+        _ => None,
+    }
+}
+
+/// Decrypts `doc` in place if it is encrypted, trying `password` when one is
+/// given and then the empty user password. `None` for documents that aren't
+/// encrypted.
+pub fn unlock(doc: &mut Document, password: Option<&str>) -> Option<EncryptionInfo> {
+    let mut info = read_encryption(doc)?;
+    if !has_complete_keys(doc, info.revision) {
+        tracing::warn!("/O or /U is truncated, falling back to a raw scan");
+        info.raw_scan_only = true;
+        return Some(info);
+    }
+    let attempts = password
+        .map(|password| (password, PasswordUsed::Supplied))
+        .into_iter()
+        .chain([("", PasswordUsed::Empty)]);
+    for (password, used) in attempts {
+        match doc.decrypt(password) {
+            Ok(()) => {
+                info.decrypted = true;
+                info.password = Some(used);
+                return Some(info);
+            }
+            Err(err) => tracing::debug!(password = ?used, error = %err, "decryption failed"),
+        }
+    }
+    tracing::warn!("document is encrypted and no password opened it, falling back to a raw scan");
+    info.raw_scan_only = true;
+    Some(info)
+}
+
+/// Whether `/O` and `/U` are as long as revision `revision` makes them,
+/// which lopdf assumes and panics on when they aren't.
+fn has_complete_keys(doc: &Document, revision: Option<i64>) -> bool {
+    let Some(dict) = doc
+        .trailer
+        .get(b"Encrypt")
+        .ok()
+        .and_then(|encrypt| resolve(doc, encrypt))
+        .and_then(|encrypt| encrypt.as_dict().ok())
+    else {
+        return false;
+    };
+    let length = if revision.is_some_and(|revision| revision >= 5) {
+        48
+    } else {
+        32
+    };
+    [b"O", b"U"].iter().all(|key| {
+        dict.get(*key)
+            .ok()
+            .and_then(|value| resolve(doc, value))
+            .and_then(|value| value.as_str().ok())
+            .is_some_and(|value| value.len() >= length)
+    })
+}
+
+/// Whether the raw file references an encryption dictionary, for files lopdf
+/// fails to load at all.
+pub fn declares_encryption(raw: &[u8]) -> bool {
+    raw.windows(b"/Encrypt".len())
+        .any(|window| window == b"/Encrypt")
+}
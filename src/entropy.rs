@@ -0,0 +1,117 @@
+use crate::filters::StreamCache;
+use crate::CompiledConfig;
+use lopdf::{Document, Object, Stream};
+use serde::{Deserialize, Serialize};
+
+/// Streams shorter than this can't reach a high entropy however random they
+/// are (at most log2 of their length), so they are left out.
+const MIN_ENTROPY_CHECKED_SIZE: usize = 256;
+
+/// Filters whose output is still compressed image data, which is expected to
+/// look random.
+const IMAGE_FILTERS: [&[u8]; 6] = [
+    b"DCTDecode",
+    b"DCT",
+    b"JPXDecode",
+    b"JBIG2Decode",
+    b"CCITTFaxDecode",
+    b"CCF",
+];
+
+/// Streams counted by the whole bits per byte of their entropy: index 0 holds
+/// those from 0 up to 1 bit, index 7 those from 7 up to and including 8.
+pub type EntropyHistogram = [usize; 8];
+
+#[derive(Deserialize, Serialize)]
+pub struct HighEntropyStream {
+    pub object_id: u32,
+    /// Bits per byte of the decoded content, or of the raw content when the
+    /// stream has no filters.
+    pub entropy: f64,
+    /// Bits per byte of the content as stored in the file.
+    pub raw_entropy: f64,
+    pub size: usize,
+}
+
+/// Shannon entropy of `data` in bits per byte, from 0.0 for a single repeated
+/// byte to 8.0 for uniformly random data.
+pub fn shannon_entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0u64; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    let total = data.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Measures every stream, decoded where `cache` has it, into a histogram and
+/// flags those above `Config::max_stream_entropy`. Encrypted or packed
+/// payloads look random, but so do JPEGs and other compressed images, which
+/// are skipped: a random-looking stream that doesn't declare itself an image
+/// is what stands out.
+pub fn check_stream_entropy(
+    doc: &Document,
+    cache: &StreamCache,
+    config: &CompiledConfig,
+) -> (EntropyHistogram, Vec<HighEntropyStream>) {
+    let mut histogram = EntropyHistogram::default();
+    let mut high_entropy = Vec::new();
+
+    for (id, object) in doc.objects.iter() {
+        let Ok(stream) = object.as_stream() else {
+            continue;
+        };
+        let content = match cache.get(id.0) {
+            Some(decoded) => decoded,
+            // Filtered streams that didn't decode say nothing about their content
+            None if stream.dict.has(b"Filter") => continue,
+            None => &stream.content[..],
+        };
+        let entropy = shannon_entropy(content);
+        histogram[(entropy as usize).min(histogram.len() - 1)] += 1;
+
+        if content.len() >= MIN_ENTROPY_CHECKED_SIZE
+            && entropy > config.config.max_stream_entropy
+            && !is_image(stream)
+        {
+            high_entropy.push(HighEntropyStream {
+                object_id: id.0,
+                entropy,
+                raw_entropy: shannon_entropy(&stream.content),
+                size: content.len(),
+            });
+        }
+    }
+
+    high_entropy.sort_by_key(|stream| stream.object_id);
+    (histogram, high_entropy)
+}
+
+/// Whether the stream declares itself an image, by its subtype or by an image
+/// filter.
+fn is_image(stream: &Stream) -> bool {
+    let subtype = stream
+        .dict
+        .get(b"Subtype")
+        .and_then(Object::as_name)
+        .is_ok_and(|subtype| subtype == b"Image");
+    let image_filter = match stream.dict.get(b"Filter") {
+        Ok(Object::Name(name)) => IMAGE_FILTERS.contains(&name.as_slice()),
+        Ok(Object::Array(names)) => names
+            .iter()
+            .filter_map(|name| name.as_name().ok())
+            .any(|name| IMAGE_FILTERS.contains(&name)),
+        _ => false,
+    };
+    subtype || image_filter
+}
@@ -0,0 +1,48 @@
+use std::any::Any;
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SentinelError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to parse PDF: {0}")]
+    Pdf(#[from] lopdf::Error),
+    #[error("invalid pattern in config: {0}")]
+    Pattern(#[from] regex::Error),
+    #[error("failed to serialize result: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The config file's path and what is wrong with it.
+    #[error("invalid config file {}: {}", .0.display(), .1)]
+    Config(PathBuf, String),
+    #[error("failed to watch directory: {0}")]
+    Watch(#[from] notify::Error),
+    #[error("failed to load rules library: {0}")]
+    Plugin(#[from] libloading::Error),
+    /// The revision asked for and how many the document has.
+    #[error("no revision {0}; the document has {1}")]
+    Revision(usize, usize),
+    /// The message of a panic caught during analysis.
+    #[error("analysis panicked: {0}")]
+    Panic(String),
+    #[cfg(feature = "tokio")]
+    #[error("analysis task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+    #[cfg(feature = "yara")]
+    #[error("failed to load YARA rules: {0}")]
+    Yara(#[from] yara::Error),
+}
+
+impl SentinelError {
+    /// Wraps what `std::panic::catch_unwind` caught.
+    pub fn from_panic(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => "unknown panic".to_string(),
+            },
+        };
+        SentinelError::Panic(message)
+    }
+}
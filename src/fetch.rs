@@ -0,0 +1,96 @@
+//! Downloads of PDFs given as `http://` or `https://` URLs, behind the
+//! `fetch` feature. Without it the fetcher still exists, so inputs are
+//! handled the same way, but every download fails with an error saying the
+//! feature is needed.
+
+use pdf_sentinel::SentinelError;
+use std::io;
+#[cfg(feature = "fetch")]
+use std::io::Read;
+use std::time::Duration;
+
+/// Whether `input` names a remote document rather than a file.
+pub fn is_url(input: &str) -> bool {
+    let scheme = input.split_once("://").map(|(scheme, _)| scheme);
+    scheme.is_some_and(|scheme| {
+        scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")
+    })
+}
+
+pub struct Fetcher {
+    #[cfg(feature = "fetch")]
+    agent: ureq::Agent,
+    max_size: u64,
+}
+
+impl Fetcher {
+    /// Downloads larger than `max_size` bytes, or that take longer than
+    /// `timeout` in all, fail.
+    pub fn new(max_size: u64, timeout: Duration) -> Self {
+        #[cfg(not(feature = "fetch"))]
+        let _ = timeout;
+        Fetcher {
+            #[cfg(feature = "fetch")]
+            agent: ureq::AgentBuilder::new()
+                .timeout(timeout)
+                .user_agent(concat!(
+                    env!("CARGO_PKG_NAME"),
+                    "/",
+                    env!("CARGO_PKG_VERSION")
+                ))
+                .build(),
+            max_size,
+        }
+    }
+
+    /// The body of `url`, after any redirects.
+    #[cfg(feature = "fetch")]
+    pub fn fetch(&self, url: &str) -> Result<Vec<u8>, SentinelError> {
+        let response = match self.agent.get(url).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(status, _)) => {
+                return Err(io::Error::other(format!("{} answered HTTP {}", url, status)).into())
+            }
+            Err(ureq::Error::Transport(err)) => {
+                return Err(io::Error::other(err.to_string()).into())
+            }
+        };
+        let declared = response
+            .header("Content-Length")
+            .and_then(|length| length.parse::<u64>().ok());
+        if declared.is_some_and(|length| length > self.max_size) {
+            return Err(self.too_large(url));
+        }
+
+        // One byte past the limit tells a body that is too large from one
+        // that is exactly the limit
+        let mut data = Vec::new();
+        response
+            .into_reader()
+            .take(self.max_size + 1)
+            .read_to_end(&mut data)?;
+        if data.len() as u64 > self.max_size {
+            return Err(self.too_large(url));
+        }
+        Ok(data)
+    }
+
+    #[cfg(not(feature = "fetch"))]
+    pub fn fetch(&self, url: &str) -> Result<Vec<u8>, SentinelError> {
+        let _ = self.max_size;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("fetching {} needs the fetch feature", url),
+        )
+        .into())
+    }
+
+    #[cfg(feature = "fetch")]
+    fn too_large(&self, url: &str) -> SentinelError {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is larger than {} bytes", url, self.max_size),
+        )
+        .into()
+    }
+}
@@ -0,0 +1,389 @@
+use crate::error::SentinelError;
+use crate::CompiledConfig;
+use flate2::read::ZlibDecoder;
+use lopdf::{Document, Object, Stream};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+
+/// Decoded size past which `decode_stream` gives up, and the default of
+/// `Config::max_decompressed_stream_size`.
+pub const DEFAULT_MAX_STREAM_SIZE: u64 = 100 * 1024 * 1024;
+
+/// Output below this size is never held against `Config::max_compression_ratio`:
+/// small runs of one colour or of zeros legitimately compress very well.
+const MIN_RATIO_CHECKED_SIZE: u64 = 1024 * 1024;
+
+/// Which limit a stream ran into.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum BombLimit {
+    /// `Config::max_decompressed_stream_size`
+    StreamSize,
+    /// `Config::max_compression_ratio`
+    CompressionRatio,
+    /// `Config::max_total_decompressed_size`
+    DocumentBudget,
+}
+
+impl BombLimit {
+    pub fn description(&self) -> &'static str {
+        match self {
+            BombLimit::StreamSize => "stream size limit",
+            BombLimit::CompressionRatio => "compression ratio limit",
+            BombLimit::DocumentBudget => "document decompression budget",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CompressionBombHint {
+    pub object_id: u32,
+    pub compressed_size: u64,
+    pub bytes_before_abort: u64,
+    pub limit: BombLimit,
+}
+
+/// Returns the decoded content of a stream, or `None` when a filter is not
+/// supported, the data is corrupt or it decodes past
+/// `DEFAULT_MAX_STREAM_SIZE`. Unfiltered streams are returned as is.
+pub fn decode_stream(stream: &Stream) -> Option<Vec<u8>> {
+    decode_stream_bounded(stream, DEFAULT_MAX_STREAM_SIZE)?.ok()
+}
+
+/// Like `decode_stream`, but stops decompressing once the output reaches
+/// `limit` bytes. If there was more to come, the error holds how many bytes
+/// were produced before giving up. Filter chains are applied in order, and
+/// the limit applies to every stage.
+pub fn decode_stream_bounded(stream: &Stream, limit: u64) -> Option<Result<Vec<u8>, u64>> {
+    let filters: Vec<&[u8]> = match stream.dict.get(b"Filter") {
+        Err(_) => return Some(Ok(stream.content.clone())),
+        Ok(Object::Name(name)) => vec![name],
+        Ok(Object::Array(names)) => names
+            .iter()
+            .map(|name| name.as_name().ok())
+            .collect::<Option<_>>()?,
+        Ok(_) => return None,
+    };
+
+    let mut data = stream.content.clone();
+    for (index, filter) in filters.into_iter().enumerate() {
+        data = match filter {
+            b"FlateDecode" | b"Fl" => flate_decode(&data, limit)?,
+            b"ASCIIHexDecode" | b"AHx" => ascii_hex_decode(&data)?,
+            b"ASCII85Decode" | b"A85" => ascii85_decode(&data)?,
+            b"LZWDecode" | b"LZW" => lzw_decode(&data, early_change(stream, index), limit)?,
+            b"RunLengthDecode" | b"RL" => run_length_decode(&data, limit)?,
+            _ => return None,
+        };
+        if data.len() as u64 > limit {
+            return Some(Err(limit));
+        }
+    }
+    Some(Ok(data))
+}
+
+fn flate_decode(data: &[u8], limit: u64) -> Option<Vec<u8>> {
+    // One byte past the limit is enough for the caller to see it was reached
+    let mut decompressed = Vec::new();
+    ZlibDecoder::new(data)
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut decompressed)
+        .ok()?;
+    Some(decompressed)
+}
+
+fn ascii_hex_decode(data: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(data.len() / 2);
+    let mut high = None;
+    for &byte in data {
+        if byte == b'>' {
+            break;
+        }
+        if byte.is_ascii_whitespace() {
+            continue;
+        }
+        let digit = (byte as char).to_digit(16)? as u8;
+        match high.take() {
+            Some(high) => decoded.push(high << 4 | digit),
+            None => high = Some(digit),
+        }
+    }
+    // An odd final digit is followed by an implied 0
+    if let Some(high) = high {
+        decoded.push(high << 4);
+    }
+    Some(decoded)
+}
+
+fn ascii85_decode(data: &[u8]) -> Option<Vec<u8>> {
+    let data = data.strip_prefix(b"<~").unwrap_or(data);
+    let mut decoded = Vec::with_capacity(data.len() * 4 / 5);
+    let mut group = [0u8; 5];
+    let mut len = 0;
+
+    for &byte in data {
+        match byte {
+            b'~' => break,
+            b'z' if len == 0 => decoded.extend_from_slice(&[0; 4]),
+            b'!'..=b'u' => {
+                group[len] = byte - b'!';
+                len += 1;
+                if len == 5 {
+                    decoded.extend_from_slice(&ascii85_group(&group)?);
+                    len = 0;
+                }
+            }
+            _ if byte.is_ascii_whitespace() => {}
+            _ => return None,
+        }
+    }
+
+    // A final group of n characters is padded with 'u' and yields n - 1 bytes
+    match len {
+        0 => {}
+        1 => return None,
+        _ => {
+            group[len..].fill(b'u' - b'!');
+            decoded.extend_from_slice(&ascii85_group(&group)?[..len - 1]);
+        }
+    }
+    Some(decoded)
+}
+
+fn ascii85_group(group: &[u8; 5]) -> Option<[u8; 4]> {
+    let value = group.iter().try_fold(0u32, |value, &digit| {
+        value.checked_mul(85)?.checked_add(digit as u32)
+    })?;
+    Some(value.to_be_bytes())
+}
+
+/// `/EarlyChange` from the stream's decode parameters for filter `index`,
+/// which defaults to 1.
+fn early_change(stream: &Stream, index: usize) -> bool {
+    let params = match stream.dict.get(b"DecodeParms") {
+        Ok(Object::Array(params)) => params.get(index),
+        Ok(params) => Some(params),
+        Err(_) => None,
+    };
+    params
+        .and_then(|params| params.as_dict().ok())
+        .and_then(|params| params.get(b"EarlyChange").ok())
+        .and_then(|early_change| early_change.as_i64().ok())
+        .is_none_or(|early_change| early_change != 0)
+}
+
+const LZW_CLEAR_TABLE: usize = 256;
+const LZW_END_OF_DATA: usize = 257;
+const LZW_MAX_TABLE_SIZE: usize = 4096;
+
+fn lzw_decode(data: &[u8], early_change: bool, limit: u64) -> Option<Vec<u8>> {
+    let mut table: Vec<Vec<u8>> = (0..=255u8).map(|byte| vec![byte]).collect();
+    table.extend([Vec::new(), Vec::new()]);
+    let mut decoded = Vec::new();
+    let mut previous: Option<usize> = None;
+    let mut width = 9;
+    let mut bit_buffer = 0u32;
+    let mut bit_count = 0;
+    let mut bytes = data.iter();
+
+    loop {
+        while bit_count < width {
+            let Some(&byte) = bytes.next() else {
+                return Some(decoded);
+            };
+            bit_buffer = bit_buffer << 8 | byte as u32;
+            bit_count += 8;
+        }
+        let code = (bit_buffer >> (bit_count - width)) as usize & ((1 << width) - 1);
+        bit_count -= width;
+
+        match code {
+            LZW_CLEAR_TABLE => {
+                table.truncate(LZW_END_OF_DATA + 1);
+                width = 9;
+                previous = None;
+                continue;
+            }
+            LZW_END_OF_DATA => return Some(decoded),
+            _ => {}
+        }
+
+        let entry = if code < table.len() {
+            table[code].clone()
+        } else if code == table.len() {
+            // The code being defined by this very step: previous + its first byte
+            let previous = &table[previous?];
+            let mut entry = previous.clone();
+            entry.push(previous[0]);
+            entry
+        } else {
+            return None;
+        };
+        if let Some(previous) = previous {
+            if table.len() < LZW_MAX_TABLE_SIZE {
+                let mut new_entry = table[previous].clone();
+                new_entry.push(entry[0]);
+                table.push(new_entry);
+            }
+        }
+
+        decoded.extend_from_slice(&entry);
+        if decoded.len() as u64 > limit {
+            return Some(decoded);
+        }
+        previous = Some(code);
+
+        // With early change the width grows one code before the table needs it
+        let next_code = table.len() + early_change as usize;
+        width = match next_code {
+            0..=511 => 9,
+            512..=1023 => 10,
+            1024..=2047 => 11,
+            _ => 12,
+        };
+    }
+}
+
+fn run_length_decode(data: &[u8], limit: u64) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    let mut bytes = data.iter();
+    while let Some(&length) = bytes.next() {
+        match length {
+            128 => break,
+            0..=127 => {
+                let literal = bytes.as_slice().get(..length as usize + 1)?;
+                decoded.extend_from_slice(literal);
+                bytes = bytes.as_slice()[literal.len()..].iter();
+            }
+            _ => {
+                let &byte = bytes.next()?;
+                decoded.resize(decoded.len() + 257 - length as usize, byte);
+            }
+        }
+        if decoded.len() as u64 > limit {
+            break;
+        }
+    }
+    Some(decoded)
+}
+
+/// Decoded content of every stream in a document, keyed by object number, so
+/// that each stream is decompressed once per analysis however many checks
+/// read it. Streams `decode_stream` can't handle are left out, and so are
+/// streams that run into one of the decompression limits (`BombLimit`); those
+/// are recorded as compression bomb hints instead.
+pub struct StreamCache {
+    decoded: HashMap<u32, (Vec<u8>, u64)>,
+    compression_bombs: Vec<CompressionBombHint>,
+}
+
+impl StreamCache {
+    pub fn get(&self, id: u32) -> Option<&[u8]> {
+        self.decoded.get(&id).map(|(content, _)| content.as_slice())
+    }
+
+    /// Decoded streams by object number, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &[u8])> {
+        self.decoded
+            .iter()
+            .map(|(id, (content, _))| (*id, content.as_slice()))
+    }
+
+    pub fn decompressed_size(&self, id: u32) -> Option<u64> {
+        self.decoded.get(&id).map(|(_, size)| *size)
+    }
+
+    pub fn take_compression_bombs(&mut self) -> Vec<CompressionBombHint> {
+        std::mem::take(&mut self.compression_bombs)
+    }
+}
+
+pub fn populate_stream_cache(
+    doc: &Document,
+    config: &CompiledConfig,
+) -> Result<StreamCache, SentinelError> {
+    let mut cache = StreamCache {
+        decoded: HashMap::new(),
+        compression_bombs: Vec::new(),
+    };
+    let mut budget = config.config.max_total_decompressed_size;
+    let mut skipped = 0;
+
+    for (id, object) in doc.objects.iter() {
+        let Ok(stream) = object.as_stream() else {
+            continue;
+        };
+        if budget == 0 {
+            skipped += 1;
+            continue;
+        }
+        let compressed_size = stream.content.len() as u64;
+        let (limit, kind) =
+            stream_limit(stream.dict.has(b"Filter"), compressed_size, budget, config);
+        match decode_stream_bounded(stream, limit) {
+            Some(Ok(content)) => {
+                let size = content.len() as u64;
+                budget -= size;
+                cache.decoded.insert(id.0, (content, size));
+            }
+            Some(Err(bytes_before_abort)) => {
+                tracing::warn!(
+                    object_id = id.0,
+                    limit,
+                    "stream decompresses past the {}, skipping",
+                    kind.description()
+                );
+                if kind == BombLimit::DocumentBudget {
+                    budget = 0;
+                }
+                cache.compression_bombs.push(CompressionBombHint {
+                    object_id: id.0,
+                    compressed_size,
+                    bytes_before_abort,
+                    limit: kind,
+                });
+            }
+            None => tracing::debug!(
+                object_id = id.0,
+                filter = ?stream.dict.get(b"Filter").ok(),
+                "unsupported filter or corrupt data, skipping stream"
+            ),
+        }
+    }
+
+    if skipped > 0 {
+        tracing::warn!(
+            streams = skipped,
+            "decompression budget exhausted, streams left undecoded"
+        );
+    }
+    Ok(cache)
+}
+
+/// The tightest of the limits that apply to a stream of `compressed_size`
+/// bytes, and which one it is. The ratio only counts for `filtered` streams,
+/// and the document budget only when it is tighter than the others.
+pub(crate) fn stream_limit(
+    filtered: bool,
+    compressed_size: u64,
+    budget: u64,
+    config: &CompiledConfig,
+) -> (u64, BombLimit) {
+    let mut limit = (
+        config.config.max_decompressed_stream_size,
+        BombLimit::StreamSize,
+    );
+
+    // Unfiltered content is as large as it is, whatever the ratio
+    if filtered {
+        let ratio_limit = (compressed_size as f64 * config.config.max_compression_ratio) as u64;
+        let ratio_limit = ratio_limit.max(MIN_RATIO_CHECKED_SIZE);
+        if ratio_limit < limit.0 {
+            limit = (ratio_limit, BombLimit::CompressionRatio);
+        }
+    }
+    if budget < limit.0 {
+        limit = (budget, BombLimit::DocumentBudget);
+    }
+    limit
+}
@@ -0,0 +1,1098 @@
+//! Every detection as a list of findings, each naming the rule that made it,
+//! the object it concerns and, where there is one, what matched.
+//!
+//! The result fields hold the details of each check; findings are the one
+//! flat list that reports, SARIF and other consumers walk instead.
+
+use crate::actions::ActionKind;
+use crate::js_ast::AcrobatApi;
+use crate::metadata::MetadataAnomalyKind;
+use crate::patterns::PatternTarget;
+use crate::scoring::Severity;
+use crate::virustotal::VtSubject;
+use crate::{AnalysisResult, JsSource};
+use lopdf::xref::XrefEntry;
+use lopdf::Document;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::borrow::Cow;
+
+/// Evidence longer than this many characters is cut short.
+const MAX_EVIDENCE_LENGTH: usize = 200;
+
+/// A detection rule's fixed description. The ids are stable across releases,
+/// so that suppressions and dashboards keyed by them keep working.
+pub struct RuleInfo {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub severity: Severity,
+    pub description: &'static str,
+}
+
+pub const RULES: &[RuleInfo] = &[
+    RuleInfo {
+        id: "PS001",
+        name: "KnownMaliciousHash",
+        severity: Severity::Critical,
+        description: "The file's SHA-256 is on the known-bad list",
+    },
+    RuleInfo {
+        id: "PS002",
+        name: "JavaScript",
+        severity: Severity::Medium,
+        description: "The document contains JavaScript",
+    },
+    RuleInfo {
+        id: "PS003",
+        name: "OpenActionJavaScript",
+        severity: Severity::High,
+        description: "JavaScript runs when the document is opened",
+    },
+    RuleInfo {
+        id: "PS004",
+        name: "DangerousJavaScriptApi",
+        severity: Severity::High,
+        description: "A script calls an Acrobat API that drops, sends or opens files",
+    },
+    RuleInfo {
+        id: "PS005",
+        name: "AutomaticAction",
+        severity: Severity::Medium,
+        description: "The document has an /OpenAction or /AA entry",
+    },
+    RuleInfo {
+        id: "PS006",
+        name: "LaunchAction",
+        severity: Severity::High,
+        description: "A /Launch action starts an external program",
+    },
+    RuleInfo {
+        id: "PS007",
+        name: "FormFieldAction",
+        severity: Severity::Medium,
+        description: "A form field triggers an action",
+    },
+    RuleInfo {
+        id: "PS008",
+        name: "SplitJavaScript",
+        severity: Severity::Medium,
+        description: "Script fragments combine into suspicious JavaScript",
+    },
+    RuleInfo {
+        id: "PS009",
+        name: "SuspiciousName",
+        severity: Severity::Medium,
+        description: "A name or string matches a suspicious pattern",
+    },
+    RuleInfo {
+        id: "PS010",
+        name: "Shellcode",
+        severity: Severity::High,
+        description: "A stream looks like shellcode",
+    },
+    RuleInfo {
+        id: "PS011",
+        name: "HeapSpray",
+        severity: Severity::High,
+        description: "An array is dominated by a heap spray value",
+    },
+    RuleInfo {
+        id: "PS012",
+        name: "CompressionBomb",
+        severity: Severity::Medium,
+        description: "A stream inflates past a decompression limit",
+    },
+    RuleInfo {
+        id: "PS013",
+        name: "Steganography",
+        severity: Severity::Medium,
+        description: "An image's low bits look like hidden data",
+    },
+    RuleInfo {
+        id: "PS014",
+        name: "MaliciousCreator",
+        severity: Severity::High,
+        description: "The Creator or Producer is a known malware builder",
+    },
+    RuleInfo {
+        id: "PS015",
+        name: "UnknownCreator",
+        severity: Severity::Low,
+        description: "The Creator or Producer is not a known-safe tool",
+    },
+    RuleInfo {
+        id: "PS016",
+        name: "SuspiciousMetadata",
+        severity: Severity::Medium,
+        description: "A document metadata value matches a suspicious pattern",
+    },
+    RuleInfo {
+        id: "PS017",
+        name: "MetadataDateMismatch",
+        severity: Severity::Low,
+        description: "Info and XMP creation dates disagree",
+    },
+    RuleInfo {
+        id: "PS018",
+        name: "HiddenContent",
+        severity: Severity::Medium,
+        description: "The document contains hidden content",
+    },
+    RuleInfo {
+        id: "PS019",
+        name: "ObjectStreams",
+        severity: Severity::Low,
+        description: "Objects are packed into object streams",
+    },
+    RuleInfo {
+        id: "PS020",
+        name: "UnusualObject",
+        severity: Severity::Low,
+        description: "An object has an unusual type",
+    },
+    RuleInfo {
+        id: "PS021",
+        name: "Uri",
+        severity: Severity::Low,
+        description: "The document links to a URI",
+    },
+    RuleInfo {
+        id: "PS022",
+        name: "XrefAnomaly",
+        severity: Severity::Medium,
+        description: "An object is not where the cross-reference table says",
+    },
+    RuleInfo {
+        id: "PS023",
+        name: "StructureViolation",
+        severity: Severity::Low,
+        description: "The page tree breaks a specification invariant",
+    },
+    RuleInfo {
+        id: "PS024",
+        name: "SuspiciousNamedDestinations",
+        severity: Severity::Medium,
+        description: "Named destinations look like heap grooming",
+    },
+    RuleInfo {
+        id: "PS025",
+        name: "LargeFile",
+        severity: Severity::Low,
+        description: "The file is larger than the configured threshold",
+    },
+    RuleInfo {
+        id: "PS026",
+        name: "CustomRule",
+        severity: Severity::Medium,
+        description: "A rule loaded with --rules reported a finding",
+    },
+    RuleInfo {
+        id: "PS027",
+        name: "ExternalAction",
+        severity: Severity::Medium,
+        description: "An action opens another document or sends or loads form data",
+    },
+    RuleInfo {
+        id: "PS028",
+        name: "EmbeddedFile",
+        severity: Severity::Medium,
+        description: "The document carries an embedded file",
+    },
+    RuleInfo {
+        id: "PS029",
+        name: "YaraMatch",
+        severity: Severity::Medium,
+        description: "A YARA rule matched a decoded stream or script",
+    },
+    RuleInfo {
+        id: "PS030",
+        name: "SuspiciousStreamContent",
+        severity: Severity::Medium,
+        description: "A decoded stream matches a suspicious pattern",
+    },
+    RuleInfo {
+        id: "PS031",
+        name: "UtilPrintf",
+        severity: AcrobatApi::UtilPrintf.severity(),
+        description: "A script calls util.printf, the CVE-2008-2992 overflow",
+    },
+    RuleInfo {
+        id: "PS032",
+        name: "CollabCollectEmailInfo",
+        severity: AcrobatApi::CollabCollectEmailInfo.severity(),
+        description: "A script calls Collab.collectEmailInfo, the CVE-2007-5659 overflow",
+    },
+    RuleInfo {
+        id: "PS033",
+        name: "CollabGetIcon",
+        severity: AcrobatApi::CollabGetIcon.severity(),
+        description: "A script calls Collab.getIcon, the CVE-2009-0927 overflow",
+    },
+    RuleInfo {
+        id: "PS034",
+        name: "MediaNewPlayer",
+        severity: AcrobatApi::MediaNewPlayer.severity(),
+        description: "A script calls media.newPlayer, the CVE-2009-4324 use-after-free",
+    },
+    RuleInfo {
+        id: "PS035",
+        name: "ExportDataObject",
+        severity: AcrobatApi::ExportDataObject.severity(),
+        description: "A script writes an attachment to disk with exportDataObject",
+    },
+    RuleInfo {
+        id: "PS036",
+        name: "AppLaunchUrl",
+        severity: AcrobatApi::AppLaunchUrl.severity(),
+        description: "A script opens a web page with app.launchURL",
+    },
+    RuleInfo {
+        id: "PS037",
+        name: "UnescapeHeapSpray",
+        severity: AcrobatApi::UnescapeHeapSpray.severity(),
+        description: "A script unescapes %u shellcode and grows it in a loop",
+    },
+    RuleInfo {
+        id: "PS038",
+        name: "ObfuscatedSuspiciousContent",
+        severity: Severity::High,
+        description: "A script matches a suspicious pattern only once deobfuscated",
+    },
+    RuleInfo {
+        id: "PS039",
+        name: "HighEntropyStream",
+        severity: Severity::Medium,
+        description: "A non-image stream looks random, like a packed or encrypted payload",
+    },
+    RuleInfo {
+        id: "PS040",
+        name: "EscapedName",
+        severity: Severity::Medium,
+        description: "A name escapes a letter or digit as #xx to dodge byte matching",
+    },
+    RuleInfo {
+        id: "PS041",
+        name: "Encrypted",
+        severity: Severity::Medium,
+        description: "The document is encrypted, which hides its strings and streams",
+    },
+    RuleInfo {
+        id: "PS042",
+        name: "Unparseable",
+        severity: Severity::High,
+        description: "The document fails to parse, as samples often do to slip past scanners",
+    },
+    RuleInfo {
+        id: "PS043",
+        name: "DataBeforeHeader",
+        severity: Severity::Medium,
+        description: "Data precedes the %PDF header, as in polyglot files",
+    },
+    RuleInfo {
+        id: "PS044",
+        name: "DataAfterEof",
+        severity: Severity::Medium,
+        description: "Data follows the final %%EOF, where droppers append payloads",
+    },
+    RuleInfo {
+        id: "PS045",
+        name: "MissingFileMarker",
+        severity: Severity::Low,
+        description: "The %PDF header or %%EOF marker is missing",
+    },
+    RuleInfo {
+        id: "PS046",
+        name: "ShadowedObject",
+        severity: Severity::Low,
+        description: "An incremental update redefines an object, hiding its earlier version",
+    },
+    RuleInfo {
+        id: "PS047",
+        name: "ShadowedAction",
+        severity: Severity::High,
+        description: "An incremental update replaces the catalog or changes an object's actions",
+    },
+    RuleInfo {
+        id: "PS048",
+        name: "XrefSizeMismatch",
+        severity: Severity::Low,
+        description: "The trailer's /Size doesn't match the cross-reference data",
+    },
+    RuleInfo {
+        id: "PS049",
+        name: "XrefMissingObject",
+        severity: Severity::Medium,
+        description: "A cross-reference entry points at an object the file doesn't define",
+    },
+    RuleInfo {
+        id: "PS050",
+        name: "UnlistedObject",
+        severity: Severity::Medium,
+        description: "An object is defined in the file but missing from the cross-reference data",
+    },
+    RuleInfo {
+        id: "PS051",
+        name: "SuspiciousUrl",
+        severity: Severity::Medium,
+        description: "A link uses an IP host, lookalike domain, shortener or misleading text",
+    },
+    RuleInfo {
+        id: "PS052",
+        name: "XfaForm",
+        severity: Severity::Medium,
+        description: "The form is an XFA form, whose XML can hold scripts and submit actions",
+    },
+    RuleInfo {
+        id: "PS053",
+        name: "XfaScript",
+        severity: Severity::High,
+        description: "An XFA packet contains JavaScript",
+    },
+    RuleInfo {
+        id: "PS054",
+        name: "XfaSubmit",
+        severity: Severity::Medium,
+        description: "An XFA form submits its data to an external URL",
+    },
+    RuleInfo {
+        id: "PS055",
+        name: "MediaAnnotation",
+        severity: Severity::Medium,
+        description: "A RichMedia, 3D, Sound or Movie annotation or action plays embedded media",
+    },
+    RuleInfo {
+        id: "PS056",
+        name: "MediaPayload",
+        severity: Severity::High,
+        description: "Media embeds a Flash, U3D or PRC file, formats long used for viewer exploits",
+    },
+    RuleInfo {
+        id: "PS057",
+        name: "EmbeddedExecutable",
+        severity: Severity::High,
+        description: "A decoded stream contains a PE or ELF executable",
+    },
+    RuleInfo {
+        id: "PS058",
+        name: "EmbeddedArchive",
+        severity: Severity::Medium,
+        description: "A decoded stream contains a ZIP, RAR or OLE compound file",
+    },
+    RuleInfo {
+        id: "PS059",
+        name: "VirusTotalDetection",
+        severity: Severity::Critical,
+        description: "VirusTotal engines flag the file or one of its payloads as malicious",
+    },
+    RuleInfo {
+        id: "PS060",
+        name: "EmbeddedPdf",
+        severity: Severity::Medium,
+        description: "An attachment or stream holds another PDF, analyzed in turn",
+    },
+    RuleInfo {
+        id: "PS061",
+        name: "Polyglot",
+        severity: Severity::High,
+        description: "The file is also a valid ZIP, JAR or HTML file",
+    },
+    RuleInfo {
+        id: "PS062",
+        name: "ModifiedBeforeCreated",
+        severity: Severity::Low,
+        description: "The metadata says the document was modified before it was created",
+    },
+    RuleInfo {
+        id: "PS063",
+        name: "FutureMetadataDate",
+        severity: Severity::Low,
+        description: "A metadata date lies in the future",
+    },
+    RuleInfo {
+        id: "PS064",
+        name: "MissingProducer",
+        severity: Severity::Low,
+        description: "A document with active content names no Producer",
+    },
+];
+
+#[derive(Deserialize, Serialize)]
+pub struct Finding {
+    /// One of the ids in [`RULES`].
+    #[serde(deserialize_with = "deserialize_rule_id")]
+    pub rule_id: Cow<'static, str>,
+    /// What was found, e.g. "JavaScript in object 12".
+    pub title: String,
+    pub severity: Severity,
+    pub object_id: Option<u32>,
+    /// Byte offset of the object in the file, per the cross-reference table.
+    /// `None` for objects unpacked from object streams and for streaming
+    /// analysis.
+    pub offset: Option<u64>,
+    /// What matched: the script, name, pattern match or target concerned.
+    pub evidence: Option<String>,
+    /// For a match of `Config::suspicious_patterns`, the id of the rule.
+    pub pattern_id: Option<String>,
+}
+
+/// Looks up a rule by id.
+///
+/// # Panics
+///
+/// If `id` is not in [`RULES`], which would be a bug in this module.
+pub fn rule(id: &str) -> &'static RuleInfo {
+    RULES
+        .iter()
+        .find(|rule| rule.id == id)
+        .unwrap_or_else(|| panic!("unknown rule id {}", id))
+}
+
+/// Reads a rule id back as the one in [`RULES`], rejecting ids it doesn't
+/// have.
+fn deserialize_rule_id<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Cow<'static, str>, D::Error> {
+    let id = String::deserialize(deserializer)?;
+    RULES
+        .iter()
+        .find(|rule| rule.id == id)
+        .map(|rule| Cow::Borrowed(rule.id))
+        .ok_or_else(|| D::Error::custom(format!("unknown rule id {}", id)))
+}
+
+/// The ids of the rules behind `findings`, each once, in order.
+pub fn triggered_rules(findings: &[Finding]) -> Vec<&'static str> {
+    let mut rule_ids: Vec<&'static str> = findings
+        .iter()
+        .map(|finding| rule(&finding.rule_id).id)
+        .collect();
+    rule_ids.sort_unstable();
+    rule_ids.dedup();
+    rule_ids
+}
+
+/// Lists every detection in `result` as a finding. `doc`, when the result
+/// came from a parsed document, supplies the file offsets of the objects.
+pub fn collect_findings(result: &AnalysisResult, doc: Option<&Document>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let new_finding =
+        |rule_id: &'static str, title: String, object_id: Option<u32>, evidence: Option<String>| {
+            Finding {
+                rule_id: Cow::Borrowed(rule_id),
+                title,
+                severity: rule(rule_id).severity,
+                object_id,
+                offset: object_id.and_then(|id| doc.and_then(|doc| object_offset(doc, id))),
+                evidence: evidence.map(|evidence| truncate(&evidence)),
+                pattern_id: None,
+            }
+        };
+    let mut push = |rule_id, title, object_id, evidence| {
+        findings.push(new_finding(rule_id, title, object_id, evidence));
+    };
+
+    if result.known_malicious {
+        push(
+            "PS001",
+            "SHA-256 matches a known-malicious file".to_string(),
+            None,
+            None,
+        );
+    }
+    for js in &result.javascript_objects {
+        push(
+            "PS002",
+            format!("JavaScript in object {}", js.id),
+            Some(js.id),
+            Some(js.content.clone()),
+        );
+        for api in &js.dangerous_apis {
+            push(
+                "PS004",
+                format!("{} called in object {}", api, js.id),
+                Some(js.id),
+                Some(api.clone()),
+            );
+        }
+        for call in &js.ast_findings.acrobat_apis {
+            let name = call.api.name();
+            push(
+                call.api.rule_id(),
+                format!("{} called in object {}", name, js.id),
+                Some(js.id),
+                Some(name.to_string()),
+            );
+        }
+    }
+    if let Some(open_action) = &result.open_action_js {
+        let id = open_action.action_object_id;
+        push(
+            "PS003",
+            format!("JavaScript in object {} runs on open", id),
+            Some(id),
+            Some(open_action.js_content.clone()),
+        );
+    }
+    if result.has_auto_action {
+        push(
+            "PS005",
+            "Document has an automatic action".to_string(),
+            None,
+            None,
+        );
+    }
+    for action in &result.external_actions {
+        let rule = match action.kind {
+            ActionKind::Launch => "PS006",
+            _ => "PS027",
+        };
+        let message = match &action.target {
+            Some(target) => format!(
+                "{} action in object {} targets {}",
+                action.kind.name(),
+                action.object_id,
+                target
+            ),
+            None => format!(
+                "{} action in object {}",
+                action.kind.name(),
+                action.object_id
+            ),
+        };
+        push(rule, message, Some(action.object_id), action.target.clone());
+    }
+    for file in &result.embedded_files {
+        push(
+            "PS028",
+            format!(
+                "Object {} embeds {}{}",
+                file.object_id,
+                file.name.as_deref().unwrap_or("an unnamed file"),
+                file.sha256
+                    .as_deref()
+                    .map(|sha256| format!(" (SHA-256 {})", sha256))
+                    .unwrap_or_default()
+            ),
+            Some(file.object_id),
+            file.name.clone(),
+        );
+    }
+    // The inner document's findings follow its own, pinned to the stream
+    // that holds it, since their objects are the inner document's
+    for pdf in &result.embedded_pdfs {
+        push(
+            "PS060",
+            format!(
+                "Object {} holds {}, classified {} with score {}",
+                pdf.object_id,
+                pdf.name.as_deref().unwrap_or("a PDF"),
+                pdf.result.classification.label(),
+                pdf.result.severity_score
+            ),
+            Some(pdf.object_id),
+            pdf.result.sha256.clone(),
+        );
+        for finding in &pdf.result.findings {
+            push(
+                rule(&finding.rule_id).id,
+                format!("In the PDF in object {}: {}", pdf.object_id, finding.title),
+                Some(pdf.object_id),
+                finding.evidence.clone(),
+            );
+        }
+    }
+    for annotation in &result.media {
+        push(
+            "PS055",
+            format!(
+                "{} in object {} with {} payloads",
+                annotation.kind.description(),
+                annotation.object_id,
+                annotation.payloads.len()
+            ),
+            Some(annotation.object_id),
+            None,
+        );
+        for payload in &annotation.payloads {
+            let Some(format) = payload.format else {
+                continue;
+            };
+            push(
+                "PS056",
+                format!(
+                    "{} payload in object {}{}",
+                    format.description(),
+                    payload.object_id,
+                    payload
+                        .sha256
+                        .as_deref()
+                        .map(|sha256| format!(" (SHA-256 {})", sha256))
+                        .unwrap_or_default()
+                ),
+                Some(payload.object_id),
+                payload.name.clone(),
+            );
+        }
+    }
+    for action in &result.form_field_actions {
+        push(
+            "PS007",
+            format!(
+                "/{} action on form field {}",
+                action.trigger, action.field_name
+            ),
+            Some(action.object_id),
+            None,
+        );
+    }
+    if let Some(xfa) = &result.xfa {
+        let packets: Vec<u32> = xfa.packets.iter().map(|packet| packet.object_id).collect();
+        push(
+            "PS052",
+            format!("XFA form in objects {:?}", packets),
+            packets.first().copied(),
+            None,
+        );
+        for js in &result.javascript_objects {
+            if matches!(js.source, JsSource::XfaScript) {
+                push(
+                    "PS053",
+                    format!("JavaScript in the XFA packet in object {}", js.id),
+                    Some(js.id),
+                    Some(js.content.clone()),
+                );
+            }
+        }
+        for submit in xfa.submit_targets.iter().filter(|submit| submit.external) {
+            push(
+                "PS054",
+                format!("XFA form submits to {}", submit.target),
+                Some(submit.object_id),
+                Some(submit.target.clone()),
+            );
+        }
+    }
+    for candidate in &result.split_js_candidates {
+        push(
+            "PS008",
+            format!(
+                "Fragments {:?} joined by object {} match {:?}",
+                candidate.fragment_ids, candidate.parent_object_id, candidate.matched_patterns
+            ),
+            Some(candidate.parent_object_id),
+            None,
+        );
+    }
+    for (id, hints) in &result.shellcode_hints {
+        if hints.nop_sled_detected {
+            push(
+                "PS010",
+                format!(
+                    "Object {} has a NOP sled of {} bytes at offset {}",
+                    id, hints.nop_sled_length, hints.nop_sled_offset
+                ),
+                Some(*id),
+                None,
+            );
+        }
+        if let Some(pattern) = &hints.repeated_pattern {
+            push(
+                "PS010",
+                format!(
+                    "Object {} repeats {:02x?} over {} bytes at offset {}",
+                    id, pattern, hints.pattern_run_length, hints.pattern_offset
+                ),
+                Some(*id),
+                None,
+            );
+        }
+        for payload in &hints.escaped_payloads {
+            push(
+                "PS010",
+                format!(
+                    "Object {} has {} bytes of {} as Unicode escapes at offset {}",
+                    id,
+                    payload.decoded_length,
+                    payload.kind.description(),
+                    payload.offset
+                ),
+                Some(*id),
+                None,
+            );
+        }
+    }
+    for candidate in &result.heap_spray_candidates {
+        push(
+            "PS011",
+            format!(
+                "{:#x} makes up {:.0}% of {} elements in object {}",
+                candidate.dominant_value,
+                candidate.dominance_ratio * 100.0,
+                candidate.array_length,
+                candidate.object_id
+            ),
+            Some(candidate.object_id),
+            None,
+        );
+    }
+    for hint in &result.compression_bomb_hints {
+        push(
+            "PS012",
+            format!(
+                "Object {} inflated past {} bytes from {}, hitting the {}",
+                hint.object_id,
+                hint.bytes_before_abort,
+                hint.compressed_size,
+                hint.limit.description()
+            ),
+            Some(hint.object_id),
+            None,
+        );
+    }
+    for stream in &result.high_entropy_streams {
+        push(
+            "PS039",
+            format!(
+                "Object {} has {:.2} bits of entropy per byte over {} bytes",
+                stream.object_id, stream.entropy, stream.size
+            ),
+            Some(stream.object_id),
+            None,
+        );
+    }
+    for signature in &result.embedded_signatures {
+        push(
+            signature.kind.rule_id(),
+            format!(
+                "{} at offset {} of stream {}",
+                signature.kind.description(),
+                signature.offset,
+                signature.object_id
+            ),
+            Some(signature.object_id),
+            None,
+        );
+    }
+    for (id, hint) in &result.steganography_hints {
+        push(
+            "PS013",
+            format!("Object {} has chi-square p-value {:.4}", id, hint.p_value),
+            Some(*id),
+            None,
+        );
+    }
+    if let Some(creator) = &result.malicious_creator {
+        push(
+            "PS014",
+            format!("Built with {}", creator),
+            None,
+            Some(creator.clone()),
+        );
+    }
+    if result.unknown_creator {
+        push(
+            "PS015",
+            "Creator/Producer is not a known-safe tool".to_string(),
+            None,
+            None,
+        );
+    }
+    if let Some(mismatch) = &result.metadata_date_mismatch {
+        push(
+            "PS017",
+            format!(
+                "Creation dates disagree by {} days: Info {}, XMP {}",
+                mismatch.delta_days, mismatch.info_date, mismatch.xmp_date
+            ),
+            None,
+            None,
+        );
+    }
+    for anomaly in &result.metadata_anomalies {
+        let rule_id = match anomaly.kind {
+            MetadataAnomalyKind::ModifiedBeforeCreated => "PS062",
+            MetadataAnomalyKind::FutureDate => "PS063",
+            MetadataAnomalyKind::MissingProducer => "PS064",
+        };
+        push(rule_id, anomaly.detail.clone(), None, None);
+    }
+    if result.hidden_content {
+        push(
+            "PS018",
+            "Document contains hidden content".to_string(),
+            None,
+            None,
+        );
+    }
+    if result.has_obj_stm {
+        push(
+            "PS019",
+            "Document uses object streams".to_string(),
+            None,
+            None,
+        );
+    }
+    for object in &result.unusual_objects {
+        push(
+            "PS020",
+            format!("Unusual object type {}", object),
+            None,
+            None,
+        );
+    }
+    for url in &result.urls {
+        push(
+            "PS021",
+            format!("Links to {}", url.url),
+            url.object_ids.first().copied(),
+            Some(url.url.clone()),
+        );
+        if !url.indicators.is_empty() {
+            let indicators: Vec<&str> = url
+                .indicators
+                .iter()
+                .map(|indicator| indicator.description())
+                .collect();
+            push(
+                "PS051",
+                format!("Link to {} has {}", url.url, indicators.join(", ")),
+                url.object_ids.first().copied(),
+                Some(url.url.clone()),
+            );
+        }
+    }
+    for anomaly in &result.xref_anomalies {
+        push(
+            "PS022",
+            format!(
+                "Object {} declared at offset {} but found at {}",
+                anomaly.object_id, anomaly.declared_offset, anomaly.actual_offset
+            ),
+            Some(anomaly.object_id),
+            None,
+        );
+    }
+    let consistency = &result.xref_consistency;
+    if let Some(mismatch) = &consistency.size_mismatch {
+        push(
+            "PS048",
+            match mismatch.declared {
+                Some(declared) => format!(
+                    "Trailer /Size is {} but the cross-reference data needs {}",
+                    declared, mismatch.expected
+                ),
+                None => "Trailer has no /Size".to_string(),
+            },
+            None,
+            None,
+        );
+    }
+    for &id in &consistency.missing_objects {
+        push(
+            "PS049",
+            format!("Cross-reference entry for object {} points at nothing", id),
+            Some(id),
+            None,
+        );
+    }
+    for &id in &consistency.unlisted_objects {
+        push(
+            "PS050",
+            format!("Object {} is not in the cross-reference data", id),
+            Some(id),
+            None,
+        );
+    }
+    for shadowed in &result.revisions.shadowed_objects {
+        let revisions: Vec<String> = shadowed
+            .revisions
+            .iter()
+            .map(|revision| revision.to_string())
+            .collect();
+        push(
+            if shadowed.is_sensitive() {
+                "PS047"
+            } else {
+                "PS046"
+            },
+            format!(
+                "Object {} is defined in revisions {}",
+                shadowed.object_id,
+                revisions.join(", ")
+            ),
+            Some(shadowed.object_id),
+            (!shadowed.changed_actions.is_empty())
+                .then(|| format!("/{}", shadowed.changed_actions.join(", /"))),
+        );
+    }
+    for anomaly in &result.framing.anomalies {
+        let mut title = format!("File has {}", anomaly.kind.description());
+        if anomaly.length > 0 {
+            title += &format!(": {} bytes at offset {}", anomaly.length, anomaly.offset);
+        }
+        push(
+            anomaly.kind.rule_id(),
+            title,
+            None,
+            anomaly.format.as_deref().map(str::to_string),
+        );
+    }
+    for polyglot in &result.framing.polyglots {
+        push(
+            "PS061",
+            format!(
+                "File is also {}, starting at offset {}",
+                polyglot.format.description(),
+                polyglot.offset
+            ),
+            None,
+            Some(polyglot.details.clone()),
+        );
+    }
+    if let Some(error) = &result.parse_error {
+        push(
+            "PS042",
+            "Document failed to parse; objects were carved from the raw bytes".to_string(),
+            None,
+            Some(error.clone()),
+        );
+    }
+    if let Some(encryption) = &result.encryption {
+        let title = if encryption.raw_scan_only {
+            "Document is encrypted and could not be decrypted; only a raw scan ran"
+        } else {
+            "Document is encrypted"
+        };
+        push(
+            "PS041",
+            title.to_string(),
+            None,
+            encryption.algorithm.clone(),
+        );
+    }
+    for name in &result.escaped_names {
+        push(
+            "PS040",
+            format!(
+                "{} is written as {} at offset {}",
+                name.decoded, name.raw, name.offset
+            ),
+            None,
+            Some(name.raw.clone()),
+        );
+    }
+    for violation in &result.structure_violations {
+        push(
+            "PS023",
+            format!("[{}] {}", violation.rule, violation.detail),
+            violation.object_id,
+            None,
+        );
+    }
+    if result.named_destinations.is_suspicious() {
+        push(
+            "PS024",
+            format!(
+                "{} of {} named destinations have sequential integer names",
+                result.named_destinations.sequential_integer_names,
+                result.named_destinations.total_count
+            ),
+            None,
+            None,
+        );
+    }
+    if result.large_file_size {
+        push(
+            "PS025",
+            "File exceeds the size threshold".to_string(),
+            None,
+            None,
+        );
+    }
+    for finding in &result.rule_findings {
+        push(
+            "PS026",
+            format!("[{}] {}", finding.rule, finding.description),
+            finding.object_id,
+            None,
+        );
+    }
+    for found in &result.yara_matches {
+        push(
+            "PS029",
+            format!(
+                "YARA rule {}:{} matched object {}",
+                found.namespace, found.rule, found.object_id
+            ),
+            Some(found.object_id),
+            None,
+        );
+    }
+    for lookup in result
+        .virustotal
+        .iter()
+        .filter(|lookup| lookup.is_detected())
+    {
+        let object_id = match lookup.subject {
+            VtSubject::File => None,
+            VtSubject::Payload(id) => Some(id),
+        };
+        push(
+            "PS059",
+            format!(
+                "VirusTotal detection ratio {} for {}",
+                lookup.ratio().unwrap_or_default(),
+                lookup.sha256
+            ),
+            object_id,
+            Some(lookup.sha256.clone()),
+        );
+    }
+    for found in &result.pattern_matches {
+        let object_id = found.object_id.unwrap_or_default();
+        let (rule_id, title) = match found.target {
+            PatternTarget::Names => ("PS009", format!("Suspicious name {}", found.evidence)),
+            PatternTarget::Streams => (
+                "PS030",
+                format!(
+                    "Object {} contains {:?} at offset {}",
+                    object_id,
+                    found.evidence,
+                    found.offset.unwrap_or_default()
+                ),
+            ),
+            PatternTarget::Js => (
+                "PS038",
+                format!("{:?} hidden in object {}", found.evidence, object_id),
+            ),
+            PatternTarget::Metadata => (
+                "PS016",
+                format!("Suspicious metadata value {}", found.evidence),
+            ),
+        };
+        let mut finding = new_finding(
+            rule_id,
+            title,
+            found.object_id,
+            Some(found.evidence.clone()),
+        );
+        finding.pattern_id = Some(found.rule_id.clone());
+        findings.push(finding);
+    }
+
+    findings
+}
+
+fn object_offset(doc: &Document, id: u32) -> Option<u64> {
+    match doc.reference_table.get(id)? {
+        XrefEntry::Normal { offset, .. } => Some(u64::from(*offset)),
+        _ => None,
+    }
+}
+
+fn truncate(evidence: &str) -> String {
+    match evidence.char_indices().nth(MAX_EVIDENCE_LENGTH) {
+        Some((end, _)) => format!("{}...", &evidence[..end]),
+        None => evidence.to_string(),
+    }
+}
@@ -0,0 +1,292 @@
+use crate::encoding::normalize_pdf_string;
+use crate::filters::StreamCache;
+use crate::urls::{split_url, url_indicators, UrlIndicator};
+use crate::{push_javascript, resolve, JavaScriptObject, JsSource};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+const MAX_FIELD_DEPTH: usize = 32;
+
+fn xfa_script() -> &'static Regex {
+    static XFA_SCRIPT: OnceLock<Regex> = OnceLock::new();
+    XFA_SCRIPT.get_or_init(|| Regex::new(r"(?is)<script\b([^>]*)>(.*?)</script\s*>").unwrap())
+}
+
+fn xfa_content_type() -> &'static Regex {
+    static XFA_CONTENT_TYPE: OnceLock<Regex> = OnceLock::new();
+    XFA_CONTENT_TYPE
+        .get_or_init(|| Regex::new(r#"(?i)\bcontentType\s*=\s*["']([^"']*)["']"#).unwrap())
+}
+
+fn xfa_submit() -> &'static Regex {
+    static XFA_SUBMIT: OnceLock<Regex> = OnceLock::new();
+    XFA_SUBMIT.get_or_init(|| {
+        Regex::new(r#"(?is)<submit\b[^>]*?\btarget\s*=\s*["']([^"']*)["']"#).unwrap()
+    })
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct FormFieldAction {
+    pub field_name: String,
+    pub object_id: u32,
+    pub trigger: String,
+    pub has_javascript: bool,
+}
+
+/// Walks the `/AcroForm` field tree and lists every additional action (`/AA`)
+/// attached to a field, e.g. `/K` for keystrokes or `/Fo` for focus.
+pub fn check_acroform_injection(doc: &Document) -> Vec<FormFieldAction> {
+    let mut actions = Vec::new();
+
+    let fields = acroform(doc)
+        .and_then(|acroform| acroform.get(b"Fields").ok())
+        .and_then(|fields| resolve(doc, fields))
+        .and_then(|fields| fields.as_array().ok());
+
+    if let Some(fields) = fields {
+        let mut visited = HashSet::new();
+        for field in fields {
+            if let Ok(id) = field.as_reference() {
+                collect_field_actions(doc, id, "", 0, &mut visited, &mut actions);
+            }
+        }
+    }
+
+    actions
+}
+
+/// The catalog's `/AcroForm` dictionary, if the document has a form.
+pub fn acroform(doc: &Document) -> Option<&Dictionary> {
+    doc.catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"AcroForm").ok())
+        .and_then(|acroform| resolve(doc, acroform))
+        .and_then(|acroform| acroform.as_dict().ok())
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct XfaPacket {
+    /// The packet's name from the `/XFA` array, e.g. `template` or
+    /// `datasets`; `None` when `/XFA` is a single stream.
+    pub name: Option<String>,
+    pub object_id: u32,
+    /// Decoded size in bytes.
+    pub size: usize,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct XfaSubmit {
+    /// The stream holding the packet with the `<submit>` element.
+    pub object_id: u32,
+    pub target: String,
+    /// Whether the target is an absolute URL that sends the form's data off
+    /// the machine.
+    pub external: bool,
+    pub indicators: Vec<UrlIndicator>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct XfaForm {
+    pub packets: Vec<XfaPacket>,
+    /// `<script>` elements written in JavaScript. Their code is listed with
+    /// the document's other scripts.
+    pub scripts: usize,
+    /// `<script>` elements in FormCalc, XFA's own scripting language.
+    pub formcalc_scripts: usize,
+    pub submit_targets: Vec<XfaSubmit>,
+}
+
+/// Reads the XFA packets the `/AcroForm`'s `/XFA` entry holds, either one
+/// stream or an array alternating packet names and streams. XFA forms carry
+/// their own scripts and submit actions in XML, out of sight of anything
+/// that looks only for `/JS` entries, so JavaScript found in them is added to
+/// `js_objects`.
+pub fn find_xfa(
+    doc: &Document,
+    cache: &StreamCache,
+    js_objects: &mut Vec<JavaScriptObject>,
+) -> Option<XfaForm> {
+    let xfa = acroform(doc)?.get(b"XFA").ok()?;
+    let packets: Vec<(Option<String>, u32)> = match resolve(doc, xfa)? {
+        Object::Array(items) => items
+            .chunks(2)
+            .filter_map(|pair| {
+                let name = pair[0].as_str().ok().map(normalize_pdf_string);
+                Some((name, pair.get(1)?.as_reference().ok()?.0))
+            })
+            .collect(),
+        _ => vec![(None, xfa.as_reference().ok()?.0)],
+    };
+
+    let mut form = XfaForm::default();
+    for (name, id) in packets {
+        let Some(content) = cache.get(id) else {
+            continue;
+        };
+        form.packets.push(XfaPacket {
+            name,
+            object_id: id,
+            size: content.len(),
+        });
+        let xml = String::from_utf8_lossy(content);
+        scan_xfa_packet(&xml, id, &mut form, js_objects);
+    }
+    Some(form)
+}
+
+fn scan_xfa_packet(xml: &str, id: u32, form: &mut XfaForm, js_objects: &mut Vec<JavaScriptObject>) {
+    for script in xfa_script().captures_iter(xml) {
+        let content_type = script
+            .get(1)
+            .and_then(|attributes| xfa_content_type().captures(attributes.as_str()))
+            .and_then(|content_type| content_type.get(1))
+            .map_or("", |content_type| content_type.as_str());
+        // FormCalc is the default when a script names no type
+        if !content_type.to_ascii_lowercase().contains("javascript") {
+            form.formcalc_scripts += 1;
+            continue;
+        }
+        let code = script.get(2).map_or("", |code| code.as_str());
+        let code = decode_xml_text(code);
+        if code.trim().is_empty() {
+            continue;
+        }
+        form.scripts += 1;
+        push_javascript(js_objects, id, code, JsSource::XfaScript);
+    }
+
+    for submit in xfa_submit().captures_iter(xml) {
+        let target = decode_xml_text(submit.get(1).map_or("", |target| target.as_str()));
+        if form.submit_targets.iter().any(|seen| seen.target == target) {
+            continue;
+        }
+        form.submit_targets.push(XfaSubmit {
+            object_id: id,
+            external: split_url(&target).is_some(),
+            indicators: url_indicators(&target, None),
+            target,
+        });
+    }
+}
+
+/// Unwraps `<![CDATA[...]]>` sections and replaces the predefined and
+/// numeric character references everywhere else.
+fn decode_xml_text(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("<![CDATA[") {
+        decoded.push_str(&decode_entities(&rest[..start]));
+        let section = &rest[start + "<![CDATA[".len()..];
+        let end = section.find("]]>").unwrap_or(section.len());
+        decoded.push_str(&section[..end]);
+        rest = section.get(end + "]]>".len()..).unwrap_or("");
+    }
+    decoded.push_str(&decode_entities(rest));
+    decoded
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';').filter(|&end| end <= 10) else {
+            decoded.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..end];
+        let character = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#')?.parse().ok())
+                .and_then(char::from_u32),
+        };
+        match character {
+            Some(character) => {
+                decoded.push(character);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn collect_field_actions(
+    doc: &Document,
+    id: ObjectId,
+    parent_name: &str,
+    depth: usize,
+    visited: &mut HashSet<ObjectId>,
+    actions: &mut Vec<FormFieldAction>,
+) {
+    if depth > MAX_FIELD_DEPTH {
+        tracing::warn!(object_id = id.0, depth, "form field tree nested too deeply");
+        return;
+    }
+    if !visited.insert(id) {
+        return;
+    }
+    let Ok(field) = doc.get_dictionary(id) else {
+        return;
+    };
+
+    let partial_name = field
+        .get(b"T")
+        .ok()
+        .and_then(|name| name.as_str().ok())
+        .map(normalize_pdf_string);
+    let field_name = match partial_name {
+        Some(name) if parent_name.is_empty() => name,
+        Some(name) => format!("{}.{}", parent_name, name),
+        None => parent_name.to_string(),
+    };
+
+    let additional_actions = field
+        .get(b"AA")
+        .ok()
+        .and_then(|aa| resolve(doc, aa))
+        .and_then(|aa| aa.as_dict().ok());
+    if let Some(additional_actions) = additional_actions {
+        for (trigger, action) in additional_actions.iter() {
+            actions.push(FormFieldAction {
+                field_name: field_name.clone(),
+                object_id: id.0,
+                trigger: String::from_utf8_lossy(trigger).into_owned(),
+                has_javascript: is_javascript_action(doc, action),
+            });
+        }
+    }
+
+    if let Ok(kids) = field.get(b"Kids").and_then(|kids| kids.as_array()) {
+        for kid in kids {
+            if let Ok(kid_id) = kid.as_reference() {
+                collect_field_actions(doc, kid_id, &field_name, depth + 1, visited, actions);
+            }
+        }
+    }
+}
+
+fn is_javascript_action(doc: &Document, action: &Object) -> bool {
+    resolve(doc, action)
+        .and_then(|action| action.as_dict().ok())
+        .and_then(|action| action.get(b"S").ok())
+        .and_then(|subtype| subtype.as_name().ok())
+        .is_some_and(|subtype| subtype == b"JavaScript")
+}
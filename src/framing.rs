@@ -0,0 +1,337 @@
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::borrow::Cow;
+
+/// Magic numbers of formats that get glued to PDFs to make polyglots and
+/// droppers, with the name reported for them.
+const KNOWN_FORMATS: [(&[u8], &str); 9] = [
+    (b"PK\x03\x04", "ZIP"),
+    (b"MZ", "PE executable"),
+    (b"\x7fELF", "ELF executable"),
+    (b"<!DOCTYPE", "HTML"),
+    (b"<html", "HTML"),
+    (b"GIF8", "GIF"),
+    (b"\x89PNG", "PNG"),
+    (b"\xff\xd8\xff", "JPEG"),
+    (b"Rar!", "RAR"),
+];
+
+const EOCD_MAGIC: &[u8] = b"PK\x05\x06";
+/// Size of a ZIP end of central directory record without its comment.
+const EOCD_LEN: usize = 22;
+const CENTRAL_MAGIC: &[u8] = b"PK\x01\x02";
+/// Size of a ZIP central directory entry without its name, extra field and
+/// comment.
+const CENTRAL_LEN: usize = 46;
+/// Archive readers look for the end record no further from the end of the
+/// file than the longest comment it may have.
+const MAX_ZIP_COMMENT: usize = 0xffff;
+const JAR_MANIFEST: &str = "META-INF/MANIFEST.MF";
+/// How many archive entries a polyglot's details name.
+const LISTED_ENTRIES: usize = 5;
+
+/// Tags a browser sniffing the file would render it as a page for.
+const HTML_TAGS: [&[u8]; 7] = [
+    b"<!doctype html",
+    b"<html",
+    b"<head",
+    b"<body",
+    b"<script",
+    b"<iframe",
+    b"<svg",
+];
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct FileFraming {
+    /// Where `%PDF-` starts; 0 in a well-formed file.
+    pub header_offset: Option<usize>,
+    /// Offsets of every `%%EOF` marker. There is one per revision, so more
+    /// than one is normal for incrementally updated files.
+    pub eof_offsets: Vec<usize>,
+    /// Bytes after the final `%%EOF` and its line break.
+    pub trailing_bytes: usize,
+    pub anomalies: Vec<FramingAnomaly>,
+    /// Other formats the file is just as valid in.
+    pub polyglots: Vec<Polyglot>,
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum FramingAnomalyKind {
+    /// Bytes before the `%PDF-` header, which viewers skip but other
+    /// programs may read as a file of their own.
+    DataBeforeHeader,
+    /// Bytes after the final `%%EOF`, where appended payloads go.
+    DataAfterEof,
+    MissingHeader,
+    MissingEof,
+}
+
+impl FramingAnomalyKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            FramingAnomalyKind::DataBeforeHeader => "data before the %PDF header",
+            FramingAnomalyKind::DataAfterEof => "data after the final %%EOF",
+            FramingAnomalyKind::MissingHeader => "no %PDF header",
+            FramingAnomalyKind::MissingEof => "no %%EOF marker",
+        }
+    }
+
+    pub fn rule_id(&self) -> &'static str {
+        match self {
+            FramingAnomalyKind::DataBeforeHeader => "PS043",
+            FramingAnomalyKind::DataAfterEof => "PS044",
+            FramingAnomalyKind::MissingHeader | FramingAnomalyKind::MissingEof => "PS045",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct FramingAnomaly {
+    pub kind: FramingAnomalyKind,
+    /// Where the stray data starts, or where the marker was expected.
+    pub offset: usize,
+    pub length: usize,
+    /// What the stray data looks like, when it starts with a known magic
+    /// number.
+    #[serde(deserialize_with = "deserialize_format")]
+    pub format: Option<Cow<'static, str>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum PolyglotFormat {
+    Zip,
+    /// A ZIP archive with a manifest, which Java runs.
+    Jar,
+    Html,
+}
+
+impl PolyglotFormat {
+    pub fn description(&self) -> &'static str {
+        match self {
+            PolyglotFormat::Zip => "a ZIP archive",
+            PolyglotFormat::Jar => "a Java archive (JAR)",
+            PolyglotFormat::Html => "an HTML page",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Polyglot {
+    pub format: PolyglotFormat,
+    /// Where the other format's data starts: the start of the archive, or
+    /// the first tag of the page.
+    pub offset: usize,
+    /// What makes the file valid in it, e.g. the archive's entries.
+    pub details: String,
+}
+
+/// Checks what surrounds the PDF in the file: polyglots put HTML, ZIP or
+/// executable data in front of the `%PDF-` header, and droppers append their
+/// payload after the last `%%EOF`, where viewers never look.
+pub fn check_framing(raw: &[u8]) -> FileFraming {
+    let mut framing = FileFraming {
+        header_offset: find(raw, b"%PDF-"),
+        eof_offsets: find_eof_markers(raw),
+        ..Default::default()
+    };
+
+    match framing.header_offset {
+        Some(0) => {}
+        Some(offset) => framing.anomalies.push(FramingAnomaly {
+            kind: FramingAnomalyKind::DataBeforeHeader,
+            offset: 0,
+            length: offset,
+            format: sniff_format(raw),
+        }),
+        None => framing.anomalies.push(FramingAnomaly {
+            kind: FramingAnomalyKind::MissingHeader,
+            offset: 0,
+            length: 0,
+            format: sniff_format(raw),
+        }),
+    }
+    framing.polyglots.extend(check_zip(raw));
+    if let Some(header) = framing.header_offset {
+        framing
+            .polyglots
+            .extend(check_html(&raw[..header], 0, "before the %PDF header"));
+    }
+
+    let Some(&last_eof) = framing.eof_offsets.last() else {
+        framing.anomalies.push(FramingAnomaly {
+            kind: FramingAnomalyKind::MissingEof,
+            offset: raw.len(),
+            length: 0,
+            format: None,
+        });
+        return framing;
+    };
+    let end = end_of_marker(raw, last_eof);
+    let trailing = &raw[end..];
+    framing.trailing_bytes = trailing.len();
+    if !is_padding(trailing) {
+        framing.anomalies.push(FramingAnomaly {
+            kind: FramingAnomalyKind::DataAfterEof,
+            offset: end,
+            length: trailing.len(),
+            format: sniff_format(trailing.trim_ascii_start()),
+        });
+        framing
+            .polyglots
+            .extend(check_html(trailing, end, "after the final %%EOF"));
+    }
+
+    framing
+}
+
+/// Finds a ZIP archive the way archive readers do, from the end record at
+/// the end of the file, which polyglots get to by appending the archive or
+/// by making the rest of the PDF its comment. Readers find the central
+/// directory right before the end record whatever offset it gives, which
+/// is what lets an archive follow other data.
+fn check_zip(raw: &[u8]) -> Option<Polyglot> {
+    let last = raw.len().checked_sub(EOCD_LEN)?;
+    let first = raw.len().saturating_sub(EOCD_LEN + MAX_ZIP_COMMENT);
+    let eocd = (first..=last).rev().find(|&offset| {
+        let comment = usize::from(u16_at(raw, offset + 20));
+        raw[offset..].starts_with(EOCD_MAGIC)
+            && raw
+                .get(offset + EOCD_LEN + comment..)
+                .is_some_and(is_padding)
+    })?;
+    let entries = usize::from(u16_at(raw, eocd + 10));
+    let size = u32_at(raw, eocd + 12) as usize;
+    let recorded_offset = u32_at(raw, eocd + 16) as usize;
+    let directory = eocd.checked_sub(size)?;
+    if entries == 0 {
+        return None;
+    }
+
+    let mut names = Vec::with_capacity(entries.min(LISTED_ENTRIES + 1));
+    let mut is_jar = false;
+    let mut offset = directory;
+    for _ in 0..entries {
+        let record = raw.get(offset..offset + CENTRAL_LEN)?;
+        if !record.starts_with(CENTRAL_MAGIC) {
+            return None;
+        }
+        let name_length = usize::from(u16_at(record, 28));
+        let extra_length = usize::from(u16_at(record, 30));
+        let comment_length = usize::from(u16_at(record, 32));
+        let name = raw.get(offset + CENTRAL_LEN..offset + CENTRAL_LEN + name_length)?;
+        let name = String::from_utf8_lossy(name);
+        is_jar |= name.eq_ignore_ascii_case(JAR_MANIFEST);
+        if names.len() < LISTED_ENTRIES {
+            names.push(name.into_owned());
+        }
+        offset += CENTRAL_LEN + name_length + extra_length + comment_length;
+    }
+
+    let mut details = format!("{} entries: {}", entries, names.join(", "));
+    if entries > names.len() {
+        details += &format!(" and {} more", entries - names.len());
+    }
+    Some(Polyglot {
+        format: if is_jar {
+            PolyglotFormat::Jar
+        } else {
+            PolyglotFormat::Zip
+        },
+        // Offsets in the archive count from its start
+        offset: directory.checked_sub(recorded_offset).unwrap_or(directory),
+        details,
+    })
+}
+
+/// Finds the first HTML tag in `data`, which is `offset` bytes into the
+/// file.
+fn check_html(data: &[u8], offset: usize, place: &str) -> Option<Polyglot> {
+    let (position, tag) = HTML_TAGS
+        .iter()
+        .filter_map(|tag| find_tag(data, tag).map(|position| (position, tag)))
+        .min_by_key(|&(position, _)| position)?;
+    Some(Polyglot {
+        format: PolyglotFormat::Html,
+        offset: offset + position,
+        details: format!("{}> tag {}", String::from_utf8_lossy(tag), place),
+    })
+}
+
+/// Where `tag` starts in `data`, ignoring case, as a whole tag name rather
+/// than the start of a longer one.
+fn find_tag(data: &[u8], tag: &[u8]) -> Option<usize> {
+    data.windows(tag.len())
+        .enumerate()
+        .find(|&(position, window)| {
+            window.eq_ignore_ascii_case(tag)
+                && !data
+                    .get(position + tag.len())
+                    .is_some_and(u8::is_ascii_alphanumeric)
+        })
+        .map(|(position, _)| position)
+}
+
+/// Whitespace and NULs, which writers pad files with.
+fn is_padding(data: &[u8]) -> bool {
+    data.iter()
+        .all(|&byte| byte.is_ascii_whitespace() || byte == 0)
+}
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    data.get(offset..offset + 2)
+        .map_or(0, |bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    data.get(offset..offset + 4).map_or(0, |bytes| {
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    })
+}
+
+fn sniff_format(data: &[u8]) -> Option<Cow<'static, str>> {
+    KNOWN_FORMATS
+        .iter()
+        .find(|(magic, _)| {
+            data.len() >= magic.len() && data[..magic.len()].eq_ignore_ascii_case(magic)
+        })
+        .map(|&(_, format)| Cow::Borrowed(format))
+}
+
+/// Reads a format name back as the one in `KNOWN_FORMATS`.
+fn deserialize_format<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Cow<'static, str>>, D::Error> {
+    let Some(name) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    KNOWN_FORMATS
+        .iter()
+        .find(|&&(_, format)| format == name)
+        .map(|&(_, format)| Some(Cow::Borrowed(format)))
+        .ok_or_else(|| D::Error::custom(format!("unknown format {}", name)))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Offsets of every `%%EOF` marker in `raw`.
+pub(crate) fn find_eof_markers(raw: &[u8]) -> Vec<usize> {
+    raw.windows(b"%%EOF".len())
+        .enumerate()
+        .filter(|(_, window)| *window == b"%%EOF")
+        .map(|(offset, _)| offset)
+        .collect()
+}
+
+/// Where the `%%EOF` marker at `eof` ends, including its line break.
+pub(crate) fn end_of_marker(raw: &[u8], eof: usize) -> usize {
+    let end = eof + b"%%EOF".len();
+    end + raw[end..]
+        .iter()
+        .take_while(|&&byte| byte == b'\r' || byte == b'\n')
+        .count()
+        .min(2)
+}
@@ -0,0 +1,249 @@
+use crate::encoding::get_name_key;
+use crate::AnalysisResult;
+use lopdf::{Dictionary, Document, Object};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+
+/// Keys that point back up the object tree (page tree parents, annotation
+/// pages, outline siblings). Following them would report a cycle for every
+/// well-formed document, so they are left out of the graph.
+const BACK_POINTER_KEYS: [&[u8]; 3] = [b"Parent", b"P", b"Prev"];
+
+/// Adjacency list of indirect references between objects, keyed by object number.
+#[derive(Default)]
+pub struct ReferenceGraph {
+    pub edges: HashMap<u32, Vec<u32>>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum VisitState {
+    InProgress,
+    Done,
+}
+
+pub fn build_reference_graph(doc: &Document) -> ReferenceGraph {
+    let mut graph = ReferenceGraph::default();
+
+    for (id, object) in doc.objects.iter() {
+        let mut references = Vec::new();
+        collect_references(object, &mut references);
+        references.sort_unstable();
+        references.dedup();
+        graph.edges.entry(id.0).or_default().extend(references);
+    }
+
+    graph
+}
+
+fn collect_references(object: &Object, references: &mut Vec<u32>) {
+    match object {
+        Object::Reference(id) => references.push(id.0),
+        Object::Array(items) => {
+            for item in items {
+                collect_references(item, references);
+            }
+        }
+        Object::Dictionary(dict) => collect_dictionary_references(dict, references),
+        Object::Stream(stream) => collect_dictionary_references(&stream.dict, references),
+        _ => {}
+    }
+}
+
+fn collect_dictionary_references(dict: &Dictionary, references: &mut Vec<u32>) {
+    for (key, value) in dict.iter() {
+        if !BACK_POINTER_KEYS.contains(&key.as_slice()) {
+            collect_references(value, references);
+        }
+    }
+}
+
+/// Finds reference cycles with an iterative DFS so that deeply nested
+/// documents cannot overflow the stack. Each cycle is reported once, starting
+/// at the first object of the cycle reached by the traversal.
+pub fn detect_cycles(graph: &ReferenceGraph) -> Vec<Vec<u32>> {
+    let mut state: HashMap<u32, VisitState> = HashMap::new();
+    let mut cycles = Vec::new();
+
+    let mut roots: Vec<u32> = graph.edges.keys().copied().collect();
+    roots.sort_unstable();
+
+    for root in roots {
+        if state.contains_key(&root) {
+            continue;
+        }
+        state.insert(root, VisitState::InProgress);
+        let mut stack: Vec<(u32, usize)> = vec![(root, 0)];
+
+        while let Some((node, next_child)) = stack.last_mut() {
+            let node = *node;
+            let child = graph.edges[&node].get(*next_child).copied();
+            *next_child += 1;
+
+            match child {
+                Some(child) => match state.get(&child) {
+                    None if graph.edges.contains_key(&child) => {
+                        state.insert(child, VisitState::InProgress);
+                        stack.push((child, 0));
+                    }
+                    Some(VisitState::InProgress) => {
+                        if let Some(start) = stack.iter().position(|(id, _)| *id == child) {
+                            cycles.push(stack[start..].iter().map(|(id, _)| *id).collect());
+                        }
+                    }
+                    _ => {}
+                },
+                None => {
+                    state.insert(node, VisitState::Done);
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    cycles
+}
+
+/// Returns the object numbers that are referenced but not defined in the document.
+pub fn find_dangling_references(graph: &ReferenceGraph) -> Vec<u32> {
+    let mut dangling: Vec<u32> = graph
+        .edges
+        .values()
+        .flatten()
+        .filter(|target| !graph.edges.contains_key(target))
+        .copied()
+        .collect();
+    dangling.sort_unstable();
+    dangling.dedup();
+    dangling
+}
+
+/// Writes the reference graph as a Graphviz DOT digraph, for `dot -Tsvg` and
+/// the like. Each node is an object, labeled with its number and `/Type` and
+/// filled by what it is: orange for scripts, red for objects a finding
+/// cites, yellow for object streams, blue for the catalog and green for the
+/// page tree. Edges are labeled with the key that holds the reference, so
+/// that an `/OpenAction` chain can be followed by eye. References to
+/// objects the document doesn't define end in dashed nodes.
+pub fn to_dot(doc: &Document, result: &AnalysisResult) -> String {
+    let scripts: BTreeSet<u32> = result.javascript_objects.iter().map(|js| js.id).collect();
+    let cited: BTreeSet<u32> = result
+        .findings
+        .iter()
+        .filter_map(|finding| finding.object_id)
+        .collect();
+    let objects: BTreeMap<u32, &Object> = doc
+        .objects
+        .iter()
+        .map(|(&(number, _), object)| (number, object))
+        .collect();
+
+    let mut dot = String::from("digraph pdf {\n");
+    dot.push_str("  node [shape=box, style=filled, fillcolor=white, fontname=Helvetica];\n");
+    dot.push_str("  edge [fontname=Helvetica, fontsize=10];\n");
+    let mut missing = BTreeSet::new();
+    for (&id, object) in &objects {
+        let kind = object_type(object);
+        let has_js = match object {
+            Object::Dictionary(dict) => dict.has(b"JS"),
+            _ => false,
+        };
+        let color = if scripts.contains(&id) || has_js {
+            "orange"
+        } else if cited.contains(&id) {
+            "salmon"
+        } else {
+            match kind.as_deref() {
+                Some("ObjStm") => "khaki",
+                Some("Catalog") => "lightblue",
+                Some("Pages" | "Page") => "palegreen",
+                _ => "white",
+            }
+        };
+        let mut label = format!("{} 0 R", id);
+        if let Some(kind) = &kind {
+            let _ = write!(label, "\\n/{}", escape(kind));
+        }
+        if matches!(object, Object::Stream(_)) {
+            label.push_str("\\nstream");
+        }
+        let _ = writeln!(dot, "  {} [label=\"{}\", fillcolor={}];", id, label, color);
+
+        // Back pointers are left out as in `build_reference_graph`
+        let references = labeled_references(object).into_iter().filter(|(_, key)| {
+            !key.split('/')
+                .any(|name| BACK_POINTER_KEYS.contains(&name.as_bytes()))
+        });
+        for (target, key) in references {
+            if !objects.contains_key(&target) {
+                missing.insert(target);
+            }
+            let _ = writeln!(dot, "  {} -> {} [label=\"{}\"];", id, target, escape(&key));
+        }
+    }
+    for id in missing {
+        let _ = writeln!(
+            dot,
+            "  {} [label=\"{} 0 R\\nmissing\", style=dashed];",
+            id, id
+        );
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// The `/Type` of a dictionary or stream, else its `/S`, such as an
+/// action's.
+pub fn object_type(object: &Object) -> Option<String> {
+    let dict = match object {
+        Object::Dictionary(dict) => dict,
+        Object::Stream(stream) => &stream.dict,
+        _ => return None,
+    };
+    [b"Type".as_slice(), b"S"]
+        .into_iter()
+        .find_map(|key| match get_name_key(dict, key)? {
+            Object::Name(name) => Some(String::from_utf8_lossy(name).into_owned()),
+            _ => None,
+        })
+}
+
+/// The references `object` holds, each with the keys it is under joined by
+/// slashes, such as `AA/O` for a page's open action, in order of the
+/// objects referenced. Unlike [`build_reference_graph`], back pointers such
+/// as `/Parent` are kept.
+pub fn labeled_references(object: &Object) -> Vec<(u32, String)> {
+    let mut references = Vec::new();
+    collect_labeled_references(object, "", &mut references);
+    references.sort();
+    references.dedup();
+    references
+}
+
+fn collect_labeled_references(object: &Object, key: &str, references: &mut Vec<(u32, String)>) {
+    let dict = match object {
+        Object::Reference(id) => return references.push((id.0, key.to_string())),
+        Object::Array(items) => {
+            for item in items {
+                collect_labeled_references(item, key, references);
+            }
+            return;
+        }
+        Object::Dictionary(dict) => dict,
+        Object::Stream(stream) => &stream.dict,
+        _ => return,
+    };
+    for (name, value) in dict.iter() {
+        let name = String::from_utf8_lossy(name);
+        let path = match key {
+            "" => name.into_owned(),
+            _ => format!("{}/{}", key, name),
+        };
+        collect_labeled_references(value, &path, references);
+    }
+}
+
+/// Escapes the quotes and backslashes of text from the document for a DOT
+/// string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,89 @@
+use crate::CompiledConfig;
+use lopdf::{Document, Object};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const MIN_DOMINANCE_RATIO: f64 = 0.8;
+const MAX_NESTING_DEPTH: usize = 32;
+
+#[derive(Deserialize, Serialize)]
+pub struct HeapSprayCandidate {
+    pub object_id: u32,
+    pub array_length: usize,
+    pub dominant_value: i64,
+    pub dominance_ratio: f64,
+}
+
+/// Looks for arrays longer than `Config::heap_spray_min_length` that are mostly
+/// one repeated integer, such as thousands of `0x0c0c0c0c`. Arrays nested in
+/// dictionaries and streams are checked too and reported under the indirect
+/// object that contains them.
+pub fn check_heap_spray_arrays(doc: &Document, config: &CompiledConfig) -> Vec<HeapSprayCandidate> {
+    let min_length = config.config.heap_spray_min_length;
+    let mut candidates = Vec::new();
+
+    for (id, object) in doc.objects.iter() {
+        collect_candidates(id.0, object, min_length, 0, &mut candidates);
+    }
+
+    candidates
+}
+
+fn collect_candidates(
+    object_id: u32,
+    object: &Object,
+    min_length: usize,
+    depth: usize,
+    candidates: &mut Vec<HeapSprayCandidate>,
+) {
+    if depth > MAX_NESTING_DEPTH {
+        tracing::warn!(
+            object_id,
+            depth,
+            "object nested too deeply, not descending further"
+        );
+        return;
+    }
+
+    match object {
+        Object::Array(items) => {
+            if items.len() > min_length {
+                if let Some(candidate) = dominant_integer(object_id, items) {
+                    candidates.push(candidate);
+                }
+            }
+            for item in items {
+                collect_candidates(object_id, item, min_length, depth + 1, candidates);
+            }
+        }
+        Object::Dictionary(dict) => {
+            for (_, value) in dict.iter() {
+                collect_candidates(object_id, value, min_length, depth + 1, candidates);
+            }
+        }
+        Object::Stream(stream) => {
+            for (_, value) in stream.dict.iter() {
+                collect_candidates(object_id, value, min_length, depth + 1, candidates);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn dominant_integer(object_id: u32, items: &[Object]) -> Option<HeapSprayCandidate> {
+    let mut counts: HashMap<i64, usize> = HashMap::new();
+    for item in items {
+        if let Object::Integer(value) = item {
+            *counts.entry(*value).or_default() += 1;
+        }
+    }
+
+    let (value, count) = counts.into_iter().max_by_key(|&(_, count)| count)?;
+    let dominance_ratio = count as f64 / items.len() as f64;
+    (dominance_ratio > MIN_DOMINANCE_RATIO).then_some(HeapSprayCandidate {
+        object_id,
+        array_length: items.len(),
+        dominant_value: value,
+        dominance_ratio,
+    })
+}
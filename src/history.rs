@@ -0,0 +1,132 @@
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use pdf_sentinel::{AnalysisResult, SentinelError};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::io;
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS scans (
+    id INTEGER PRIMARY KEY,
+    scanned_at TEXT NOT NULL,
+    file TEXT NOT NULL,
+    sha256 TEXT,
+    severity_score INTEGER NOT NULL,
+    severity TEXT NOT NULL,
+    classification TEXT NOT NULL,
+    findings TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS scans_by_hash ON scans (sha256);
+CREATE INDEX IF NOT EXISTS scans_by_time ON scans (scanned_at);
+";
+
+/// One row of the history.
+#[derive(Serialize)]
+pub struct Scan {
+    /// RFC 3339 in UTC, which sorts as it reads.
+    pub scanned_at: String,
+    pub file: String,
+    pub sha256: Option<String>,
+    pub severity_score: u32,
+    pub severity: String,
+    pub classification: String,
+    /// The findings as JSON output had them at the time.
+    pub findings: serde_json::Value,
+}
+
+/// Every file scanned with `--history`, in an SQLite database, so that a
+/// file seen before can be looked up with what it scored then.
+pub struct History {
+    connection: Connection,
+}
+
+impl History {
+    /// Opens the database at `path`, creating it if need be.
+    pub fn open(path: &Path) -> Result<Self, SentinelError> {
+        let connection = Connection::open(path).map_err(sqlite_error)?;
+        connection.execute_batch(SCHEMA).map_err(sqlite_error)?;
+        Ok(History { connection })
+    }
+
+    pub fn record(&self, file: &str, result: &AnalysisResult) -> Result<(), SentinelError> {
+        let findings = serde_json::to_string(&result.findings)?;
+        self.connection
+            .execute(
+                "INSERT INTO scans (scanned_at, file, sha256, severity_score, severity, \
+                 classification, findings) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    timestamp(Utc::now()),
+                    file,
+                    result.sha256,
+                    result.severity_score,
+                    result.severity.label(),
+                    result.classification.label(),
+                    findings
+                ],
+            )
+            .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    /// The scans of the file with hash `sha256` and those made at or after
+    /// `since`, a date or an RFC 3339 time, oldest first. Either filter may
+    /// be left out.
+    pub fn query(
+        &self,
+        sha256: Option<&str>,
+        since: Option<&str>,
+    ) -> Result<Vec<Scan>, SentinelError> {
+        let since = since.map(parse_since).transpose()?;
+        let mut statement = self
+            .connection
+            .prepare(
+                "SELECT scanned_at, file, sha256, severity_score, severity, classification, \
+                 findings FROM scans WHERE (?1 IS NULL OR sha256 = lower(?1)) \
+                 AND (?2 IS NULL OR scanned_at >= ?2) ORDER BY scanned_at, id",
+            )
+            .map_err(sqlite_error)?;
+        let rows = statement
+            .query_map(params![sha256, since], |row| {
+                let findings: String = row.get(6)?;
+                Ok(Scan {
+                    scanned_at: row.get(0)?,
+                    file: row.get(1)?,
+                    sha256: row.get(2)?,
+                    severity_score: row.get(3)?,
+                    severity: row.get(4)?,
+                    classification: row.get(5)?,
+                    findings: serde_json::from_str(&findings).unwrap_or_default(),
+                })
+            })
+            .map_err(sqlite_error)?;
+        rows.collect::<Result<_, _>>().map_err(sqlite_error)
+    }
+}
+
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// `2024-05-01` is midnight UTC that day.
+fn parse_since(since: &str) -> Result<String, SentinelError> {
+    let time = match NaiveDate::parse_from_str(since, "%Y-%m-%d") {
+        Ok(date) => date.and_hms_opt(0, 0, 0).map(|time| time.and_utc()),
+        Err(_) => DateTime::parse_from_rfc3339(since)
+            .ok()
+            .map(|time| time.with_timezone(&Utc)),
+    };
+    let time = time.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{:?} is neither a YYYY-MM-DD date nor an RFC 3339 time",
+                since
+            ),
+        )
+    })?;
+    Ok(timestamp(time))
+}
+
+fn sqlite_error(err: rusqlite::Error) -> SentinelError {
+    io::Error::other(err.to_string()).into()
+}
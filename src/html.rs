@@ -0,0 +1,474 @@
+use chrono::{SecondsFormat, Utc};
+use lopdf::{Document, Object};
+
+use crate::classification::ThreatClassification;
+use crate::filters::decode_stream_bounded;
+use crate::scoring::Severity;
+use crate::AnalysisResult;
+
+/// Bytes of each suspicious stream shown in its hex view.
+const HEX_VIEW_BYTES: usize = 4096;
+
+/// Hex views per file; past this many, findings are left to the table.
+const MAX_HEX_VIEWS: usize = 32;
+
+const JS_KEYWORDS: [&str; 32] = [
+    "break",
+    "case",
+    "catch",
+    "const",
+    "continue",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "eval",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "null",
+    "return",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "undefined",
+    "var",
+    "void",
+    "while",
+    "with",
+];
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2em auto; max-width: 72em; color: #222; }
+h1 { margin-bottom: 0; }
+header p { color: #666; margin-top: 0.25em; }
+section.file { border-top: 2px solid #ddd; margin-top: 2em; }
+table { border-collapse: collapse; width: 100%; margin: 0.5em 0 1em; }
+th, td { text-align: left; padding: 0.3em 0.6em; border-bottom: 1px solid #eee; vertical-align: top; }
+th { background: #f6f6f6; }
+td.hash { font-family: monospace; }
+.verdict { display: inline-block; padding: 0.2em 0.7em; border-radius: 1em; color: #fff; font-weight: bold; }
+.clean { background: #2e7d32; }
+.suspicious { background: #f9a825; }
+.exploit { background: #ef6c00; }
+.malicious { background: #c62828; }
+.gauge { display: flex; width: 24em; margin: 0.5em 0; }
+.gauge span { flex: 1; text-align: center; padding: 0.3em 0; background: #eee; color: #999; }
+.gauge span.on { color: #fff; font-weight: bold; }
+.gauge span.low.on { background: #2e7d32; }
+.gauge span.medium.on { background: #f9a825; }
+.gauge span.high.on { background: #ef6c00; }
+.gauge span.critical.on { background: #c62828; }
+.sev-Critical, .sev-High { color: #c62828; font-weight: bold; }
+.sev-Medium { color: #ef6c00; }
+.bars { display: flex; align-items: flex-end; height: 6em; gap: 2px; }
+.bars div { flex: 1; background: #5c6bc0; min-height: 1px; }
+.bar-labels { display: flex; gap: 2px; font-size: 0.8em; color: #666; }
+.bar-labels span { flex: 1; text-align: center; }
+pre { background: #f8f8f8; padding: 0.8em; overflow-x: auto; font-size: 0.9em; }
+code { word-break: break-all; }
+details { margin: 0.5em 0; }
+summary { cursor: pointer; }
+.kw { color: #7b1fa2; font-weight: bold; }
+.str { color: #2e7d32; }
+.num { color: #1565c0; }
+.com { color: #888; font-style: italic; }
+";
+
+/// A standalone HTML report, with no scripts or outside resources, for
+/// attaching to incident tickets: per file, the verdict and a severity
+/// gauge, the findings, object statistics, the scripts with their syntax
+/// highlighted, and collapsible hex views of the streams findings point at.
+pub struct HtmlReport {
+    max_stream_size: u64,
+    /// File name, verdict label, verdict CSS class and score, for the
+    /// overview.
+    overview: Vec<(String, &'static str, &'static str, u32)>,
+    sections: Vec<String>,
+}
+
+impl HtmlReport {
+    /// `max_stream_size` bounds how far a stream is decompressed for its hex
+    /// view, as `Config::max_decompressed_stream_size` does for analysis.
+    pub fn new(max_stream_size: u64) -> Self {
+        HtmlReport {
+            max_stream_size,
+            overview: Vec::new(),
+            sections: Vec::new(),
+        }
+    }
+
+    /// Adds a file's section. Hex views need the parsed document; without
+    /// it they are left out.
+    pub fn add_file(&mut self, file: &str, result: &AnalysisResult, doc: Option<&Document>) {
+        let anchor = format!("file-{}", self.sections.len() + 1);
+        let label = result.classification.label();
+        let class = verdict_class(&result.classification);
+        let mut html = format!(
+            "<section class=\"file\" id=\"{}\">\n<h2>{}</h2>\n",
+            anchor,
+            escape(file)
+        );
+
+        html.push_str(&summary(result, label, class));
+        html.push_str(&findings_table(result));
+        html.push_str(&statistics(result));
+        html.push_str(&scripts(result));
+        if let Some(doc) = doc {
+            html.push_str(&hex_views(doc, result, self.max_stream_size));
+        }
+        html.push_str("</section>\n");
+
+        self.overview
+            .push((file.to_string(), label, class, result.severity_score));
+        self.sections.push(html);
+    }
+
+    pub fn finish(&self) -> String {
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{} report</title>\n<style>{}</style>\n</head>\n<body>\n<header>\n\
+             <h1>{} report</h1>\n<p>Version {}, generated {}</p>\n</header>\n",
+            env!("CARGO_PKG_NAME"),
+            STYLE,
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+        );
+        if self.overview.len() > 1 {
+            html.push_str("<table>\n<tr><th>File</th><th>Verdict</th><th>Score</th></tr>\n");
+            for (index, (file, label, class, score)) in self.overview.iter().enumerate() {
+                html.push_str(&format!(
+                    "<tr><td><a href=\"#file-{}\">{}</a></td>\
+                     <td><span class=\"verdict {}\">{}</span></td><td>{}</td></tr>\n",
+                    index + 1,
+                    escape(file),
+                    class,
+                    label,
+                    score
+                ));
+            }
+            html.push_str("</table>\n");
+        }
+        for section in &self.sections {
+            html.push_str(section);
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+fn summary(result: &AnalysisResult, label: &str, class: &str) -> String {
+    let mut html = format!(
+        "<p><span class=\"verdict {}\">{}</span> Severity {}, score {}</p>\n<div class=\"gauge\">",
+        class,
+        label,
+        result.severity.label(),
+        result.severity_score
+    );
+    for severity in [
+        Severity::Low,
+        Severity::Medium,
+        Severity::High,
+        Severity::Critical,
+    ] {
+        html.push_str(&format!(
+            "<span class=\"{}{}\">{}</span>",
+            severity.label().to_lowercase(),
+            if severity == result.severity {
+                " on"
+            } else {
+                ""
+            },
+            severity.label()
+        ));
+    }
+    html.push_str("</div>\n");
+
+    let indicators = result.classification.indicators();
+    if !indicators.is_empty() {
+        html.push_str("<ul>\n");
+        for indicator in indicators {
+            html.push_str(&format!("<li>{}</li>\n", escape(indicator)));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    html.push_str("<table>\n");
+    let size = result.file_size.map(|size| format!("{} bytes", size));
+    let rows = [
+        ("Size", &size),
+        ("MD5", &result.md5),
+        ("SHA-1", &result.sha1),
+        ("SHA-256", &result.sha256),
+    ];
+    for (name, value) in rows {
+        if let Some(value) = value {
+            html.push_str(&format!(
+                "<tr><th>{}</th><td class=\"hash\">{}</td></tr>\n",
+                name,
+                escape(value)
+            ));
+        }
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+fn findings_table(result: &AnalysisResult) -> String {
+    let mut html = format!("<h3>Findings ({})</h3>\n", result.findings.len());
+    if result.findings.is_empty() {
+        return html;
+    }
+    html.push_str(
+        "<table>\n<tr><th>Rule</th><th>Severity</th><th>Finding</th><th>Object</th>\
+         <th>Offset</th><th>Evidence</th></tr>\n",
+    );
+    for finding in &result.findings {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td class=\"sev-{}\">{}</td><td>{}</td><td>{}</td><td>{}</td>\
+             <td><code>{}</code></td></tr>\n",
+            finding.rule_id,
+            finding.severity.label(),
+            finding.severity.label(),
+            escape(&finding.title),
+            finding.object_id.map_or(String::new(), |id| id.to_string()),
+            finding
+                .offset
+                .map_or(String::new(), |offset| offset.to_string()),
+            escape(finding.evidence.as_deref().unwrap_or(""))
+        ));
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+fn statistics(result: &AnalysisResult) -> String {
+    let stats = &result.object_statistics;
+    let mut html = format!(
+        "<h3>Objects</h3>\n<table>\n<tr><th>Objects</th><td>{}</td></tr>\n\
+         <tr><th>Streams</th><td>{}</td></tr>\n<tr><th>JavaScript</th><td>{}</td></tr>\n\
+         <tr><th>Object streams</th><td>{}</td></tr>\n</table>\n",
+        stats.total_objects, stats.stream_objects, stats.js_objects, stats.obj_stm_objects
+    );
+
+    // One bar per bit of entropy per byte, scaled to the fullest
+    let fullest = stats.entropy_histogram.iter().copied().max();
+    if let Some(fullest) = fullest.filter(|&count| count > 0) {
+        html.push_str("<p>Stream entropy, in bits per byte</p>\n<div class=\"bars\">");
+        for count in stats.entropy_histogram {
+            html.push_str(&format!(
+                "<div style=\"height: {}%\" title=\"{} streams\"></div>",
+                count * 100 / fullest,
+                count
+            ));
+        }
+        html.push_str("</div>\n<div class=\"bar-labels\">");
+        for bits in 0..stats.entropy_histogram.len() {
+            html.push_str(&format!("<span>{}-{}</span>", bits, bits + 1));
+        }
+        html.push_str("</div>\n");
+    }
+    html
+}
+
+fn scripts(result: &AnalysisResult) -> String {
+    if result.javascript_objects.is_empty() {
+        return String::new();
+    }
+    let mut html = format!(
+        "<h3>JavaScript ({})</h3>\n",
+        result.javascript_objects.len()
+    );
+    for js in &result.javascript_objects {
+        html.push_str(&format!(
+            "<details open>\n<summary>Object {} ({} bytes, SHA-256 <code>{}</code>)</summary>\n\
+             <pre><code>{}</code></pre>\n",
+            js.id,
+            js.content.len(),
+            js.sha256,
+            highlight_js(&js.content)
+        ));
+        if let Some(deobfuscated) = &js.deobfuscated {
+            html.push_str(&format!(
+                "<p>Deobfuscated:</p>\n<pre><code>{}</code></pre>\n",
+                highlight_js(deobfuscated)
+            ));
+        }
+        html.push_str("</details>\n");
+    }
+    html
+}
+
+/// Collapsed hex views of the streams that findings name, with the findings
+/// as the reason each is shown.
+fn hex_views(doc: &Document, result: &AnalysisResult, max_stream_size: u64) -> String {
+    let mut streams: Vec<(u32, Vec<&str>)> = Vec::new();
+    for finding in &result.findings {
+        let Some(id) = finding.object_id else {
+            continue;
+        };
+        match streams.iter_mut().find(|(seen, _)| *seen == id) {
+            Some((_, reasons)) => {
+                if !reasons.contains(&finding.title.as_str()) {
+                    reasons.push(&finding.title)
+                }
+            }
+            None => streams.push((id, vec![&finding.title])),
+        }
+    }
+
+    let mut html = String::new();
+    let mut shown = 0;
+    for (id, reasons) in streams {
+        if shown == MAX_HEX_VIEWS {
+            break;
+        }
+        let Some(stream) = doc
+            .objects
+            .iter()
+            .find(|(object_id, _)| object_id.0 == id)
+            .and_then(|(_, object)| match object {
+                Object::Stream(stream) => Some(stream),
+                _ => None,
+            })
+        else {
+            continue;
+        };
+        let (content, decoded) = match decode_stream_bounded(stream, max_stream_size) {
+            Some(Ok(content)) => (content, true),
+            _ => (stream.content.clone(), false),
+        };
+        html.push_str(&format!(
+            "<details>\n<summary>Object {}: {} ({} bytes{})</summary>\n<pre>{}</pre>\n</details>\n",
+            id,
+            escape(&reasons.join("; ")),
+            content.len(),
+            if decoded { "" } else { ", not decoded" },
+            escape(&hex_dump(&content[..content.len().min(HEX_VIEW_BYTES)]))
+        ));
+        shown += 1;
+    }
+    if html.is_empty() {
+        return html;
+    }
+    format!("<h3>Suspicious streams</h3>\n{}", html)
+}
+
+/// Sixteen bytes a line: offset, hex and the printable ASCII.
+pub fn hex_dump(data: &[u8]) -> String {
+    let mut dump = String::new();
+    for (line, chunk) in data.chunks(16).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let ascii: String = chunk
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        dump.push_str(&format!(
+            "{:08x}  {:<47}  |{}|\n",
+            line * 16,
+            hex.join(" "),
+            ascii
+        ));
+    }
+    dump
+}
+
+/// Escapes the script and wraps its keywords, strings, numbers and
+/// comments in spans. A rough lexer is enough: the script is only read,
+/// and a regex literal read as something else merely looks wrong.
+fn highlight_js(script: &str) -> String {
+    let chars: Vec<char> = script.chars().collect();
+    let mut html = String::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let (class, end) = js_token(&chars, start);
+        let text: String = chars[start..end].iter().collect();
+        match class {
+            Some(class) => html.push_str(&format!(
+                "<span class=\"{}\">{}</span>",
+                class,
+                escape(&text)
+            )),
+            None => html.push_str(&escape(&text)),
+        }
+        start = end;
+    }
+    html
+}
+
+/// The highlight class and end of the token at `start`.
+fn js_token(chars: &[char], start: usize) -> (Option<&'static str>, usize) {
+    let rest = &chars[start..];
+    let end_of = |offset: Option<usize>, extra: usize| {
+        offset.map_or(chars.len(), |offset| start + offset + extra)
+    };
+    match rest {
+        ['/', '/', ..] => (Some("com"), end_of(rest.iter().position(|&c| c == '\n'), 0)),
+        ['/', '*', ..] => (
+            Some("com"),
+            end_of(rest[2..].windows(2).position(|pair| pair == ['*', '/']), 4),
+        ),
+        [quote @ ('"' | '\'' | '`'), ..] => {
+            let mut end = start + 1;
+            while end < chars.len() {
+                match chars[end] {
+                    '\\' => end += 2,
+                    c if c == *quote => return (Some("str"), end + 1),
+                    _ => end += 1,
+                }
+            }
+            (Some("str"), chars.len())
+        }
+        [c, ..] if c.is_ascii_digit() => (
+            Some("num"),
+            end_of(
+                rest.iter()
+                    .position(|&c| !(c.is_ascii_alphanumeric() || c == '.')),
+                0,
+            ),
+        ),
+        [c, ..] if c.is_alphabetic() || *c == '_' || *c == '$' => {
+            let end = end_of(
+                rest.iter()
+                    .position(|&c| !(c.is_alphanumeric() || c == '_' || c == '$')),
+                0,
+            );
+            let word: String = chars[start..end].iter().collect();
+            (JS_KEYWORDS.contains(&word.as_str()).then_some("kw"), end)
+        }
+        _ => (None, start + 1),
+    }
+}
+
+fn verdict_class(classification: &ThreatClassification) -> &'static str {
+    match classification {
+        ThreatClassification::Clean => "clean",
+        ThreatClassification::Suspicious(_) => "suspicious",
+        ThreatClassification::ProbableExploit(_) => "exploit",
+        ThreatClassification::ConfirmedMalicious(_) => "malicious",
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
@@ -0,0 +1,449 @@
+use crate::batch::{has_pdf_marker, SNIFF_WINDOW};
+use pdf_sentinel::findings::triggered_rules;
+use pdf_sentinel::scoring::Severity;
+use pdf_sentinel::{AnalysisResult, Analyzer, SentinelError};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often idle connections and the listener check whether Ctrl-C was
+/// pressed.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// A request that has started must keep arriving at least this fast.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longest ICAP request or header line accepted.
+const MAX_LINE: usize = 8 * 1024;
+/// Most ICAP headers accepted in one request.
+const MAX_HEADERS: usize = 100;
+/// Most bytes of encapsulated HTTP headers accepted, as the Encapsulated
+/// header's last offset gives them.
+const MAX_HTTP_HEADERS: usize = 64 * 1024;
+
+const SERVICE: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+/// Changes with every release, so that proxies drop verdicts cached from an
+/// older one.
+const ISTAG: &str = concat!(
+    "\"",
+    env!("CARGO_PKG_NAME"),
+    "-",
+    env!("CARGO_PKG_VERSION"),
+    "\""
+);
+
+/// An ICAP (RFC 3507) service that proxies such as Squid send downloads to
+/// with RESPMOD, and uploads to with REQMOD. PDFs at or above the severity
+/// are replaced with a 403 page; everything else is let through unchanged.
+///
+/// Bodies that are not PDFs, by content type or header, are let through
+/// after the preview without being read. PDFs that fail to parse, often
+/// because the proxy only saw part of the file, are let through as well.
+pub struct IcapService<'a> {
+    analyzer: &'a Analyzer,
+    severity: Severity,
+    max_body_size: u64,
+}
+
+/// An ICAP request's method and headers.
+struct RequestHead {
+    method: String,
+    headers: Vec<(String, String)>,
+}
+
+impl RequestHead {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl<'a> IcapService<'a> {
+    /// `max_body_size` bounds the bytes kept of any one body; larger ones are
+    /// let through unscanned.
+    pub fn new(analyzer: &'a Analyzer, severity: Severity, max_body_size: u64) -> Self {
+        IcapService {
+            analyzer,
+            severity,
+            max_body_size,
+        }
+    }
+
+    /// Serves on `address` (port 1344 is ICAP's) until Ctrl-C, a thread per
+    /// connection. Requests already being handled when the signal arrives
+    /// are answered before returning.
+    pub fn serve(&self, address: &str) -> Result<(), SentinelError> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let running = Arc::new(AtomicBool::new(true));
+        let handler_flag = Arc::clone(&running);
+        ctrlc::set_handler(move || handler_flag.store(false, Ordering::SeqCst))
+            .map_err(|err| SentinelError::Io(io::Error::other(err)))?;
+        eprintln!("ICAP service listening on {}", address);
+
+        let running: &AtomicBool = &running;
+        std::thread::scope(|scope| {
+            while running.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    Ok((stream, peer)) => {
+                        scope.spawn(move || {
+                            if let Err(err) = self.handle_connection(stream, running) {
+                                tracing::warn!(%peer, error = %err, "ICAP connection failed");
+                            }
+                        });
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(POLL_INTERVAL)
+                    }
+                    Err(err) => tracing::error!(error = %err, "failed to accept a connection"),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Answers requests on `stream` until the client closes it or asks to.
+    fn handle_connection(&self, stream: TcpStream, running: &AtomicBool) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+        loop {
+            // Wait for the next request, looking out for Ctrl-C in between
+            reader.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+            loop {
+                match reader.fill_buf() {
+                    Ok([]) => return Ok(()),
+                    Ok(_) => break,
+                    Err(err)
+                        if matches!(
+                            err.kind(),
+                            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                        ) =>
+                    {
+                        if !running.load(Ordering::SeqCst) {
+                            return Ok(());
+                        }
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            reader.get_ref().set_read_timeout(Some(REQUEST_TIMEOUT))?;
+
+            let handled = read_request_head(&mut reader).and_then(|head| {
+                let keep_alive = !head
+                    .header("Connection")
+                    .is_some_and(|value| value.eq_ignore_ascii_case("close"));
+                match head.method.as_str() {
+                    "OPTIONS" => write_options(&mut writer)?,
+                    "REQMOD" | "RESPMOD" => self.modify(&head, &mut reader, &mut writer)?,
+                    _ => write_status(&mut writer, 501, "Method Not Implemented")?,
+                }
+                Ok(keep_alive)
+            });
+            let keep_alive = match handled {
+                Ok(keep_alive) => keep_alive,
+                Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                    write_status(&mut writer, 400, "Bad Request")?;
+                    false
+                }
+                Err(err) => return Err(err),
+            };
+            writer.flush()?;
+            if !keep_alive {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Handles REQMOD and RESPMOD, which differ only in whether the HTTP
+    /// request or the response carries the body to scan.
+    fn modify(
+        &self,
+        head: &RequestHead,
+        reader: &mut impl BufRead,
+        writer: &mut impl Write,
+    ) -> io::Result<()> {
+        let sections = head
+            .header("Encapsulated")
+            .map(parse_encapsulated)
+            .ok_or_else(|| invalid("no Encapsulated header"))??;
+        let Some(&(last, headers_length)) = sections.last() else {
+            return Err(invalid("empty Encapsulated header"));
+        };
+        if headers_length > MAX_HTTP_HEADERS {
+            return Err(invalid("encapsulated HTTP headers too long"));
+        }
+        let has_body = last.ends_with("-body") && last != "null-body";
+        let mut http_headers = vec![0; headers_length];
+        reader.read_exact(&mut http_headers)?;
+        let section = |name: &str| -> &[u8] {
+            let Some(index) = sections.iter().position(|&(key, _)| key == name) else {
+                return &[];
+            };
+            let end = sections
+                .get(index + 1)
+                .map_or(headers_length, |&(_, at)| at);
+            http_headers.get(sections[index].1..end).unwrap_or_default()
+        };
+        let (http_request, http_response) = (section("req-hdr"), section("res-hdr"));
+        let scanned_headers = if head.method == "RESPMOD" {
+            http_response
+        } else {
+            http_request
+        };
+        let declared_pdf = http_header(scanned_headers, "Content-Type")
+            .is_some_and(|kind| kind.to_ascii_lowercase().contains("pdf"));
+        let allow_204 = head
+            .header("Allow")
+            .is_some_and(|allow| allow.split(',').any(|code| code.trim() == "204"));
+
+        let mut body = Vec::new();
+        if has_body {
+            let complete = read_chunks(reader, &mut body, self.max_body_size)?;
+            if head.header("Preview").is_some() && !complete {
+                // The preview alone shows whether the rest is worth reading,
+                // and a 204 is always allowed in answer to one
+                if !declared_pdf && !has_pdf_marker(&body) {
+                    return write_status(writer, 204, "No Content");
+                }
+                writer.write_all(b"ICAP/1.0 100 Continue\r\n\r\n")?;
+                writer.flush()?;
+                read_chunks(reader, &mut body, self.max_body_size)?;
+            }
+        }
+
+        let too_large = body.len() as u64 > self.max_body_size;
+        let result = if too_large {
+            tracing::warn!(
+                max_body_size = self.max_body_size,
+                "body too large to scan, let through"
+            );
+            None
+        } else if declared_pdf || has_pdf_marker(&body) {
+            self.analyzer
+                .analyze_bytes(&body)
+                .inspect_err(|err| tracing::warn!(error = %err, "PDF not analyzed, let through"))
+                .ok()
+        } else {
+            None
+        };
+
+        match result.filter(|result| result.severity >= self.severity) {
+            Some(result) => write_blocked(writer, &result),
+            None if allow_204 => write_status(writer, 204, "No Content"),
+            // What was dropped can't be sent back
+            None if too_large => write_status(writer, 500, "Server Error"),
+            None if head.method == "RESPMOD" => {
+                write_unmodified(writer, "res", http_response, has_body.then_some(&body[..]))
+            }
+            None => write_unmodified(writer, "req", http_request, has_body.then_some(&body[..])),
+        }
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Reads a line, without its line break. Lines longer than `MAX_LINE` are
+/// invalid.
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = Vec::new();
+    reader.take(MAX_LINE as u64).read_until(b'\n', &mut line)?;
+    if line.last() != Some(&b'\n') {
+        if line.len() == MAX_LINE {
+            return Err(invalid("line too long"));
+        }
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    line.pop();
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    String::from_utf8(line).map_err(|_| invalid("header is not UTF-8"))
+}
+
+fn read_request_head(reader: &mut impl BufRead) -> io::Result<RequestHead> {
+    let request_line = read_line(reader)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(_uri), Some("ICAP/1.0")) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid("not an ICAP/1.0 request"));
+    };
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(invalid("too many headers"));
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| invalid("malformed header"))?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+    Ok(RequestHead {
+        method: method.to_string(),
+        headers,
+    })
+}
+
+/// Parses e.g. `req-hdr=0, res-hdr=137, res-body=296` into its sections and
+/// their offsets, which must ascend.
+fn parse_encapsulated(value: &str) -> io::Result<Vec<(&str, usize)>> {
+    let mut sections: Vec<(&str, usize)> = Vec::new();
+    for entry in value.split(',') {
+        let (name, offset) = entry
+            .trim()
+            .split_once('=')
+            .ok_or_else(|| invalid("malformed Encapsulated header"))?;
+        let offset: usize = offset
+            .parse()
+            .map_err(|_| invalid("malformed Encapsulated offset"))?;
+        if sections.last().is_some_and(|&(_, last)| offset < last) {
+            return Err(invalid("Encapsulated offsets out of order"));
+        }
+        sections.push((name, offset));
+    }
+    Ok(sections)
+}
+
+/// A header of an encapsulated HTTP message.
+fn http_header(message: &[u8], name: &str) -> Option<String> {
+    String::from_utf8_lossy(message)
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim().to_string())
+}
+
+/// Reads a chunked body up to its last, empty chunk, keeping no more than
+/// one byte past `limit` so that a body too large can be told apart.
+/// Returns whether the last chunk was marked `ieof`, i.e. a preview that
+/// holds the whole body.
+fn read_chunks(reader: &mut impl BufRead, body: &mut Vec<u8>, limit: u64) -> io::Result<bool> {
+    let keep_up_to = usize::try_from(limit.saturating_add(1)).unwrap_or(usize::MAX);
+    loop {
+        let line = read_line(reader)?;
+        let (size, extension) = line.split_once(';').unwrap_or((&line, ""));
+        let size =
+            usize::from_str_radix(size.trim(), 16).map_err(|_| invalid("malformed chunk size"))?;
+        if size == 0 {
+            // Trailers, if any, end with an empty line
+            while !read_line(reader)?.is_empty() {}
+            return Ok(extension.trim() == "ieof");
+        }
+
+        let keep = keep_up_to.saturating_sub(body.len()).min(size);
+        let start = body.len();
+        body.resize(start + keep, 0);
+        reader.read_exact(&mut body[start..])?;
+        let skip = (size - keep) as u64;
+        if io::copy(&mut reader.by_ref().take(skip), &mut io::sink())? != skip {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        if !read_line(reader)?.is_empty() {
+            return Err(invalid("chunk longer than its size"));
+        }
+    }
+}
+
+fn write_status(writer: &mut impl Write, code: u16, reason: &str) -> io::Result<()> {
+    write!(
+        writer,
+        "ICAP/1.0 {} {}\r\nISTag: {}\r\nEncapsulated: null-body=0\r\n\r\n",
+        code, reason, ISTAG
+    )
+}
+
+fn write_options(writer: &mut impl Write) -> io::Result<()> {
+    write!(
+        writer,
+        "ICAP/1.0 200 OK\r\n\
+         Methods: RESPMOD, REQMOD\r\n\
+         Service: {}\r\n\
+         ISTag: {}\r\n\
+         Allow: 204\r\n\
+         Preview: {}\r\n\
+         Transfer-Preview: *\r\n\
+         Options-TTL: 3600\r\n\
+         Encapsulated: null-body=0\r\n\r\n",
+        SERVICE, ISTAG, SNIFF_WINDOW
+    )
+}
+
+/// Sends the HTTP message back as it came, `kind` being `req` or `res`.
+fn write_unmodified(
+    writer: &mut impl Write,
+    kind: &str,
+    http_headers: &[u8],
+    body: Option<&[u8]>,
+) -> io::Result<()> {
+    let body_section = match body {
+        Some(_) => format!("{}-body", kind),
+        None => "null-body".to_string(),
+    };
+    write!(
+        writer,
+        "ICAP/1.0 200 OK\r\nISTag: {}\r\nEncapsulated: {}-hdr=0, {}={}\r\n\r\n",
+        ISTAG,
+        kind,
+        body_section,
+        http_headers.len()
+    )?;
+    writer.write_all(http_headers)?;
+    if let Some(body) = body {
+        write_chunked(writer, body)?;
+    }
+    Ok(())
+}
+
+/// Replaces the HTTP message with a 403 page naming the verdict.
+fn write_blocked(writer: &mut impl Write, result: &AnalysisResult) -> io::Result<()> {
+    let rules = triggered_rules(&result.findings).join(",");
+    let page = format!(
+        "<!DOCTYPE html>\n<html><head><title>PDF blocked</title></head><body>\
+         <h1>PDF blocked</h1><p>{} classified this PDF as {} at {} severity \
+         (rules {}).</p></body></html>\n",
+        env!("CARGO_PKG_NAME"),
+        result.classification.label(),
+        result.severity.label(),
+        rules
+    );
+    let http_headers = format!(
+        "HTTP/1.1 403 Forbidden\r\n\
+         Content-Type: text/html; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Cache-Control: no-store\r\n\
+         Connection: close\r\n\r\n",
+        page.len()
+    );
+    write!(
+        writer,
+        "ICAP/1.0 200 OK\r\n\
+         ISTag: {}\r\n\
+         X-Infection-Found: Type=0; Resolution=2; Threat={} ({});\r\n\
+         Encapsulated: res-hdr=0, res-body={}\r\n\r\n{}",
+        ISTAG,
+        result.classification.label(),
+        rules,
+        http_headers.len(),
+        http_headers
+    )?;
+    write_chunked(writer, page.as_bytes())
+}
+
+fn write_chunked(writer: &mut impl Write, data: &[u8]) -> io::Result<()> {
+    if !data.is_empty() {
+        write!(writer, "{:x}\r\n", data.len())?;
+        writer.write_all(data)?;
+        writer.write_all(b"\r\n")?;
+    }
+    writer.write_all(b"0\r\n\r\n")
+}
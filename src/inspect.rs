@@ -0,0 +1,519 @@
+//! `pdf-sentinel inspect FILE`: a shell for going through a document by
+//! hand once the verdict is in, in the manner of peepdf. Commands are read a
+//! line at a time from stdin, so a session can be scripted by piping them
+//! in.
+
+use lopdf::{Document, Object, Stream, StringFormat};
+use pdf_sentinel::actions::find_external_actions;
+use pdf_sentinel::attachments::find_embedded_files;
+use pdf_sentinel::colorspace::check_colorspace_anomalies;
+use pdf_sentinel::dates::detect_metadata_date_mismatch;
+use pdf_sentinel::destinations::analyze_named_destinations;
+use pdf_sentinel::encryption::unlock;
+use pdf_sentinel::entropy::check_stream_entropy;
+use pdf_sentinel::filters::{populate_stream_cache, StreamCache};
+use pdf_sentinel::forms::check_acroform_injection;
+use pdf_sentinel::framing::check_framing;
+use pdf_sentinel::graph::{labeled_references, object_type};
+use pdf_sentinel::heap_spray::check_heap_spray_arrays;
+use pdf_sentinel::html::hex_dump;
+use pdf_sentinel::javascript::{check_open_action_js, detect_split_javascript};
+use pdf_sentinel::js_ast::analyze_js_ast;
+use pdf_sentinel::media::find_media;
+use pdf_sentinel::names::find_escaped_names;
+use pdf_sentinel::revisions::check_revisions;
+use pdf_sentinel::shellcode::check_shellcode_heuristics;
+use pdf_sentinel::signatures::find_embedded_signatures;
+use pdf_sentinel::structure::validate_structure;
+use pdf_sentinel::urls::find_urls;
+use pdf_sentinel::xref::{check_xref_consistency, check_xref_stream_anomalies};
+use pdf_sentinel::{AnalysisResult, Analyzer, SentinelError};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+
+/// Content longer than this is cut short when shown.
+const MAX_SHOWN_BYTES: usize = 64 * 1024;
+
+const HELP: &str = "\
+objects              list every object with its type and stream size
+object N             show object N, with a stream's decoded content
+stream N             show only the decoded content of stream N
+search TEXT          find the objects whose strings, names or decoded
+search hex HEX       stream content hold TEXT, or the bytes HEX
+refs N               the references object N makes, by key
+refby N              the objects that reference object N
+follow N KEY         show what KEY of object N references, e.g.
+                     follow 1 OpenAction
+findings             the automated verdict and its findings
+run DETECTOR [N]     run one detector again and show what it finds;
+                     run alone lists them
+help                 this list
+quit                 leave";
+
+/// Detectors `run` knows, with what they look at. Those marked N take the
+/// object whose content or script to check.
+const DETECTORS: [(&str, &str); 21] = [
+    (
+        "actions",
+        "Launch, GoToR, GoToE, SubmitForm and ImportData actions",
+    ),
+    ("attachments", "embedded files"),
+    ("colorspace", "colorspace anomalies"),
+    ("dates", "Info and XMP creation dates that disagree"),
+    ("destinations", "suspicious named destinations"),
+    ("entropy", "high-entropy streams"),
+    ("forms", "form field actions"),
+    (
+        "framing",
+        "data outside the header and %%EOF, and polyglots",
+    ),
+    ("heap-spray", "arrays of heap spray values"),
+    ("js N", "the script of object N, parsed"),
+    ("media", "RichMedia, 3D, Sound and Movie annotations"),
+    ("names", "names with needless #xx escapes"),
+    ("open-action", "a script the document runs when opened"),
+    (
+        "revisions",
+        "incremental updates and the objects they redefine",
+    ),
+    ("shellcode N", "shellcode in the content of object N"),
+    ("signatures", "executables and archives inside streams"),
+    ("split-js", "scripts split across objects"),
+    ("structure", "structure violations"),
+    ("urls", "the URLs linked to"),
+    (
+        "xref",
+        "cross-reference tables that don't match the objects",
+    ),
+    ("xref-streams", "xref stream offsets that point elsewhere"),
+];
+
+struct Inspector<'a> {
+    doc: Document,
+    raw: Vec<u8>,
+    streams: StreamCache,
+    result: AnalysisResult,
+    analyzer: &'a Analyzer,
+}
+
+/// Analyzes `path`, prints the verdict and reads commands until `quit` or
+/// the end of input.
+pub fn inspect(
+    path: &Path,
+    analyzer: &Analyzer,
+    password: Option<&str>,
+) -> Result<(), SentinelError> {
+    let raw = std::fs::read(path)?;
+    let result = analyzer.analyze_bytes(&raw)?;
+    let mut doc = Document::load_mem(&raw)?;
+    unlock(&mut doc, password);
+    let streams = populate_stream_cache(&doc, analyzer.compiled_config())?;
+    let inspector = Inspector {
+        doc,
+        raw,
+        streams,
+        result,
+        analyzer,
+    };
+    println!(
+        "{}: {} (score {}), {} objects. Type help for the commands.",
+        path.display(),
+        inspector.result.classification.label(),
+        inspector.result.severity_score,
+        inspector.doc.objects.len()
+    );
+
+    let interactive = io::stdin().is_terminal();
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        if interactive {
+            print!("pdf> ");
+            io::stdout().flush()?;
+        }
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => continue,
+            ["quit" | "exit"] => break,
+            words => {
+                if let Err(message) = inspector.run(words) {
+                    println!("error: {}", message);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Inspector<'_> {
+    fn run(&self, words: &[&str]) -> Result<(), String> {
+        match words {
+            ["help"] => println!("{}", HELP),
+            ["objects"] => self.list_objects(),
+            ["object", id] => self.show_object(parse_id(id)?)?,
+            ["stream", id] => self.show_stream(parse_id(id)?)?,
+            ["search", "hex", hex] => self.search(&parse_hex(hex)?),
+            ["search", text @ ..] if !text.is_empty() => self.search(text.join(" ").as_bytes()),
+            ["refs", id] => self.show_references(parse_id(id)?)?,
+            ["refby", id] => self.show_referrers(parse_id(id)?),
+            ["follow", id, key] => self.follow(parse_id(id)?, key)?,
+            ["findings"] => self.show_findings(),
+            ["run"] => {
+                for (name, description) in DETECTORS {
+                    println!("{:<14} {}", name, description);
+                }
+            }
+            ["run", name, args @ ..] => {
+                let found = self.run_detector(name, args)?;
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&found).map_err(|err| err.to_string())?
+                );
+            }
+            [command, ..] => return Err(format!("unknown command {}; try help", command)),
+            [] => {}
+        }
+        Ok(())
+    }
+
+    fn object(&self, id: u32) -> Result<&Object, String> {
+        self.doc
+            .objects
+            .iter()
+            .find(|((number, _), _)| *number == id)
+            .map(|(_, object)| object)
+            .ok_or_else(|| format!("no object {}", id))
+    }
+
+    fn list_objects(&self) {
+        for (&(id, generation), object) in &self.doc.objects {
+            let kind = object_type(object).map_or_else(String::new, |kind| format!("/{}", kind));
+            match object {
+                Object::Stream(stream) => println!(
+                    "{:>6} {}  stream {} bytes  {}",
+                    id,
+                    generation,
+                    stream.content.len(),
+                    kind
+                ),
+                _ => println!(
+                    "{:>6} {}  {:<20}  {}",
+                    id,
+                    generation,
+                    type_name(object),
+                    kind
+                ),
+            }
+        }
+    }
+
+    fn show_object(&self, id: u32) -> Result<(), String> {
+        let object = self.object(id)?;
+        println!("{}", format_object(object, 0));
+        if let Object::Stream(_) = object {
+            self.show_stream(id)?;
+        }
+        Ok(())
+    }
+
+    fn show_stream(&self, id: u32) -> Result<(), String> {
+        let Object::Stream(stream) = self.object(id)? else {
+            return Err(format!("object {} is not a stream", id));
+        };
+        let content = match self.streams.get(id) {
+            Some(content) => content,
+            None => {
+                println!("(not decoded; showing the stored bytes)");
+                &stream.content
+            }
+        };
+        show_content(content);
+        Ok(())
+    }
+
+    fn search(&self, needle: &[u8]) {
+        if needle.is_empty() {
+            return;
+        }
+        let mut found = false;
+        for (&(id, _), object) in &self.doc.objects {
+            let mut values = Vec::new();
+            collect_values(object, &mut values);
+            if values.iter().any(|value| contains(value, needle)) {
+                println!("object {}: in a string or name", id);
+                found = true;
+            }
+            if let Some(content) = self.streams.get(id) {
+                let offsets: Vec<usize> = content
+                    .windows(needle.len())
+                    .enumerate()
+                    .filter(|(_, window)| *window == needle)
+                    .map(|(offset, _)| offset)
+                    .collect();
+                if !offsets.is_empty() {
+                    println!(
+                        "object {}: in the decoded stream at offsets {:?}",
+                        id, offsets
+                    );
+                    found = true;
+                }
+            }
+        }
+        if !found {
+            println!("not found");
+        }
+    }
+
+    fn show_references(&self, id: u32) -> Result<(), String> {
+        for (target, key) in labeled_references(self.object(id)?) {
+            println!("/{} -> {} 0 R", key, target);
+        }
+        Ok(())
+    }
+
+    fn show_referrers(&self, id: u32) {
+        for (&(referrer, _), object) in &self.doc.objects {
+            for (target, key) in labeled_references(object) {
+                if target == id {
+                    println!("{} 0 R /{}", referrer, key);
+                }
+            }
+        }
+    }
+
+    fn follow(&self, id: u32, key: &str) -> Result<(), String> {
+        let key = key.trim_start_matches('/');
+        let targets: Vec<u32> = labeled_references(self.object(id)?)
+            .into_iter()
+            .filter(|(_, path)| path == key)
+            .map(|(target, _)| target)
+            .collect();
+        if targets.is_empty() {
+            return Err(format!("object {} has no reference under /{}", id, key));
+        }
+        for target in targets {
+            println!("{} 0 R:", target);
+            self.show_object(target)?;
+        }
+        Ok(())
+    }
+
+    fn show_findings(&self) {
+        let result = &self.result;
+        println!(
+            "{}, score {} ({})",
+            result.classification.label(),
+            result.severity_score,
+            result.severity.label()
+        );
+        for finding in &result.findings {
+            match finding.object_id {
+                Some(id) => println!("[{}] {} (object {})", finding.rule_id, finding.title, id),
+                None => println!("[{}] {}", finding.rule_id, finding.title),
+            }
+        }
+    }
+
+    /// The content a detector that takes an object looks at: a stream's
+    /// decoded content, or the `/JS` string of an action.
+    fn content(&self, id: u32) -> Result<Vec<u8>, String> {
+        match self.object(id)? {
+            Object::Stream(stream) => Ok(self
+                .streams
+                .get(id)
+                .map_or_else(|| stream.content.clone(), <[u8]>::to_vec)),
+            Object::Dictionary(dict) => match dict.get(b"JS") {
+                Ok(Object::String(script, _)) => Ok(script.clone()),
+                Ok(Object::Reference(target)) => self.content(target.0),
+                _ => Err(format!(
+                    "object {} is neither a stream nor holds a /JS string",
+                    id
+                )),
+            },
+            Object::String(text, _) => Ok(text.clone()),
+            _ => Err(format!("object {} has no content to check", id)),
+        }
+    }
+
+    fn run_detector(&self, name: &str, args: &[&str]) -> Result<Value, String> {
+        let doc = &self.doc;
+        let config = self.analyzer.compiled_config();
+        let object = || match args {
+            [id] => parse_id(id),
+            _ => Err(format!("run {} takes an object number", name)),
+        };
+        let found = match name {
+            "actions" => json!(find_external_actions(doc)),
+            "attachments" => json!(find_embedded_files(doc, &self.streams)),
+            "colorspace" => json!(check_colorspace_anomalies(doc, &self.streams)),
+            "dates" => json!(detect_metadata_date_mismatch(doc, &self.streams)),
+            "destinations" => json!(analyze_named_destinations(doc)),
+            "entropy" => json!(check_stream_entropy(doc, &self.streams, config).1),
+            "forms" => json!(check_acroform_injection(doc)),
+            "framing" => json!(check_framing(&self.raw)),
+            "heap-spray" => json!(check_heap_spray_arrays(doc, config)),
+            "js" => {
+                let script = self.content(object()?)?;
+                json!(analyze_js_ast(&String::from_utf8_lossy(&script)))
+            }
+            "media" => json!(find_media(doc, &self.streams)),
+            "names" => json!(find_escaped_names(&self.raw)),
+            "open-action" => json!(check_open_action_js(doc, &self.streams)),
+            "revisions" => json!(check_revisions(&self.raw)),
+            "shellcode" => json!(check_shellcode_heuristics(&self.content(object()?)?)),
+            "signatures" => json!(find_embedded_signatures(self.streams.iter())),
+            "split-js" => json!(detect_split_javascript(doc, config)),
+            "structure" => json!(validate_structure(doc)),
+            "urls" => json!(find_urls(doc)),
+            "xref" => json!(check_xref_consistency(doc, &self.raw)),
+            "xref-streams" => json!(check_xref_stream_anomalies(doc, &self.raw)),
+            _ => return Err(format!("unknown detector {}; run alone lists them", name)),
+        };
+        Ok(found)
+    }
+}
+
+fn parse_id(text: &str) -> Result<u32, String> {
+    text.parse()
+        .map_err(|_| format!("{:?} is not an object number", text))
+}
+
+fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    if !text.len().is_multiple_of(2) {
+        return Err(format!("{:?} is not an even number of hex digits", text));
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| {
+            text.get(i..i + 2)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(|| format!("{:?} is not hex", text))
+        })
+        .collect()
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+/// The strings and names in `object` and the direct objects inside it.
+fn collect_values<'a>(object: &'a Object, values: &mut Vec<&'a [u8]>) {
+    match object {
+        Object::Name(name) => values.push(name),
+        Object::String(text, _) => values.push(text),
+        Object::Array(items) => {
+            for item in items {
+                collect_values(item, values);
+            }
+        }
+        Object::Dictionary(dict) | Object::Stream(Stream { dict, .. }) => {
+            for (key, value) in dict.iter() {
+                values.push(key);
+                collect_values(value, values);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn type_name(object: &Object) -> &'static str {
+    match object {
+        Object::Null => "null",
+        Object::Boolean(_) => "boolean",
+        Object::Integer(_) => "integer",
+        Object::Real(_) => "real",
+        Object::Name(_) => "name",
+        Object::String(..) => "string",
+        Object::Array(_) => "array",
+        Object::Dictionary(_) => "dictionary",
+        Object::Stream(_) => "stream",
+        Object::Reference(_) => "reference",
+    }
+}
+
+/// Writes `object` back in PDF syntax, a dictionary entry a line.
+pub fn format_object(object: &Object, indent: usize) -> String {
+    match object {
+        Object::Null => "null".to_string(),
+        Object::Boolean(value) => value.to_string(),
+        Object::Integer(value) => value.to_string(),
+        Object::Real(value) => value.to_string(),
+        Object::Name(name) => format!("/{}", String::from_utf8_lossy(name)),
+        Object::String(text, StringFormat::Hexadecimal) => {
+            let hex: String = text.iter().map(|byte| format!("{:02x}", byte)).collect();
+            format!("<{}>", hex)
+        }
+        Object::String(text, StringFormat::Literal) => format!("({})", escape_string(text)),
+        Object::Array(items) => {
+            let items: Vec<String> = items
+                .iter()
+                .map(|item| format_object(item, indent))
+                .collect();
+            format!("[{}]", items.join(" "))
+        }
+        Object::Dictionary(dict) => {
+            let pad = "  ".repeat(indent + 1);
+            let mut text = "<<\n".to_string();
+            for (key, value) in dict.iter() {
+                text.push_str(&format!(
+                    "{}/{} {}\n",
+                    pad,
+                    String::from_utf8_lossy(key),
+                    format_object(value, indent + 1)
+                ));
+            }
+            text.push_str(&"  ".repeat(indent));
+            text.push_str(">>");
+            text
+        }
+        Object::Stream(stream) => format!(
+            "{}\nstream ({} bytes stored)",
+            format_object(&Object::Dictionary(stream.dict.clone()), indent),
+            stream.content.len()
+        ),
+        Object::Reference((id, generation)) => format!("{} {} R", id, generation),
+    }
+}
+
+/// A literal string's bytes with its delimiters and anything unprintable
+/// escaped as PDF writes them.
+fn escape_string(text: &[u8]) -> String {
+    let mut escaped = String::new();
+    for &byte in text {
+        match byte {
+            b'(' | b')' | b'\\' => {
+                escaped.push('\\');
+                escaped.push(byte as char);
+            }
+            b'\n' => escaped.push_str("\\n"),
+            b'\r' => escaped.push_str("\\r"),
+            b'\t' => escaped.push_str("\\t"),
+            byte if byte.is_ascii_graphic() || byte == b' ' => escaped.push(byte as char),
+            byte => escaped.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    escaped
+}
+
+/// Text as it is, anything else as a hex dump, cut short past
+/// `MAX_SHOWN_BYTES`.
+fn show_content(content: &[u8]) {
+    let shown = &content[..content.len().min(MAX_SHOWN_BYTES)];
+    let is_text = std::str::from_utf8(shown).is_ok_and(|text| {
+        text.chars()
+            .all(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+    });
+    if is_text {
+        println!("{}", String::from_utf8_lossy(shown));
+    } else {
+        print!("{}", hex_dump(shown));
+    }
+    if shown.len() < content.len() {
+        println!("({} more bytes not shown)", content.len() - shown.len());
+    }
+}
@@ -0,0 +1,170 @@
+use crate::encoding::normalize_pdf_string;
+use crate::filters::StreamCache;
+use crate::patterns::PatternTarget;
+use crate::{cached_stream, is_javascript_action, resolve, CompiledConfig};
+use lopdf::{Dictionary, Document, Object};
+use serde::{Deserialize, Serialize};
+
+const MAX_FRAGMENT_LENGTH: usize = 50;
+/// How many `/Next` links to follow from the open action.
+const MAX_ACTION_CHAIN: usize = 16;
+
+#[derive(Deserialize, Serialize)]
+pub struct OpenActionJs {
+    pub action_object_id: u32,
+    pub js_content: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct SplitJsCandidate {
+    pub parent_object_id: u32,
+    pub fragment_ids: Vec<u32>,
+    pub combined_content: String,
+    pub matched_patterns: Vec<String>,
+}
+
+/// Looks for arrays of references to short string objects that only match the
+/// suspicious patterns once concatenated in array order, the way exploit kits
+/// split a script to dodge per-object scanning.
+pub fn detect_split_javascript(doc: &Document, config: &CompiledConfig) -> Vec<SplitJsCandidate> {
+    let mut candidates = Vec::new();
+
+    for (id, object) in doc.objects.iter() {
+        let arrays: Vec<&Vec<Object>> = match object {
+            Object::Array(items) => vec![items],
+            Object::Dictionary(dict) => dict
+                .iter()
+                .filter_map(|(_, value)| value.as_array().ok())
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        for items in arrays {
+            if let Some(candidate) = check_fragment_array(doc, id.0, items, config) {
+                candidates.push(candidate);
+            }
+        }
+    }
+
+    candidates
+}
+
+fn check_fragment_array(
+    doc: &Document,
+    parent_object_id: u32,
+    items: &[Object],
+    config: &CompiledConfig,
+) -> Option<SplitJsCandidate> {
+    if items.len() < 2 {
+        return None;
+    }
+
+    let mut fragment_ids = Vec::with_capacity(items.len());
+    let mut combined_content = String::new();
+
+    for item in items {
+        let id = item.as_reference().ok()?;
+        let fragment = match doc.get_object(id).ok()? {
+            Object::String(bytes, _) if bytes.len() < MAX_FRAGMENT_LENGTH => {
+                normalize_pdf_string(bytes)
+            }
+            _ => return None,
+        };
+        if config
+            .suspicious_patterns
+            .is_match(PatternTarget::Js, &fragment)
+        {
+            return None;
+        }
+        fragment_ids.push(id.0);
+        combined_content.push_str(&fragment);
+    }
+
+    let mut matched_patterns: Vec<String> = config
+        .suspicious_patterns
+        .find_all(PatternTarget::Js, &combined_content)
+        .iter()
+        .map(|hit| hit.text.to_string())
+        .collect();
+    if matched_patterns.is_empty() {
+        return None;
+    }
+    matched_patterns.sort();
+    matched_patterns.dedup();
+
+    Some(SplitJsCandidate {
+        parent_object_id,
+        fragment_ids,
+        combined_content,
+        matched_patterns,
+    })
+}
+
+/// Finds a JavaScript action that runs as soon as the document is opened:
+/// the catalog's `/OpenAction` itself or an action chained from it through
+/// `/Next`. A direct action dictionary is attributed to the catalog object.
+pub fn check_open_action_js(doc: &Document, streams: &StreamCache) -> Option<OpenActionJs> {
+    let catalog_id = doc.trailer.get(b"Root").ok()?.as_reference().ok()?;
+    let open_action = doc
+        .get_dictionary(catalog_id)
+        .ok()?
+        .get(b"OpenAction")
+        .ok()?;
+
+    let mut pending = vec![(catalog_id.0, open_action)];
+    let mut visited = 0;
+    while let Some((owner_id, action)) = pending.pop() {
+        visited += 1;
+        if visited > MAX_ACTION_CHAIN {
+            tracing::warn!(
+                limit = MAX_ACTION_CHAIN,
+                "/Next action chain too long, not following further"
+            );
+            break;
+        }
+
+        let action_object_id = match action {
+            Object::Reference(id) => id.0,
+            _ => owner_id,
+        };
+        let Some(Object::Dictionary(dict)) = resolve(doc, action) else {
+            continue;
+        };
+
+        if let Some(js_content) = javascript_action_content(doc, streams, dict) {
+            return Some(OpenActionJs {
+                action_object_id,
+                js_content,
+            });
+        }
+
+        match dict.get(b"Next") {
+            Ok(Object::Array(next)) => {
+                pending.extend(next.iter().rev().map(|action| (action_object_id, action)))
+            }
+            Ok(next) => pending.push((action_object_id, next)),
+            Err(_) => {}
+        }
+    }
+
+    None
+}
+
+fn javascript_action_content(
+    doc: &Document,
+    streams: &StreamCache,
+    action: &Dictionary,
+) -> Option<String> {
+    if !is_javascript_action(doc, action) {
+        return None;
+    }
+
+    let js = action.get(b"JS").ok()?;
+    match resolve(doc, js)? {
+        Object::String(bytes, _) => Some(normalize_pdf_string(bytes)),
+        Object::Stream(_) => {
+            Some(String::from_utf8_lossy(cached_stream(doc, streams, js)?).into_owned())
+        }
+        _ => None,
+    }
+}
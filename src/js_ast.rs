@@ -0,0 +1,307 @@
+use crate::scoring::Severity;
+use serde::{Deserialize, Serialize};
+use swc_common::{sync::Lrc, FileName, SourceMap};
+use swc_ecma_ast::{
+    AssignExpr, AssignOp, BinExpr, BinaryOp, CallExpr, Callee, DoWhileStmt, EsVersion, Expr,
+    ForInStmt, ForOfStmt, ForStmt, Lit, MemberProp, Script, WhileStmt,
+};
+use swc_ecma_parser::{lexer::Lexer, Parser, StringInput, Syntax};
+use swc_ecma_visit::{Visit, VisitWith};
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct JsAstFindings {
+    pub dynamic_eval_patterns: usize,
+    pub string_concat_calls: usize,
+    pub iife_count: usize,
+    /// Exploitable or dangerous Acrobat APIs the script calls, each once.
+    pub acrobat_apis: Vec<AcrobatApiCall>,
+}
+
+/// Acrobat JavaScript APIs that exploits and droppers rely on.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum AcrobatApi {
+    /// `util.printf`, whose format parsing overflows in Reader 8 (CVE-2008-2992).
+    UtilPrintf,
+    /// `Collab.collectEmailInfo`, overflowed by a long `msg` (CVE-2007-5659).
+    CollabCollectEmailInfo,
+    /// `Collab.getIcon`, overflowed by a long icon name (CVE-2009-0927).
+    CollabGetIcon,
+    /// `media.newPlayer`, a use-after-free with a null argument (CVE-2009-4324).
+    MediaNewPlayer,
+    /// `exportDataObject`, which writes an attachment to disk and, with
+    /// `nLaunch`, opens it.
+    ExportDataObject,
+    /// `app.launchURL`, which opens a web page in the browser.
+    AppLaunchUrl,
+    /// `unescape` of `%u` shellcode together with a loop that grows a string:
+    /// the heap spray that the exploits above need.
+    UnescapeHeapSpray,
+}
+
+/// Method names that identify each API. Receivers are not checked, since
+/// scripts alias them (`var u = util; u.printf(...)`) and none of these
+/// names occur elsewhere in Acrobat's API.
+const ACROBAT_API_METHODS: &[(&str, AcrobatApi)] = &[
+    ("printf", AcrobatApi::UtilPrintf),
+    ("collectEmailInfo", AcrobatApi::CollabCollectEmailInfo),
+    ("getIcon", AcrobatApi::CollabGetIcon),
+    ("newPlayer", AcrobatApi::MediaNewPlayer),
+    ("exportDataObject", AcrobatApi::ExportDataObject),
+    ("launchURL", AcrobatApi::AppLaunchUrl),
+];
+
+impl AcrobatApi {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AcrobatApi::UtilPrintf => "util.printf",
+            AcrobatApi::CollabCollectEmailInfo => "Collab.collectEmailInfo",
+            AcrobatApi::CollabGetIcon => "Collab.getIcon",
+            AcrobatApi::MediaNewPlayer => "media.newPlayer",
+            AcrobatApi::ExportDataObject => "exportDataObject",
+            AcrobatApi::AppLaunchUrl => "app.launchURL",
+            AcrobatApi::UnescapeHeapSpray => "unescape heap spray",
+        }
+    }
+
+    /// Critical for known exploit vectors, down to Medium for APIs that are
+    /// only dangerous in what they lead to.
+    pub const fn severity(&self) -> Severity {
+        match self {
+            AcrobatApi::UtilPrintf
+            | AcrobatApi::CollabCollectEmailInfo
+            | AcrobatApi::CollabGetIcon
+            | AcrobatApi::MediaNewPlayer
+            | AcrobatApi::UnescapeHeapSpray => Severity::Critical,
+            AcrobatApi::ExportDataObject => Severity::High,
+            AcrobatApi::AppLaunchUrl => Severity::Medium,
+        }
+    }
+
+    /// The rule in [`crate::findings::RULES`] that reports this API.
+    pub fn rule_id(&self) -> &'static str {
+        match self {
+            AcrobatApi::UtilPrintf => "PS031",
+            AcrobatApi::CollabCollectEmailInfo => "PS032",
+            AcrobatApi::CollabGetIcon => "PS033",
+            AcrobatApi::MediaNewPlayer => "PS034",
+            AcrobatApi::ExportDataObject => "PS035",
+            AcrobatApi::AppLaunchUrl => "PS036",
+            AcrobatApi::UnescapeHeapSpray => "PS037",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct AcrobatApiCall {
+    pub api: AcrobatApi,
+    pub severity: Severity,
+    /// How many call sites the script has.
+    pub calls: usize,
+}
+
+/// Parses `source` as a script and counts call patterns that regex matching
+/// misses: computed member calls (`window["ev" + "al"](...)`), string
+/// concatenation fed into calls, and immediately-invoked functions, and lists
+/// the dangerous Acrobat APIs it calls. Sources that fail to parse yield no
+/// findings.
+pub fn analyze_js_ast(source: &str) -> JsAstFindings {
+    let source_map: Lrc<SourceMap> = Default::default();
+    let file = source_map.new_source_file(FileName::Anon.into(), source.to_string());
+    let lexer = Lexer::new(
+        Syntax::Es(Default::default()),
+        EsVersion::latest(),
+        StringInput::from(&*file),
+        None,
+    );
+
+    let mut findings = JsAstFindings::default();
+    match Parser::new_from(lexer).parse_script() {
+        Ok(script) => {
+            script.visit_with(&mut findings);
+            findings.acrobat_apis = find_acrobat_apis(&script);
+        }
+        Err(err) => tracing::debug!(error = ?err, "JavaScript failed to parse, no AST findings"),
+    }
+    findings
+}
+
+impl Visit for JsAstFindings {
+    fn visit_call_expr(&mut self, call: &CallExpr) {
+        if let Callee::Expr(callee) = &call.callee {
+            match unwrap_parens(callee) {
+                Expr::Member(member) => {
+                    if let MemberProp::Computed(prop) = &member.prop {
+                        self.dynamic_eval_patterns += 1;
+                        if is_string_concat(&prop.expr) {
+                            self.string_concat_calls += 1;
+                        }
+                    }
+                }
+                Expr::Fn(_) | Expr::Arrow(_) => self.iife_count += 1,
+                _ => {}
+            }
+        }
+
+        if call.args.iter().any(|arg| is_string_concat(&arg.expr)) {
+            self.string_concat_calls += 1;
+        }
+
+        call.visit_children_with(self);
+    }
+}
+
+fn unwrap_parens(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Paren(paren) => unwrap_parens(&paren.expr),
+        _ => expr,
+    }
+}
+
+/// A `+` expression with a string literal somewhere in its operands.
+fn is_string_concat(expr: &Expr) -> bool {
+    match unwrap_parens(expr) {
+        Expr::Bin(BinExpr {
+            op: BinaryOp::Add,
+            left,
+            right,
+            ..
+        }) => has_string_operand(left) || has_string_operand(right),
+        _ => false,
+    }
+}
+
+fn has_string_operand(expr: &Expr) -> bool {
+    match unwrap_parens(expr) {
+        Expr::Lit(Lit::Str(_)) | Expr::Tpl(_) => true,
+        Expr::Bin(BinExpr {
+            op: BinaryOp::Add,
+            left,
+            right,
+            ..
+        }) => has_string_operand(left) || has_string_operand(right),
+        _ => false,
+    }
+}
+
+fn find_acrobat_apis(script: &Script) -> Vec<AcrobatApiCall> {
+    let mut scan = AcrobatApiScan::default();
+    script.visit_with(&mut scan);
+    if scan.shellcode_unescape && scan.string_grown_in_loop {
+        scan.record(AcrobatApi::UnescapeHeapSpray);
+    }
+    scan.calls
+}
+
+#[derive(Default)]
+struct AcrobatApiScan {
+    calls: Vec<AcrobatApiCall>,
+    loop_depth: usize,
+    /// `unescape` was called on a string literal holding `%u` escapes.
+    shellcode_unescape: bool,
+    /// A loop body appends to something with `+=`.
+    string_grown_in_loop: bool,
+}
+
+impl AcrobatApiScan {
+    fn record(&mut self, api: AcrobatApi) {
+        match self.calls.iter_mut().find(|call| call.api == api) {
+            Some(call) => call.calls += 1,
+            None => self.calls.push(AcrobatApiCall {
+                api,
+                severity: api.severity(),
+                calls: 1,
+            }),
+        }
+    }
+
+    fn visit_loop_body(&mut self, visit: impl FnOnce(&mut Self)) {
+        self.loop_depth += 1;
+        visit(self);
+        self.loop_depth -= 1;
+    }
+}
+
+impl Visit for AcrobatApiScan {
+    fn visit_call_expr(&mut self, call: &CallExpr) {
+        if let Callee::Expr(callee) = &call.callee {
+            match callee_name(callee) {
+                Some("unescape") => {
+                    let shellcode = call
+                        .args
+                        .first()
+                        .is_some_and(|arg| has_percent_u_literal(&arg.expr));
+                    self.shellcode_unescape |= shellcode;
+                }
+                Some(name) => {
+                    if let Some((_, api)) = ACROBAT_API_METHODS
+                        .iter()
+                        .find(|(method, _)| *method == name)
+                    {
+                        self.record(*api);
+                    }
+                }
+                None => {}
+            }
+        }
+        call.visit_children_with(self);
+    }
+
+    fn visit_assign_expr(&mut self, assign: &AssignExpr) {
+        if assign.op == AssignOp::AddAssign && self.loop_depth > 0 {
+            self.string_grown_in_loop = true;
+        }
+        assign.visit_children_with(self);
+    }
+
+    fn visit_for_stmt(&mut self, stmt: &ForStmt) {
+        self.visit_loop_body(|scan| stmt.visit_children_with(scan));
+    }
+
+    fn visit_for_in_stmt(&mut self, stmt: &ForInStmt) {
+        self.visit_loop_body(|scan| stmt.visit_children_with(scan));
+    }
+
+    fn visit_for_of_stmt(&mut self, stmt: &ForOfStmt) {
+        self.visit_loop_body(|scan| stmt.visit_children_with(scan));
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) {
+        self.visit_loop_body(|scan| stmt.visit_children_with(scan));
+    }
+
+    fn visit_do_while_stmt(&mut self, stmt: &DoWhileStmt) {
+        self.visit_loop_body(|scan| stmt.visit_children_with(scan));
+    }
+}
+
+/// The function or method name a call goes through: `f` for `f()`, and `m`
+/// for `a.m()` and `a["m"]()`.
+fn callee_name(callee: &Expr) -> Option<&str> {
+    match unwrap_parens(callee) {
+        Expr::Ident(ident) => Some(&ident.sym),
+        Expr::Member(member) => match &member.prop {
+            MemberProp::Ident(ident) => Some(&ident.sym),
+            MemberProp::Computed(prop) => match unwrap_parens(&prop.expr) {
+                Expr::Lit(Lit::Str(name)) => name.value.as_str(),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A string literal with `%u` escapes, alone or among the operands of a
+/// concatenation.
+fn has_percent_u_literal(expr: &Expr) -> bool {
+    match unwrap_parens(expr) {
+        Expr::Lit(Lit::Str(literal)) => literal.value.to_string_lossy().contains("%u"),
+        Expr::Bin(BinExpr {
+            op: BinaryOp::Add,
+            left,
+            right,
+            ..
+        }) => has_percent_u_literal(left) || has_percent_u_literal(right),
+        _ => false,
+    }
+}
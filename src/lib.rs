@@ -0,0 +1,1575 @@
+//! Static analysis of PDF files for signs of malicious content.
+//!
+//! [`Analyzer`] is the entry point: build one from a [`Config`] and reuse it
+//! for every document. The individual checks are available from their
+//! modules for callers that only need some of them.
+//!
+//! ```no_run
+//! use pdf_sentinel::{Analyzer, Config};
+//! use std::path::Path;
+//!
+//! let analyzer = Analyzer::new(Config::default())?;
+//! let result = analyzer.analyze_file(Path::new("sample.pdf"))?;
+//! println!("{} (score {})", result.classification.label(), result.severity_score);
+//! # Ok::<(), pdf_sentinel::SentinelError>(())
+//! ```
+
+pub mod actions;
+#[cfg(feature = "tokio")]
+pub mod async_api;
+pub mod attachments;
+pub mod baseline;
+pub mod cache;
+pub mod carve;
+pub mod classification;
+pub mod colorspace;
+pub mod dates;
+pub mod deobfuscate;
+pub mod destinations;
+pub mod diff;
+pub mod encoding;
+pub mod encryption;
+pub mod entropy;
+pub mod error;
+pub mod filters;
+pub mod findings;
+pub mod forms;
+pub mod framing;
+pub mod graph;
+pub mod heap_spray;
+pub mod html;
+pub mod javascript;
+pub mod js_ast;
+pub mod media;
+pub mod metadata;
+pub mod misp;
+pub mod names;
+pub mod nested;
+pub mod objstm;
+pub mod patterns;
+pub mod pdfa;
+pub mod pdfid;
+pub mod revisions;
+pub mod rules;
+pub mod sanitize;
+pub mod sarif;
+pub mod scoring;
+pub mod screen;
+pub mod shellcode;
+pub mod signatures;
+pub mod stego;
+pub mod stix;
+pub mod streaming;
+pub mod structure;
+pub mod suppressions;
+pub mod urls;
+pub mod virustotal;
+pub mod xref;
+pub mod yara_scan;
+
+pub use classification::ThreatClassification;
+pub use error::SentinelError;
+pub use scoring::{
+    ScoreContribution, ScoreExplanation, Scoring, ScoringWeights, Severity, SeverityBands,
+};
+
+use actions::{find_external_actions, ActionFinding};
+use arc_swap::ArcSwap;
+use attachments::{find_embedded_files, EmbeddedFile};
+use baseline::{baselined_result, Baseline, BaselineEntry};
+use cache::ResultCache;
+use classification::classify;
+use colorspace::{check_colorspace_anomalies, ColorspaceAnomaly};
+use dates::{detect_metadata_date_mismatch, DateMismatch};
+use deobfuscate::deobfuscate_js;
+use destinations::{analyze_named_destinations, NamedDestinationAnalysis};
+use encoding::{decode_name, get_name_key, has_name_key, normalize_pdf_string};
+use encryption::{declares_encryption, read_encryption, unlock, EncryptionInfo};
+use entropy::{check_stream_entropy, EntropyHistogram, HighEntropyStream};
+use filters::{populate_stream_cache, CompressionBombHint, StreamCache, DEFAULT_MAX_STREAM_SIZE};
+use findings::collect_findings;
+use forms::{acroform, check_acroform_injection, find_xfa, FormFieldAction, XfaForm};
+use framing::{check_framing, FileFraming};
+use graph::{build_reference_graph, detect_cycles, find_dangling_references};
+use heap_spray::{check_heap_spray_arrays, HeapSprayCandidate};
+use javascript::{check_open_action_js, detect_split_javascript, OpenActionJs, SplitJsCandidate};
+use js_ast::{analyze_js_ast, JsAstFindings};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use md5::Md5;
+use media::{find_media, MediaAnnotation};
+use metadata::{
+    check_metadata_anomalies, extract_metadata, xmp_text, DocumentMetadata, MetadataAnomaly,
+};
+use names::{find_escaped_names, EscapedName};
+use nested::{analyze_embedded_pdfs, EmbeddedPdf};
+use objstm::expand_object_streams;
+use patterns::{
+    default_pattern_rules, CompiledPatterns, Hit, PatternMatch, PatternRule, PatternTarget,
+};
+use pdfa::{check_pdfa_conformance, xmp_metadata, PdfaConformanceReport};
+use rayon::prelude::*;
+use regex::Regex;
+use revisions::{check_revisions, select_revision, RevisionAnalysis};
+use rules::{Finding, RuleEngine};
+use scoring::calculate_severity_score;
+use screen::skip_after_quick_screen;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use shellcode::{check_shellcode_heuristics, ShellcodeHeuristicResult};
+use signatures::{find_embedded_signatures, EmbeddedSignature};
+use std::collections::HashSet;
+use std::io::Cursor;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::{mpsc, Arc};
+use stego::{check_image_steganography, SteganographyHint};
+use structure::{validate_structure, StructureViolation};
+use suppressions::{apply_suppressions, SuppressedFinding, Suppression};
+use urls::{find_urls, LinkUrl};
+#[cfg(feature = "virustotal")]
+use virustotal::VirusTotal;
+use virustotal::VtLookup;
+use xref::{
+    check_xref_consistency, check_xref_stream_anomalies, count_xref_sections, XrefAnomaly,
+    XrefConsistency,
+};
+use yara_scan::YaraMatch;
+#[cfg(feature = "yara")]
+use yara_scan::YaraRules;
+
+/// Runs every check on a document with one compiled config. Cloning is
+/// cheap, so share one analyzer rather than building one per document.
+#[derive(Clone)]
+pub struct Analyzer {
+    config: CompiledConfig,
+}
+
+impl Analyzer {
+    /// Fails if any of the config's patterns doesn't compile.
+    pub fn new(config: Config) -> Result<Self, SentinelError> {
+        Ok(Analyzer {
+            config: CompiledConfig::new(config)?,
+        })
+    }
+
+    /// Runs `rules` after the built-in checks; their findings add to the score.
+    pub fn with_rules(mut self, rules: RuleEngine) -> Self {
+        self.config.rules = Some(Arc::new(rules));
+        self
+    }
+
+    /// Tries `password` on encrypted documents, before the empty user
+    /// password.
+    pub fn with_password(mut self, password: String) -> Self {
+        self.config.password = Some(Arc::from(password));
+        self
+    }
+
+    /// Analyzes documents as they were at `revision`, counting the original
+    /// as 1, instead of with all their incremental updates applied.
+    pub fn with_revision(mut self, revision: usize) -> Self {
+        self.config.revision = Some(revision);
+        self
+    }
+
+    /// Runs `rules` over every decoded stream and script.
+    #[cfg(feature = "yara")]
+    pub fn with_yara_rules(mut self, rules: YaraRules) -> Self {
+        self.config.yara = Some(Arc::new(rules));
+        self
+    }
+
+    /// Reuses the results `cache` holds from earlier runs with the same
+    /// settings, and caches new ones there. Give it after the other
+    /// settings: its fingerprint is taken of the analyzer as it is then.
+    pub fn with_cache(mut self, mut cache: ResultCache) -> Self {
+        cache.configure(&self.config);
+        self.config.cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Looks up the file's hash and those of its attachments and media on
+    /// VirusTotal, merging the detections into the score.
+    #[cfg(feature = "virustotal")]
+    pub fn with_virustotal(mut self, client: VirusTotal) -> Self {
+        self.config.virustotal = Some(Arc::new(client));
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config.config
+    }
+
+    /// The compiled form of `config()`, for the checks that take one directly.
+    pub fn compiled_config(&self) -> &CompiledConfig {
+        &self.config
+    }
+
+    /// Analyzes an already parsed document. `raw` must be the bytes it was
+    /// parsed from; the cross-reference and file size checks read them. The
+    /// result carries no hash, and neither the known-bad hash list nor the
+    /// baseline is consulted.
+    /// Encrypted documents are analyzed as they are; decrypt them first.
+    pub fn analyze(&self, doc: &Document, raw: &[u8]) -> Result<AnalysisResult, SentinelError> {
+        catch_panics(|| analyze_pdf(doc, raw, None, read_encryption(doc), &self.config))
+    }
+
+    /// Parses and analyzes a PDF held in memory.
+    pub fn analyze_bytes(&self, data: &[u8]) -> Result<AnalysisResult, SentinelError> {
+        catch_panics(|| analyze_pdf_bytes(data, &self.config))
+    }
+
+    pub fn analyze_file(&self, path: &Path) -> Result<AnalysisResult, SentinelError> {
+        catch_panics(|| analyze_pdf_file(path, &self.config))
+    }
+
+    /// See [`streaming::analyze_pdf_streaming`] for what this finds and misses.
+    pub fn analyze_streaming(&self, path: &Path) -> Result<AnalysisResult, SentinelError> {
+        catch_panics(|| streaming::analyze_pdf_streaming(path, &self.config))
+    }
+
+    /// Analyzes files in parallel, each paired with its path, in the order
+    /// given. Unless `thorough` is set, files that [`screen::quick_screen`]
+    /// finds nothing in are left out of the results instead of being
+    /// analyzed. A file that fails gets its error; the others are unaffected.
+    pub fn analyze_files(
+        &self,
+        files: &[String],
+        thorough: bool,
+    ) -> Vec<(String, Result<AnalysisResult, SentinelError>)> {
+        files
+            .par_iter()
+            .filter(|file| !skip_after_quick_screen(Path::new(file), thorough))
+            .map(|file| (file.clone(), self.analyze_file(Path::new(file))))
+            .collect()
+    }
+
+    /// Like [`Analyzer::analyze_files`], but hands each file's result to
+    /// `on_result` on the calling thread as soon as it is ready, in the order
+    /// the analyses finish, so that output can keep up with a long batch.
+    /// Returns how many files were analyzed.
+    pub fn analyze_files_with(
+        &self,
+        files: &[String],
+        thorough: bool,
+        mut on_result: impl FnMut(String, Result<AnalysisResult, SentinelError>),
+    ) -> usize {
+        let (tx, rx) = mpsc::channel();
+        std::thread::scope(|scope| {
+            // The pool's own threads are left to the analyses; the caller
+            // only waits for results
+            scope.spawn(move || {
+                files
+                    .par_iter()
+                    .filter(|file| !skip_after_quick_screen(Path::new(file), thorough))
+                    .for_each_with(tx, |tx, file| {
+                        let _ = tx.send((file.clone(), self.analyze_file(Path::new(file))));
+                    });
+            });
+            let mut analyzed = 0;
+            for (file, result) in rx {
+                analyzed += 1;
+                on_result(file, result);
+            }
+            analyzed
+        })
+    }
+}
+
+/// Turns a panic during `analyze` into [`SentinelError::Panic`]. The parser is
+/// not hardened against every malformed input, and one hostile file must not
+/// take a batch or a watch loop down with it.
+fn catch_panics<T>(analyze: impl FnOnce() -> Result<T, SentinelError>) -> Result<T, SentinelError> {
+    panic::catch_unwind(AssertUnwindSafe(analyze))
+        .unwrap_or_else(|payload| Err(SentinelError::from_panic(payload)))
+}
+
+/// Fields missing from a config file take their [`Default`] values.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    pub file_size_threshold: u64,
+    /// What to flag in names, stream content, scripts and metadata.
+    pub suspicious_patterns: Vec<PatternRule>,
+    /// Categories of `suspicious_patterns` that are not looked for.
+    pub disabled_pattern_categories: Vec<String>,
+    #[serde(default)]
+    pub known_bad_hashes: HashSet<String>,
+    /// Files approved as they are, which are reported clean without being
+    /// analyzed, e.g. from `--baseline`.
+    pub baseline: Baseline,
+    #[serde(default = "default_max_js_object_ratio")]
+    pub max_js_object_ratio: f64,
+    #[serde(default = "default_max_stream_object_ratio")]
+    pub max_stream_object_ratio: f64,
+    /// Creator/Producer patterns for tools that are expected to produce the
+    /// documents being scanned. Anything else is flagged as unknown.
+    pub known_safe_creators: Vec<String>,
+    /// Creator/Producer patterns seen in malware builders.
+    pub known_malicious_creators: Vec<String>,
+    /// Arrays longer than this are checked for heap spray values.
+    #[serde(default = "default_heap_spray_min_length")]
+    pub heap_spray_min_length: usize,
+    /// Streams that decompress past this many bytes are abandoned and
+    /// reported as possible compression bombs.
+    #[serde(default = "default_max_decompressed_stream_size")]
+    pub max_decompressed_stream_size: u64,
+    /// Streams that decode to more than this many times their encoded size
+    /// are abandoned as well, once past a megabyte of output.
+    #[serde(default = "default_max_compression_ratio")]
+    pub max_compression_ratio: f64,
+    /// Decoded bytes all the streams of one document may add up to. The
+    /// stream that crosses it is reported and the rest are not decoded.
+    #[serde(default = "default_max_total_decompressed_size")]
+    pub max_total_decompressed_size: u64,
+    /// Decoded streams above this many bits per byte of entropy (at most 8)
+    /// are flagged unless they are images.
+    #[serde(default = "default_max_stream_entropy")]
+    pub max_stream_entropy: f64,
+    /// `/Info` and XMP creation dates further apart than this are flagged.
+    #[serde(default = "default_max_metadata_date_delta_days")]
+    pub max_metadata_date_delta_days: u32,
+    /// Acrobat JavaScript APIs that drop, send or open files. Matched as plain
+    /// substrings of decoded scripts only, not of other stream content.
+    #[serde(default = "default_dangerous_js_apis")]
+    pub dangerous_js_apis: Vec<String>,
+    /// Severity score at which the CLI exits with code 2. `u32::MAX` never fails.
+    #[serde(default = "default_fail_threshold")]
+    pub fail_threshold: u32,
+    /// Levels of PDFs inside PDFs that are analyzed; 0 analyzes none.
+    #[serde(default = "default_max_nesting_depth")]
+    pub max_nesting_depth: usize,
+    /// How findings add up to the severity score and its band.
+    pub scoring: Scoring,
+    /// Findings known to be benign, which don't count toward the score,
+    /// e.g. from `--suppressions`.
+    pub suppressions: Vec<Suppression>,
+}
+
+fn default_max_js_object_ratio() -> f64 {
+    0.15
+}
+
+fn default_max_stream_object_ratio() -> f64 {
+    0.80
+}
+
+fn default_heap_spray_min_length() -> usize {
+    500
+}
+
+fn default_max_decompressed_stream_size() -> u64 {
+    DEFAULT_MAX_STREAM_SIZE
+}
+
+fn default_max_compression_ratio() -> f64 {
+    1000.0
+}
+
+fn default_max_total_decompressed_size() -> u64 {
+    512 * 1024 * 1024
+}
+
+fn default_max_stream_entropy() -> f64 {
+    7.5
+}
+
+fn default_max_metadata_date_delta_days() -> u32 {
+    30
+}
+
+fn default_dangerous_js_apis() -> Vec<String> {
+    [
+        "exportDataObject",
+        "saveAs",
+        "submitForm",
+        "openDoc",
+        "getURL",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+fn default_fail_threshold() -> u32 {
+    u32::MAX
+}
+
+fn default_max_nesting_depth() -> usize {
+    3
+}
+
+/// `Config` with its patterns compiled once up front, so that a bad pattern is
+/// reported before any file is analyzed. The compiled patterns are shared, so
+/// cloning is cheap and an updated copy reuses every pattern that didn't change.
+#[derive(Clone)]
+pub struct CompiledConfig {
+    config: Config,
+    suspicious_patterns: Arc<CompiledPatterns>,
+    known_safe_creators: Option<Arc<Regex>>,
+    known_malicious_creators: Option<Arc<Regex>>,
+    /// Custom rules run after the built-in checks, e.g. from `--rules`.
+    rules: Option<Arc<RuleEngine>>,
+    /// Tried on encrypted documents, e.g. from `--password`.
+    password: Option<Arc<str>>,
+    /// The revision to analyze instead of the final one, e.g. from
+    /// `--revision`.
+    revision: Option<usize>,
+    /// How many documents the one being analyzed is nested in.
+    depth: usize,
+    #[cfg(feature = "yara")]
+    yara: Option<Arc<YaraRules>>,
+    /// Shared so that every analysis keeps to one request rate.
+    #[cfg(feature = "virustotal")]
+    virustotal: Option<Arc<VirusTotal>>,
+    /// Results of earlier runs, e.g. from `--cache`.
+    cache: Option<Arc<ResultCache>>,
+}
+
+impl CompiledConfig {
+    pub fn new(config: Config) -> Result<Self, SentinelError> {
+        let suspicious_patterns = Arc::new(CompiledPatterns::new(
+            &config.suspicious_patterns,
+            &config.disabled_pattern_categories,
+        )?);
+        let known_safe_creators = compile_patterns(&config.known_safe_creators)?;
+        let known_malicious_creators = compile_patterns(&config.known_malicious_creators)?;
+
+        Ok(CompiledConfig {
+            config: normalize_config(config),
+            suspicious_patterns,
+            known_safe_creators,
+            known_malicious_creators,
+            rules: None,
+            password: None,
+            revision: None,
+            depth: 0,
+            #[cfg(feature = "yara")]
+            yara: None,
+            #[cfg(feature = "virustotal")]
+            virustotal: None,
+            cache: None,
+        })
+    }
+
+    /// Replaces the config, recompiling only the pattern lists that differ
+    /// from the current ones. Nothing changes if any new pattern is invalid.
+    pub fn update(&mut self, new_config: Config) -> Result<(), SentinelError> {
+        let suspicious_patterns = if new_config.suspicious_patterns
+            == self.config.suspicious_patterns
+            && new_config.disabled_pattern_categories == self.config.disabled_pattern_categories
+        {
+            Arc::clone(&self.suspicious_patterns)
+        } else {
+            Arc::new(CompiledPatterns::new(
+                &new_config.suspicious_patterns,
+                &new_config.disabled_pattern_categories,
+            )?)
+        };
+        let known_safe_creators = recompile_patterns(
+            &self.config.known_safe_creators,
+            &new_config.known_safe_creators,
+            &self.known_safe_creators,
+        )?;
+        let known_malicious_creators = recompile_patterns(
+            &self.config.known_malicious_creators,
+            &new_config.known_malicious_creators,
+            &self.known_malicious_creators,
+        )?;
+
+        self.config = normalize_config(new_config);
+        self.suspicious_patterns = suspicious_patterns;
+        self.known_safe_creators = known_safe_creators;
+        self.known_malicious_creators = known_malicious_creators;
+        // Results under the old config no longer hold
+        if let Some(cache) = &self.cache {
+            let mut cache = ResultCache::clone(cache);
+            cache.configure(self);
+            self.cache = Some(Arc::new(cache));
+        }
+        Ok(())
+    }
+}
+
+fn normalize_config(mut config: Config) -> Config {
+    config.known_bad_hashes = config
+        .known_bad_hashes
+        .iter()
+        .map(|hash| hash.to_ascii_lowercase())
+        .collect();
+    config
+}
+
+/// A `CompiledConfig` that can be replaced while analyses are running.
+///
+/// Each analysis should `load` once and use that snapshot throughout: an
+/// `update` never blocks readers, and analyses already in progress keep
+/// seeing the config they started with. Only analyses that load after the
+/// update returns see the new one. Concurrent updates race, and the last
+/// one stored wins.
+pub struct SharedConfig {
+    current: ArcSwap<CompiledConfig>,
+}
+
+impl SharedConfig {
+    pub fn new(config: CompiledConfig) -> Self {
+        SharedConfig {
+            current: ArcSwap::from_pointee(config),
+        }
+    }
+
+    pub fn load(&self) -> Arc<CompiledConfig> {
+        self.current.load_full()
+    }
+
+    pub fn update(&self, new_config: Config) -> Result<(), SentinelError> {
+        let mut next = CompiledConfig::clone(&self.current.load());
+        next.update(new_config)?;
+        self.current.store(Arc::new(next));
+        Ok(())
+    }
+}
+
+/// Joins a pattern list into one regex. An empty list compiles to `None`
+/// rather than to an empty regex, which would match everything.
+fn compile_patterns(patterns: &[String]) -> Result<Option<Arc<Regex>>, SentinelError> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Arc::new(Regex::new(&patterns.join("|"))?)))
+}
+
+fn recompile_patterns(
+    old: &[String],
+    new: &[String],
+    compiled: &Option<Arc<Regex>>,
+) -> Result<Option<Arc<Regex>>, SentinelError> {
+    if old == new {
+        return Ok(compiled.clone());
+    }
+    compile_patterns(new)
+}
+
+/// Version of the JSON layout of [`AnalysisResult`]. Adding a field keeps the
+/// version; renaming, removing or retyping one bumps it.
+pub const RESULT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct AnalysisResult {
+    pub has_javascript: bool,
+    pub has_auto_action: bool,
+    pub has_obj_stm: bool,
+    /// Objects found only by unpacking object streams, and analyzed with the rest.
+    pub unpacked_objects: Vec<u32>,
+    pub suspicious_names: Vec<String>,
+    pub hidden_content: bool,
+    pub large_file_size: bool,
+    pub suspicious_metadata: bool,
+    /// The `/Info` entries and XMP properties, with the dates decoded.
+    pub metadata: Option<DocumentMetadata>,
+    pub metadata_date_mismatch: Option<DateMismatch>,
+    /// Metadata dates out of order or in the future, and a Producer missing
+    /// from a document with active content.
+    pub metadata_anomalies: Vec<MetadataAnomaly>,
+    pub unusual_objects: Vec<String>,
+    pub object_statistics: ObjectStatistics,
+    pub severity_score: u32,
+    /// What `severity_score` is made of.
+    pub explanation: ScoreExplanation,
+    /// The band `severity_score` falls into under `Scoring::bands`.
+    pub severity: Severity,
+    pub classification: ThreatClassification,
+    pub javascript_objects: Vec<JavaScriptObject>,
+    pub reference_cycles: Vec<Vec<u32>>,
+    pub dangling_refs: Vec<u32>,
+    pub shellcode_hints: Vec<(u32, ShellcodeHeuristicResult)>,
+    pub sha256: Option<String>,
+    /// MD5 and SHA-1 of the file, for threat intelligence that still keys
+    /// samples by them.
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+    /// Size of the file in bytes, when it was read whole or streamed.
+    pub file_size: Option<u64>,
+    pub known_malicious: bool,
+    /// The `Config::baseline` entry the file matched, in which case it was
+    /// not analyzed.
+    pub baselined: Option<BaselineEntry>,
+    pub split_js_candidates: Vec<SplitJsCandidate>,
+    pub colorspace_anomalies: Vec<ColorspaceAnomaly>,
+    pub named_destinations: NamedDestinationAnalysis,
+    pub ratio_findings: Vec<RatioFinding>,
+    pub xref_sections: usize,
+    pub xref_anomalies: Vec<XrefAnomaly>,
+    pub xref_consistency: XrefConsistency,
+    pub unknown_creator: bool,
+    pub malicious_creator: Option<String>,
+    pub form_field_actions: Vec<FormFieldAction>,
+    pub has_acroform: bool,
+    /// The XFA form's packets, scripts and submit targets, when the
+    /// `/AcroForm` has an `/XFA` entry.
+    pub xfa: Option<XfaForm>,
+    pub steganography_hints: Vec<(u32, SteganographyHint)>,
+    /// Every URL the document links to, once each.
+    pub uris: Vec<String>,
+    /// The same URLs with where they appear and what looks suspicious about
+    /// them.
+    pub urls: Vec<LinkUrl>,
+    pub launch_actions: Vec<u32>,
+    /// Launch, GoToR, GoToE, SubmitForm and ImportData actions with their targets.
+    pub external_actions: Vec<ActionFinding>,
+    pub embedded_files: Vec<EmbeddedFile>,
+    /// RichMedia, 3D, Sound and Movie annotations and actions, with their
+    /// payloads.
+    pub media: Vec<MediaAnnotation>,
+    pub pdfa_conformance: Option<PdfaConformanceReport>,
+    pub heap_spray_candidates: Vec<HeapSprayCandidate>,
+    pub rule_findings: Vec<Finding>,
+    /// Always empty unless built with the `yara` feature and given rules.
+    pub yara_matches: Vec<YaraMatch>,
+    /// Always empty unless built with the `virustotal` feature and given a
+    /// client.
+    pub virustotal: Vec<VtLookup>,
+    pub open_action_js: Option<OpenActionJs>,
+    pub compression_bomb_hints: Vec<CompressionBombHint>,
+    pub high_entropy_streams: Vec<HighEntropyStream>,
+    /// Executables and archives found inside decoded streams.
+    pub embedded_signatures: Vec<EmbeddedSignature>,
+    /// PDFs inside attachments and other streams, each with its own
+    /// analysis.
+    pub embedded_pdfs: Vec<EmbeddedPdf>,
+    pub structure_violations: Vec<StructureViolation>,
+    /// The `/Encrypt` parameters and how decryption went, for encrypted
+    /// documents.
+    pub encryption: Option<EncryptionInfo>,
+    /// Where the header and `%%EOF` markers sit, and any data outside them.
+    pub framing: FileFraming,
+    /// The document's incremental updates and the objects they redefine.
+    pub revisions: RevisionAnalysis,
+    /// Why lopdf rejected the file, when it was analyzed from objects carved
+    /// out of the raw bytes instead.
+    pub parse_error: Option<String>,
+    /// Names written with needless `#xx` escapes, such as `/J#61vaScript`.
+    pub escaped_names: Vec<EscapedName>,
+    /// Streams whose decoded text matches `Config::suspicious_patterns`.
+    pub suspicious_stream_content: Vec<ContentMatch>,
+    /// Every match of `Config::suspicious_patterns` behind the suspicious
+    /// names, stream content, metadata and hidden script matches above,
+    /// with the rule that matched.
+    pub pattern_matches: Vec<PatternMatch>,
+    /// Every detection above as one list, each traceable to its rule and,
+    /// where there is one, its object.
+    pub findings: Vec<findings::Finding>,
+    /// Findings matched by `Config::suppressions`, which `findings` leaves
+    /// out and the score doesn't count.
+    pub suppressed_findings: Vec<SuppressedFinding>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct ObjectStatistics {
+    pub total_objects: usize,
+    pub stream_objects: usize,
+    pub js_objects: usize,
+    pub obj_stm_objects: usize,
+    pub entropy_histogram: EntropyHistogram,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ContentMatch {
+    pub object_id: u32,
+    /// Byte offset of the match in the stream's decoded text.
+    pub offset: usize,
+    /// The matched text.
+    pub evidence: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RatioFinding {
+    pub ratio_name: String,
+    pub actual: f64,
+    pub threshold: f64,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct JavaScriptObject {
+    pub id: u32,
+    pub content: String,
+    /// SHA-256 of `content` as UTF-8, in lowercase hex, for matching the
+    /// script across samples.
+    pub sha256: String,
+    pub source: JsSource,
+    pub ast_findings: JsAstFindings,
+    /// The script with its escapes, concatenations, `fromCharCode` and
+    /// `unescape` calls decoded, when it had any.
+    pub deobfuscated: Option<String>,
+    /// Entries of `Config::dangerous_js_apis` that the script uses.
+    pub dangerous_apis: Vec<String>,
+    /// Matches of `Config::suspicious_patterns` that only appear once the
+    /// script is deobfuscated.
+    pub hidden_matches: Vec<String>,
+}
+
+impl JavaScriptObject {
+    /// The deobfuscated script if there is one, else the script itself.
+    pub fn decoded(&self) -> &str {
+        self.deobfuscated.as_deref().unwrap_or(&self.content)
+    }
+}
+
+/// Where a script's text was found.
+#[derive(Deserialize, Serialize)]
+pub enum JsSource {
+    /// A `/JS` string in object `id` itself.
+    InlineString,
+    /// The content of stream `id`.
+    Stream,
+    /// A string object that the `/JS` entry in object `id` references.
+    IndirectRef(u32),
+    /// A `<script>` in the XFA packet held in stream `id`.
+    XfaScript,
+}
+
+/// Limit on how deeply `find_all_javascript` descends into direct objects.
+const MAX_JS_NESTING_DEPTH: usize = 32;
+
+impl Config {
+    /// Reads a TOML or JSON config file; see [`load_config_file`].
+    pub fn from_file(path: &Path) -> Result<Self, SentinelError> {
+        load_config_file(path)
+    }
+}
+
+/// Reads a config file as TOML, or as JSON when its extension is `.json`.
+/// Parse errors name the file and, for TOML, the offending line.
+pub fn load_config_file<T: DeserializeOwned>(path: &Path) -> Result<T, SentinelError> {
+    let contents = std::fs::read_to_string(path)?;
+    let parsed = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => serde_json::from_str(&contents).map_err(|err| err.to_string()),
+        _ => toml::from_str(&contents).map_err(|err| err.to_string()),
+    };
+    parsed.map_err(|err| SentinelError::Config(path.to_path_buf(), err))
+}
+
+/// The defaults the command line tool runs with.
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            file_size_threshold: 10 * 1024 * 1024,
+            suspicious_patterns: default_pattern_rules(),
+            disabled_pattern_categories: Vec::new(),
+            known_safe_creators: vec![
+                r"(?i)adobe".to_string(),
+                r"(?i)microsoft".to_string(),
+                r"(?i)office".to_string(),
+                r"(?i)quartz".to_string(),
+                r"(?i)(pdf|lua|xe)tex".to_string(),
+                r"(?i)ghostscript".to_string(),
+                r"(?i)skia".to_string(),
+            ],
+            known_malicious_creators: vec![
+                r"(?i)metasploit".to_string(),
+                r"(?i)make-pdf-(javascript|embedded)".to_string(),
+            ],
+            known_bad_hashes: HashSet::new(),
+            baseline: Baseline::default(),
+            max_js_object_ratio: default_max_js_object_ratio(),
+            max_stream_object_ratio: default_max_stream_object_ratio(),
+            heap_spray_min_length: default_heap_spray_min_length(),
+            max_decompressed_stream_size: default_max_decompressed_stream_size(),
+            max_compression_ratio: default_max_compression_ratio(),
+            max_total_decompressed_size: default_max_total_decompressed_size(),
+            max_stream_entropy: default_max_stream_entropy(),
+            max_metadata_date_delta_days: default_max_metadata_date_delta_days(),
+            dangerous_js_apis: default_dangerous_js_apis(),
+            fail_threshold: default_fail_threshold(),
+            max_nesting_depth: default_max_nesting_depth(),
+            scoring: Scoring::default(),
+            suppressions: Vec::new(),
+        }
+    }
+}
+
+fn compute_sha256(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn known_malicious_result(sha256: String) -> AnalysisResult {
+    let mut result = AnalysisResult {
+        sha256: Some(sha256),
+        known_malicious: true,
+        severity_score: u32::MAX,
+        severity: Severity::Critical,
+        ..Default::default()
+    };
+    result.classification = classify(&result);
+    result.findings = collect_findings(&result, None);
+    result
+}
+
+/// Analyzes a PDF that is already in memory. The hash is checked against the
+/// known-bad list and the baseline before parsing, so known samples and
+/// approved files skip the full analysis.
+/// With `CompiledConfig::revision` set, only the file up to that revision is
+/// analyzed, but the hash is still the whole file's.
+fn analyze_pdf_bytes(
+    data: &[u8],
+    config: &CompiledConfig,
+) -> Result<AnalysisResult, SentinelError> {
+    let sha256 = compute_sha256(data);
+    let mut result = if config.config.known_bad_hashes.contains(&sha256) {
+        tracing::warn!(%sha256, "matches a known-malicious hash, skipping analysis");
+        known_malicious_result(sha256)
+    } else if let Some(entry) = config.config.baseline.find(&sha256) {
+        tracing::info!(%sha256, "matches the baseline, skipping analysis");
+        baselined_result(sha256, entry)
+    } else if let Some(result) = config.cache.as_ref().and_then(|cache| cache.get(&sha256)) {
+        tracing::debug!(%sha256, "found in the result cache, skipping analysis");
+        return Ok(result);
+    } else {
+        let revision = match config.revision {
+            Some(revision) => select_revision(data, revision)?,
+            None => data,
+        };
+        let mut result = parse_and_analyze(revision, sha256, config)?;
+        result.revisions.selected = config.revision;
+        result
+    };
+    result.md5 = Some(to_hex(&Md5::digest(data)));
+    result.sha1 = Some(to_hex(&Sha1::digest(data)));
+    result.file_size = Some(data.len() as u64);
+    if let (Some(cache), Some(sha256)) = (&config.cache, &result.sha256) {
+        cache.put(sha256, &result);
+    }
+    Ok(result)
+}
+
+/// Parses `data`, decrypting it if needed, and analyzes it. Files that don't
+/// parse are analyzed from the objects carved out of them.
+fn parse_and_analyze(
+    data: &[u8],
+    sha256: String,
+    config: &CompiledConfig,
+) -> Result<AnalysisResult, SentinelError> {
+    let mut doc = match Document::load_from(Cursor::new(data)) {
+        Ok(doc) => doc,
+        Err(err) => {
+            let objects = carve::carve_objects(data);
+            // Anything without a single object in it isn't a PDF at all
+            if objects.is_empty() {
+                return Err(err.into());
+            }
+            tracing::warn!(error = %err, "document failed to parse, carving objects from the raw bytes");
+            let encryption = declares_encryption(data).then(|| EncryptionInfo {
+                raw_scan_only: true,
+                ..Default::default()
+            });
+            return Ok(carve::analyze_pdf_carved(
+                data,
+                &objects,
+                sha256,
+                err.to_string(),
+                encryption,
+                config,
+            ));
+        }
+    };
+    let encryption = match unlock(&mut doc, config.password.as_deref()) {
+        Some(encryption) if encryption.raw_scan_only => {
+            return Ok(streaming::analyze_pdf_raw(data, sha256, encryption, config));
+        }
+        encryption => encryption,
+    };
+
+    analyze_pdf(&doc, data, Some(sha256), encryption, config)
+}
+
+fn analyze_pdf_file(path: &Path, config: &CompiledConfig) -> Result<AnalysisResult, SentinelError> {
+    analyze_pdf_bytes(&std::fs::read(path)?, config)
+}
+
+/// `raw` is the file the document was parsed from, for checks that need to
+/// look past lopdf's view of the object graph.
+fn analyze_pdf(
+    doc: &Document,
+    raw: &[u8],
+    sha256: Option<String>,
+    encryption: Option<EncryptionInfo>,
+    config: &CompiledConfig,
+) -> Result<AnalysisResult, SentinelError> {
+    tracing::debug!(
+        objects = doc.objects.len(),
+        bytes = raw.len(),
+        "analyzing document"
+    );
+    let mut result = AnalysisResult {
+        sha256,
+        encryption,
+        ..Default::default()
+    };
+    let mut streams = populate_stream_cache(doc, config)?;
+    result.compression_bomb_hints = streams.take_compression_bombs();
+
+    let expanded = expand_object_streams(doc, &streams);
+    let doc = match &expanded {
+        Some((expanded, unpacked)) => {
+            result.unpacked_objects = unpacked.clone();
+            expanded
+        }
+        None => doc,
+    };
+
+    result.has_javascript = check_for_javascript(doc);
+    result.javascript_objects = find_all_javascript(doc, &streams);
+    result.has_acroform = acroform(doc).is_some();
+    result.xfa = find_xfa(doc, &streams, &mut result.javascript_objects);
+    result.has_javascript |= result.xfa.as_ref().is_some_and(|xfa| xfa.scripts > 0);
+    for js_obj in &mut result.javascript_objects {
+        js_obj.dangerous_apis = find_dangerous_js_apis(js_obj.decoded(), config);
+        let hidden = find_hidden_matches(js_obj, config);
+        js_obj.hidden_matches = hidden.iter().map(|found| found.evidence.clone()).collect();
+        result.pattern_matches.extend(hidden);
+    }
+    result.split_js_candidates = detect_split_javascript(doc, config);
+    result.has_auto_action = check_for_auto_action(doc);
+    result.open_action_js = check_open_action_js(doc, &streams);
+    result.urls = find_urls(doc);
+    result.uris = result.urls.iter().map(|url| url.url.clone()).collect();
+    result.launch_actions = find_launch_actions(doc);
+    result.external_actions = find_external_actions(doc);
+    result.embedded_files = find_embedded_files(doc, &streams);
+    result.media = find_media(doc, &streams);
+    result.form_field_actions = check_acroform_injection(doc);
+    result.has_obj_stm = check_for_obj_stm(doc);
+    let names = check_for_suspicious_names(doc, config);
+    result.suspicious_names = names.iter().map(|found| found.evidence.clone()).collect();
+    result.pattern_matches.extend(names);
+    result.hidden_content = check_for_hidden_content(doc);
+    result.large_file_size = check_file_size(raw, config);
+    let metadata = check_metadata(doc, config);
+    result.suspicious_metadata = !metadata.is_empty();
+    result.pattern_matches.extend(metadata);
+    result.metadata = extract_metadata(doc, &streams);
+    result.metadata_date_mismatch =
+        detect_metadata_date_mismatch(doc, &streams).filter(|mismatch| {
+            mismatch.delta_days.unsigned_abs()
+                > u64::from(config.config.max_metadata_date_delta_days)
+        });
+    (result.unknown_creator, result.malicious_creator) = check_creator(doc, &streams, config);
+    result.unusual_objects = check_for_unusual_objects(doc);
+    result.object_statistics = calculate_object_statistics(doc);
+    (
+        result.object_statistics.entropy_histogram,
+        result.high_entropy_streams,
+    ) = check_stream_entropy(doc, &streams, config);
+    result.embedded_signatures = find_embedded_signatures(streams.iter());
+    result.embedded_pdfs = analyze_embedded_pdfs(streams.iter(), &result.embedded_files, config);
+    result.ratio_findings = check_object_ratio_heuristics(&result.object_statistics, config);
+    result.colorspace_anomalies = check_colorspace_anomalies(doc, &streams);
+    result.structure_violations = validate_structure(doc);
+    result.named_destinations = analyze_named_destinations(doc);
+    result.heap_spray_candidates = check_heap_spray_arrays(doc, config);
+    result.xref_sections = count_xref_sections(raw);
+    result.xref_anomalies = check_xref_stream_anomalies(doc, raw);
+    result.xref_consistency = check_xref_consistency(doc, raw);
+    result.escaped_names = find_escaped_names(raw);
+    result.framing = check_framing(raw);
+    result.revisions = check_revisions(raw);
+
+    let graph = build_reference_graph(doc);
+    result.reference_cycles = detect_cycles(&graph);
+    result.dangling_refs = find_dangling_references(&graph);
+
+    analyze_streams(doc, &streams, config, &mut result);
+    let script_shellcode = check_script_shellcode(&result.javascript_objects);
+    result.shellcode_hints.extend(script_shellcode);
+    result.pdfa_conformance = check_pdfa_conformance(doc, &streams, &result);
+    result.metadata_anomalies = check_metadata_anomalies(&result);
+    if let Some(rules) = &config.rules {
+        result.rule_findings = rules.run(doc, &streams);
+    }
+    #[cfg(feature = "yara")]
+    if let Some(yara) = &config.yara {
+        result.yara_matches = yara.scan(&streams, &result.javascript_objects);
+    }
+    #[cfg(feature = "virustotal")]
+    if let Some(virustotal) = &config.virustotal {
+        result.virustotal = virustotal.look_up(&result);
+    }
+
+    (result.severity_score, result.explanation) =
+        calculate_severity_score(&result, &config.config.scoring);
+    result.severity = config.config.scoring.bands.severity(result.severity_score);
+    result.classification = classify(&result);
+    result.findings = collect_findings(&result, Some(doc));
+    apply_suppressions(&mut result, &config.config);
+    tracing::debug!(
+        severity_score = result.severity_score,
+        classification = result.classification.label(),
+        "analysis finished"
+    );
+
+    Ok(result)
+}
+
+/// How many references `resolve` follows before giving up on a chain.
+const MAX_REFERENCE_CHAIN: usize = 8;
+
+/// Follows indirect references, including references to references, to the
+/// object at the end; direct objects are returned as is.
+fn resolve<'a>(doc: &'a Document, object: &'a Object) -> Option<&'a Object> {
+    match object {
+        Object::Reference(id) => Some(follow_reference(doc, *id)?.1),
+        _ => Some(object),
+    }
+}
+
+/// Like `resolve`, but also returns the number of the object the chain ends
+/// at. `None` for dangling references and chains that loop or run too long.
+/// Goes one object at a time, since `Document::get_object` would follow the
+/// whole chain and give no number but the first.
+fn follow_reference(doc: &Document, id: ObjectId) -> Option<(ObjectId, &Object)> {
+    let mut id = id;
+    for _ in 0..MAX_REFERENCE_CHAIN {
+        match doc.objects.get(&id)? {
+            Object::Reference(next) => id = *next,
+            object => return Some((id, object)),
+        }
+    }
+    tracing::debug!(object_id = id.0, "reference chain too long, not following");
+    None
+}
+
+/// The decoded content of the stream `object` refers to, as `cache` holds
+/// it. `None` for direct objects and for streams the cache left out.
+pub(crate) fn cached_stream<'a>(
+    doc: &Document,
+    cache: &'a StreamCache,
+    object: &Object,
+) -> Option<&'a [u8]> {
+    let (id, _) = follow_reference(doc, object.as_reference().ok()?)?;
+    cache.get(id.0)
+}
+
+/// Whether `dict` has `/S /JavaScript`, with the name possibly behind a reference.
+fn is_javascript_action(doc: &Document, dict: &Dictionary) -> bool {
+    dict.get(b"S")
+        .ok()
+        .and_then(|kind| resolve(doc, kind))
+        .and_then(|kind| kind.as_name().ok())
+        .is_some_and(|kind| *decode_name(kind) == *b"JavaScript")
+}
+
+fn check_for_javascript(doc: &Document) -> bool {
+    doc.objects
+        .values()
+        .any(|object| contains_javascript(doc, object, 0))
+}
+
+/// Whether `object`, or a dictionary or array written inside it, such as an
+/// action straight in the catalog's `/OpenAction`, marks JavaScript.
+fn contains_javascript(doc: &Document, object: &Object, depth: usize) -> bool {
+    if depth > MAX_JS_NESTING_DEPTH {
+        return false;
+    }
+    let dict = match object {
+        Object::Dictionary(dict) => dict,
+        Object::Stream(stream) => &stream.dict,
+        Object::Array(items) => {
+            return items
+                .iter()
+                .any(|item| contains_javascript(doc, item, depth + 1))
+        }
+        _ => return false,
+    };
+    has_name_key(dict, b"JS")
+        || has_name_key(dict, b"JavaScript")
+        || is_javascript_action(doc, dict)
+        || dict
+            .iter()
+            .any(|(_, value)| contains_javascript(doc, value, depth + 1))
+}
+
+/// Collects every script in the document: `/JS` strings written inline,
+/// `/JS` entries referencing a string or stream object, and streams that are
+/// marked as JavaScript themselves. A `/JS` entry in a direct dictionary, such
+/// as an action written straight into the catalog's `/OpenAction`, is
+/// reported under the object that contains it.
+fn find_all_javascript(doc: &Document, cache: &StreamCache) -> Vec<JavaScriptObject> {
+    let mut js_objects = Vec::new();
+
+    for (id, object) in doc.objects.iter() {
+        collect_javascript(doc, cache, id.0, object, 0, &mut js_objects);
+    }
+
+    js_objects
+}
+
+fn collect_javascript(
+    doc: &Document,
+    cache: &StreamCache,
+    holder: u32,
+    object: &Object,
+    depth: usize,
+    js_objects: &mut Vec<JavaScriptObject>,
+) {
+    if depth > MAX_JS_NESTING_DEPTH {
+        return;
+    }
+
+    let dict = match object {
+        Object::Dictionary(dict) => dict,
+        Object::Stream(stream) => {
+            if has_name_key(&stream.dict, b"JS") || has_name_key(&stream.dict, b"JavaScript") {
+                if let Some(decoded) = cache.get(holder) {
+                    push_stream_javascript(js_objects, holder, decoded);
+                }
+                return;
+            }
+            &stream.dict
+        }
+        Object::Array(items) => {
+            for item in items {
+                collect_javascript(doc, cache, holder, item, depth + 1, js_objects);
+            }
+            return;
+        }
+        _ => return,
+    };
+
+    match get_name_key(dict, b"JS") {
+        Some(Object::String(bytes, _)) => push_javascript(
+            js_objects,
+            holder,
+            normalize_pdf_string(bytes),
+            JsSource::InlineString,
+        ),
+        Some(Object::Reference(target)) => match follow_reference(doc, *target) {
+            Some((target, Object::String(bytes, _))) => push_javascript(
+                js_objects,
+                holder,
+                normalize_pdf_string(bytes),
+                JsSource::IndirectRef(target.0),
+            ),
+            Some((target, Object::Stream(_))) => {
+                if let Some(decoded) = cache.get(target.0) {
+                    push_stream_javascript(js_objects, target.0, decoded);
+                }
+            }
+            _ => {}
+        },
+        _ => {}
+    }
+
+    for (key, value) in dict.iter() {
+        if *decode_name(key) != *b"JS" {
+            collect_javascript(doc, cache, holder, value, depth + 1, js_objects);
+        }
+    }
+}
+
+fn find_dangerous_js_apis(content: &str, config: &CompiledConfig) -> Vec<String> {
+    config
+        .config
+        .dangerous_js_apis
+        .iter()
+        .filter(|api| content.contains(api.as_str()))
+        .cloned()
+        .collect()
+}
+
+fn find_hidden_matches(js_obj: &JavaScriptObject, config: &CompiledConfig) -> Vec<PatternMatch> {
+    let Some(deobfuscated) = &js_obj.deobfuscated else {
+        return Vec::new();
+    };
+    let patterns = &config.suspicious_patterns;
+    let visible: HashSet<&str> = patterns
+        .find_all(PatternTarget::Js, &js_obj.content)
+        .iter()
+        .map(|hit| hit.text)
+        .collect();
+    let mut hidden: Vec<PatternMatch> = Vec::new();
+    for hit in patterns.find_all(PatternTarget::Js, deobfuscated) {
+        if !visible.contains(hit.text) && !hidden.iter().any(|seen| seen.evidence == hit.text) {
+            hidden.push(hit.to_match(PatternTarget::Js, Some(js_obj.id)));
+        }
+    }
+    hidden
+}
+
+/// Several actions can share one script stream; it is reported once.
+fn push_stream_javascript(js_objects: &mut Vec<JavaScriptObject>, id: u32, decoded: &[u8]) {
+    let seen = js_objects
+        .iter()
+        .any(|js| js.id == id && matches!(js.source, JsSource::Stream));
+    if !seen {
+        push_javascript(
+            js_objects,
+            id,
+            String::from_utf8_lossy(decoded).into_owned(),
+            JsSource::Stream,
+        );
+    }
+}
+
+fn push_javascript(
+    js_objects: &mut Vec<JavaScriptObject>,
+    id: u32,
+    content: String,
+    source: JsSource,
+) {
+    let deobfuscated = deobfuscate_js(&content);
+    let mut ast_findings = analyze_js_ast(&content);
+    // Literal rewriting can reveal more calls, e.g. `util["pr" + "intf"]`,
+    // but also hides some, e.g. the `%u` literal behind a heap spray, so
+    // both sources' calls are kept, each API once
+    if let Some(decoded) = &deobfuscated {
+        for found in analyze_js_ast(decoded).acrobat_apis {
+            let apis = &mut ast_findings.acrobat_apis;
+            match apis.iter_mut().find(|call| call.api == found.api) {
+                Some(call) => call.calls = call.calls.max(found.calls),
+                None => apis.push(found),
+            }
+        }
+    }
+    js_objects.push(JavaScriptObject {
+        id,
+        ast_findings,
+        sha256: compute_sha256(content.as_bytes()),
+        content,
+        source,
+        deobfuscated,
+        dangerous_apis: Vec::new(),
+        hidden_matches: Vec::new(),
+    });
+}
+
+fn check_for_auto_action(doc: &Document) -> bool {
+    doc.objects.iter().any(|(_, object)| {
+        if let Ok(dict) = object.as_dict() {
+            has_name_key(dict, b"AA") || has_name_key(dict, b"OpenAction")
+        } else {
+            false
+        }
+    })
+}
+
+/// Object ids of `/Launch` action dictionaries.
+fn find_launch_actions(doc: &Document) -> Vec<u32> {
+    doc.objects
+        .iter()
+        .filter(|(_, object)| {
+            object
+                .as_dict()
+                .and_then(|dict| dict.get(b"S"))
+                .and_then(|s| s.as_name())
+                .is_ok_and(|name| name == b"Launch")
+        })
+        .map(|(id, _)| id.0)
+        .collect()
+}
+
+fn check_for_obj_stm(doc: &Document) -> bool {
+    doc.objects.iter().any(|(_, object)| {
+        if let Ok(dict) = object.as_dict() {
+            has_name_key(dict, b"ObjStm")
+        } else {
+            false
+        }
+    })
+}
+
+/// Names and strings that are objects of their own and match a pattern,
+/// each with all its text as the evidence.
+fn check_for_suspicious_names(doc: &Document, config: &CompiledConfig) -> Vec<PatternMatch> {
+    let patterns = &config.suspicious_patterns;
+
+    doc.objects
+        .iter()
+        .filter_map(|(id, obj)| match obj {
+            Object::Name(name) | Object::String(name, _) => {
+                let name_str = normalize_pdf_string(&decode_name(name));
+                let mut found = patterns
+                    .find(PatternTarget::Names, &name_str)?
+                    .to_match(PatternTarget::Names, Some(id.0));
+                found.evidence = name_str;
+                Some(found)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn check_for_hidden_content(doc: &Document) -> bool {
+    doc.objects.iter().any(|(_, obj)| {
+        if let Ok(dict) = obj.as_dict() {
+            has_name_key(dict, b"OCG") || has_name_key(dict, b"OCGs")
+        } else {
+            false
+        }
+    })
+}
+
+fn check_file_size(raw: &[u8], config: &CompiledConfig) -> bool {
+    raw.len() as u64 > config.config.file_size_threshold
+}
+
+fn info_dictionary(doc: &Document) -> Option<&Dictionary> {
+    let info = doc.trailer.get(b"Info").ok()?;
+    resolve(doc, info)?.as_dict().ok()
+}
+
+/// The `/Info` values that match a pattern, each with all its text as the
+/// evidence.
+fn check_metadata(doc: &Document, config: &CompiledConfig) -> Vec<PatternMatch> {
+    let Some(info_dict) = info_dictionary(doc) else {
+        return Vec::new();
+    };
+    info_dict
+        .iter()
+        .filter_map(|(_, value)| {
+            let value_str = normalize_pdf_string(value.as_str().ok()?);
+            let mut found = config
+                .suspicious_patterns
+                .find(PatternTarget::Metadata, &value_str)?
+                .to_match(PatternTarget::Metadata, None);
+            found.evidence = value_str;
+            Some(found)
+        })
+        .collect()
+}
+
+/// Checks `/Creator` and `/Producer`, and XMP's `xmp:CreatorTool` and
+/// `pdf:Producer`, against the configured tool lists. Returns whether no
+/// value came from a known-safe tool, and the first value matching a
+/// known-malicious builder.
+fn check_creator(
+    doc: &Document,
+    streams: &StreamCache,
+    config: &CompiledConfig,
+) -> (bool, Option<String>) {
+    let mut tools: Vec<String> = info_dictionary(doc)
+        .map(|info| {
+            [b"Creator".as_slice(), b"Producer".as_slice()]
+                .iter()
+                .filter_map(|key| info.get(key).ok())
+                .filter_map(|value| resolve(doc, value)?.as_str().ok())
+                .map(normalize_pdf_string)
+                .filter(|value| !value.trim().is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    // A builder that leaves `/Info` alone may still be named here
+    if let Some(xmp) = xmp_metadata(doc, streams) {
+        tools.extend(
+            ["xmp:CreatorTool", "pdf:Producer"]
+                .iter()
+                .filter_map(|name| xmp_text(&xmp, name)),
+        );
+    }
+
+    let unknown_creator = match &config.known_safe_creators {
+        Some(safe) => !tools.iter().any(|tool| safe.is_match(tool)),
+        None => tools.is_empty(),
+    };
+    let malicious_creator = config
+        .known_malicious_creators
+        .as_ref()
+        .and_then(|malicious| tools.iter().find(|tool| malicious.is_match(tool)))
+        .cloned();
+
+    (unknown_creator, malicious_creator)
+}
+
+fn check_for_unusual_objects(doc: &Document) -> Vec<String> {
+    let common_types: [&[u8]; 6] = [
+        b"Catalog",
+        b"Pages",
+        b"Page",
+        b"Font",
+        b"XObject",
+        b"Metadata",
+    ];
+    doc.objects
+        .values()
+        .filter_map(|obj| {
+            if let Ok(dict) = obj.as_dict() {
+                if let Ok(type_obj) = dict.get(b"Type") {
+                    if let Ok(type_name) = type_obj.as_name() {
+                        if !common_types.contains(&type_name) {
+                            return Some(normalize_pdf_string(type_name));
+                        }
+                    }
+                }
+            }
+            None
+        })
+        .collect()
+}
+
+fn calculate_object_statistics(doc: &Document) -> ObjectStatistics {
+    let mut stats = ObjectStatistics {
+        total_objects: doc.objects.len(),
+        ..Default::default()
+    };
+    for obj in doc.objects.values() {
+        if obj.as_stream().is_ok() {
+            stats.stream_objects += 1;
+        }
+        if let Ok(dict) = obj.as_dict() {
+            if has_name_key(dict, b"JS") || has_name_key(dict, b"JavaScript") {
+                stats.js_objects += 1;
+            }
+            if has_name_key(dict, b"ObjStm") {
+                stats.obj_stm_objects += 1;
+            }
+        }
+    }
+    stats
+}
+
+/// Flags documents dominated by JavaScript or stream objects, which catches
+/// spray-style exploits made of many individually innocuous objects.
+fn check_object_ratio_heuristics(
+    stats: &ObjectStatistics,
+    config: &CompiledConfig,
+) -> Vec<RatioFinding> {
+    if stats.total_objects == 0 {
+        return Vec::new();
+    }
+
+    let total = stats.total_objects as f64;
+    [
+        (
+            "js_object_ratio",
+            stats.js_objects as f64 / total,
+            config.config.max_js_object_ratio,
+        ),
+        (
+            "stream_object_ratio",
+            stats.stream_objects as f64 / total,
+            config.config.max_stream_object_ratio,
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, actual, threshold)| actual > threshold)
+    .map(|(ratio_name, actual, threshold)| RatioFinding {
+        ratio_name: ratio_name.to_string(),
+        actual,
+        threshold,
+    })
+    .collect()
+}
+
+/// Records where a pattern matched the decoded text of stream `object_id`.
+pub(crate) fn push_stream_match(result: &mut AnalysisResult, object_id: u32, hit: Hit) {
+    result.suspicious_stream_content.push(ContentMatch {
+        object_id,
+        offset: hit.start,
+        evidence: hit.text.to_string(),
+    });
+    let mut found = hit.to_match(PatternTarget::Streams, Some(object_id));
+    found.offset = Some(hit.start);
+    result.pattern_matches.push(found);
+}
+
+fn analyze_streams(
+    doc: &Document,
+    streams: &StreamCache,
+    config: &CompiledConfig,
+    result: &mut AnalysisResult,
+) {
+    for (id, object) in doc.objects.iter() {
+        let (Ok(stream), Some(decoded)) = (object.as_stream(), streams.get(id.0)) else {
+            continue;
+        };
+
+        if is_image_stream(stream) {
+            check_image_stream(*id, stream, decoded, result);
+        }
+
+        let content = String::from_utf8_lossy(decoded);
+        if let Some(hit) = config
+            .suspicious_patterns
+            .find(PatternTarget::Streams, &content)
+        {
+            push_stream_match(result, id.0, hit);
+        }
+
+        // Solid-colour image data is naturally repetitive
+        if !is_image_stream(stream) {
+            let hints = check_shellcode_heuristics(decoded);
+            if hints.is_detected() {
+                result.shellcode_hints.push((id.0, hints));
+            }
+        }
+    }
+}
+
+/// Scripts in streams were checked with the other decoded streams; this
+/// covers those held in strings, where `%u` payloads usually are.
+fn check_script_shellcode(scripts: &[JavaScriptObject]) -> Vec<(u32, ShellcodeHeuristicResult)> {
+    scripts
+        .iter()
+        .filter(|js| !matches!(js.source, JsSource::Stream | JsSource::XfaScript))
+        .map(|js| (js.id, check_shellcode_heuristics(js.content.as_bytes())))
+        .filter(|(_, hints)| hints.is_detected())
+        .collect()
+}
+
+/// `samples` is the decoded image data; DCT and other lossy encodings never
+/// make it into the stream cache, so their LSBs aren't tested.
+fn check_image_stream(id: ObjectId, stream: &Stream, samples: &[u8], result: &mut AnalysisResult) {
+    let color_depth = stream
+        .dict
+        .get(b"BitsPerComponent")
+        .and_then(|bits| bits.as_i64())
+        .unwrap_or(8);
+    let hint = check_image_steganography(samples, color_depth as u8);
+    if hint.suspected {
+        result.steganography_hints.push((id.0, hint));
+    }
+}
+
+fn is_image_stream(stream: &Stream) -> bool {
+    stream
+        .dict
+        .get(b"Subtype")
+        .and_then(|subtype| subtype.as_name())
+        .is_ok_and(|name| name == b"Image")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A catalog whose `/OpenAction` runs a script, with a cross-reference
+    /// table at the real offsets.
+    const MINIMAL_PDF: &[u8] = b"%PDF-1.4\n\
+        1 0 obj\n<< /Type /Catalog /Pages 2 0 R /OpenAction 3 0 R >>\nendobj\n\
+        2 0 obj\n<< /Type /Pages /Kids [] /Count 0 >>\nendobj\n\
+        3 0 obj\n<< /S /JavaScript /JS (app.alert(1)) >>\nendobj\n\
+        xref\n0 4\n\
+        0000000000 65535 f \n\
+        0000000009 00000 n \n\
+        0000000076 00000 n \n\
+        0000000128 00000 n \n\
+        trailer\n<< /Size 4 /Root 1 0 R >>\nstartxref\n183\n%%EOF\n";
+
+    #[test]
+    fn analyzes_a_pdf_from_a_byte_slice() {
+        let config = CompiledConfig::new(Config::default()).unwrap();
+        let result = analyze_pdf_bytes(MINIMAL_PDF, &config).unwrap();
+
+        assert_eq!(result.file_size, Some(MINIMAL_PDF.len() as u64));
+        assert_eq!(result.object_statistics.total_objects, 3);
+        assert!(result.has_javascript);
+        assert!(result.has_auto_action);
+        assert_eq!(result.javascript_objects.len(), 1);
+        assert_eq!(result.javascript_objects[0].id, 3);
+        assert_eq!(result.javascript_objects[0].content, "app.alert(1)");
+    }
+}
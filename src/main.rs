@@ -1,318 +1,2264 @@
-use flate2::read::ZlibDecoder;
-use lopdf::{Dictionary, Document, Object, Stream};
-use rayon::prelude::*;
-use regex::Regex;
-use serde::Deserialize;
-use std::fs::File;
-use std::io::{BufReader, Read};
+mod archive;
+mod batch;
+mod clamd;
+mod eml;
+mod fetch;
+#[cfg(feature = "history")]
+mod history;
+mod icap;
+mod inspect;
+mod quarantine;
+#[cfg(feature = "server")]
+mod server;
+mod syslog;
+mod watch;
+#[cfg(feature = "webhook")]
+mod webhook;
 
-#[derive(Deserialize)]
-struct Config {
-    file_size_threshold: u64,
-    suspicious_patterns: Vec<String>,
-    suspicious_metadata_patterns: Vec<String>,
-}
-
-#[derive(Default)]
-struct AnalysisResult {
-    has_javascript: bool,
-    has_auto_action: bool,
-    has_obj_stm: bool,
-    suspicious_names: Vec<String>,
-    hidden_content: bool,
-    large_file_size: bool,
-    suspicious_metadata: bool,
-    unusual_objects: Vec<String>,
-    object_statistics: ObjectStatistics,
-    severity_score: u32,
-    javascript_objects: Vec<JavaScriptObject>,
-}
-
-#[derive(Default)]
-struct ObjectStatistics {
-    total_objects: usize,
-    stream_objects: usize,
-    js_objects: usize,
-    obj_stm_objects: usize,
-}
-
-struct JavaScriptObject {
-    id: u32,
-    content: String,
+use archive::Extractor;
+use batch::{BatchSummary, FileFilter};
+use clamd::ClamdService;
+use clap::error::ErrorKind;
+use clap::{ArgAction, CommandFactory, Parser, Subcommand, ValueEnum};
+use fetch::{is_url, Fetcher};
+#[cfg(feature = "history")]
+use history::History;
+use icap::IcapService;
+use lopdf::{Document, Object};
+use pdf_sentinel::baseline::{Baseline, BaselineEntry};
+use pdf_sentinel::cache::ResultCache;
+use pdf_sentinel::diff::{self, ResultDiff};
+use pdf_sentinel::encryption::{unlock, PasswordUsed};
+use pdf_sentinel::filters::decode_stream_bounded;
+use pdf_sentinel::html::HtmlReport;
+use pdf_sentinel::metadata::{DocumentMetadata, MetadataDate};
+use pdf_sentinel::pdfid::PdfId;
+use pdf_sentinel::rules::RuleEngine;
+use pdf_sentinel::scoring::Severity;
+use pdf_sentinel::screen::skip_after_quick_screen;
+use pdf_sentinel::suppressions::load_suppressions;
+#[cfg(feature = "virustotal")]
+use pdf_sentinel::virustotal::VirusTotal;
+use pdf_sentinel::virustotal::VtSubject;
+#[cfg(feature = "yara")]
+use pdf_sentinel::yara_scan::YaraRules;
+use pdf_sentinel::yara_scan::YaraTarget;
+use pdf_sentinel::{attachments, graph, media, misp, sanitize, sarif, stix};
+use pdf_sentinel::{
+    AnalysisResult, Analyzer, Config, JsSource, SentinelError, ThreatClassification,
+    RESULT_SCHEMA_VERSION,
+};
+use quarantine::Quarantine;
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use syslog::{EventFormat, SyslogSink, SyslogTransport};
+use tracing_subscriber::EnvFilter;
+#[cfg(feature = "webhook")]
+use webhook::Webhook;
+
+const AFTER_HELP: &str = "\
+Pass - as FILE to read the PDF from stdin, e.g.
+  curl -s https://example.com/doc.pdf | pdf-sentinel -
+or, with the fetch feature, the URL itself:
+  pdf-sentinel https://example.com/doc.pdf
+A ZIP archive has the PDFs inside it analyzed, those in nested archives and
+those encrypted with a password from the [archive] table included.
+
+FILE may also be a glob pattern such as 'scans/**/*.pdf', which is expanded
+even when the shell leaves it alone. RUST_LOG (e.g. RUST_LOG=debug) overrides
+--verbose.
+
+Exit codes, the highest ranked first:
+  2  A file was malicious, or its score reached --fail-threshold
+  3  A file could not be read or parsed
+  1  A file was suspicious
+  0  Every file was clean
+  4  Bad arguments or config, or another error stopped the run
+Whether a file is suspicious or malicious depends on its severity and the
+[exit_codes] table of the config; --fail-on lets lower severities pass.";
+
+/// Exit code for a file at `ExitCodeOptions::suspicious` severity.
+const EXIT_SUSPICIOUS: i32 = 1;
+/// Exit code for a file at `ExitCodeOptions::malicious` severity, or whose
+/// score reached `Config::fail_threshold`.
+const EXIT_MALICIOUS: i32 = 2;
+/// Exit code for a file that could not be read or parsed.
+const EXIT_ANALYSIS_FAILED: i32 = 3;
+/// Exit code for usage and config errors, and anything else that stops the
+/// run. Clap's own 2 would read as a malicious verdict.
+const EXIT_ERROR: i32 = 4;
+
+/// Identifies the record layout of the JSON outputs; sent as the first NDJSON line.
+/// Ends in `RESULT_SCHEMA_VERSION`.
+const RESULT_SCHEMA: &str = "urn:pdf-sentinel:analysis-result:1";
+
+#[derive(Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+    Text,
+    /// JSON, an array when given several files
+    Json,
+    /// One JSON object per line as each file completes
+    Ndjson,
+    /// A SARIF 2.1.0 log for code scanning dashboards
+    Sarif,
+    /// A STIX 2.1 bundle of observables and indicators for threat-intel
+    /// platforms
+    Stix,
+    /// MISP events, one per file, for pushing into a MISP instance
+    Misp,
+    /// A standalone HTML report to attach to incident tickets
+    Html,
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let config = load_config();
-    let file = File::open("sample.pdf")?;
-    let reader = BufReader::new(file);
-    let doc = Document::load_from(reader)?;
+/// Scans PDF files for scripts, automatic actions, embedded payloads and
+/// other signs of malicious content.
+#[derive(Parser)]
+#[command(version, after_help = AFTER_HELP, subcommand_negates_reqs = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    let result = analyze_pdf(&doc, &config);
+    /// PDF files or glob patterns to analyze
+    #[arg(value_name = "FILE")]
+    #[cfg_attr(
+        not(feature = "server"),
+        arg(required_unless_present_any = ["watch", "diff", "recursive", "icap", "clamd"])
+    )]
+    #[cfg_attr(
+        feature = "server",
+        arg(required_unless_present_any = [
+            "watch", "diff", "recursive", "icap", "clamd", "serve"
+        ])
+    )]
+    inputs: Vec<String>,
 
-    print_analysis_result(&result);
+    /// TOML config file, or JSON if it ends in .json; settings it leaves out
+    /// keep their defaults
+    #[arg(short, long, value_name = "PATH", global = true)]
+    config: Option<PathBuf>,
 
-    Ok(())
+    /// Report files whose SHA-256 is in the baseline database at PATH as
+    /// clean without analyzing them [default: the config's [baseline] path]
+    #[arg(long, value_name = "PATH", global = true)]
+    baseline: Option<PathBuf>,
+
+    /// Record each file's result in the SQLite scan history at PATH, which
+    /// the history subcommand queries [default: the config's [history] path]
+    #[cfg(feature = "history")]
+    #[arg(long, value_name = "PATH", global = true)]
+    history: Option<PathBuf>,
+
+    /// Keep results in DIR and reuse them for files scanned again with the
+    /// same settings, instead of analyzing them again [default: the
+    /// config's [cache] dir]
+    #[arg(long, value_name = "DIR")]
+    cache: Option<PathBuf>,
+
+    /// Output format [default: the config file's output.format, else text]
+    #[arg(short, long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Shorthand for --format json
+    #[arg(long, conflicts_with_all = ["format", "ndjson"])]
+    json: bool,
+
+    /// Shorthand for --format ndjson
+    #[arg(long, conflicts_with = "format")]
+    ndjson: bool,
+
+    /// With text output, list which kinds of findings added how many points
+    /// to each severity score. JSON output always has this as explanation
+    #[arg(long)]
+    explain: bool,
+
+    /// Log more diagnostics to stderr; repeat for more detail
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Analyze PDF files as they are written to DIR until Ctrl-C, reporting
+    /// each as it completes to stdout and any --syslog, --webhook or
+    /// --quarantine
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["inputs", "diff", "sanitize"])]
+    watch: Option<PathBuf>,
+
+    /// Serve an HTTP API on ADDRESS (e.g. 0.0.0.0:8080) until Ctrl-C: POST a
+    /// PDF to /scan for its JSON result; GET /healthz and /metrics for
+    /// Prometheus
+    #[cfg(feature = "server")]
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        conflicts_with_all = ["inputs", "watch", "recursive", "diff", "sanitize", "icap", "clamd"]
+    )]
+    serve: Option<String>,
+
+    /// Run an ICAP service on ADDRESS (e.g. 0.0.0.0:1344) until Ctrl-C, for
+    /// web proxies to send PDF downloads and uploads to; those of
+    /// --icap-severity or above are blocked
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        conflicts_with_all = ["inputs", "watch", "recursive", "diff", "sanitize", "clamd"]
+    )]
+    icap: Option<String>,
+
+    /// Severity (low, medium, high or critical) at which --icap blocks a PDF
+    #[arg(
+        long,
+        value_name = "SEVERITY",
+        default_value = "high",
+        requires = "icap"
+    )]
+    icap_severity: Severity,
+
+    /// Answer clamd commands (INSTREAM, SCAN and the like) on ADDRESS, a
+    /// HOST:PORT or a Unix socket path, until Ctrl-C, for mail filters that
+    /// speak to ClamAV; PDFs of --clamd-severity or above are reported FOUND
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        conflicts_with_all = ["inputs", "watch", "recursive", "diff", "sanitize"]
+    )]
+    clamd: Option<String>,
+
+    /// Severity (low, medium, high or critical) at which --clamd reports a
+    /// PDF as FOUND
+    #[arg(
+        long,
+        value_name = "SEVERITY",
+        default_value = "high",
+        requires = "clamd"
+    )]
+    clamd_severity: Severity,
+
+    /// Analyze the PDFs in DIR and all its subdirectories, several at a time,
+    /// and end with a summary
+    #[arg(
+        short,
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["inputs", "watch", "diff", "sanitize", "streaming"]
+    )]
+    recursive: Option<PathBuf>,
+
+    /// With --recursive, analyze files with extension EXT; repeat for more
+    /// [default: pdf]
+    #[arg(long = "extension", value_name = "EXT", requires = "recursive")]
+    extensions: Vec<String>,
+
+    /// With --recursive, pick files by their %PDF- header rather than their
+    /// extension
+    #[arg(long, requires = "recursive", conflicts_with = "extensions")]
+    sniff: bool,
+
+    /// Analyze up to N files at once [default: one per CPU]
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Analyze both files and report the findings B adds or drops
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["A", "B"],
+        conflicts_with_all = ["inputs", "sanitize"]
+    )]
+    diff: Option<Vec<PathBuf>>,
+
+    /// Write a copy of FILE without scripts, automatic or Launch actions,
+    /// embedded files and XFA forms. This removes the obvious attack vectors
+    /// only; it does not make a PDF safe
+    #[arg(long, value_name = "OUTFILE")]
+    sanitize: Option<PathBuf>,
+
+    /// Don't look for the suspicious_patterns rules of CATEGORY (e.g.
+    /// command-execution); repeat for more
+    #[arg(long = "disable-category", value_name = "CATEGORY")]
+    disabled_categories: Vec<String>,
+
+    /// Don't count the findings that the [[suppress]] entries of FILE
+    /// accept as benign, by rule and object or by file SHA-256; -v lists
+    /// them as suppressed. Repeat for more files
+    #[arg(long, value_name = "FILE")]
+    suppressions: Vec<PathBuf>,
+
+    /// Exit with code 2 when a severity score is N or more
+    #[arg(long, value_name = "N")]
+    fail_threshold: Option<u32>,
+
+    /// Only exit as suspicious or malicious for files of SEVERITY (low,
+    /// medium, high or critical) or above; others count as clean
+    #[arg(long, value_name = "SEVERITY")]
+    fail_on: Option<Severity>,
+
+    /// Write each file's embedded attachments and media payloads to DIR for
+    /// further analysis. The files are written as is: they may well be malware
+    #[arg(long, value_name = "DIR", conflicts_with = "streaming")]
+    extract_attachments: Option<PathBuf>,
+
+    /// Write FILE's object reference graph to OUTFILE in Graphviz DOT, with
+    /// scripts, objects findings cite, object streams, the catalog and the
+    /// page tree colored, e.g. for `dot -Tsvg`
+    #[arg(
+        long,
+        value_name = "OUTFILE",
+        conflicts_with_all = ["watch", "recursive", "diff", "streaming"]
+    )]
+    dump_graph: Option<PathBuf>,
+
+    /// Print pdfid's keyword counts for each file instead of analyzing it,
+    /// in pdfid's format, for playbooks and parsers built around pdfid.
+    /// Names spelled with #xx escapes count too, and how many did is given
+    /// in parentheses
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "format", "json", "ndjson", "watch", "recursive", "diff", "sanitize", "dump_graph"
+        ]
+    )]
+    pdfid: bool,
+
+    /// Also write the findings to OUTFILE as a STIX 2.1 bundle
+    #[arg(long, value_name = "OUTFILE")]
+    stix: Option<PathBuf>,
+
+    /// Also send one CEF or LEEF event per file to the syslog receiver at
+    /// HOST:PORT
+    #[arg(long, value_name = "HOST:PORT", conflicts_with_all = ["diff", "sanitize"])]
+    syslog: Option<String>,
+
+    /// How to reach the --syslog receiver
+    #[arg(long, value_enum, default_value = "udp", requires = "syslog")]
+    syslog_transport: SyslogTransport,
+
+    /// Event format for --syslog
+    #[arg(long, value_enum, default_value = "cef", requires = "syslog")]
+    syslog_format: EventFormat,
+
+    /// Also POST each file's JSON record to URL
+    #[cfg(feature = "webhook")]
+    #[arg(long, value_name = "URL", conflicts_with_all = ["diff", "sanitize"])]
+    webhook: Option<String>,
+
+    /// Send 'NAME: VALUE' as a header with every --webhook request, e.g. for
+    /// an API token; repeat for more
+    #[cfg(feature = "webhook")]
+    #[arg(long = "webhook-header", value_name = "HEADER", requires = "webhook")]
+    webhook_headers: Vec<String>,
+
+    /// Move files of --quarantine-severity or above into DIR, renamed to
+    /// NAME.quarantine, with a NAME.quarantine.json sidecar saying why
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["diff", "sanitize"])]
+    quarantine: Option<PathBuf>,
+
+    /// Severity (low, medium, high or critical) at which --quarantine
+    /// takes a file
+    #[arg(
+        long,
+        value_name = "SEVERITY",
+        default_value = "high",
+        requires = "quarantine"
+    )]
+    quarantine_severity: Severity,
+
+    /// With --quarantine, copy files instead of moving them
+    #[arg(long, requires = "quarantine")]
+    quarantine_copy: bool,
+
+    /// Load extra detection rules from a shared library that exports
+    /// register_rules
+    #[arg(long, value_name = "LIBRARY")]
+    rules: Option<PathBuf>,
+
+    /// Scan decoded streams and scripts with the YARA rules (*.yar, *.yara)
+    /// in DIR
+    #[cfg(feature = "yara")]
+    #[arg(long, value_name = "DIR")]
+    yara: Option<PathBuf>,
+
+    /// Password for encrypted PDFs; the empty user password is always tried
+    #[arg(long, value_name = "PASSWORD")]
+    password: Option<String>,
+
+    /// Analyze documents as they were at revision N, 1 being the original,
+    /// instead of with every incremental update applied
+    #[arg(long, value_name = "N")]
+    revision: Option<usize>,
+
+    /// Look up the file's hash and those of its attachments on VirusTotal,
+    /// with the API key from VT_API_KEY or the config file. Only hashes are
+    /// sent
+    #[cfg(feature = "virustotal")]
+    #[arg(long)]
+    vt_lookup: bool,
+
+    /// Read each FILE as an email message (.eml) and analyze its PDF
+    /// attachments, forwarded messages included. Each is reported as
+    /// FILE:NAME <CONTENT-ID>
+    #[arg(long, conflicts_with_all = ["watch", "recursive", "diff", "sanitize", "streaming"])]
+    eml: bool,
+
+    /// Scan files in one low-memory pass instead of parsing the object graph;
+    /// finds less, but copes with huge files. Input from stdin or a URL always
+    /// gets the full analysis
+    #[arg(long)]
+    streaming: bool,
+
+    /// Analyze every file of a batch. By default, when given several files,
+    /// those whose first and last 4 KB show nothing suspicious are skipped
+    #[arg(long)]
+    thorough: bool,
 }
 
-fn load_config() -> Config {
-    // Load from a file or use default values
-    Config {
-        file_size_threshold: 10 * 1024 * 1024,
-        suspicious_patterns: vec![
-            r"(?i)eval".to_string(),
-            r"(?i)exec".to_string(),
-            r"(?i)spawn".to_string(),
-            r"(?i)shell".to_string(),
-        ],
-        suspicious_metadata_patterns: vec![r"(?i)(adobe|microsoft|office)".to_string()],
+impl Args {
+    fn output_format(&self, options: &OutputOptions) -> OutputFormat {
+        if self.json {
+            OutputFormat::Json
+        } else if self.ndjson {
+            OutputFormat::Ndjson
+        } else {
+            self.format.or(options.format).unwrap_or(OutputFormat::Text)
+        }
+    }
+
+    fn file_filter(&self) -> FileFilter {
+        if self.sniff {
+            FileFilter::Sniff
+        } else if self.extensions.is_empty() {
+            FileFilter::Extensions(vec!["pdf".to_string()])
+        } else {
+            FileFilter::Extensions(self.extensions.clone())
+        }
+    }
+
+    /// Level used when `RUST_LOG` is unset: errors only, then one step more
+    /// per `-v`.
+    fn log_level(&self) -> &'static str {
+        match self.verbose {
+            0 => "error",
+            1 => "warn",
+            2 => "info",
+            3 => "debug",
+            _ => "trace",
+        }
     }
 }
 
-fn analyze_pdf(doc: &Document, config: &Config) -> AnalysisResult {
-    let mut result = AnalysisResult::default();
+#[derive(Subcommand)]
+enum Command {
+    /// Manage the --baseline database of approved files
+    #[command(subcommand)]
+    Baseline(BaselineCommand),
+    /// Compare two versions of a document: the findings and score B adds
+    /// or drops, and the objects, streams and actions it adds, removes or
+    /// changes. Like --diff, with the objects as well
+    Diff {
+        #[arg(value_name = "A")]
+        before: PathBuf,
+        #[arg(value_name = "B")]
+        after: PathBuf,
+    },
+    /// Write object N of FILE out for a closer look: a stream's content, or
+    /// any other object in PDF syntax. A stream's dictionary goes to stderr
+    Extract {
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// The number of the object to write
+        #[arg(long, value_name = "N")]
+        object: u32,
 
-    result.has_javascript = check_for_javascript(doc);
-    result.javascript_objects = find_javascript_objects(doc);
-    result.has_auto_action = check_for_auto_action(doc);
-    result.has_obj_stm = check_for_obj_stm(doc);
-    result.suspicious_names = check_for_suspicious_names(doc, config);
-    result.hidden_content = check_for_hidden_content(doc);
-    result.large_file_size = check_file_size(doc, config);
-    result.suspicious_metadata = check_metadata(doc, config);
-    result.unusual_objects = check_for_unusual_objects(doc);
-    result.object_statistics = calculate_object_statistics(doc);
+        /// Write a stream's content with its filters undone rather than as
+        /// stored
+        #[arg(long)]
+        decoded: bool,
 
-    analyze_streams(doc, config, &mut result);
+        /// Write to OUTFILE rather than stdout
+        #[arg(short, long, value_name = "OUTFILE")]
+        output: Option<PathBuf>,
+    },
+    /// Analyze FILE, then read commands from stdin to go through its
+    /// objects by hand: list and show them, search them, follow their
+    /// references and run single detectors again. Type help for the list
+    Inspect {
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+    },
+    /// List the scans in the --history database, oldest first, as text,
+    /// JSON or NDJSON
+    #[cfg(feature = "history")]
+    History {
+        /// Only scans of the file with this SHA-256
+        #[arg(long, value_name = "SHA256")]
+        hash: Option<String>,
 
-    result.severity_score = calculate_severity_score(&result);
+        /// Only scans made on or after DATE, as YYYY-MM-DD or an RFC 3339
+        /// time
+        #[arg(long, value_name = "DATE")]
+        since: Option<String>,
+    },
+}
 
-    result
+#[derive(Subcommand)]
+enum BaselineCommand {
+    /// Approve each FILE that analyzes as clean; exits with 1 if any did not
+    Add {
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<PathBuf>,
+
+        /// Approve the files however they analyze
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// The `--config` file: analysis settings at the top level, plus CLI-only
+/// settings in tables of their own.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ConfigFile {
+    #[serde(flatten)]
+    analysis: Config,
+    output: OutputOptions,
+    exit_codes: ExitCodeOptions,
+    #[cfg(feature = "virustotal")]
+    virustotal: VirusTotalOptions,
+    server: ServerOptions,
+    fetch: FetchOptions,
+    archive: ArchiveOptions,
+    baseline: BaselineOptions,
+    #[cfg(feature = "history")]
+    history: HistoryOptions,
+    cache: CacheOptions,
+}
+
+/// The `[output]` table. Command line flags take precedence.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct OutputOptions {
+    format: Option<OutputFormat>,
+}
+
+/// The `[exit_codes]` table: the severities at which a file makes the run
+/// exit as suspicious or malicious.
+#[derive(Deserialize)]
+#[serde(default)]
+struct ExitCodeOptions {
+    suspicious: Severity,
+    malicious: Severity,
+}
+
+impl Default for ExitCodeOptions {
+    fn default() -> Self {
+        ExitCodeOptions {
+            suspicious: Severity::Medium,
+            malicious: Severity::High,
+        }
+    }
 }
 
-fn check_for_javascript(doc: &Document) -> bool {
-    doc.objects.iter().any(|(_, object)| {
-        if let Ok(dict) = object.as_dict() {
-            dict.has(b"JS")
-                || dict.has(b"JavaScript")
-                || dict
-                    .get(b"S")
-                    .map_or(false, |s| s.as_name().map_or(false, |n| n == b"JavaScript"))
+impl ExitCodeOptions {
+    fn verdict(
+        &self,
+        result: &AnalysisResult,
+        config: &Config,
+        fail_on: Option<Severity>,
+    ) -> Verdict {
+        if exceeds_fail_threshold(result, config) {
+            return Verdict::Malicious;
+        }
+        if fail_on.is_some_and(|fail_on| result.severity < fail_on) {
+            return Verdict::Clean;
+        }
+        if result.severity >= self.malicious {
+            Verdict::Malicious
+        } else if result.severity >= self.suspicious {
+            Verdict::Suspicious
         } else {
-            false
-        }
-    })
-}
-
-fn find_javascript_objects(doc: &Document) -> Vec<JavaScriptObject> {
-    let mut js_objects = Vec::new();
-
-    for (id, object) in doc.objects.iter() {
-        if let Ok(dict) = object.as_dict() {
-            if dict.has(b"JS") || dict.has(b"JavaScript") {
-                if let Some(stream) = object.as_stream().ok() {
-                    if let Ok(filter) = stream.filter() {
-                        if filter == "FlateDecode" {
-                            let mut decoder = ZlibDecoder::new(&stream.content[..]);
-                            let mut decompressed = Vec::new();
-                            if decoder.read_to_end(&mut decompressed).is_ok() {
-                                if let Ok(content) = str::from_utf8(&decompressed) {
-                                    js_objects.push(JavaScriptObject {
-                                        id: id.0,
-                                        content: content.to_string(),
-                                    });
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+            Verdict::Clean
         }
     }
+}
 
-    js_objects
+/// What a file counts as for the exit code.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+enum Verdict {
+    #[default]
+    Clean,
+    Suspicious,
+    Malicious,
 }
 
-fn check_for_auto_action(doc: &Document) -> bool {
-    doc.objects.iter().any(|(_, object)| {
-        if let Ok(dict) = object.as_dict() {
-            dict.has(b"AA") || dict.has(b"OpenAction")
-        } else {
-            false
+/// The `[virustotal]` table, for `--vt-lookup`.
+#[cfg(feature = "virustotal")]
+#[derive(Deserialize)]
+#[serde(default)]
+struct VirusTotalOptions {
+    /// `VT_API_KEY` takes precedence.
+    api_key: Option<String>,
+    requests_per_minute: u32,
+}
+
+#[cfg(feature = "virustotal")]
+impl Default for VirusTotalOptions {
+    fn default() -> Self {
+        VirusTotalOptions {
+            api_key: None,
+            // The public API's limit
+            requests_per_minute: 4,
+        }
+    }
+}
+
+/// The `[server]` table, for `--serve`, `--icap` and `--clamd`.
+#[derive(Deserialize)]
+#[serde(default)]
+struct ServerOptions {
+    /// Larger uploads are refused with 413 by `--serve` and with an error by
+    /// `--clamd`, and let through unscanned by `--icap`.
+    max_body_size: u64,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        ServerOptions {
+            max_body_size: 100 * 1024 * 1024,
+        }
+    }
+}
+
+/// The `[fetch]` table, for URLs given as inputs.
+#[derive(Deserialize)]
+#[serde(default)]
+struct FetchOptions {
+    /// Bytes a download may have.
+    max_size: u64,
+    /// Seconds a download may take, redirects included.
+    timeout: u64,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        FetchOptions {
+            max_size: 100 * 1024 * 1024,
+            timeout: 30,
         }
-    })
+    }
+}
+
+/// The `[archive]` table, for ZIP archives given as inputs.
+#[derive(Deserialize)]
+#[serde(default)]
+struct ArchiveOptions {
+    /// Levels of archives inside archives that are opened.
+    max_depth: usize,
+    /// Bytes that may be decompressed from one archive, nested ones
+    /// included.
+    max_size: u64,
+    /// Tried in order on encrypted entries.
+    passwords: Vec<String>,
 }
 
-fn check_for_obj_stm(doc: &Document) -> bool {
-    doc.objects.iter().any(|(_, object)| {
-        if let Ok(dict) = object.as_dict() {
-            dict.has(b"ObjStm")
+impl Default for ArchiveOptions {
+    fn default() -> Self {
+        ArchiveOptions {
+            max_depth: 3,
+            max_size: 256 * 1024 * 1024,
+            passwords: vec!["infected".to_string()],
+        }
+    }
+}
+
+/// The `[baseline]` table. `--baseline` takes precedence.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct BaselineOptions {
+    path: Option<PathBuf>,
+}
+
+/// The `[history]` table. `--history` takes precedence.
+#[cfg(feature = "history")]
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct HistoryOptions {
+    path: Option<PathBuf>,
+}
+
+/// The `[cache]` table. `--cache` takes precedence.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct CacheOptions {
+    dir: Option<PathBuf>,
+}
+
+/// Expands glob patterns among the inputs. Patterns are expanded here rather
+/// than left to the shell so that they work on Windows and when quoted. A
+/// pattern that matches nothing is kept as is, so that it is reported as a
+/// missing file rather than silently dropped.
+fn expand_inputs(inputs: &[String]) -> Vec<String> {
+    let mut expanded = Vec::new();
+    for input in inputs {
+        let matches: Vec<String> = if input.contains(['*', '?', '[']) && !is_url(input) {
+            glob::glob(input)
+                .into_iter()
+                .flatten()
+                .filter_map(Result::ok)
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        if matches.is_empty() {
+            expanded.push(input.clone());
         } else {
-            false
+            expanded.extend(matches);
         }
-    })
+    }
+    expanded
 }
 
-fn check_for_suspicious_names(doc: &Document, config: &Config) -> Vec<String> {
-    let re = Regex::new(&config.suspicious_patterns.join("|")).unwrap();
+fn main() {
+    if let Err(err) = try_main() {
+        eprintln!("Error: {}", err);
+        std::process::exit(EXIT_ERROR);
+    }
+}
 
-    doc.objects
-        .iter()
-        .filter_map(|(_, obj)| match obj {
-            Object::Name(name) | Object::String(name) => {
-                let name_str = String::from_utf8_lossy(name).to_string();
-                if re.is_match(&name_str) {
-                    Some(name_str)
-                } else {
-                    None
+/// Reports a usage error as clap would, but with [`EXIT_ERROR`].
+fn exit_usage(err: clap::Error) -> ! {
+    let _ = err.print();
+    std::process::exit(if err.use_stderr() { EXIT_ERROR } else { 0 })
+}
+
+fn try_main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::try_parse().unwrap_or_else(|err| exit_usage(err));
+    let inputs = expand_inputs(&args.inputs);
+    if args.sanitize.is_some() && inputs.len() != 1 {
+        exit_usage(Args::command().error(
+            ErrorKind::WrongNumberOfValues,
+            "--sanitize takes exactly one input file",
+        ));
+    }
+    if args.dump_graph.is_some() && inputs.len() != 1 {
+        exit_usage(Args::command().error(
+            ErrorKind::WrongNumberOfValues,
+            "--dump-graph takes exactly one input file",
+        ));
+    }
+    let config_file = load_config(args.config.as_deref())?;
+    let format = args.output_format(&config_file.output);
+    let diff_files = match (&args.command, args.diff.as_deref()) {
+        (Some(Command::Diff { before, after }), _) => Some((before, after, true)),
+        (_, Some([before, after])) => Some((before, after, false)),
+        _ => None,
+    };
+    if diff_files.is_some()
+        && matches!(
+            format,
+            OutputFormat::Sarif | OutputFormat::Stix | OutputFormat::Misp | OutputFormat::Html
+        )
+    {
+        exit_usage(Args::command().error(
+            ErrorKind::ArgumentConflict,
+            "diff has no SARIF, STIX, MISP or HTML output; use --format json",
+        ));
+    }
+
+    // Diagnostics go to stderr so they never mix with results on stdout
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(args.log_level())),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    #[cfg(feature = "history")]
+    let history = args.history.as_ref().or(config_file.history.path.as_ref());
+    #[cfg(feature = "history")]
+    if let Some(Command::History { hash, since }) = &args.command {
+        let Some(path) = history else {
+            exit_usage(Args::command().error(
+                ErrorKind::MissingRequiredArgument,
+                "history needs --history or a [history] path in the config",
+            ));
+        };
+        if !matches!(
+            format,
+            OutputFormat::Text | OutputFormat::Json | OutputFormat::Ndjson
+        ) {
+            exit_usage(Args::command().error(
+                ErrorKind::ArgumentConflict,
+                "history has only text, JSON and NDJSON output",
+            ));
+        }
+        let scans = History::open(path)?.query(hash.as_deref(), since.as_deref())?;
+        print_history(&scans, format)?;
+        return Ok(());
+    }
+
+    if let Some(jobs) = args.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()?;
+    }
+
+    let mut config = config_file.analysis;
+    if let Some(threshold) = args.fail_threshold {
+        config.fail_threshold = threshold;
+    }
+    config
+        .disabled_pattern_categories
+        .extend(args.disabled_categories.iter().cloned());
+    for path in &args.suppressions {
+        config.suppressions.extend(load_suppressions(path)?);
+    }
+    let baseline = args
+        .baseline
+        .as_ref()
+        .or(config_file.baseline.path.as_ref());
+    // Files being added to the baseline are analyzed as if it were empty
+    if let (Some(path), None) = (baseline, &args.command) {
+        config
+            .baseline
+            .approved
+            .extend(Baseline::load(path)?.approved);
+    }
+    let mut analyzer = Analyzer::new(config)?;
+    if let Some(library) = &args.rules {
+        let mut engine = RuleEngine::new();
+        engine.load_library(library)?;
+        analyzer = analyzer.with_rules(engine);
+    }
+    #[cfg(feature = "yara")]
+    if let Some(dir) = &args.yara {
+        analyzer = analyzer.with_yara_rules(YaraRules::load_dir(dir)?);
+    }
+    if let Some(password) = &args.password {
+        analyzer = analyzer.with_password(password.clone());
+    }
+    if let Some(revision) = args.revision {
+        analyzer = analyzer.with_revision(revision);
+    }
+    #[cfg(feature = "virustotal")]
+    if args.vt_lookup {
+        let options = &config_file.virustotal;
+        let api_key = std::env::var("VT_API_KEY")
+            .ok()
+            .or_else(|| options.api_key.clone());
+        let Some(api_key) = api_key else {
+            exit_usage(Args::command().error(
+                ErrorKind::MissingRequiredArgument,
+                "--vt-lookup needs an API key in VT_API_KEY or [virustotal] api_key",
+            ));
+        };
+        analyzer = analyzer.with_virustotal(VirusTotal::new(api_key, options.requests_per_minute));
+    }
+    // Last, since the cache is keyed by all of the above
+    if let Some(dir) = args.cache.as_ref().or(config_file.cache.dir.as_ref()) {
+        let mut cache = ResultCache::open(dir)?;
+        if let Some(library) = &args.rules {
+            cache = cache.depends_on(library)?;
+        }
+        #[cfg(feature = "yara")]
+        if let Some(dir) = &args.yara {
+            cache = cache.depends_on(dir)?;
+        }
+        analyzer = analyzer.with_cache(cache);
+    }
+
+    if let Some(Command::Baseline(BaselineCommand::Add { files, force })) = &args.command {
+        let Some(path) = baseline else {
+            exit_usage(Args::command().error(
+                ErrorKind::MissingRequiredArgument,
+                "baseline add needs --baseline or a [baseline] path in the config",
+            ));
+        };
+        return add_to_baseline(path, files, *force, &analyzer);
+    }
+    if let Some(Command::Extract {
+        file,
+        object,
+        decoded,
+        output,
+    }) = &args.command
+    {
+        return extract_object(
+            file,
+            *object,
+            *decoded,
+            output.as_deref(),
+            args.password.as_deref(),
+            &analyzer,
+        );
+    }
+    if let Some(Command::Inspect { file }) = &args.command {
+        inspect::inspect(file, &analyzer, args.password.as_deref())?;
+        return Ok(());
+    }
+
+    #[cfg(feature = "server")]
+    if let Some(address) = &args.serve {
+        let workers = args.jobs.unwrap_or_else(|| {
+            std::thread::available_parallelism().map_or(1, |workers| workers.get())
+        });
+        server::serve(
+            address,
+            &analyzer,
+            config_file.server.max_body_size,
+            workers,
+        )?;
+        return Ok(());
+    }
+
+    if let Some(address) = &args.icap {
+        IcapService::new(
+            &analyzer,
+            args.icap_severity,
+            config_file.server.max_body_size,
+        )
+        .serve(address)?;
+        return Ok(());
+    }
+    if let Some(address) = &args.clamd {
+        ClamdService::new(
+            &analyzer,
+            args.clamd_severity,
+            config_file.server.max_body_size,
+        )
+        .serve(address)?;
+        return Ok(());
+    }
+
+    let syslog = args
+        .syslog
+        .as_deref()
+        .map(|address| SyslogSink::connect(address, args.syslog_transport, args.syslog_format))
+        .transpose()?;
+    let quarantine = args
+        .quarantine
+        .as_deref()
+        .map(|dir| Quarantine::new(dir, args.quarantine_severity, args.quarantine_copy))
+        .transpose()?;
+    #[cfg(feature = "webhook")]
+    let webhook = args
+        .webhook
+        .as_deref()
+        .map(|url| Webhook::new(url, &args.webhook_headers))
+        .transpose()?;
+    #[cfg(feature = "history")]
+    let history = history.map(|path| History::open(path)).transpose()?;
+
+    if format == OutputFormat::Ndjson {
+        print_ndjson_header()?;
+    }
+
+    if let Some((before, after, objects)) = diff_files {
+        let mut diff = diff::diff_results(
+            &analyzer.analyze_file(before)?,
+            &analyzer.analyze_file(after)?,
+        );
+        if objects {
+            diff.objects = match (Document::load(before), Document::load(after)) {
+                (Ok(before), Ok(after)) => Some(diff::diff_documents(&before, &after)),
+                _ => None,
+            };
+        }
+        print_diff(&diff, format)?;
+        return Ok(());
+    }
+
+    let fetcher = Fetcher::new(
+        config_file.fetch.max_size,
+        Duration::from_secs(config_file.fetch.timeout),
+    );
+    if args.pdfid {
+        for input in &inputs {
+            match read_input(input, &fetcher) {
+                Ok(data) => print!("{}", PdfId::new(&data).to_text(input)),
+                Err(err) => eprintln!("{}: {}", input, err),
+            }
+        }
+        return Ok(());
+    }
+    if let Some(outfile) = &args.sanitize {
+        return sanitize_to_file(
+            &inputs[0],
+            outfile,
+            &analyzer,
+            &fetcher,
+            format,
+            args.explain,
+            args.verbose > 0,
+        );
+    }
+
+    // JSON, SARIF, STIX, MISP and HTML output are printed as one document at
+    // the end, unless watching, which has no end
+    let print_at_end = args.watch.is_none()
+        && matches!(
+            format,
+            OutputFormat::Json
+                | OutputFormat::Sarif
+                | OutputFormat::Stix
+                | OutputFormat::Misp
+                | OutputFormat::Html
+        );
+    let mut run = Run {
+        args: &args,
+        analyzer: &analyzer,
+        exit_codes: &config_file.exit_codes,
+        format,
+        print_at_end,
+        collect_results: print_at_end || args.stix.is_some(),
+        syslog,
+        #[cfg(feature = "webhook")]
+        webhook,
+        #[cfg(feature = "history")]
+        history,
+        quarantine,
+        html: (print_at_end && format == OutputFormat::Html)
+            .then(|| HtmlReport::new(analyzer.config().max_decompressed_stream_size)),
+        results: Vec::new(),
+        analysis_failed: false,
+        verdict: Verdict::Clean,
+        summary: BatchSummary::default(),
+    };
+
+    if let Some(dir) = &args.watch {
+        watch::watch_directory(dir, &analyzer, |file, result| {
+            run.record(file, None, result)
+        })?;
+        return Ok(());
+    }
+
+    let batch = inputs.len() > 1 || args.recursive.is_some();
+
+    if let Some(dir) = &args.recursive {
+        let files = batch::collect_files(dir, &args.file_filter());
+        if print_at_end {
+            // One document at the end, so it may as well list files in order
+            let analyzed = analyzer.analyze_files(&files, args.thorough);
+            run.summary.skipped = files.len() - analyzed.len();
+            for (file, result) in analyzed {
+                run.record(&file, None, result)?;
+            }
+        } else {
+            // Report each file as it completes, so that text and NDJSON
+            // output can be followed while the batch runs
+            let mut failure = None;
+            let analyzed = analyzer.analyze_files_with(&files, args.thorough, |file, result| {
+                if failure.is_none() {
+                    failure = run.record(&file, None, result).err();
                 }
+            });
+            if let Some(err) = failure {
+                return Err(err.into());
             }
-            _ => None,
-        })
-        .collect()
-}
+            run.summary.skipped = files.len() - analyzed;
+        }
+    }
 
-fn check_for_hidden_content(doc: &Document) -> bool {
-    doc.objects.iter().any(|(_, obj)| {
-        if let Ok(dict) = obj.as_dict() {
-            dict.has(b"OCG") || dict.has(b"OCGs")
+    let extractor = Extractor::new(
+        config_file.archive.max_depth,
+        config_file.archive.max_size,
+        config_file.archive.passwords,
+    );
+    for input in &inputs {
+        if args.eml {
+            scan_email(&mut run, input, &fetcher)?;
+            continue;
+        }
+        if input == "-" || is_url(input) || archive::is_zip_file(Path::new(input)) {
+            // Read whole and kept for --extract-attachments and the HTML
+            // report, since stdin can't be read twice and a URL shouldn't be
+            // fetched twice
+            match read_input(input, &fetcher) {
+                Ok(data) if archive::is_zip(&data) => {
+                    scan_archive(&mut run, input, &data, &extractor)?
+                }
+                Ok(data) => {
+                    let result = analyzer.analyze_bytes(&data);
+                    run.record(input, Some(&data), result)?;
+                }
+                Err(err) => run.record(input, None, Err(err))?,
+            }
+            continue;
+        }
+        let result = if batch && skip_after_quick_screen(Path::new(input), args.thorough) {
+            eprintln!("{}: skipped, quick screen found nothing suspicious", input);
+            run.summary.skipped += 1;
+            continue;
+        } else if args.streaming {
+            analyzer.analyze_streaming(Path::new(input))
         } else {
-            false
+            analyzer.analyze_file(Path::new(input))
+        };
+        run.record(input, None, result)?;
+    }
+
+    let Run {
+        html,
+        results,
+        analysis_failed,
+        verdict,
+        summary,
+        ..
+    } = run;
+
+    if format == OutputFormat::Json {
+        let reports: Vec<FileReport> = results
+            .iter()
+            .map(|(file, result)| FileReport::new(file, result))
+            .collect();
+        match reports.as_slice() {
+            [report] => println!("{}", serde_json::to_string_pretty(report)?),
+            reports => println!("{}", serde_json::to_string_pretty(reports)?),
         }
-    })
+    }
+
+    let files: Vec<(&str, &AnalysisResult)> = results
+        .iter()
+        .map(|(file, result)| (file.as_str(), result))
+        .collect();
+    if format == OutputFormat::Sarif {
+        println!("{}", sarif::to_sarif_log(&files)?);
+    }
+    if format == OutputFormat::Stix {
+        println!("{}", stix::to_stix_bundle(&files)?);
+    }
+    if format == OutputFormat::Misp {
+        println!("{}", misp::to_misp_events(&files)?);
+    }
+    if let Some(report) = html {
+        print!("{}", report.finish());
+    }
+
+    if let Some(outfile) = &args.stix {
+        std::fs::write(outfile, stix::to_stix_bundle(&files)?)?;
+    }
+
+    if batch {
+        summary.print(format);
+    }
+
+    // A file that could not be analyzed may be hiding anything, so it
+    // outranks a suspicious one
+    match (verdict, analysis_failed) {
+        (Verdict::Malicious, _) => std::process::exit(EXIT_MALICIOUS),
+        (_, true) => std::process::exit(EXIT_ANALYSIS_FAILED),
+        (Verdict::Suspicious, false) => std::process::exit(EXIT_SUSPICIOUS),
+        (Verdict::Clean, false) => {}
+    }
+
+    Ok(())
 }
 
-fn check_file_size(doc: &Document, config: &Config) -> bool {
-    doc.size() > config.file_size_threshold
+/// What the main loop carries from one file to the next.
+struct Run<'a> {
+    args: &'a Args,
+    analyzer: &'a Analyzer,
+    exit_codes: &'a ExitCodeOptions,
+    format: OutputFormat,
+    print_at_end: bool,
+    collect_results: bool,
+    syslog: Option<SyslogSink>,
+    #[cfg(feature = "webhook")]
+    webhook: Option<Webhook>,
+    #[cfg(feature = "history")]
+    history: Option<History>,
+    quarantine: Option<Quarantine>,
+    /// Built up file by file, since its hex views need each document.
+    html: Option<HtmlReport>,
+    results: Vec<(String, AnalysisResult)>,
+    analysis_failed: bool,
+    /// The worst verdict so far.
+    verdict: Verdict,
+    summary: BatchSummary,
 }
 
-fn check_metadata(doc: &Document, config: &Config) -> bool {
-    let re = Regex::new(&config.suspicious_metadata_patterns.join("|")).unwrap();
+impl Run<'_> {
+    /// Prints or collects one file's result. A file that failed is reported
+    /// on stderr and the batch carries on with the next one.
+    fn record(
+        &mut self,
+        input: &str,
+        data: Option<&[u8]>,
+        result: Result<AnalysisResult, SentinelError>,
+    ) -> Result<(), SentinelError> {
+        let result = match result {
+            Ok(result) => result,
+            Err(err) => {
+                eprintln!("{}: {}", input, err);
+                self.analysis_failed = true;
+                self.summary.record_failure();
+                return Ok(());
+            }
+        };
 
-    if let Some(info) = doc.trailer.get(b"Info") {
-        if let Ok(info_dict) = info.as_dict() {
-            return info_dict.iter().any(|(_, value)| {
-                if let Ok(str_value) = value.as_string() {
-                    let value_str = String::from_utf8_lossy(str_value);
-                    !re.is_match(&value_str)
-                } else {
-                    false
+        let verdict = self
+            .exit_codes
+            .verdict(&result, self.analyzer.config(), self.args.fail_on);
+        self.verdict = self.verdict.max(verdict);
+        self.summary.record(input, &result);
+        if let Some(dir) = &self.args.extract_attachments {
+            if !result.embedded_files.is_empty() || !result.media.is_empty() {
+                if let Err(err) = extract_attachments(input, data, dir, &result, self.analyzer) {
+                    eprintln!("{}: failed to extract attachments: {}", input, err);
+                    self.analysis_failed = true;
                 }
-            });
+            }
         }
+        if let Some(outfile) = &self.args.dump_graph {
+            if let Err(err) = dump_graph(input, data, outfile, &result) {
+                eprintln!("{}: failed to write the object graph: {}", input, err);
+                self.analysis_failed = true;
+            }
+        }
+        if let Some(syslog) = &mut self.syslog {
+            if let Err(err) = syslog.send(input, &result) {
+                eprintln!("{}: failed to send to syslog: {}", input, err);
+            }
+        }
+        #[cfg(feature = "webhook")]
+        if let Some(webhook) = &self.webhook {
+            if let Err(err) = webhook.send(input, &result) {
+                eprintln!("{}: failed to send to the webhook: {}", input, err);
+            }
+        }
+        #[cfg(feature = "history")]
+        if let Some(history) = &self.history {
+            if let Err(err) = history.record(input, &result) {
+                eprintln!("{}: failed to record in the history: {}", input, err);
+            }
+        }
+        if let Some(report) = &mut self.html {
+            let doc = match data {
+                Some(data) => Document::load_mem(data).ok(),
+                None => Document::load(input).ok(),
+            };
+            report.add_file(input, &result, doc.as_ref());
+        }
+        if !self.print_at_end {
+            print_result(
+                input,
+                &result,
+                self.format,
+                self.args.explain,
+                self.args.verbose > 0,
+            )?;
+        }
+        // Last, since everything above may still read the file. Data read
+        // from stdin, a URL or an email has no file to move
+        if let (Some(quarantine), None) = (&self.quarantine, data) {
+            quarantine_file(quarantine, input, &result);
+        }
+        if self.collect_results {
+            self.results.push((input.to_string(), result));
+        }
+        Ok(())
     }
-    false
 }
 
-fn check_for_unusual_objects(doc: &Document) -> Vec<String> {
-    let common_types = [
-        b"Catalog",
-        b"Pages",
-        b"Page",
-        b"Font",
-        b"XObject",
-        b"Metadata",
-    ];
-    doc.objects
+/// Analyzes the PDF attachments of the email message `input`, each as a file
+/// of its own. A message that can't be read counts as a file that failed.
+fn scan_email(run: &mut Run, input: &str, fetcher: &Fetcher) -> Result<(), SentinelError> {
+    let message = match read_input(input, fetcher) {
+        Ok(message) => message,
+        Err(err) => return run.record(input, None, Err(err)),
+    };
+    let attachments = eml::pdf_attachments(&message);
+    if attachments.is_empty() {
+        eprintln!("{}: no PDF attachments", input);
+    }
+    for (index, attachment) in attachments.iter().enumerate() {
+        let name = attachment.report_name(input, index + 1);
+        let result = run.analyzer.analyze_bytes(&attachment.data);
+        run.record(&name, Some(&attachment.data), result)?;
+    }
+    Ok(())
+}
+
+/// Analyzes the PDFs in the ZIP archive `input`, each as a file of its own.
+/// An archive that can't be read, or an entry that can't be extracted,
+/// counts as a file that failed.
+fn scan_archive(
+    run: &mut Run,
+    input: &str,
+    data: &[u8],
+    extractor: &Extractor,
+) -> Result<(), SentinelError> {
+    let entries = match extractor.pdf_entries(input, data) {
+        Ok(entries) => entries,
+        Err(err) => return run.record(input, None, Err(err)),
+    };
+    if entries.is_empty() {
+        eprintln!("{}: no PDFs in the archive", input);
+    }
+    for entry in entries {
+        match entry.data {
+            Ok(data) => {
+                let result = run.analyzer.analyze_bytes(&data);
+                run.record(&entry.path, Some(&data), result)?;
+            }
+            Err(err) => run.record(&entry.path, None, Err(err))?,
+        }
+    }
+    Ok(())
+}
+
+/// Quarantines `input` if its severity calls for it, reporting where it went
+/// on stderr. A file that can't be moved is left where it is.
+fn quarantine_file(quarantine: &Quarantine, input: &str, result: &AnalysisResult) {
+    match quarantine.isolate(Path::new(input), result) {
+        Ok(Some(target)) => eprintln!("{}: quarantined to {}", input, target.display()),
+        Ok(None) => {}
+        Err(err) => eprintln!("{}: failed to quarantine: {}", input, err),
+    }
+}
+
+fn exceeds_fail_threshold(result: &AnalysisResult, config: &Config) -> bool {
+    let threshold = config.fail_threshold;
+    threshold != u32::MAX && result.severity_score >= threshold
+}
+
+/// JSON record for one analyzed file: the result fields plus the schema
+/// version and the file name.
+#[derive(Serialize)]
+struct FileReport<'a> {
+    schema_version: u32,
+    file: &'a str,
+    #[serde(flatten)]
+    result: &'a AnalysisResult,
+}
+
+impl<'a> FileReport<'a> {
+    fn new(file: &'a str, result: &'a AnalysisResult) -> Self {
+        FileReport {
+            schema_version: RESULT_SCHEMA_VERSION,
+            file,
+            result,
+        }
+    }
+}
+
+fn print_ndjson_header() -> Result<(), SentinelError> {
+    println!(
+        "{}",
+        serde_json::to_string(&serde_json::json!({ "$schema": RESULT_SCHEMA }))?
+    );
+    Ok(())
+}
+
+/// Prints a single file's result as soon as it is available. `Json` output is
+/// printed as one pretty object; batch callers collect into an array instead.
+fn print_result(
+    file: &str,
+    result: &AnalysisResult,
+    format: OutputFormat,
+    explain: bool,
+    verbose: bool,
+) -> Result<(), SentinelError> {
+    let report = FileReport::new(file, result);
+    match format {
+        OutputFormat::Text => {
+            println!("{}:", file);
+            print_analysis_result(result, explain, verbose);
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        OutputFormat::Ndjson => println!("{}", serde_json::to_string(&report)?),
+        OutputFormat::Sarif => println!("{}", sarif::to_sarif_log(&[(file, result)])?),
+        OutputFormat::Stix => println!("{}", stix::to_stix_bundle(&[(file, result)])?),
+        OutputFormat::Misp => println!("{}", misp::to_misp_events(&[(file, result)])?),
+        OutputFormat::Html => {
+            // Stdin can't be read again, so its report goes without hex views
+            let mut report = HtmlReport::new(Config::default().max_decompressed_stream_size);
+            report.add_file(file, result, Document::load(file).ok().as_ref());
+            print!("{}", report.finish());
+        }
+    }
+    Ok(())
+}
+
+/// Analyzes `input`, prints the result as usual, and writes a sanitized copy
+/// to `outfile`. What was removed goes to stderr so JSON output stays intact.
+fn sanitize_to_file(
+    input: &str,
+    outfile: &Path,
+    analyzer: &Analyzer,
+    fetcher: &Fetcher,
+    format: OutputFormat,
+    explain: bool,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let data = read_input(input, fetcher)?;
+    let result = analyzer.analyze_bytes(&data)?;
+    let mut doc = Document::load_from(Cursor::new(&data))?;
+    let report = sanitize::sanitize(&mut doc, &result);
+    doc.save(outfile)?;
+
+    print_result(input, &result, format, explain, verbose)?;
+    eprintln!("Sanitized copy written to {}", outfile.display());
+    for removed in &report.removed_objects {
+        eprintln!(
+            "  removed object {} ({})",
+            removed.object_id,
+            removed.reason.description()
+        );
+    }
+    for (id, key) in &report.stripped_keys {
+        eprintln!("  stripped /{} from object {}", key, id);
+    }
+    Ok(())
+}
+
+/// The whole of `input`, be it a file, - for stdin or a URL.
+fn read_input(input: &str, fetcher: &Fetcher) -> Result<Vec<u8>, SentinelError> {
+    if input == "-" {
+        read_stdin()
+    } else if is_url(input) {
+        fetcher.fetch(input)
+    } else {
+        Ok(std::fs::read(input)?)
+    }
+}
+
+/// Stdin can only be read once, so the whole PDF is buffered first.
+fn read_stdin() -> Result<Vec<u8>, SentinelError> {
+    let mut buffer = Vec::new();
+    std::io::stdin().lock().read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Writes the attachments and media payloads of `input`, or of `data` when
+/// it was read from stdin or a URL, to `dir`. Where each went goes to stderr
+/// so JSON output stays intact.
+fn extract_attachments(
+    input: &str,
+    data: Option<&[u8]>,
+    dir: &Path,
+    result: &AnalysisResult,
+    analyzer: &Analyzer,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let doc = match data {
+        Some(data) => Document::load_mem(data)?,
+        None => Document::load(input)?,
+    };
+    let max_size = analyzer.config().max_decompressed_stream_size;
+    for path in attachments::extract_embedded_files(&doc, dir, max_size)? {
+        eprintln!("{}: extracted attachment to {}", input, path.display());
+    }
+    for path in media::extract_media_payloads(&doc, &result.media, dir, max_size)? {
+        eprintln!("{}: extracted media payload to {}", input, path.display());
+    }
+    Ok(())
+}
+
+fn dump_graph(
+    input: &str,
+    data: Option<&[u8]>,
+    outfile: &Path,
+    result: &AnalysisResult,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let doc = match data {
+        Some(data) => Document::load_mem(data)?,
+        None => Document::load(input)?,
+    };
+    std::fs::write(outfile, graph::to_dot(&doc, result))?;
+    Ok(())
+}
+
+/// Writes object `id` of `file` to `output`, or to stdout: the content of a
+/// stream, decoded when `decoded` is set, or any other object in PDF syntax.
+/// Encrypted documents are decrypted first, as analysis would.
+fn extract_object(
+    file: &Path,
+    id: u32,
+    decoded: bool,
+    output: Option<&Path>,
+    password: Option<&str>,
+    analyzer: &Analyzer,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut doc = Document::load(file)?;
+    unlock(&mut doc, password);
+    let object = doc
+        .objects
         .iter()
-        .filter_map(|(_, obj)| {
-            if let Ok(dict) = obj.as_dict() {
-                if let Some(type_obj) = dict.get(b"Type") {
-                    if let Ok(type_name) = type_obj.as_name() {
-                        if !common_types.contains(&type_name) {
-                            return Some(String::from_utf8_lossy(type_name).to_string());
-                        }
+        .find(|((number, _), _)| *number == id)
+        .map(|(_, object)| object)
+        .ok_or_else(|| format!("{}: no object {}", file.display(), id))?;
+    let contents = match object {
+        Object::Stream(stream) => {
+            eprintln!(
+                "{}",
+                inspect::format_object(&Object::Dictionary(stream.dict.clone()), 0)
+            );
+            if decoded {
+                let max_size = analyzer.config().max_decompressed_stream_size;
+                match decode_stream_bounded(stream, max_size) {
+                    Some(Ok(content)) => content,
+                    Some(Err(_)) => {
+                        return Err(format!(
+                            "{}: object {} decodes to more than {} bytes",
+                            file.display(),
+                            id,
+                            max_size
+                        )
+                        .into())
+                    }
+                    None => {
+                        return Err(format!(
+                            "{}: object {} has filters that can't be decoded",
+                            file.display(),
+                            id
+                        )
+                        .into())
                     }
                 }
+            } else {
+                stream.content.clone()
             }
-            None
-        })
-        .collect()
+        }
+        object => format!("{}\n", inspect::format_object(object, 0)).into_bytes(),
+    };
+    match output {
+        Some(path) => std::fs::write(path, contents)?,
+        None => std::io::stdout().write_all(&contents)?,
+    }
+    Ok(())
 }
 
-fn calculate_object_statistics(doc: &Document) -> ObjectStatistics {
-    let mut stats = ObjectStatistics::default();
-    stats.total_objects = doc.objects.len();
-    for (_, obj) in doc.objects.iter() {
-        if obj.as_stream().is_ok() {
-            stats.stream_objects += 1;
+/// Adds `files` to the baseline database at `path`, creating it if need be.
+/// Files that don't analyze as clean are left out unless `force` is set, and
+/// make the run exit as suspicious.
+fn add_to_baseline(
+    path: &Path,
+    files: &[PathBuf],
+    force: bool,
+    analyzer: &Analyzer,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut baseline = match Baseline::load(path) {
+        Err(SentinelError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+            Baseline::default()
+        }
+        loaded => loaded?,
+    };
+    let mut changed = false;
+    let mut refused = false;
+    for file in files {
+        let data = std::fs::read(file)?;
+        let name = file.file_name().unwrap_or(file.as_os_str());
+        let entry = BaselineEntry::new(&name.to_string_lossy(), &data);
+        if baseline.find(&entry.sha256).is_some() {
+            println!("{}: already in the baseline", file.display());
+            continue;
         }
-        if let Ok(dict) = obj.as_dict() {
-            if dict.has(b"JS") || dict.has(b"JavaScript") {
-                stats.js_objects += 1;
+        let result = analyzer.analyze_bytes(&data)?;
+        if !force && !matches!(result.classification, ThreatClassification::Clean) {
+            eprintln!(
+                "{}: not added, classified {}; --force adds it anyway",
+                file.display(),
+                result.classification.label()
+            );
+            refused = true;
+            continue;
+        }
+        println!("{}: added {}", file.display(), entry.sha256);
+        baseline.approved.push(entry);
+        changed = true;
+    }
+    if changed {
+        baseline.save(path)?;
+    }
+    if refused {
+        std::process::exit(EXIT_SUSPICIOUS);
+    }
+    Ok(())
+}
+
+fn load_config(path: Option<&Path>) -> Result<ConfigFile, SentinelError> {
+    match path {
+        Some(path) => pdf_sentinel::load_config_file(path),
+        None => Ok(ConfigFile::default()),
+    }
+}
+
+#[cfg(feature = "history")]
+fn print_history(scans: &[history::Scan], format: OutputFormat) -> Result<(), SentinelError> {
+    match format {
+        OutputFormat::Text => {
+            for scan in scans {
+                println!(
+                    "{}  {}  score {} ({}), {}  {}",
+                    scan.scanned_at,
+                    scan.file,
+                    scan.severity_score,
+                    scan.severity,
+                    scan.classification,
+                    scan.sha256.as_deref().unwrap_or("?")
+                );
             }
-            if dict.has(b"ObjStm") {
-                stats.obj_stm_objects += 1;
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(scans)?),
+        OutputFormat::Ndjson => {
+            for scan in scans {
+                println!("{}", serde_json::to_string(scan)?);
             }
         }
+        OutputFormat::Sarif | OutputFormat::Stix | OutputFormat::Misp | OutputFormat::Html => {
+            unreachable!("history with SARIF, STIX, MISP or HTML output is rejected in main")
+        }
     }
-    stats
+    Ok(())
 }
 
-fn analyze_streams(doc: &Document, config: &Config, result: &mut AnalysisResult) {
-    let re = Regex::new(&config.suspicious_patterns.join("|")).unwrap();
-
-    for (_, object) in doc.objects.iter() {
-        if let Ok(stream) = object.as_stream() {
-            if let Ok(filter) = stream.filter() {
-                if filter == "FlateDecode" {
-                    let mut decoder = ZlibDecoder::new(&stream.content[..]);
-                    let mut decompressed = Vec::new();
-                    if decoder.read_to_end(&mut decompressed).is_ok() {
-                        let content = String::from_utf8_lossy(&decompressed);
-                        if re.is_match(&content) {
-                            result
-                                .suspicious_names
-                                .push("Suspicious content in stream".to_string());
-                        }
+fn print_diff(diff: &ResultDiff, format: OutputFormat) -> Result<(), SentinelError> {
+    match format {
+        OutputFormat::Text => {
+            println!("Severity change: {:+}", diff.severity_delta);
+            for change in &diff.score_changes {
+                println!(
+                    "  {}: {} -> {} points",
+                    change.weight, change.before, change.after
+                );
+            }
+            for finding in &diff.added_findings {
+                println!("+ {}", finding);
+            }
+            for finding in &diff.removed_findings {
+                println!("- {}", finding);
+            }
+            if !diff.new_javascript_objects.is_empty() {
+                println!("New JavaScript objects: {:?}", diff.new_javascript_objects);
+            }
+            if !diff.removed_javascript_objects.is_empty() {
+                println!(
+                    "Removed JavaScript objects: {:?}",
+                    diff.removed_javascript_objects
+                );
+            }
+            if let Some(objects) = &diff.objects {
+                let lists = [
+                    ("Added objects", &objects.added_objects),
+                    ("Removed objects", &objects.removed_objects),
+                    ("Modified objects", &objects.modified_objects),
+                    ("Added streams", &objects.added_streams),
+                    ("Removed streams", &objects.removed_streams),
+                    ("Modified streams", &objects.modified_streams),
+                ];
+                for (label, ids) in lists {
+                    if !ids.is_empty() {
+                        println!("{}: {:?}", label, ids);
                     }
                 }
+                for action in &objects.new_actions {
+                    println!("+ {} action in object {}", action.action, action.object_id);
+                }
+                for action in &objects.removed_actions {
+                    println!("- {} action in object {}", action.action, action.object_id);
+                }
             }
         }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(diff)?),
+        OutputFormat::Ndjson => println!("{}", serde_json::to_string(diff)?),
+        OutputFormat::Sarif | OutputFormat::Stix | OutputFormat::Misp | OutputFormat::Html => {
+            unreachable!("diff with SARIF, STIX, MISP or HTML output is rejected in main")
+        }
     }
+    Ok(())
 }
 
-fn calculate_severity_score(result: &AnalysisResult) -> u32 {
-    let mut score = 0;
-    if result.has_javascript {
-        score += 3;
-    }
-    if result.has_auto_action {
-        score += 2;
+/// The points each kind of finding added, named after its weight in the
+/// `[scoring]` table so that it can be tuned there.
+fn print_metadata(metadata: &DocumentMetadata) {
+    println!("- Metadata:");
+    let date = |date: &MetadataDate| date.time.clone().unwrap_or_else(|| date.raw.clone());
+    let info = [
+        ("Title", metadata.title.clone()),
+        ("Author", metadata.author.clone()),
+        ("Subject", metadata.subject.clone()),
+        ("Keywords", metadata.keywords.clone()),
+        ("Creator", metadata.creator.clone()),
+        ("Producer", metadata.producer.clone()),
+        ("CreationDate", metadata.creation_date.as_ref().map(date)),
+        ("ModDate", metadata.mod_date.as_ref().map(date)),
+    ];
+    for (key, value) in info {
+        if let Some(value) = value {
+            println!("  {}: {}", key, value);
+        }
     }
-    if result.has_obj_stm {
-        score += 2;
+    if let Some(xmp) = &metadata.xmp {
+        let properties = [
+            ("dc:title", &xmp.title),
+            ("dc:creator", &xmp.creator),
+            ("xmp:CreatorTool", &xmp.creator_tool),
+            ("pdf:Producer", &xmp.producer),
+            ("xmp:CreateDate", &xmp.create_date),
+            ("xmp:ModifyDate", &xmp.modify_date),
+            ("xmp:MetadataDate", &xmp.metadata_date),
+            ("xmpMM:DocumentID", &xmp.document_id),
+            ("xmpMM:InstanceID", &xmp.instance_id),
+        ];
+        for (name, value) in properties {
+            if let Some(value) = value {
+                println!("  XMP {}: {}", name, value);
+            }
+        }
     }
-    score += result.suspicious_names.len() as u32;
-    if result.hidden_content {
-        score += 2;
+}
+
+fn print_score_explanation(result: &AnalysisResult) {
+    if result.known_malicious {
+        println!("  all points: the file matches a known-malicious hash");
+        return;
     }
-    if result.large_file_size {
-        score += 1;
+    for contribution in &result.explanation.contributions {
+        println!(
+            "  {}: +{}{}",
+            contribution.weight,
+            contribution.points,
+            contribution
+                .uncapped_points
+                .map(|points| format!(" (capped from {})", points))
+                .unwrap_or_default()
+        );
     }
-    if result.suspicious_metadata {
-        score += 2;
+    if let Some(score) = result.explanation.uncapped_score {
+        println!("  total capped at max_score from {}", score);
     }
-    score += result.unusual_objects.len() as u32;
-    score += (result.object_statistics.js_objects * 2) as u32;
-    score += result.object_statistics.obj_stm_objects as u32;
-    score
 }
 
-fn print_analysis_result(result: &AnalysisResult) {
+fn print_analysis_result(result: &AnalysisResult, explain: bool, verbose: bool) {
     println!("PDF Analysis Result:");
+    if let Some(md5) = &result.md5 {
+        println!("- MD5: {}", md5);
+    }
+    if let Some(sha1) = &result.sha1 {
+        println!("- SHA-1: {}", sha1);
+    }
+    if let Some(sha256) = &result.sha256 {
+        println!("- SHA-256: {}", sha256);
+    }
+    if result.known_malicious {
+        println!("- Matches a known-malicious hash");
+    }
+    if let Some(entry) = &result.baselined {
+        println!(
+            "- Approved in the baseline as {} (Producer {}, Creator {}); not analyzed",
+            entry.name.as_deref().unwrap_or("<unnamed>"),
+            entry.producer.as_deref().unwrap_or("?"),
+            entry.creator.as_deref().unwrap_or("?")
+        );
+    }
     println!("- Contains JavaScript: {}", result.has_javascript);
     println!("- Contains Auto Action: {}", result.has_auto_action);
+    if let Some(open_action) = &result.open_action_js {
+        println!(
+            "- JavaScript runs on open (object {})",
+            open_action.action_object_id
+        );
+    }
     println!("- Contains Object Streams: {}", result.has_obj_stm);
+    if !result.unpacked_objects.is_empty() {
+        println!(
+            "- Objects unpacked from object streams: {:?}",
+            result.unpacked_objects
+        );
+    }
+    println!("- URIs:");
+    for url in &result.urls {
+        let indicators: Vec<&str> = url
+            .indicators
+            .iter()
+            .map(|indicator| indicator.description())
+            .collect();
+        println!(
+            "  {} in objects {:?}{}",
+            url.url,
+            url.object_ids,
+            if indicators.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", indicators.join(", "))
+            }
+        );
+    }
+    println!("- Launch actions in objects: {:?}", result.launch_actions);
+    println!("- Embedded files:");
+    for file in &result.embedded_files {
+        println!(
+            "  Object {}: {} ({} bytes, SHA-256 {}){}",
+            file.object_id,
+            file.name.as_deref().unwrap_or("<unnamed>"),
+            file.size.map_or("?".to_string(), |size| size.to_string()),
+            file.sha256.as_deref().unwrap_or("?"),
+            if file.executable { ", executable" } else { "" }
+        );
+    }
+    println!("- Embedded media:");
+    for annotation in &result.media {
+        println!(
+            "  {} in object {}",
+            annotation.kind.description(),
+            annotation.object_id
+        );
+        for payload in &annotation.payloads {
+            println!(
+                "    Object {}: {} ({} bytes, SHA-256 {})",
+                payload.object_id,
+                payload
+                    .format
+                    .map_or("unknown format", |format| format.description()),
+                payload
+                    .size
+                    .map_or("?".to_string(), |size| size.to_string()),
+                payload.sha256.as_deref().unwrap_or("?")
+            );
+        }
+    }
+    println!("- External actions:");
+    for action in &result.external_actions {
+        println!(
+            "  {} in object {}{}",
+            action.kind.name(),
+            action.object_id,
+            action
+                .target
+                .as_deref()
+                .map(|target| format!(" -> {}", target))
+                .unwrap_or_default()
+        );
+    }
+    println!("- Form field actions:");
+    for action in &result.form_field_actions {
+        println!(
+            "  {} (object {}) on /{}{}",
+            action.field_name,
+            action.object_id,
+            action.trigger,
+            if action.has_javascript {
+                ", runs JavaScript"
+            } else {
+                ""
+            }
+        );
+    }
+    println!("- Contains AcroForm: {}", result.has_acroform);
+    if let Some(xfa) = &result.xfa {
+        println!(
+            "- XFA form: {} packets, {} JavaScript and {} FormCalc scripts",
+            xfa.packets.len(),
+            xfa.scripts,
+            xfa.formcalc_scripts
+        );
+        for packet in &xfa.packets {
+            println!(
+                "  {} in object {} ({} bytes)",
+                packet.name.as_deref().unwrap_or("<whole form>"),
+                packet.object_id,
+                packet.size
+            );
+        }
+        for submit in &xfa.submit_targets {
+            let indicators: Vec<&str> = submit
+                .indicators
+                .iter()
+                .map(|indicator| indicator.description())
+                .collect();
+            println!(
+                "  Submits to {} (object {}){}",
+                submit.target,
+                submit.object_id,
+                if indicators.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({})", indicators.join(", "))
+                }
+            );
+        }
+    }
     println!("- Suspicious names found: {:?}", result.suspicious_names);
+    println!("- Suspicious stream content:");
+    for found in &result.suspicious_stream_content {
+        println!(
+            "  Object {}: {:?} at offset {}",
+            found.object_id, found.evidence, found.offset
+        );
+    }
     println!("- Contains hidden content: {}", result.hidden_content);
     println!("- Large file size: {}", result.large_file_size);
     println!("- Suspicious metadata: {}", result.suspicious_metadata);
+    if let Some(metadata) = &result.metadata {
+        print_metadata(metadata);
+    }
+    if !result.pattern_matches.is_empty() {
+        println!("- Pattern rule matches:");
+        for found in &result.pattern_matches {
+            println!(
+                "  {} ({}): {} {:?}{}",
+                found.rule_id,
+                found.category,
+                found.target.description(),
+                found.evidence,
+                found
+                    .object_id
+                    .map(|id| format!(" in object {}", id))
+                    .unwrap_or_default()
+            );
+        }
+    }
+    if let Some(mismatch) = &result.metadata_date_mismatch {
+        println!(
+            "- Creation dates disagree by {} days: Info {}, XMP {}",
+            mismatch.delta_days, mismatch.info_date, mismatch.xmp_date
+        );
+    }
+    for anomaly in &result.metadata_anomalies {
+        println!("- Metadata anomaly: {}", anomaly.detail);
+    }
+    println!("- Unknown creator/producer: {}", result.unknown_creator);
+    if let Some(creator) = &result.malicious_creator {
+        println!("- Known malicious creator/producer: {}", creator);
+    }
     println!("- Unusual objects: {:?}", result.unusual_objects);
+    println!("- Reference cycles: {:?}", result.reference_cycles);
+    println!("- Dangling references: {:?}", result.dangling_refs);
+    println!("- Split JavaScript candidates:");
+    for candidate in &result.split_js_candidates {
+        println!(
+            "  Object {} joins fragments {:?} matching {:?}: {}",
+            candidate.parent_object_id,
+            candidate.fragment_ids,
+            candidate.matched_patterns,
+            candidate.combined_content
+        );
+    }
+    println!("- Structure violations:");
+    for violation in &result.structure_violations {
+        match violation.object_id {
+            Some(id) => println!("  [{}] object {}: {}", violation.rule, id, violation.detail),
+            None => println!("  [{}] {}", violation.rule, violation.detail),
+        }
+    }
+    println!("- Colorspace anomalies:");
+    for anomaly in &result.colorspace_anomalies {
+        println!("  Page {}: {}", anomaly.page_id, anomaly.detail);
+    }
+    println!(
+        "- Named destinations: {} total, {} sequential integer names, suspicious offsets {:x?}",
+        result.named_destinations.total_count,
+        result.named_destinations.sequential_integer_names,
+        result.named_destinations.suspicious_offsets
+    );
+    for finding in &result.ratio_findings {
+        println!(
+            "- Object ratio {} is {:.2} (threshold {:.2})",
+            finding.ratio_name, finding.actual, finding.threshold
+        );
+    }
+    println!("- Cross-reference sections: {}", result.xref_sections);
+    for anomaly in &result.xref_anomalies {
+        println!(
+            "  Object {} declared at offset {} but found at {}",
+            anomaly.object_id, anomaly.declared_offset, anomaly.actual_offset
+        );
+    }
+    let consistency = &result.xref_consistency;
+    if let Some(mismatch) = &consistency.size_mismatch {
+        println!(
+            "  Trailer /Size {} instead of {}",
+            mismatch
+                .declared
+                .map_or("missing".to_string(), |size| size.to_string()),
+            mismatch.expected
+        );
+    }
+    if !consistency.missing_objects.is_empty() {
+        println!(
+            "  Entries for objects not in the file: {:?}",
+            consistency.missing_objects
+        );
+    }
+    if !consistency.unlisted_objects.is_empty() {
+        println!(
+            "  Objects not in the cross-reference data: {:?}",
+            consistency.unlisted_objects
+        );
+    }
+    println!("- Revisions: {}", result.revisions.revisions.len());
+    if let Some(selected) = result.revisions.selected {
+        println!("  Analyzed as of revision {}", selected);
+    }
+    for shadowed in &result.revisions.shadowed_objects {
+        println!(
+            "  Object {} defined in revisions {:?}{}{}",
+            shadowed.object_id,
+            shadowed.revisions,
+            if shadowed.catalog { ", catalog" } else { "" },
+            if shadowed.changed_actions.is_empty() {
+                String::new()
+            } else {
+                format!(", actions changed: {}", shadowed.changed_actions.join(", "))
+            }
+        );
+    }
+    println!(
+        "- File framing: header at offset {}, {} %%EOF markers, {} trailing bytes",
+        result
+            .framing
+            .header_offset
+            .map_or("none".to_string(), |offset| offset.to_string()),
+        result.framing.eof_offsets.len(),
+        result.framing.trailing_bytes
+    );
+    for anomaly in &result.framing.anomalies {
+        println!(
+            "  {} at offset {}, {} bytes{}",
+            anomaly.kind.description(),
+            anomaly.offset,
+            anomaly.length,
+            anomaly
+                .format
+                .as_ref()
+                .map(|format| format!(" ({})", format))
+                .unwrap_or_default()
+        );
+    }
+    for polyglot in &result.framing.polyglots {
+        println!(
+            "  Also {} at offset {}: {}",
+            polyglot.format.description(),
+            polyglot.offset,
+            polyglot.details
+        );
+    }
+    if let Some(error) = &result.parse_error {
+        println!(
+            "- Parse failure: {} (objects carved from the raw bytes)",
+            error
+        );
+    }
+    if let Some(encryption) = &result.encryption {
+        println!(
+            "- Encryption: {} (V {}, R {}), {}",
+            encryption
+                .algorithm
+                .as_deref()
+                .unwrap_or("unknown algorithm"),
+            encryption
+                .version
+                .map_or("?".to_string(), |version| version.to_string()),
+            encryption
+                .revision
+                .map_or("?".to_string(), |revision| revision.to_string()),
+            match (encryption.password, encryption.raw_scan_only) {
+                (_, true) => "not decrypted, raw scan only",
+                (Some(PasswordUsed::Supplied), _) => "decrypted with the given password",
+                _ => "decrypted with the empty password",
+            }
+        );
+    }
+    println!("- Escaped names:");
+    for name in &result.escaped_names {
+        println!(
+            "  {} ({}) at offset {}",
+            name.raw, name.decoded, name.offset
+        );
+    }
+    println!("- Shellcode hints:");
+    for (id, hints) in &result.shellcode_hints {
+        println!(
+            "  Object {}: NOP sled length {} at offset {}, repeated pattern {:02x?} over {} bytes at offset {}",
+            id,
+            hints.nop_sled_length,
+            hints.nop_sled_offset,
+            hints.repeated_pattern.as_deref().unwrap_or_default(),
+            hints.pattern_run_length,
+            hints.pattern_offset
+        );
+        for payload in &hints.escaped_payloads {
+            println!(
+                "    Escaped {}: {} bytes at offset {}, {} bytes decoded",
+                payload.kind.description(),
+                payload.length,
+                payload.offset,
+                payload.decoded_length
+            );
+        }
+    }
+    if let Some(report) = &result.pdfa_conformance {
+        println!(
+            "- Claims {} conformance; violations: {:?}",
+            report.claimed_level, report.violations
+        );
+    }
+    println!("- Compression bomb hints:");
+    for hint in &result.compression_bomb_hints {
+        println!(
+            "  Object {}: {} compressed bytes inflated past {} bytes ({})",
+            hint.object_id,
+            hint.compressed_size,
+            hint.bytes_before_abort,
+            hint.limit.description()
+        );
+    }
+    println!("- High-entropy streams:");
+    for stream in &result.high_entropy_streams {
+        println!(
+            "  Object {}: {:.2} bits per byte decoded, {:.2} raw, {} bytes",
+            stream.object_id, stream.entropy, stream.raw_entropy, stream.size
+        );
+    }
+    println!("- Executables and archives in streams:");
+    for signature in &result.embedded_signatures {
+        println!(
+            "  Object {}: {} at offset {}",
+            signature.object_id,
+            signature.kind.description(),
+            signature.offset
+        );
+    }
+    println!("- Embedded PDFs:");
+    for pdf in &result.embedded_pdfs {
+        let classification = &pdf.result.classification;
+        println!(
+            "  Object {}{}: {}, score {}",
+            pdf.object_id,
+            pdf.name
+                .as_deref()
+                .map(|name| format!(" ({})", name))
+                .unwrap_or_default(),
+            classification.label(),
+            pdf.result.severity_score
+        );
+        for indicator in classification.indicators() {
+            println!("    {}", indicator);
+        }
+    }
+    println!("- Steganography hints:");
+    for (id, hint) in &result.steganography_hints {
+        println!(
+            "  Object {}: chi-square {:.2}, p-value {:.4}",
+            id, hint.chi_square, hint.p_value
+        );
+    }
+    println!("- Heap spray arrays:");
+    for candidate in &result.heap_spray_candidates {
+        println!(
+            "  Object {}: {:#x} makes up {:.0}% of {} elements",
+            candidate.object_id,
+            candidate.dominant_value,
+            candidate.dominance_ratio * 100.0,
+            candidate.array_length
+        );
+    }
+    println!("- Custom rule findings:");
+    for finding in &result.rule_findings {
+        match finding.object_id {
+            Some(id) => println!(
+                "  [{}] object {}: {}",
+                finding.rule, id, finding.description
+            ),
+            None => println!("  [{}] {}", finding.rule, finding.description),
+        }
+    }
+    if !result.virustotal.is_empty() {
+        println!("- VirusTotal:");
+        for lookup in &result.virustotal {
+            println!(
+                "  {} {}: {}",
+                match lookup.subject {
+                    VtSubject::File => "File".to_string(),
+                    VtSubject::Payload(id) => format!("Object {}", id),
+                },
+                lookup.sha256,
+                lookup
+                    .ratio()
+                    .map_or("not found".to_string(), |ratio| format!(
+                        "{} engines",
+                        ratio
+                    ))
+            );
+        }
+    }
+    if !result.yara_matches.is_empty() {
+        println!("- YARA matches:");
+        for found in &result.yara_matches {
+            println!(
+                "  {}:{} in object {}{}",
+                found.namespace,
+                found.rule,
+                found.object_id,
+                match found.target {
+                    YaraTarget::Stream => " (stream)",
+                    YaraTarget::JavaScript => " (script)",
+                }
+            );
+        }
+    }
+    if !result.suppressed_findings.is_empty() {
+        if verbose {
+            println!("- Suppressed findings:");
+            for suppressed in &result.suppressed_findings {
+                let finding = &suppressed.finding;
+                println!(
+                    "  suppressed [{}] {}{}",
+                    finding.rule_id,
+                    finding.title,
+                    suppressed
+                        .reason
+                        .as_deref()
+                        .map(|reason| format!(" ({})", reason))
+                        .unwrap_or_default()
+                );
+            }
+        } else {
+            println!(
+                "- Suppressed findings: {} (-v lists them)",
+                result.suppressed_findings.len()
+            );
+        }
+    }
     println!("- Object Statistics:");
     println!("JavaScript Objects:");
     for js_obj in &result.javascript_objects {
         println!("Object ID: {}", js_obj.id);
+        println!("SHA-256: {}", js_obj.sha256);
+        match js_obj.source {
+            JsSource::InlineString => println!("Source: inline /JS string"),
+            JsSource::Stream => println!("Source: stream"),
+            JsSource::IndirectRef(target) => println!("Source: string object {}", target),
+            JsSource::XfaScript => println!("Source: XFA <script>"),
+        }
+        println!(
+            "Dynamic calls: {}, string-concatenation calls: {}, IIFEs: {}",
+            js_obj.ast_findings.dynamic_eval_patterns,
+            js_obj.ast_findings.string_concat_calls,
+            js_obj.ast_findings.iife_count
+        );
+        if !js_obj.dangerous_apis.is_empty() {
+            println!("Dangerous APIs: {}", js_obj.dangerous_apis.join(", "));
+        }
+        if !js_obj.hidden_matches.is_empty() {
+            println!(
+                "Hidden by obfuscation: {}",
+                js_obj.hidden_matches.join(", ")
+            );
+        }
+        for call in &js_obj.ast_findings.acrobat_apis {
+            println!(
+                "Acrobat API: {} ({}, {} calls)",
+                call.api.name(),
+                call.severity.label(),
+                call.calls
+            );
+        }
         println!("JavaScript Content:\n{}", js_obj.content);
+        if let Some(deobfuscated) = &js_obj.deobfuscated {
+            println!("Deobfuscated:\n{}", deobfuscated);
+        }
         println!("--------------------");
     }
     println!(
@@ -331,33 +2277,26 @@ fn print_analysis_result(result: &AnalysisResult) {
         "  Object Stream Objects: {}",
         result.object_statistics.obj_stm_objects
     );
+    println!(
+        "  Streams by entropy (bits per byte, 0 to 8): {:?}",
+        result.object_statistics.entropy_histogram
+    );
     println!("- Severity Score: {}", result.severity_score);
-
-    let severity = match result.severity_score {
-        0..=2 => "Low",
-        3..=5 => "Medium",
-        6..=10 => "High",
-        _ => "Critical",
-    };
+    if explain {
+        print_score_explanation(result);
+    }
 
     println!(
-        "\nOverall assessment: {} (Severity: {})",
-        if result.severity_score > 0 {
-            "Potentially malicious"
+        "\nOverall assessment: {}{} (Severity: {})",
+        result.classification.label(),
+        if result.baselined.is_some() {
+            " (baselined)"
         } else {
-            "Likely benign"
+            ""
         },
-        severity
+        result.severity.label()
     );
-}
-
-fn analyze_multiple_pdfs(files: Vec<String>, config: &Config) -> Vec<(String, AnalysisResult)> {
-    files
-        .par_iter()
-        .map(|file| {
-            let doc = Document::load(file).unwrap();
-            let result = analyze_pdf(&doc, config);
-            (file.clone(), result)
-        })
-        .collect()
+    for indicator in result.classification.indicators() {
+        println!("  - {}", indicator);
+    }
 }
@@ -0,0 +1,269 @@
+use crate::actions::file_specification_name;
+use crate::attachments::safe_file_name;
+use crate::error::SentinelError;
+use crate::filters::{decode_stream_bounded, StreamCache};
+use crate::to_hex;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Limit on how deeply a media annotation's dictionaries are followed to
+/// reach its payloads.
+const MAX_MEDIA_DEPTH: usize = 16;
+
+/// Entries that lead away from the media, to the page or its appearance.
+const SKIPPED_KEYS: [&[u8]; 4] = [b"P", b"Parent", b"AP", b"Annots"];
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum MediaKind {
+    /// A `/RichMedia` annotation, Acrobat 9's container for Flash and video.
+    RichMedia,
+    /// A `/3D` annotation with a U3D or PRC scene.
+    ThreeD,
+    /// A `/Sound` annotation or action.
+    Sound,
+    /// A `/Movie` annotation or action.
+    Movie,
+}
+
+impl MediaKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            MediaKind::RichMedia => "RichMedia",
+            MediaKind::ThreeD => "3D",
+            MediaKind::Sound => "Sound",
+            MediaKind::Movie => "Movie",
+        }
+    }
+
+    fn from_subtype(subtype: &[u8]) -> Option<Self> {
+        match subtype {
+            b"RichMedia" => Some(MediaKind::RichMedia),
+            b"3D" => Some(MediaKind::ThreeD),
+            b"Sound" => Some(MediaKind::Sound),
+            b"Movie" => Some(MediaKind::Movie),
+            _ => None,
+        }
+    }
+}
+
+/// Payload formats whose players were exploited again and again before
+/// viewers dropped them.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum MediaFormat {
+    /// Flash, plain (`FWS`) or compressed (`CWS`, `ZWS`).
+    Swf,
+    U3d,
+    Prc,
+}
+
+impl MediaFormat {
+    pub fn description(&self) -> &'static str {
+        match self {
+            MediaFormat::Swf => "Flash",
+            MediaFormat::U3d => "U3D",
+            MediaFormat::Prc => "PRC",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            MediaFormat::Swf => "swf",
+            MediaFormat::U3d => "u3d",
+            MediaFormat::Prc => "prc",
+        }
+    }
+
+    fn sniff(content: &[u8]) -> Option<Self> {
+        match content.get(..3)? {
+            b"FWS" | b"CWS" | b"ZWS" => Some(MediaFormat::Swf),
+            b"U3D" => Some(MediaFormat::U3d),
+            b"PRC" => Some(MediaFormat::Prc),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct MediaPayload {
+    pub object_id: u32,
+    /// The name of the file specification it is embedded through, if any.
+    pub name: Option<String>,
+    pub format: Option<MediaFormat>,
+    /// Decoded size; `None` when the stream could not be decoded.
+    pub size: Option<u64>,
+    /// SHA-256 of the decoded content, in lowercase hex.
+    pub sha256: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct MediaAnnotation {
+    /// The annotation or action.
+    pub object_id: u32,
+    pub kind: MediaKind,
+    /// Streams reachable from it, other than its appearance.
+    pub payloads: Vec<MediaPayload>,
+}
+
+impl MediaAnnotation {
+    /// Whether it carries Flash or a 3D scene, the formats behind most
+    /// exploits of embedded media.
+    pub fn has_exploitable_payload(&self) -> bool {
+        self.payloads.iter().any(|payload| payload.format.is_some())
+    }
+}
+
+/// Finds `/RichMedia`, `/3D`, `/Sound` and `/Movie` annotations, and the
+/// sound and movie actions, with the streams they play. Payloads are
+/// reached through file specifications, `/3DD` scene streams and the like;
+/// the format is taken from the decoded content's magic number, whatever the
+/// document claims.
+pub fn find_media(doc: &Document, cache: &StreamCache) -> Vec<MediaAnnotation> {
+    let mut media = Vec::new();
+    for (id, object) in doc.objects.iter() {
+        let Object::Dictionary(dict) = object else {
+            continue;
+        };
+        let kind = [b"Subtype".as_slice(), b"S"]
+            .iter()
+            .filter_map(|key| dict.get(key).and_then(Object::as_name).ok())
+            .find_map(MediaKind::from_subtype);
+        let Some(kind) = kind else {
+            continue;
+        };
+
+        let mut streams = Vec::new();
+        let mut visited = HashSet::from([*id]);
+        collect_streams(doc, dict, None, 0, &mut visited, &mut streams);
+        let payloads = streams
+            .into_iter()
+            .map(|(stream_id, name)| {
+                let content = cache.get(stream_id.0);
+                MediaPayload {
+                    object_id: stream_id.0,
+                    name,
+                    format: content.and_then(MediaFormat::sniff),
+                    size: content.map(|content| content.len() as u64),
+                    sha256: content.map(|content| to_hex(&Sha256::digest(content))),
+                }
+            })
+            .collect();
+        media.push(MediaAnnotation {
+            object_id: id.0,
+            kind,
+            payloads,
+        });
+    }
+    media
+}
+
+fn collect_streams(
+    doc: &Document,
+    dict: &Dictionary,
+    name: Option<&str>,
+    depth: usize,
+    visited: &mut HashSet<ObjectId>,
+    streams: &mut Vec<(ObjectId, Option<String>)>,
+) {
+    if depth > MAX_MEDIA_DEPTH {
+        return;
+    }
+    // Streams below a file specification are the file it names
+    let spec_name = dict
+        .has(b"EF")
+        .then(|| file_specification_name(doc, dict))
+        .flatten();
+    let name = spec_name.as_deref().or(name);
+
+    for (key, value) in dict.iter() {
+        if SKIPPED_KEYS.contains(&key.as_slice()) {
+            continue;
+        }
+        collect_object(doc, value, name, depth + 1, visited, streams);
+    }
+}
+
+fn collect_object(
+    doc: &Document,
+    object: &Object,
+    name: Option<&str>,
+    depth: usize,
+    visited: &mut HashSet<ObjectId>,
+    streams: &mut Vec<(ObjectId, Option<String>)>,
+) {
+    match object {
+        Object::Reference(id) => {
+            if !visited.insert(*id) {
+                return;
+            }
+            match doc.get_object(*id) {
+                Ok(Object::Stream(stream)) => {
+                    streams.push((*id, name.map(str::to_string)));
+                    collect_streams(doc, &stream.dict, name, depth, visited, streams);
+                }
+                Ok(target) => collect_object(doc, target, name, depth, visited, streams),
+                Err(_) => {}
+            }
+        }
+        Object::Dictionary(dict) => collect_streams(doc, dict, name, depth, visited, streams),
+        Object::Array(items) => {
+            for item in items {
+                collect_object(doc, item, name, depth + 1, visited, streams);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Writes the decoded payload of every media annotation to `dir`, creating
+/// it if needed, and returns the paths written. Payloads that are also
+/// `/EmbeddedFile` streams are left to `extract_embedded_files`. Files are
+/// named `<object id>-<name>`, or after their format when they have no name.
+pub fn extract_media_payloads(
+    doc: &Document,
+    media: &[MediaAnnotation],
+    dir: &Path,
+    max_size: u64,
+) -> Result<Vec<PathBuf>, SentinelError> {
+    std::fs::create_dir_all(dir)?;
+    let mut written = Vec::new();
+
+    for payload in media.iter().flat_map(|annotation| &annotation.payloads) {
+        let Some(stream) = doc
+            .objects
+            .iter()
+            .find(|(id, _)| id.0 == payload.object_id)
+            .and_then(|(_, object)| object.as_stream().ok())
+            .filter(|stream| {
+                stream
+                    .dict
+                    .get(b"Type")
+                    .and_then(Object::as_name)
+                    .map_or(true, |kind| kind != b"EmbeddedFile")
+            })
+        else {
+            continue;
+        };
+        let Some(Ok(content)) = decode_stream_bounded(stream, max_size) else {
+            tracing::warn!(
+                object_id = payload.object_id,
+                "media payload could not be decoded, skipping"
+            );
+            continue;
+        };
+        let name = match (&payload.name, payload.format) {
+            (Some(name), _) => safe_file_name(name),
+            (None, Some(format)) => format!("media.{}", format.extension()),
+            (None, None) => "media".to_string(),
+        };
+        let path = dir.join(format!("{}-{}", payload.object_id, name));
+        if !written.contains(&path) {
+            std::fs::write(&path, content)?;
+            written.push(path);
+        }
+    }
+
+    Ok(written)
+}
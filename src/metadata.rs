@@ -0,0 +1,256 @@
+//! The document's provenance as its metadata records it: the `/Info`
+//! dictionary and the catalog's XMP packet, with the dates decoded, so that
+//! who made a file with what, and when, can be seen at a glance. Also the
+//! inconsistencies in it that tools don't produce but hand-crafted files
+//! do.
+
+use crate::dates::{parse_iso_date, parse_pdf_date};
+use crate::encoding::normalize_pdf_string;
+use crate::filters::StreamCache;
+use crate::pdfa::xmp_metadata;
+use crate::{info_dictionary, resolve, AnalysisResult};
+use chrono::{DateTime, Duration, FixedOffset, SecondsFormat, Utc};
+use lopdf::Document;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// XMP packets longer than this are cut short in `XmpMetadata::packet`.
+const MAX_XMP_PACKET: usize = 64 * 1024;
+
+/// The standard entries of the `/Info` dictionary, and the XMP packet.
+#[derive(Default, Deserialize, Serialize)]
+pub struct DocumentMetadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub keywords: Option<String>,
+    pub creator: Option<String>,
+    pub producer: Option<String>,
+    pub creation_date: Option<MetadataDate>,
+    pub mod_date: Option<MetadataDate>,
+    pub xmp: Option<XmpMetadata>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct MetadataDate {
+    /// As written, e.g. `D:20240501120000+02'00'`.
+    pub raw: String,
+    /// In RFC 3339, when `raw` parses as a date.
+    pub time: Option<String>,
+}
+
+/// The common properties of the XMP packet, as written.
+#[derive(Default, Deserialize, Serialize)]
+pub struct XmpMetadata {
+    pub title: Option<String>,
+    /// `dc:creator`, the author.
+    pub creator: Option<String>,
+    pub creator_tool: Option<String>,
+    pub producer: Option<String>,
+    pub create_date: Option<String>,
+    pub modify_date: Option<String>,
+    pub metadata_date: Option<String>,
+    pub document_id: Option<String>,
+    pub instance_id: Option<String>,
+    /// The packet itself, up to 64 KiB.
+    pub packet: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum MetadataAnomalyKind {
+    /// `/ModDate` before `/CreationDate`, or the same of the XMP dates.
+    ModifiedBeforeCreated,
+    /// A creation, modification or metadata date after the analysis.
+    FutureDate,
+    /// Neither `/Producer` nor XMP `pdf:Producer`, in a document with
+    /// scripts, forms, attachments, media or external actions, which the
+    /// tools that make such documents always record themselves in.
+    MissingProducer,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct MetadataAnomaly {
+    pub kind: MetadataAnomalyKind,
+    pub detail: String,
+}
+
+/// Reads the `/Info` dictionary and the XMP packet. `None` when the document
+/// has neither.
+pub fn extract_metadata(doc: &Document, streams: &StreamCache) -> Option<DocumentMetadata> {
+    let info = info_dictionary(doc);
+    let xmp = xmp_metadata(doc, streams);
+    if info.is_none() && xmp.is_none() {
+        return None;
+    }
+    let entry = |key: &[u8]| {
+        let value = resolve(doc, info?.get(key).ok()?)?;
+        Some(normalize_pdf_string(value.as_str().ok()?)).filter(|text| !text.trim().is_empty())
+    };
+    let date = |key: &[u8]| {
+        entry(key).map(|raw| MetadataDate {
+            time: parse_pdf_date(&raw)
+                .map(|time| time.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+            raw,
+        })
+    };
+    Some(DocumentMetadata {
+        title: entry(b"Title"),
+        author: entry(b"Author"),
+        subject: entry(b"Subject"),
+        keywords: entry(b"Keywords"),
+        creator: entry(b"Creator"),
+        producer: entry(b"Producer"),
+        creation_date: date(b"CreationDate"),
+        mod_date: date(b"ModDate"),
+        xmp: xmp.map(|packet| read_xmp(&packet)),
+    })
+}
+
+fn read_xmp(packet: &str) -> XmpMetadata {
+    let mut end = packet.len().min(MAX_XMP_PACKET);
+    while !packet.is_char_boundary(end) {
+        end -= 1;
+    }
+    XmpMetadata {
+        title: xmp_text(packet, "dc:title"),
+        creator: xmp_text(packet, "dc:creator"),
+        creator_tool: xmp_text(packet, "xmp:CreatorTool"),
+        producer: xmp_text(packet, "pdf:Producer"),
+        create_date: xmp_text(packet, "xmp:CreateDate"),
+        modify_date: xmp_text(packet, "xmp:ModifyDate"),
+        metadata_date: xmp_text(packet, "xmp:MetadataDate"),
+        document_id: xmp_text(packet, "xmpMM:DocumentID"),
+        instance_id: xmp_text(packet, "xmpMM:InstanceID"),
+        packet: packet[..end].to_string(),
+    }
+}
+
+/// The text of property `name`, whether an attribute or an element. Of an
+/// `rdf:Alt`, `rdf:Seq` or `rdf:Bag`, the first item is taken, which for a
+/// title is the default language.
+pub(crate) fn xmp_text(xmp: &str, name: &str) -> Option<String> {
+    let name = regex::escape(name);
+    let attribute = Regex::new(&format!(r#"\s{}\s*=\s*(?:"([^"]*)"|'([^']*)')"#, name)).ok()?;
+    let element = Regex::new(&format!(r"(?s)<{}(?:\s[^>]*)?>(.*?)</{}>", name, name)).ok()?;
+    let text = match attribute.captures(xmp) {
+        Some(captures) => captures.get(1).or(captures.get(2))?.as_str().to_string(),
+        None => {
+            let content = element.captures(xmp)?.get(1)?.as_str();
+            let item = Regex::new(r"(?s)<rdf:li(?:\s[^>]*)?>(.*?)</rdf:li>").ok()?;
+            let content = item
+                .captures(content)
+                .and_then(|captures| captures.get(1))
+                .map_or(content, |item| item.as_str());
+            Regex::new(r"<[^>]*>")
+                .ok()?
+                .replace_all(content, "")
+                .into_owned()
+        }
+    };
+    let text = unescape_xml(text.trim());
+    (!text.is_empty()).then_some(text)
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Dates up to this many days after the analysis aren't counted as in the
+/// future, allowing for clocks and time zones that are a little off.
+const FUTURE_DATE_GRACE_DAYS: i64 = 1;
+
+/// Looks for dates out of order or in the future in `result.metadata`, and
+/// for a missing Producer given the active content `result` found.
+pub fn check_metadata_anomalies(result: &AnalysisResult) -> Vec<MetadataAnomaly> {
+    let mut anomalies = Vec::new();
+    let metadata = result.metadata.as_ref();
+    let xmp = metadata.and_then(|metadata| metadata.xmp.as_ref());
+    let info_date = |date: Option<&MetadataDate>| {
+        let date = date?;
+        let time = DateTime::parse_from_rfc3339(date.time.as_deref()?).ok()?;
+        Some((date.raw.clone(), time))
+    };
+    let xmp_date = |date: Option<&String>| Some((date?.clone(), parse_iso_date(date?)?));
+    let dates: Vec<(&str, String, DateTime<FixedOffset>)> = [
+        (
+            "/CreationDate",
+            info_date(metadata.and_then(|metadata| metadata.creation_date.as_ref())),
+        ),
+        (
+            "/ModDate",
+            info_date(metadata.and_then(|metadata| metadata.mod_date.as_ref())),
+        ),
+        (
+            "xmp:CreateDate",
+            xmp_date(xmp.and_then(|xmp| xmp.create_date.as_ref())),
+        ),
+        (
+            "xmp:ModifyDate",
+            xmp_date(xmp.and_then(|xmp| xmp.modify_date.as_ref())),
+        ),
+        (
+            "xmp:MetadataDate",
+            xmp_date(xmp.and_then(|xmp| xmp.metadata_date.as_ref())),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(name, date)| date.map(|(raw, time)| (name, raw, time)))
+    .collect();
+    let date = |name: &str| dates.iter().find(|(date, ..)| *date == name);
+
+    for (created, modified) in [
+        ("/CreationDate", "/ModDate"),
+        ("xmp:CreateDate", "xmp:ModifyDate"),
+    ] {
+        if let (Some((_, created_raw, created_at)), Some((_, modified_raw, modified_at))) =
+            (date(created), date(modified))
+        {
+            if modified_at < created_at {
+                anomalies.push(MetadataAnomaly {
+                    kind: MetadataAnomalyKind::ModifiedBeforeCreated,
+                    detail: format!(
+                        "{} {} is before {} {}",
+                        modified, modified_raw, created, created_raw
+                    ),
+                });
+            }
+        }
+    }
+    let latest = Utc::now() + Duration::days(FUTURE_DATE_GRACE_DAYS);
+    for (name, raw, time) in &dates {
+        if *time > latest {
+            anomalies.push(MetadataAnomaly {
+                kind: MetadataAnomalyKind::FutureDate,
+                detail: format!("{} {} is in the future", name, raw),
+            });
+        }
+    }
+
+    let has_producer = metadata.is_some_and(|metadata| {
+        metadata.producer.is_some() || xmp.is_some_and(|xmp| xmp.producer.is_some())
+    });
+    let features: Vec<&str> = [
+        (result.has_javascript, "JavaScript"),
+        (result.has_acroform, "a form"),
+        (!result.embedded_files.is_empty(), "attachments"),
+        (!result.media.is_empty(), "media"),
+        (!result.external_actions.is_empty(), "external actions"),
+    ]
+    .into_iter()
+    .filter_map(|(present, feature)| present.then_some(feature))
+    .collect();
+    if !has_producer && !features.is_empty() {
+        anomalies.push(MetadataAnomaly {
+            kind: MetadataAnomalyKind::MissingProducer,
+            detail: format!(
+                "No Producer, though the document has {}",
+                features.join(", ")
+            ),
+        });
+    }
+    anomalies
+}
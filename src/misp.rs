@@ -0,0 +1,152 @@
+use chrono::Utc;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::classification::ThreatClassification;
+use crate::error::SentinelError;
+use crate::findings::{rule, triggered_rules};
+use crate::scoring::Severity;
+use crate::AnalysisResult;
+
+/// Machine tag namespace for the tags derived from an analysis.
+const TAG_NAMESPACE: &str = "pdf-sentinel";
+
+/// Builds MISP events from analysis results, keyed by file name, in the
+/// `{"response": [{"Event": ...}]}` shape MISP exports and imports. Each file
+/// is an event of its own, with attributes for its hashes, the URLs it links
+/// to and the hashes of its embedded files and media payloads, and a tag for
+/// every rule it triggered.
+///
+/// Events are left unpublished and limited to the organisation; sharing them
+/// further is for the analyst to decide in MISP.
+pub fn to_misp_events(results: &[(&str, &AnalysisResult)]) -> Result<String, SentinelError> {
+    let events: Vec<Value> = results
+        .iter()
+        .map(|&(file, result)| json!({ "Event": misp_event(file, result) }))
+        .collect();
+    Ok(serde_json::to_string_pretty(
+        &json!({ "response": events }),
+    )?)
+}
+
+fn misp_event(file: &str, result: &AnalysisResult) -> Value {
+    let now = Utc::now();
+    // Only documents that aren't clean should raise alerts downstream
+    let to_ids = !matches!(result.classification, ThreatClassification::Clean);
+
+    let mut attributes = vec![attribute("filename", "Payload delivery", file, false, None)];
+    let hashes = [
+        ("md5", &result.md5),
+        ("sha1", &result.sha1),
+        ("sha256", &result.sha256),
+    ];
+    for (kind, hash) in hashes {
+        if let Some(hash) = hash {
+            attributes.push(attribute(kind, "Payload delivery", hash, to_ids, None));
+        }
+    }
+    if let Some(size) = result.file_size {
+        attributes.push(attribute(
+            "size-in-bytes",
+            "Other",
+            &size.to_string(),
+            false,
+            None,
+        ));
+    }
+
+    for attachment in &result.embedded_files {
+        let Some(sha256) = &attachment.sha256 else {
+            continue;
+        };
+        let comment = format!("Embedded file in object {}", attachment.object_id);
+        attributes.push(match &attachment.name {
+            Some(name) => attribute(
+                "filename|sha256",
+                "Payload delivery",
+                &format!("{}|{}", name, sha256),
+                to_ids,
+                Some(comment),
+            ),
+            None => attribute("sha256", "Payload delivery", sha256, to_ids, Some(comment)),
+        });
+    }
+    for payload in result.media.iter().flat_map(|media| &media.payloads) {
+        if let Some(sha256) = &payload.sha256 {
+            let comment = format!("Media payload in object {}", payload.object_id);
+            attributes.push(attribute(
+                "sha256",
+                "Payload delivery",
+                sha256,
+                to_ids,
+                Some(comment),
+            ));
+        }
+    }
+    for uri in &result.uris {
+        attributes.push(attribute("url", "Network activity", uri, to_ids, None));
+    }
+
+    let mut tags = vec![tag("classification", result.classification.label())];
+    tags.extend(
+        triggered_rules(&result.findings)
+            .into_iter()
+            .map(|id| tag("rule", rule(id).name)),
+    );
+
+    json!({
+        "uuid": Uuid::new_v4().to_string(),
+        "info": format!(
+            "{} analysis of {}: {}",
+            env!("CARGO_PKG_NAME"),
+            file,
+            result.classification.label()
+        ),
+        "date": now.date_naive().to_string(),
+        "timestamp": now.timestamp().to_string(),
+        "threat_level_id": threat_level(result.severity),
+        // Completed
+        "analysis": "2",
+        // Your organisation only
+        "distribution": "0",
+        "published": false,
+        "Attribute": attributes,
+        "Tag": tags,
+    })
+}
+
+fn attribute(
+    kind: &str,
+    category: &str,
+    value: &str,
+    to_ids: bool,
+    comment: Option<String>,
+) -> Value {
+    let mut attribute = json!({
+        "uuid": Uuid::new_v4().to_string(),
+        "type": kind,
+        "category": category,
+        "value": value,
+        "to_ids": to_ids,
+        "distribution": "5",
+    });
+    if let Some(comment) = comment {
+        attribute["comment"] = json!(comment);
+    }
+    attribute
+}
+
+/// A machine tag, e.g. `pdf-sentinel:rule="OpenActionJavaScript"`.
+fn tag(predicate: &str, value: &str) -> Value {
+    json!({ "name": format!("{}:{}=\"{}\"", TAG_NAMESPACE, predicate, value) })
+}
+
+/// MISP's threat levels run from 1 (high) to 3 (low), with 4 for undefined;
+/// critical has no level of its own.
+fn threat_level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical | Severity::High => "1",
+        Severity::Medium => "2",
+        Severity::Low => "3",
+    }
+}
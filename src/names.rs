@@ -0,0 +1,52 @@
+use crate::encoding::decode_name;
+use regex::bytes::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Escaped names reported per file; one is as telling as a thousand.
+const MAX_ESCAPED_NAMES: usize = 100;
+
+#[derive(Deserialize, Serialize)]
+pub struct EscapedName {
+    /// Byte offset of the name's `/` in the file.
+    pub offset: usize,
+    /// The name as written, e.g. `/J#61vaScript`.
+    pub raw: String,
+    /// The name it stands for, e.g. `/JavaScript`.
+    pub decoded: String,
+}
+
+/// Finds names in the raw file that write a letter or digit as a `#xx`
+/// escape. Escapes are meant for delimiters, whitespace and bytes outside
+/// ASCII, as in `/Times#20Roman`; escaping a character that needs none only
+/// serves to hide the name from byte matching. Names inside compressed object
+/// streams aren't seen here, but the checks that read the parsed document
+/// decode names wherever they are.
+pub fn find_escaped_names(raw: &[u8]) -> Vec<EscapedName> {
+    let name = Regex::new(
+        r"(?-u)/[^\x00\t\n\x0c\r ()<>\[\]{}/%]*#[0-9A-Fa-f]{2}[^\x00\t\n\x0c\r ()<>\[\]{}/%]*",
+    )
+    .unwrap();
+
+    name.find_iter(raw)
+        .filter(|found| escapes_regular_character(&found.as_bytes()[1..]))
+        .take(MAX_ESCAPED_NAMES)
+        .map(|found| EscapedName {
+            offset: found.start(),
+            raw: String::from_utf8_lossy(found.as_bytes()).into_owned(),
+            decoded: format!(
+                "/{}",
+                String::from_utf8_lossy(&decode_name(&found.as_bytes()[1..]))
+            ),
+        })
+        .collect()
+}
+
+fn escapes_regular_character(name: &[u8]) -> bool {
+    name.windows(3).any(|escape| {
+        escape[0] == b'#'
+            && std::str::from_utf8(&escape[1..])
+                .ok()
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .is_some_and(|byte| byte.is_ascii_alphanumeric())
+    })
+}
@@ -0,0 +1,94 @@
+//! PDFs inside PDFs. An attachment or any other stream that decodes to a PDF
+//! is analyzed as a document of its own, down to
+//! [`Config::max_nesting_depth`](crate::Config::max_nesting_depth) levels,
+//! so that a payload wrapped in a harmless-looking outer document is judged
+//! on what it does itself.
+
+use crate::attachments::EmbeddedFile;
+use crate::{analyze_pdf_bytes, catch_panics, AnalysisResult, CompiledConfig};
+use serde::{Deserialize, Serialize};
+
+/// How far into a stream the `%PDF-` header may start. Viewers accept
+/// anything up to a kilobyte of junk before it.
+const HEADER_WINDOW: usize = 1024;
+
+#[derive(Deserialize, Serialize)]
+pub struct EmbeddedPdf {
+    /// The stream holding it.
+    pub object_id: u32,
+    /// The name its file specification gives it, for an attachment.
+    pub name: Option<String>,
+    /// The embedded document's own analysis, with any PDFs inside it in
+    /// turn.
+    pub result: AnalysisResult,
+}
+
+/// Whether decoded stream content is a PDF.
+pub fn is_pdf(content: &[u8]) -> bool {
+    content[..content.len().min(HEADER_WINDOW)]
+        .windows(5)
+        .any(|window| window == b"%PDF-")
+}
+
+/// Analyzes every decoded stream that is a PDF, ordered by object number.
+/// Once the streams are nested as deep as the config allows, the PDFs are
+/// only logged. A nested document that can't be analyzed at all is left
+/// out.
+pub(crate) fn analyze_embedded_pdfs<'a>(
+    streams: impl IntoIterator<Item = (u32, &'a [u8])>,
+    embedded_files: &[EmbeddedFile],
+    config: &CompiledConfig,
+) -> Vec<EmbeddedPdf> {
+    let mut pdfs: Vec<(u32, &[u8])> = streams
+        .into_iter()
+        .filter(|(_, content)| is_pdf(content))
+        .collect();
+    if pdfs.is_empty() {
+        return Vec::new();
+    }
+    if config.depth >= config.config.max_nesting_depth {
+        tracing::warn!(
+            streams = pdfs.len(),
+            "PDFs nested deeper than {} levels, not analyzing them",
+            config.config.max_nesting_depth
+        );
+        return Vec::new();
+    }
+    pdfs.sort_by_key(|&(id, _)| id);
+
+    let mut nested = config.clone();
+    nested.depth += 1;
+    // The revision is the outer document's; the inner ones are analyzed
+    // whole
+    nested.revision = None;
+    // Attachment hashes were already looked up with the outer document's
+    #[cfg(feature = "virustotal")]
+    {
+        nested.virustotal = None;
+    }
+    // Nested documents are analyzed to a depth the outer one decides, so
+    // their results don't stand on their own
+    nested.cache = None;
+
+    pdfs.into_iter()
+        .filter_map(|(object_id, content)| {
+            let result = catch_panics(|| analyze_pdf_bytes(content, &nested));
+            let result = match result {
+                Ok(result) => result,
+                Err(err) => {
+                    tracing::warn!(object_id, error = %err, "embedded PDF could not be analyzed");
+                    return None;
+                }
+            };
+            let name = embedded_files
+                .iter()
+                .find(|file| file.object_id == object_id)
+                .and_then(|file| file.name.clone());
+            Some(EmbeddedPdf {
+                object_id,
+                name,
+                result,
+            })
+        })
+        .collect()
+}
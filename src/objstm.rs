@@ -0,0 +1,71 @@
+use crate::filters::StreamCache;
+use lopdf::{Document, Object, ObjectStream, Stream};
+
+/// Unpacks every `/Type /ObjStm` stream and returns a copy of the document
+/// with the objects it finds added, together with their numbers, or `None`
+/// when every packed object was already loaded.
+///
+/// The parser only loads the packed objects a cross-reference stream points
+/// at, so an object stream the xref leaves out hides its contents from every
+/// other check. Objects the document already has win over packed copies,
+/// and a stream whose content was not decoded (unsupported filter or a
+/// compression bomb) is skipped.
+pub fn expand_object_streams(
+    doc: &Document,
+    streams: &StreamCache,
+) -> Option<(Document, Vec<u32>)> {
+    let mut extracted = Vec::new();
+
+    for (id, object) in doc.objects.iter() {
+        let Ok(stream) = object.as_stream() else {
+            continue;
+        };
+        if !is_object_stream(stream) {
+            continue;
+        }
+        let Some(content) = streams.get(id.0) else {
+            continue;
+        };
+
+        // Already decoded, so hand lopdf the content without its filters
+        let mut dict = stream.dict.clone();
+        dict.remove(b"Filter");
+        dict.remove(b"DecodeParms");
+        let mut decoded = Stream::new(dict, content.to_vec());
+        match ObjectStream::new(&mut decoded) {
+            Ok(packed) => extracted.extend(
+                packed
+                    .objects
+                    .into_iter()
+                    .filter(|(packed_id, _)| !doc.objects.contains_key(packed_id)),
+            ),
+            Err(err) => tracing::debug!(
+                object_id = id.0,
+                error = %err,
+                "object stream could not be parsed"
+            ),
+        }
+    }
+
+    if extracted.is_empty() {
+        return None;
+    }
+
+    let mut expanded = doc.clone();
+    let mut ids = Vec::new();
+    for (id, object) in extracted {
+        if expanded.objects.insert(id, object).is_none() {
+            ids.push(id.0);
+        }
+    }
+    tracing::debug!(objects = ids.len(), "unpacked objects from object streams");
+    Some((expanded, ids))
+}
+
+fn is_object_stream(stream: &Stream) -> bool {
+    stream
+        .dict
+        .get(b"Type")
+        .and_then(Object::as_name)
+        .is_ok_and(|kind| kind == b"ObjStm")
+}
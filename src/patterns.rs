@@ -0,0 +1,249 @@
+//! The configurable patterns flagged in names, stream content, scripts and
+//! metadata. Each rule has an id that findings cite, so that a match can be
+//! traced back to the config, and a category that a scan can switch off as a
+//! whole.
+
+use crate::SentinelError;
+use regex::{Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+
+/// One entry of `Config::suspicious_patterns`.
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+pub struct PatternRule {
+    pub id: String,
+    /// What the pattern is evidence of, e.g. `command-execution`. Entries of
+    /// `Config::disabled_pattern_categories` turn off all the rules they
+    /// name.
+    #[serde(default = "default_category")]
+    pub category: String,
+    pub regex: String,
+    /// Added to the severity score per match, on top of the weight for
+    /// where it matched.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// Where the pattern is looked for. By default everywhere but metadata,
+    /// as before rules had targets.
+    #[serde(default = "default_targets")]
+    pub applies_to: Vec<PatternTarget>,
+}
+
+fn default_category() -> String {
+    "custom".to_string()
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+fn default_targets() -> Vec<PatternTarget> {
+    vec![
+        PatternTarget::Names,
+        PatternTarget::Streams,
+        PatternTarget::Js,
+    ]
+}
+
+impl PatternRule {
+    /// A rule with the defaults a config file entry gets for what it leaves
+    /// out.
+    pub fn new(id: &str, regex: &str) -> Self {
+        PatternRule {
+            id: id.to_string(),
+            category: default_category(),
+            regex: regex.to_string(),
+            weight: default_weight(),
+            applies_to: default_targets(),
+        }
+    }
+}
+
+/// The rules the command line tool runs with.
+pub fn default_pattern_rules() -> Vec<PatternRule> {
+    let rule = |id, category: &str, regex| PatternRule {
+        category: category.to_string(),
+        ..PatternRule::new(id, regex)
+    };
+    vec![
+        rule("eval", "dynamic-code", r"(?i)eval"),
+        rule("exec", "command-execution", r"(?i)exec"),
+        rule("spawn", "command-execution", r"(?i)spawn"),
+        rule("shell", "command-execution", r"(?i)shell"),
+        PatternRule {
+            applies_to: vec![PatternTarget::Metadata],
+            ..rule(
+                "metadata-script",
+                "script-injection",
+                r"(?i)(javascript:|<script|eval\s*\()",
+            )
+        },
+    ]
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PatternTarget {
+    /// Names and strings, such as `/JavaScript` or the text of a `/JS`
+    /// string.
+    Names,
+    /// Decoded stream content.
+    Streams,
+    /// Scripts, for what only deobfuscation or joining split fragments
+    /// reveals.
+    Js,
+    /// Values of the `/Info` dictionary.
+    Metadata,
+}
+
+impl PatternTarget {
+    pub fn description(&self) -> &'static str {
+        match self {
+            PatternTarget::Names => "name",
+            PatternTarget::Streams => "stream",
+            PatternTarget::Js => "script",
+            PatternTarget::Metadata => "metadata",
+        }
+    }
+}
+
+/// A match of a [`PatternRule`].
+#[derive(Deserialize, Serialize)]
+pub struct PatternMatch {
+    pub rule_id: String,
+    pub category: String,
+    pub target: PatternTarget,
+    /// The object matched in. `None` for metadata.
+    pub object_id: Option<u32>,
+    /// Byte offset of the match in a stream's decoded text.
+    pub offset: Option<usize>,
+    /// The matched text, or for a name or metadata value all of it.
+    pub evidence: String,
+    /// The rule's weight.
+    pub weight: u32,
+}
+
+/// The rules that apply to one target, as one set to tell which of them
+/// match and one regex each to tell where.
+struct TargetPatterns {
+    target: PatternTarget,
+    set: RegexSet,
+    regexes: Vec<Regex>,
+    /// Index into `CompiledPatterns::rules` of each regex.
+    rules: Vec<usize>,
+}
+
+/// The rules of `Config::suspicious_patterns` outside the disabled
+/// categories, compiled.
+pub(crate) struct CompiledPatterns {
+    rules: Vec<PatternRule>,
+    targets: Vec<TargetPatterns>,
+}
+
+/// Where a rule matched some text.
+pub(crate) struct Hit<'r, 't> {
+    pub rule: &'r PatternRule,
+    pub start: usize,
+    pub text: &'t str,
+}
+
+impl Hit<'_, '_> {
+    pub fn to_match(&self, target: PatternTarget, object_id: Option<u32>) -> PatternMatch {
+        PatternMatch {
+            rule_id: self.rule.id.clone(),
+            category: self.rule.category.clone(),
+            target,
+            object_id,
+            offset: None,
+            evidence: self.text.to_string(),
+            weight: self.rule.weight,
+        }
+    }
+}
+
+impl CompiledPatterns {
+    pub fn new(
+        rules: &[PatternRule],
+        disabled_categories: &[String],
+    ) -> Result<Self, SentinelError> {
+        let rules: Vec<PatternRule> = rules
+            .iter()
+            .filter(|rule| !disabled_categories.contains(&rule.category))
+            .cloned()
+            .collect();
+        let mut targets = Vec::new();
+        for target in [
+            PatternTarget::Names,
+            PatternTarget::Streams,
+            PatternTarget::Js,
+            PatternTarget::Metadata,
+        ] {
+            let indices: Vec<usize> = (0..rules.len())
+                .filter(|&index| rules[index].applies_to.contains(&target))
+                .collect();
+            let patterns: Vec<&str> = indices
+                .iter()
+                .map(|&index| rules[index].regex.as_str())
+                .collect();
+            targets.push(TargetPatterns {
+                target,
+                set: RegexSet::new(&patterns)?,
+                regexes: patterns
+                    .iter()
+                    .map(|pattern| Regex::new(pattern))
+                    .collect::<Result<_, _>>()?,
+                rules: indices,
+            });
+        }
+        Ok(CompiledPatterns { rules, targets })
+    }
+
+    fn target(&self, target: PatternTarget) -> &TargetPatterns {
+        self.targets
+            .iter()
+            .find(|patterns| patterns.target == target)
+            .expect("every target is compiled")
+    }
+
+    pub fn is_match(&self, target: PatternTarget, text: &str) -> bool {
+        self.target(target).set.is_match(text)
+    }
+
+    /// The leftmost match of any rule, the first rule winning a tie.
+    pub fn find<'t>(&self, target: PatternTarget, text: &'t str) -> Option<Hit<'_, 't>> {
+        let patterns = self.target(target);
+        patterns
+            .set
+            .matches(text)
+            .into_iter()
+            .filter_map(|index| {
+                let found = patterns.regexes[index].find(text)?;
+                Some(Hit {
+                    rule: &self.rules[patterns.rules[index]],
+                    start: found.start(),
+                    text: found.as_str(),
+                })
+            })
+            .min_by_key(|hit| hit.start)
+    }
+
+    /// Every match of every rule, in order of where they start.
+    pub fn find_all<'t>(&self, target: PatternTarget, text: &'t str) -> Vec<Hit<'_, 't>> {
+        let patterns = self.target(target);
+        let mut hits: Vec<Hit> = patterns
+            .set
+            .matches(text)
+            .into_iter()
+            .flat_map(|index| {
+                let rule = &self.rules[patterns.rules[index]];
+                patterns.regexes[index]
+                    .find_iter(text)
+                    .map(move |found| Hit {
+                        rule,
+                        start: found.start(),
+                        text: found.as_str(),
+                    })
+            })
+            .collect();
+        hits.sort_by_key(|hit| hit.start);
+        hits
+    }
+}
@@ -0,0 +1,82 @@
+use crate::filters::StreamCache;
+use crate::{cached_stream, resolve, AnalysisResult};
+use lopdf::{Document, Object};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize)]
+pub struct PdfaConformanceReport {
+    pub claimed_level: String,
+    pub violations: Vec<String>,
+}
+
+/// Reads the PDF/A identification schema from the catalog's XMP metadata and,
+/// if the document claims conformance, lists the features ISO 19005 forbids
+/// that it nevertheless contains. Returns `None` when no claim is made.
+pub fn check_pdfa_conformance(
+    doc: &Document,
+    streams: &StreamCache,
+    result: &AnalysisResult,
+) -> Option<PdfaConformanceReport> {
+    let xmp = xmp_metadata(doc, streams)?;
+    let part: u32 = xmp_property(&xmp, "pdfaid:part")?.parse().ok()?;
+    let conformance = xmp_property(&xmp, "pdfaid:conformance").unwrap_or_default();
+    let claimed_level = format!("PDF/A-{}{}", part, conformance.to_ascii_lowercase());
+
+    let mut violations = Vec::new();
+    if doc.trailer.has(b"Encrypt") {
+        violations.push("encryption".to_string());
+    }
+    if result.has_javascript {
+        violations.push("JavaScript".to_string());
+    }
+    if !result.launch_actions.is_empty() {
+        violations.push("Launch action".to_string());
+    }
+    // PDF/A-3 allows arbitrary attachments
+    if part < 3 && has_embedded_files(doc) {
+        violations.push("embedded files".to_string());
+    }
+
+    Some(PdfaConformanceReport {
+        claimed_level,
+        violations,
+    })
+}
+
+/// The catalog's XMP metadata packet, decoded.
+pub fn xmp_metadata(doc: &Document, streams: &StreamCache) -> Option<String> {
+    let metadata = doc.catalog().ok()?.get(b"Metadata").ok()?;
+    Some(String::from_utf8_lossy(cached_stream(doc, streams, metadata)?).into_owned())
+}
+
+/// Looks up a property by its prefixed name. XMP allows the property both as
+/// an attribute (`pdfaid:part="1"`) and as an element
+/// (`<pdfaid:part>1</pdfaid:part>`).
+pub fn xmp_property(xmp: &str, name: &str) -> Option<String> {
+    let pattern = format!(r#"{}(?:\s*=\s*["']|\s*>)\s*([^"'<\s]+)"#, name);
+    let re = Regex::new(&pattern).ok()?;
+    Some(re.captures(xmp)?.get(1)?.as_str().to_string())
+}
+
+fn has_embedded_files(doc: &Document) -> bool {
+    let in_name_tree = doc
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"Names").ok())
+        .and_then(|names| resolve(doc, names))
+        .and_then(|names| names.as_dict().ok())
+        .is_some_and(|names| names.has(b"EmbeddedFiles"));
+
+    // File attachment annotations reference their files outside the name tree
+    in_name_tree
+        || doc.objects.values().any(|object| match object {
+            Object::Dictionary(dict) => dict.has(b"EF"),
+            Object::Stream(stream) => stream
+                .dict
+                .get(b"Type")
+                .and_then(|kind| kind.as_name())
+                .is_ok_and(|kind| kind == b"EmbeddedFile"),
+            _ => false,
+        })
+}
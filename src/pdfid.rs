@@ -0,0 +1,155 @@
+//! Keyword counts in the manner of Didier Stevens' pdfid, for triage
+//! playbooks and parsers written against its output. Like pdfid, this looks
+//! at the raw bytes only, streams included, without parsing the document, so
+//! the counts match what pdfid would report rather than what the analysis
+//! finds.
+
+use crate::encoding::decode_name;
+use std::fmt::Write;
+
+/// The pdfid release whose output `PdfId::to_text` reproduces, printed in
+/// its first line where parsers may check for it.
+pub const PDFID_VERSION: &str = "0.2.8";
+
+/// The keywords pdfid counts by default, in its order.
+pub const KEYWORDS: [&str; 20] = [
+    "obj",
+    "endobj",
+    "stream",
+    "endstream",
+    "xref",
+    "trailer",
+    "startxref",
+    "/Page",
+    "/Encrypt",
+    "/ObjStm",
+    "/JS",
+    "/JavaScript",
+    "/AA",
+    "/OpenAction",
+    "/AcroForm",
+    "/JBIG2Decode",
+    "/RichMedia",
+    "/Launch",
+    "/EmbeddedFile",
+    "/XFA",
+];
+
+/// Counted after `KEYWORDS`: `/Colors` with a value over 2^24, which the
+/// FlateDecode predictor overflow of CVE-2009-3459 needs.
+pub const LARGE_COLORS: &str = "/Colors > 2^24";
+
+pub struct KeywordCount {
+    pub keyword: &'static str,
+    pub count: usize,
+    /// How many of `count` were names spelled with `#xx` escapes.
+    pub obfuscated: usize,
+}
+
+pub struct PdfId {
+    /// The `%PDF-x.y` header in the first 1024 bytes. Without one the file
+    /// is not counted, as pdfid doesn't.
+    pub header: Option<String>,
+    pub keywords: Vec<KeywordCount>,
+}
+
+impl PdfId {
+    pub fn new(raw: &[u8]) -> Self {
+        let header = find_header(raw);
+        let mut keywords: Vec<KeywordCount> = KEYWORDS
+            .iter()
+            .chain([&LARGE_COLORS])
+            .map(|&keyword| KeywordCount {
+                keyword,
+                count: 0,
+                obfuscated: 0,
+            })
+            .collect();
+        if header.is_none() {
+            return PdfId { header, keywords };
+        }
+
+        let mut tokens = tokens(raw).peekable();
+        while let Some(token) = tokens.next() {
+            let word = decode_name(token);
+            let obfuscated = word.len() != token.len();
+            let index = if &*word == b"/Colors" {
+                let value = tokens
+                    .peek()
+                    .and_then(|next| std::str::from_utf8(next).ok())
+                    .and_then(|next| next.parse::<u64>().ok());
+                if value.is_none_or(|value| value <= 1 << 24) {
+                    continue;
+                }
+                KEYWORDS.len()
+            } else {
+                match KEYWORDS
+                    .iter()
+                    .position(|keyword| keyword.as_bytes() == &*word)
+                {
+                    Some(index) => index,
+                    None => continue,
+                }
+            };
+            keywords[index].count += 1;
+            if obfuscated {
+                keywords[index].obfuscated += 1;
+            }
+        }
+        PdfId { header, keywords }
+    }
+
+    /// The report as pdfid prints it for `file`.
+    pub fn to_text(&self, file: &str) -> String {
+        let mut text = format!("PDFiD {} {}\n", PDFID_VERSION, file);
+        let Some(header) = &self.header else {
+            text.push_str(" Not a PDF document\n");
+            return text;
+        };
+        let _ = writeln!(text, " PDF Header: {}", header);
+        for keyword in &self.keywords {
+            let _ = write!(text, " {:<16} {:>7}", keyword.keyword, keyword.count);
+            if keyword.obfuscated > 0 {
+                let _ = write!(text, "({})", keyword.obfuscated);
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+fn find_header(raw: &[u8]) -> Option<String> {
+    let start = &raw[..raw.len().min(1024)];
+    let offset = start.windows(5).position(|window| window == b"%PDF-")?;
+    let header: Vec<u8> = raw[offset..]
+        .iter()
+        .take_while(|byte| !byte.is_ascii_whitespace())
+        .take(10)
+        .copied()
+        .collect();
+    Some(String::from_utf8_lossy(&header).into_owned())
+}
+
+/// Splits `raw` into words at whitespace and delimiters, as pdfid does. A
+/// name keeps its leading `/`.
+fn tokens(raw: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, &byte) in raw.iter().enumerate() {
+        let delimiter = byte.is_ascii_whitespace() || byte == 0 || b"()<>[]{}%/".contains(&byte);
+        if !delimiter {
+            start.get_or_insert(i);
+            continue;
+        }
+        if let Some(start) = start.take() {
+            words.push(&raw[start..i]);
+        }
+        if byte == b'/' {
+            start = Some(i);
+        }
+    }
+    if let Some(start) = start {
+        words.push(&raw[start..]);
+    }
+    words.into_iter()
+}
@@ -0,0 +1,139 @@
+use chrono::{SecondsFormat, Utc};
+use pdf_sentinel::findings::triggered_rules;
+use pdf_sentinel::scoring::Severity;
+use pdf_sentinel::{AnalysisResult, SentinelError};
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Appended to quarantined files so that nothing opens them by accident.
+const EXTENSION: &str = "quarantine";
+
+/// Gives up on finding a free name after this many files of the same name.
+const MAX_NAME_ATTEMPTS: u32 = 1000;
+
+/// Written next to each quarantined file as `<name>.quarantine.json`.
+#[derive(Serialize)]
+struct Sidecar<'a> {
+    original_path: String,
+    quarantined_at: String,
+    /// Whether the original was left in place.
+    copied: bool,
+    sha256: Option<&'a str>,
+    classification: &'a str,
+    severity: Severity,
+    severity_score: u32,
+    rules: Vec<&'static str>,
+}
+
+/// Moves, or copies, files at or above a severity into a directory of their
+/// own, each renamed to `<name>.quarantine` with a JSON sidecar saying where
+/// it came from and why it is there.
+pub struct Quarantine {
+    dir: PathBuf,
+    severity: Severity,
+    copy: bool,
+}
+
+impl Quarantine {
+    /// Creates `dir` if needed, so a path that can't be written fails before
+    /// any file is analyzed.
+    pub fn new(dir: &Path, severity: Severity, copy: bool) -> Result<Self, SentinelError> {
+        fs::create_dir_all(dir)?;
+        Ok(Quarantine {
+            dir: dir.to_path_buf(),
+            severity,
+            copy,
+        })
+    }
+
+    /// Quarantines `path` when its result reaches the severity, returning
+    /// where it went.
+    pub fn isolate(
+        &self,
+        path: &Path,
+        result: &AnalysisResult,
+    ) -> Result<Option<PathBuf>, SentinelError> {
+        if result.severity < self.severity {
+            return Ok(None);
+        }
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file name"))?;
+
+        let (target, sidecar_path, mut sidecar) = self.reserve(&name.to_string_lossy())?;
+        let sidecar_json = serde_json::to_vec_pretty(&Sidecar {
+            original_path: fs::canonicalize(path)
+                .unwrap_or_else(|_| path.to_path_buf())
+                .to_string_lossy()
+                .into_owned(),
+            quarantined_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            copied: self.copy,
+            sha256: result.sha256.as_deref(),
+            classification: result.classification.label(),
+            severity: result.severity,
+            severity_score: result.severity_score,
+            rules: triggered_rules(&result.findings),
+        })?;
+
+        let placed = sidecar
+            .write_all(&sidecar_json)
+            .and_then(|()| self.place(path, &target));
+        if let Err(err) = placed {
+            let _ = fs::remove_file(&sidecar_path);
+            return Err(err.into());
+        }
+        Ok(Some(target))
+    }
+
+    /// Picks `<name>.quarantine`, or `<name>.N.quarantine` when that is
+    /// taken, and claims it by creating its sidecar, so two runs sharing the
+    /// directory never pick the same name.
+    fn reserve(&self, name: &str) -> io::Result<(PathBuf, PathBuf, fs::File)> {
+        for attempt in 0..MAX_NAME_ATTEMPTS {
+            let target = match attempt {
+                0 => self.dir.join(format!("{}.{}", name, EXTENSION)),
+                n => self.dir.join(format!("{}.{}.{}", name, n, EXTENSION)),
+            };
+            let mut sidecar_path = target.clone().into_os_string();
+            sidecar_path.push(".json");
+            let sidecar_path = PathBuf::from(sidecar_path);
+            if target.exists() {
+                continue;
+            }
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&sidecar_path)
+            {
+                Ok(sidecar) => return Ok((target, sidecar_path, sidecar)),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("no free name for {} in {}", name, self.dir.display()),
+        ))
+    }
+
+    /// A rename is atomic, but only within a filesystem. Across filesystems,
+    /// and when copying, the file is written under a temporary name and
+    /// renamed into place, so the quarantine never holds half a file.
+    fn place(&self, path: &Path, target: &Path) -> io::Result<()> {
+        if !self.copy && fs::rename(path, target).is_ok() {
+            return Ok(());
+        }
+        let partial = target.with_extension(format!("{}.partial", EXTENSION));
+        let copied = fs::copy(path, &partial).and_then(|_| fs::rename(&partial, target));
+        if let Err(err) = copied {
+            let _ = fs::remove_file(&partial);
+            return Err(err);
+        }
+        if !self.copy {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use regex::bytes::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::carve::carve_objects;
+use crate::encoding::name_pattern;
+use crate::error::SentinelError;
+use crate::framing::{end_of_marker, find_eof_markers};
+use crate::streaming::name_regex;
+
+/// Names whose appearance or disappearance in a redefined object changes what
+/// the document does when opened.
+const ACTION_NAMES: [&str; 6] = ["OpenAction", "AA", "JS", "JavaScript", "Launch", "URI"];
+
+#[derive(Deserialize, Serialize)]
+pub struct Revision {
+    /// 1 for the original document, counting up with each update.
+    pub number: usize,
+    /// Where the revision ends in the file, just past its `%%EOF`.
+    pub end_offset: usize,
+    /// Objects the revision defines or redefines, in file order.
+    pub objects: Vec<u32>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ShadowedObject {
+    pub object_id: u32,
+    /// Revisions that define the object, oldest first. Viewers only show the
+    /// last definition; the others stay in the file unseen.
+    pub revisions: Vec<usize>,
+    /// Whether any definition is the document catalog.
+    pub catalog: bool,
+    /// Action names that the first and last definitions don't share, such as
+    /// an `/OpenAction` an update removed.
+    pub changed_actions: Vec<String>,
+}
+
+impl ShadowedObject {
+    /// A replaced catalog or changed actions can hide a payload behind a
+    /// benign final revision.
+    pub fn is_sensitive(&self) -> bool {
+        self.catalog || !self.changed_actions.is_empty()
+    }
+}
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct RevisionAnalysis {
+    pub revisions: Vec<Revision>,
+    /// Objects defined again by a later revision.
+    pub shadowed_objects: Vec<ShadowedObject>,
+    /// The revision analyzed when `--revision` picked an earlier one.
+    pub selected: Option<usize>,
+}
+
+/// One definition of an object, as carved from a revision.
+struct Definition {
+    revision: usize,
+    catalog: bool,
+    actions: Vec<&'static str>,
+}
+
+/// Splits the file into revisions at each `%%EOF` and finds the objects that
+/// later revisions define again. Attackers append an update that replaces a
+/// malicious catalog or action with a harmless one, so the payload is still
+/// in the file but not in the document a viewer or parser sees. Objects
+/// packed in object streams aren't seen.
+pub fn check_revisions(raw: &[u8]) -> RevisionAnalysis {
+    let catalog = Regex::new(&format!(
+        r"(?-u){}[\x00\t\n\x0c\r ]*{}\b",
+        name_pattern("Type"),
+        name_pattern("Catalog")
+    ))
+    .unwrap();
+    let actions: Vec<(&str, Regex)> = ACTION_NAMES
+        .iter()
+        .map(|&name| (name, name_regex(&[name])))
+        .collect();
+
+    let mut analysis = RevisionAnalysis::default();
+    let mut definitions: BTreeMap<u32, Vec<Definition>> = BTreeMap::new();
+    let mut start = 0;
+
+    for (index, end) in revision_ends(raw).into_iter().enumerate() {
+        let number = index + 1;
+        let objects = carve_objects(&raw[start..end]);
+        for object in &objects {
+            definitions.entry(object.id).or_default().push(Definition {
+                revision: number,
+                catalog: catalog.is_match(object.body),
+                actions: actions
+                    .iter()
+                    .filter(|(_, name)| name.is_match(object.body))
+                    .map(|&(name, _)| name)
+                    .collect(),
+            });
+        }
+        analysis.revisions.push(Revision {
+            number,
+            end_offset: end,
+            objects: objects.iter().map(|object| object.id).collect(),
+        });
+        start = end;
+    }
+
+    for (object_id, definitions) in definitions {
+        let mut revisions: Vec<usize> = definitions.iter().map(|def| def.revision).collect();
+        revisions.dedup();
+        let (Some(first), Some(last)) = (definitions.first(), definitions.last()) else {
+            continue;
+        };
+        if revisions.len() < 2 {
+            continue;
+        }
+        let changed_actions = ACTION_NAMES
+            .iter()
+            .filter(|name| first.actions.contains(name) != last.actions.contains(name))
+            .map(|name| name.to_string())
+            .collect();
+        analysis.shadowed_objects.push(ShadowedObject {
+            object_id,
+            revisions,
+            catalog: definitions.iter().any(|def| def.catalog),
+            changed_actions,
+        });
+    }
+
+    analysis
+}
+
+/// The file as it was at `revision`, counting the original as 1: everything
+/// up to that revision's `%%EOF`. Earlier revisions are complete documents,
+/// so the prefix parses on its own.
+pub fn select_revision(raw: &[u8], revision: usize) -> Result<&[u8], SentinelError> {
+    let ends = revision_ends(raw);
+    match revision.checked_sub(1).and_then(|index| ends.get(index)) {
+        Some(&end) => Ok(&raw[..end]),
+        None => Err(SentinelError::Revision(revision, ends.len())),
+    }
+}
+
+fn revision_ends(raw: &[u8]) -> Vec<usize> {
+    find_eof_markers(raw)
+        .into_iter()
+        .map(|eof| end_of_marker(raw, eof))
+        .collect()
+}
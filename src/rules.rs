@@ -0,0 +1,479 @@
+//! Pluggable detection rules.
+//!
+//! The built-in rules below wrap the document-level checks that `analyze_pdf`
+//! runs anyway, so that callers can compose their own rule sets from them.
+//! Rules registered with a `RuleEngine` run in addition to the built-in
+//! analysis, and each finding adds the rule's severity weight to the score.
+
+use crate::destinations::analyze_named_destinations;
+use crate::error::SentinelError;
+use crate::filters::StreamCache;
+use crate::forms::check_acroform_injection;
+use crate::graph::{build_reference_graph, detect_cycles};
+use crate::heap_spray::check_heap_spray_arrays;
+use crate::javascript::detect_split_javascript;
+use crate::shellcode::check_shellcode_heuristics;
+use crate::stego::check_image_steganography;
+use crate::{
+    check_creator, check_for_auto_action, check_for_hidden_content, check_for_javascript,
+    check_for_obj_stm, check_for_suspicious_names, check_for_unusual_objects, check_metadata,
+    find_launch_actions, is_image_stream, CompiledConfig,
+};
+use libloading::{Library, Symbol};
+use lopdf::Document;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Symbol a rules library must export, with the signature
+/// `fn register_rules(engine: &mut RuleEngine)`.
+const REGISTER_SYMBOL: &[u8] = b"register_rules";
+
+#[derive(Deserialize, Serialize)]
+pub struct Finding {
+    /// Name of the rule that reported this; filled in by the engine.
+    pub rule: String,
+    pub object_id: Option<u32>,
+    pub description: String,
+    /// The reporting rule's severity weight; filled in by the engine.
+    pub weight: u32,
+}
+
+impl Finding {
+    pub fn new(object_id: Option<u32>, description: impl Into<String>) -> Self {
+        Finding {
+            rule: String::new(),
+            object_id,
+            description: description.into(),
+            weight: 0,
+        }
+    }
+}
+
+pub trait Rule: Send + Sync {
+    fn name(&self) -> &str;
+    fn check(&self, doc: &Document, cache: &StreamCache) -> Vec<Finding>;
+    /// Added to the severity score once per finding.
+    fn severity_weight(&self) -> u32;
+}
+
+#[derive(Default)]
+pub struct RuleEngine {
+    // Declared before `libraries` so that rules built from a library's code
+    // are dropped before the library is unloaded.
+    rules: Vec<Box<dyn Rule>>,
+    libraries: Vec<Library>,
+}
+
+impl RuleEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register_rule(&mut self, rule: Box<dyn Rule>) {
+        self.rules.push(rule);
+    }
+
+    /// Loads a shared library and lets its `register_rules` add rules to this
+    /// engine. Rust has no stable ABI, so the library must be built with the
+    /// same compiler and pdf-sentinel version as the binary loading it.
+    pub fn load_library(&mut self, path: &Path) -> Result<(), SentinelError> {
+        // SAFETY: loading runs the library's initializers and trusts it to
+        // export `register_rules` with the documented signature. Only load
+        // libraries you would run as code.
+        unsafe {
+            let library = Library::new(path)?;
+            let register: Symbol<fn(&mut RuleEngine)> = library.get(REGISTER_SYMBOL)?;
+            register(self);
+            self.libraries.push(library);
+        }
+        tracing::debug!(path = %path.display(), rules = self.rules.len(), "loaded rules library");
+        Ok(())
+    }
+
+    pub fn run(&self, doc: &Document, cache: &StreamCache) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for rule in &self.rules {
+            for mut finding in rule.check(doc, cache) {
+                finding.rule = rule.name().to_string();
+                finding.weight = rule.severity_weight();
+                findings.push(finding);
+            }
+        }
+        findings
+    }
+}
+
+fn flag(present: bool, description: &str) -> Vec<Finding> {
+    if present {
+        vec![Finding::new(None, description)]
+    } else {
+        Vec::new()
+    }
+}
+
+pub struct JavaScriptRule;
+
+impl Rule for JavaScriptRule {
+    fn name(&self) -> &str {
+        "javascript"
+    }
+
+    fn check(&self, doc: &Document, _cache: &StreamCache) -> Vec<Finding> {
+        flag(check_for_javascript(doc), "document contains JavaScript")
+    }
+
+    fn severity_weight(&self) -> u32 {
+        3
+    }
+}
+
+pub struct AutoActionRule;
+
+impl Rule for AutoActionRule {
+    fn name(&self) -> &str {
+        "auto-action"
+    }
+
+    fn check(&self, doc: &Document, _cache: &StreamCache) -> Vec<Finding> {
+        flag(
+            check_for_auto_action(doc),
+            "document has an automatic action",
+        )
+    }
+
+    fn severity_weight(&self) -> u32 {
+        2
+    }
+}
+
+pub struct LaunchActionRule;
+
+impl Rule for LaunchActionRule {
+    fn name(&self) -> &str {
+        "launch-action"
+    }
+
+    fn check(&self, doc: &Document, _cache: &StreamCache) -> Vec<Finding> {
+        find_launch_actions(doc)
+            .into_iter()
+            .map(|id| Finding::new(Some(id), "Launch action"))
+            .collect()
+    }
+
+    fn severity_weight(&self) -> u32 {
+        3
+    }
+}
+
+pub struct ObjectStreamRule;
+
+impl Rule for ObjectStreamRule {
+    fn name(&self) -> &str {
+        "object-stream"
+    }
+
+    fn check(&self, doc: &Document, _cache: &StreamCache) -> Vec<Finding> {
+        flag(check_for_obj_stm(doc), "document uses object streams")
+    }
+
+    fn severity_weight(&self) -> u32 {
+        2
+    }
+}
+
+pub struct HiddenContentRule;
+
+impl Rule for HiddenContentRule {
+    fn name(&self) -> &str {
+        "hidden-content"
+    }
+
+    fn check(&self, doc: &Document, _cache: &StreamCache) -> Vec<Finding> {
+        flag(
+            check_for_hidden_content(doc),
+            "document has optional content groups",
+        )
+    }
+
+    fn severity_weight(&self) -> u32 {
+        2
+    }
+}
+
+pub struct UnusualObjectRule;
+
+impl Rule for UnusualObjectRule {
+    fn name(&self) -> &str {
+        "unusual-object"
+    }
+
+    fn check(&self, doc: &Document, _cache: &StreamCache) -> Vec<Finding> {
+        check_for_unusual_objects(doc)
+            .into_iter()
+            .map(|kind| Finding::new(None, format!("unusual object type {}", kind)))
+            .collect()
+    }
+
+    fn severity_weight(&self) -> u32 {
+        1
+    }
+}
+
+pub struct ReferenceCycleRule;
+
+impl Rule for ReferenceCycleRule {
+    fn name(&self) -> &str {
+        "reference-cycle"
+    }
+
+    fn check(&self, doc: &Document, _cache: &StreamCache) -> Vec<Finding> {
+        detect_cycles(&build_reference_graph(doc))
+            .into_iter()
+            .map(|cycle| {
+                Finding::new(
+                    cycle.first().copied(),
+                    format!("reference cycle through {:?}", cycle),
+                )
+            })
+            .collect()
+    }
+
+    fn severity_weight(&self) -> u32 {
+        1
+    }
+}
+
+pub struct NamedDestinationRule;
+
+impl Rule for NamedDestinationRule {
+    fn name(&self) -> &str {
+        "named-destinations"
+    }
+
+    fn check(&self, doc: &Document, _cache: &StreamCache) -> Vec<Finding> {
+        flag(
+            analyze_named_destinations(doc).is_suspicious(),
+            "named destinations look like a heap spray",
+        )
+    }
+
+    fn severity_weight(&self) -> u32 {
+        2
+    }
+}
+
+pub struct FormActionRule;
+
+impl Rule for FormActionRule {
+    fn name(&self) -> &str {
+        "form-action"
+    }
+
+    fn check(&self, doc: &Document, _cache: &StreamCache) -> Vec<Finding> {
+        check_acroform_injection(doc)
+            .into_iter()
+            .filter(|action| action.has_javascript)
+            .map(|action| {
+                Finding::new(
+                    Some(action.object_id),
+                    format!(
+                        "/{} JavaScript on form field {}",
+                        action.trigger, action.field_name
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    fn severity_weight(&self) -> u32 {
+        1
+    }
+}
+
+pub struct ShellcodeRule;
+
+impl Rule for ShellcodeRule {
+    fn name(&self) -> &str {
+        "shellcode"
+    }
+
+    fn check(&self, doc: &Document, cache: &StreamCache) -> Vec<Finding> {
+        doc.objects
+            .iter()
+            .filter(|(_, object)| object.as_stream().is_ok_and(|s| !is_image_stream(s)))
+            .filter_map(|(id, _)| {
+                let hints = check_shellcode_heuristics(cache.get(id.0)?);
+                hints
+                    .is_detected()
+                    .then(|| Finding::new(Some(id.0), "shellcode-like byte patterns"))
+            })
+            .collect()
+    }
+
+    fn severity_weight(&self) -> u32 {
+        4
+    }
+}
+
+pub struct SteganographyRule;
+
+impl Rule for SteganographyRule {
+    fn name(&self) -> &str {
+        "steganography"
+    }
+
+    fn check(&self, doc: &Document, cache: &StreamCache) -> Vec<Finding> {
+        doc.objects
+            .iter()
+            .filter_map(|(id, object)| {
+                let stream = object.as_stream().ok().filter(|s| is_image_stream(s))?;
+                let color_depth = stream
+                    .dict
+                    .get(b"BitsPerComponent")
+                    .and_then(|bits| bits.as_i64())
+                    .unwrap_or(8);
+                let hint = check_image_steganography(cache.get(id.0)?, color_depth as u8);
+                hint.suspected.then(|| {
+                    Finding::new(
+                        Some(id.0),
+                        format!("image LSBs fail chi-square test (p = {:.4})", hint.p_value),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    fn severity_weight(&self) -> u32 {
+        2
+    }
+}
+
+pub struct SuspiciousNameRule {
+    pub config: Arc<CompiledConfig>,
+}
+
+impl Rule for SuspiciousNameRule {
+    fn name(&self) -> &str {
+        "suspicious-name"
+    }
+
+    fn check(&self, doc: &Document, _cache: &StreamCache) -> Vec<Finding> {
+        check_for_suspicious_names(doc, &self.config)
+            .into_iter()
+            .map(|found| {
+                Finding::new(
+                    found.object_id,
+                    format!("suspicious name {}", found.evidence),
+                )
+            })
+            .collect()
+    }
+
+    fn severity_weight(&self) -> u32 {
+        1
+    }
+}
+
+pub struct MetadataRule {
+    pub config: Arc<CompiledConfig>,
+}
+
+impl Rule for MetadataRule {
+    fn name(&self) -> &str {
+        "metadata"
+    }
+
+    fn check(&self, doc: &Document, _cache: &StreamCache) -> Vec<Finding> {
+        flag(
+            !check_metadata(doc, &self.config).is_empty(),
+            "suspicious document metadata",
+        )
+    }
+
+    fn severity_weight(&self) -> u32 {
+        2
+    }
+}
+
+pub struct MaliciousCreatorRule {
+    pub config: Arc<CompiledConfig>,
+}
+
+impl Rule for MaliciousCreatorRule {
+    fn name(&self) -> &str {
+        "malicious-creator"
+    }
+
+    fn check(&self, doc: &Document, cache: &StreamCache) -> Vec<Finding> {
+        check_creator(doc, cache, &self.config)
+            .1
+            .into_iter()
+            .map(|creator| Finding::new(None, format!("built with {}", creator)))
+            .collect()
+    }
+
+    fn severity_weight(&self) -> u32 {
+        4
+    }
+}
+
+pub struct SplitJavaScriptRule {
+    pub config: Arc<CompiledConfig>,
+}
+
+impl Rule for SplitJavaScriptRule {
+    fn name(&self) -> &str {
+        "split-javascript"
+    }
+
+    fn check(&self, doc: &Document, _cache: &StreamCache) -> Vec<Finding> {
+        detect_split_javascript(doc, &self.config)
+            .into_iter()
+            .map(|candidate| {
+                Finding::new(
+                    Some(candidate.parent_object_id),
+                    format!(
+                        "JavaScript split across {} fragments",
+                        candidate.fragment_ids.len()
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    fn severity_weight(&self) -> u32 {
+        3
+    }
+}
+
+pub struct HeapSprayRule {
+    pub config: Arc<CompiledConfig>,
+}
+
+impl Rule for HeapSprayRule {
+    fn name(&self) -> &str {
+        "heap-spray"
+    }
+
+    fn check(&self, doc: &Document, _cache: &StreamCache) -> Vec<Finding> {
+        check_heap_spray_arrays(doc, &self.config)
+            .into_iter()
+            .map(|candidate| {
+                Finding::new(
+                    Some(candidate.object_id),
+                    format!(
+                        "{:#x} fills {} of {} array elements",
+                        candidate.dominant_value,
+                        (candidate.dominance_ratio * candidate.array_length as f64) as usize,
+                        candidate.array_length
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    fn severity_weight(&self) -> u32 {
+        4
+    }
+}
@@ -0,0 +1,150 @@
+//! Removes the most common attack vectors from a document.
+//!
+//! This is not a guarantee of safety. It strips what the analysis knows
+//! about (scripts, automatic and Launch actions, embedded files, XFA forms),
+//! but exploits in fonts, images or the parser itself pass through
+//! untouched, and the output may not render as the original did. Treat a
+//! sanitized PDF as less dangerous, not as safe.
+
+use crate::{AnalysisResult, JsSource};
+use lopdf::{Dictionary, Document, Object};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Keys whose values run without the user choosing to; replaced with null.
+const AUTOMATIC_ACTION_KEYS: [&[u8]; 2] = [b"OpenAction", b"AA"];
+/// Name tree entries for document-level scripts and attachments; removed.
+const NAME_TREE_KEYS: [&[u8]; 2] = [b"JavaScript", b"EmbeddedFiles"];
+/// Scripts written straight into a dictionary, and the XFA form with the
+/// catalog flag that makes readers render it; removed. Without `/XFA`,
+/// readers fall back to the AcroForm fields.
+const SCRIPT_KEYS: [&[u8]; 3] = [b"JS", b"XFA", b"NeedsRendering"];
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+pub enum RemovalReason {
+    JavaScript,
+    LaunchAction,
+    EmbeddedFile,
+    /// A stream holding a packet of the XFA form.
+    XfaPacket,
+}
+
+impl RemovalReason {
+    pub fn description(&self) -> &'static str {
+        match self {
+            RemovalReason::JavaScript => "JavaScript",
+            RemovalReason::LaunchAction => "Launch action",
+            RemovalReason::EmbeddedFile => "embedded file",
+            RemovalReason::XfaPacket => "XFA packet",
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RemovedObject {
+    pub object_id: u32,
+    pub reason: RemovalReason,
+}
+
+#[derive(Default, Serialize)]
+pub struct SanitizationReport {
+    pub removed_objects: Vec<RemovedObject>,
+    pub stripped_keys: Vec<(u32, String)>,
+}
+
+/// Deletes JavaScript, Launch action, embedded file and XFA packet objects,
+/// nulls out `/OpenAction` and `/AA`, removes `/JS` and `/XFA` entries, and
+/// drops the document's JavaScript and embedded file name trees. References
+/// to deleted objects are left to dangle, which readers treat as null. See
+/// the module docs for limits.
+pub fn sanitize(doc: &mut Document, result: &AnalysisResult) -> SanitizationReport {
+    let mut report = SanitizationReport::default();
+
+    let mut doomed: BTreeMap<u32, RemovalReason> = BTreeMap::new();
+    // A script written inline is reported under the object holding it,
+    // such as a page with an `/AA`, which loses only the entry below. The
+    // object goes when it is an action itself, which the loop after this
+    // finds.
+    for js in &result.javascript_objects {
+        let script = match js.source {
+            JsSource::Stream | JsSource::XfaScript => js.id,
+            JsSource::IndirectRef(target) => target,
+            JsSource::InlineString => continue,
+        };
+        doomed.insert(script, RemovalReason::JavaScript);
+    }
+    for &id in &result.launch_actions {
+        doomed.insert(id, RemovalReason::LaunchAction);
+    }
+    for packet in result.xfa.iter().flat_map(|xfa| &xfa.packets) {
+        doomed.insert(packet.object_id, RemovalReason::XfaPacket);
+    }
+    for (id, object) in doc.objects.iter() {
+        let reason = match object {
+            Object::Dictionary(dict) => action_reason(dict),
+            Object::Stream(stream) => stream
+                .dict
+                .get(b"Type")
+                .and_then(|kind| kind.as_name())
+                .is_ok_and(|kind| kind == b"EmbeddedFile")
+                .then_some(RemovalReason::EmbeddedFile),
+            _ => None,
+        };
+        if let Some(reason) = reason {
+            doomed.entry(id.0).or_insert(reason);
+        }
+    }
+    doc.objects.retain(|id, _| match doomed.get(&id.0) {
+        Some(&reason) => {
+            report.removed_objects.push(RemovedObject {
+                object_id: id.0,
+                reason,
+            });
+            false
+        }
+        None => true,
+    });
+
+    for (id, object) in doc.objects.iter_mut() {
+        let dict = match object {
+            Object::Dictionary(dict) => dict,
+            Object::Stream(stream) => &mut stream.dict,
+            _ => continue,
+        };
+
+        for key in AUTOMATIC_ACTION_KEYS {
+            if dict.has(key) {
+                dict.set(key, Object::Null);
+                report
+                    .stripped_keys
+                    .push((id.0, String::from_utf8_lossy(key).into_owned()));
+            }
+        }
+        for key in NAME_TREE_KEYS.into_iter().chain(SCRIPT_KEYS) {
+            if dict.remove(key).is_some() {
+                report
+                    .stripped_keys
+                    .push((id.0, String::from_utf8_lossy(key).into_owned()));
+            }
+        }
+        // Link and widget actions written inline rather than as objects
+        let inline_action = dict
+            .get(b"A")
+            .and_then(|action| action.as_dict())
+            .is_ok_and(|action| action_reason(action).is_some());
+        if inline_action {
+            dict.set("A", Object::Null);
+            report.stripped_keys.push((id.0, "A".to_string()));
+        }
+    }
+
+    report
+}
+
+fn action_reason(dict: &Dictionary) -> Option<RemovalReason> {
+    match dict.get(b"S").and_then(|kind| kind.as_name()).ok()? {
+        b"JavaScript" => Some(RemovalReason::JavaScript),
+        b"Launch" => Some(RemovalReason::LaunchAction),
+        _ => None,
+    }
+}
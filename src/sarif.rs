@@ -0,0 +1,93 @@
+use serde_json::{json, Value};
+
+use crate::error::SentinelError;
+use crate::findings::{Finding, RULES};
+use crate::scoring::Severity;
+use crate::AnalysisResult;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Builds a SARIF 2.1.0 log with one run covering every file, for code
+/// scanning dashboards. Each finding becomes a result located at the file,
+/// plus a logical location naming the PDF object where there is one. PDFs
+/// have no lines, so the region, when the object's offset is known, is a
+/// byte offset.
+pub fn to_sarif_log(results: &[(&str, &AnalysisResult)]) -> Result<String, SentinelError> {
+    let mut sarif_results = Vec::new();
+    for (file, result) in results {
+        for finding in &result.findings {
+            sarif_results.push(sarif_result(file, finding));
+        }
+    }
+
+    let rules: Vec<Value> = RULES
+        .iter()
+        .map(|rule| {
+            let level = level(rule.severity);
+            json!({
+                "id": rule.id,
+                "name": rule.name,
+                "shortDescription": { "text": rule.description },
+                "defaultConfiguration": { "level": level },
+                "properties": { "security-severity": security_severity(level) },
+            })
+        })
+        .collect();
+
+    let log = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                },
+            },
+            "results": sarif_results,
+        }],
+    });
+    Ok(serde_json::to_string_pretty(&log)?)
+}
+
+fn sarif_result(file: &str, finding: &Finding) -> Value {
+    let mut location = json!({
+        "physicalLocation": { "artifactLocation": { "uri": file } },
+    });
+    if let Some(offset) = finding.offset {
+        location["physicalLocation"]["region"] = json!({ "byteOffset": offset });
+    }
+    if let Some(id) = finding.object_id {
+        location["logicalLocations"] = json!([{
+            "name": format!("object {}", id),
+            "kind": "object",
+        }]);
+    }
+    json!({
+        "ruleId": finding.rule_id,
+        "ruleIndex": RULES.iter().position(|rule| rule.id == finding.rule_id),
+        "level": level(finding.severity),
+        "message": { "text": finding.title },
+        "locations": [location],
+    })
+}
+
+/// SARIF has three levels to the four severities.
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical | Severity::High => "error",
+        Severity::Medium => "warning",
+        Severity::Low => "note",
+    }
+}
+
+/// GitHub code scanning ranks security alerts by this 0.0 to 10.0 score
+/// rather than by `level`.
+fn security_severity(level: &str) -> &'static str {
+    match level {
+        "error" => "8.0",
+        "warning" => "5.0",
+        _ => "2.0",
+    }
+}
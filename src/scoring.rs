@@ -0,0 +1,751 @@
+//! Turns findings into a severity score and a severity band, as tuned by the
+//! `[scoring]` table of the config.
+
+use crate::actions::ActionKind;
+use crate::metadata::MetadataAnomalyKind;
+use crate::AnalysisResult;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// How findings are scored: a weight per kind of finding, optional caps on
+/// what each kind may add, and the bands the total falls into.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Scoring {
+    #[serde(flatten)]
+    pub weights: ScoringWeights,
+    /// Most points a kind of finding may add however often it occurs, keyed
+    /// by the name of its weight. Custom rule findings share the key
+    /// `custom_rule`, and matches of `Config::suspicious_patterns` the key
+    /// `pattern_rule`. Kinds without an entry are not capped.
+    pub caps: HashMap<String, u32>,
+    /// Most points a document may score in total. `u32::MAX` is no cap.
+    pub max_score: u32,
+    pub bands: SeverityBands,
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Scoring {
+            weights: ScoringWeights::default(),
+            caps: HashMap::new(),
+            max_score: u32::MAX,
+            bands: SeverityBands::default(),
+        }
+    }
+}
+
+/// Points added to the severity score per finding. Findings that can occur
+/// more than once (names, objects, hints) add their weight for each one.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ScoringWeights {
+    pub javascript: u32,
+    pub auto_action: u32,
+    pub open_action_javascript: u32,
+    pub object_streams: u32,
+    pub suspicious_name: u32,
+    pub suspicious_stream_content: u32,
+    pub hidden_content: u32,
+    pub large_file: u32,
+    pub suspicious_metadata: u32,
+    pub metadata_date_mismatch: u32,
+    /// Per pair of creation and modification dates out of order.
+    pub modified_before_created: u32,
+    /// Per metadata date in the future.
+    pub future_metadata_date: u32,
+    pub missing_producer: u32,
+    pub structure_violation: u32,
+    pub unknown_creator: u32,
+    pub malicious_creator: u32,
+    pub unusual_object: u32,
+    pub javascript_object: u32,
+    pub object_stream_object: u32,
+    pub reference_cycle: u32,
+    /// Added once per ten dangling references.
+    pub dangling_references: u32,
+    pub shellcode: u32,
+    pub heap_spray: u32,
+    pub split_javascript: u32,
+    pub steganography: u32,
+    pub compression_bomb: u32,
+    pub high_entropy_stream: u32,
+    /// Per stream holding a PE or ELF executable.
+    pub embedded_executable: u32,
+    /// Per stream holding a ZIP, RAR or OLE compound file.
+    pub embedded_archive: u32,
+    pub pdfa_violation: u32,
+    pub suspicious_named_destinations: u32,
+    pub ratio_finding: u32,
+    pub xref_anomaly: u32,
+    /// Per `/Size` mismatch, missing object and unlisted object.
+    pub xref_inconsistency: u32,
+    pub escaped_name: u32,
+    pub suspicious_url: u32,
+    pub encrypted: u32,
+    /// Added on top of `encrypted` when no password opened the document.
+    pub undecryptable: u32,
+    pub parse_failure: u32,
+    pub framing_anomaly: u32,
+    /// Per other format the file is valid in, on top of the framing
+    /// anomaly its data usually is.
+    pub polyglot: u32,
+    pub shadowed_object: u32,
+    /// Added on top of `shadowed_object` for a redefined catalog or changed
+    /// actions.
+    pub shadowed_action: u32,
+    pub form_field_javascript: u32,
+    pub xfa_form: u32,
+    /// Per JavaScript `<script>` in the XFA packets.
+    pub xfa_javascript: u32,
+    /// Per XFA submit target that is an external URL.
+    pub xfa_submit: u32,
+    /// Added once if any form field has a keystroke (`/K`) action.
+    pub keystroke_action: u32,
+    pub dynamic_eval: u32,
+    pub string_concat_call: u32,
+    pub dangerous_js_api: u32,
+    /// Times 1 to 4 for the API's severity, Low to Critical.
+    pub acrobat_api: u32,
+    pub hidden_match: u32,
+    /// Added once per script that contains an immediately invoked function.
+    pub iife: u32,
+    pub launch_action: u32,
+    pub go_to_remote_action: u32,
+    pub go_to_embedded_action: u32,
+    pub submit_form_action: u32,
+    pub import_data_action: u32,
+    pub embedded_file: u32,
+    /// Added on top of `embedded_file` for names like `*.exe` or `*.js`.
+    pub executable_embedded_file: u32,
+    /// Per PDF inside an attachment or stream, on top of that PDF's own
+    /// score.
+    pub embedded_pdf: u32,
+    pub media_annotation: u32,
+    /// Per Flash, U3D or PRC payload a media annotation plays.
+    pub media_payload: u32,
+    pub yara_match: u32,
+    /// Per file or payload hash that VirusTotal engines flag.
+    pub virustotal_detection: u32,
+}
+
+impl Default for ScoringWeights {
+    fn default() -> Self {
+        ScoringWeights {
+            javascript: 3,
+            auto_action: 2,
+            open_action_javascript: 5,
+            object_streams: 2,
+            suspicious_name: 1,
+            suspicious_stream_content: 1,
+            hidden_content: 2,
+            large_file: 1,
+            suspicious_metadata: 2,
+            metadata_date_mismatch: 2,
+            modified_before_created: 2,
+            future_metadata_date: 2,
+            missing_producer: 1,
+            structure_violation: 1,
+            unknown_creator: 1,
+            malicious_creator: 4,
+            unusual_object: 1,
+            javascript_object: 2,
+            object_stream_object: 1,
+            reference_cycle: 1,
+            dangling_references: 1,
+            shellcode: 4,
+            heap_spray: 4,
+            split_javascript: 3,
+            steganography: 2,
+            compression_bomb: 3,
+            high_entropy_stream: 2,
+            embedded_executable: 4,
+            embedded_archive: 2,
+            pdfa_violation: 3,
+            suspicious_named_destinations: 2,
+            ratio_finding: 2,
+            xref_anomaly: 1,
+            xref_inconsistency: 1,
+            escaped_name: 2,
+            suspicious_url: 2,
+            encrypted: 1,
+            undecryptable: 3,
+            parse_failure: 3,
+            framing_anomaly: 2,
+            polyglot: 4,
+            shadowed_object: 1,
+            shadowed_action: 3,
+            form_field_javascript: 1,
+            xfa_form: 1,
+            xfa_javascript: 3,
+            xfa_submit: 2,
+            keystroke_action: 3,
+            dynamic_eval: 2,
+            string_concat_call: 1,
+            dangerous_js_api: 2,
+            acrobat_api: 2,
+            hidden_match: 3,
+            iife: 1,
+            launch_action: 4,
+            go_to_remote_action: 2,
+            go_to_embedded_action: 2,
+            submit_form_action: 2,
+            import_data_action: 2,
+            embedded_file: 2,
+            executable_embedded_file: 4,
+            embedded_pdf: 2,
+            media_annotation: 1,
+            media_payload: 3,
+            yara_match: 3,
+            virustotal_detection: 5,
+        }
+    }
+}
+
+/// The lowest score of each band above `Low`.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SeverityBands {
+    pub medium: u32,
+    pub high: u32,
+    pub critical: u32,
+}
+
+impl Default for SeverityBands {
+    fn default() -> Self {
+        SeverityBands {
+            medium: 3,
+            high: 6,
+            critical: 11,
+        }
+    }
+}
+
+impl SeverityBands {
+    pub fn severity(&self, score: u32) -> Severity {
+        if score >= self.critical {
+            Severity::Critical
+        } else if score >= self.high {
+            Severity::High
+        } else if score >= self.medium {
+            Severity::Medium
+        } else {
+            Severity::Low
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum Severity {
+    #[default]
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Low => "Low",
+            Severity::Medium => "Medium",
+            Severity::High => "High",
+            Severity::Critical => "Critical",
+        }
+    }
+
+    fn multiplier(&self) -> u32 {
+        match self {
+            Severity::Low => 1,
+            Severity::Medium => 2,
+            Severity::High => 3,
+            Severity::Critical => 4,
+        }
+    }
+}
+
+/// Parses a label, ignoring case, as given on the command line.
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(label: &str) -> Result<Self, Self::Err> {
+        [
+            Severity::Low,
+            Severity::Medium,
+            Severity::High,
+            Severity::Critical,
+        ]
+        .into_iter()
+        .find(|severity| severity.label().eq_ignore_ascii_case(label))
+        .ok_or_else(|| format!("expected low, medium, high or critical, not {}", label))
+    }
+}
+
+/// How a severity score adds up, for analysts to justify a verdict and tune
+/// the weights.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct ScoreExplanation {
+    /// What each kind of finding added, most first. Kinds without findings
+    /// are left out.
+    pub contributions: Vec<ScoreContribution>,
+    /// The sum of the contributions, when `Scoring::max_score` lowered it.
+    pub uncapped_score: Option<u32>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ScoreContribution {
+    /// The name of the weight in the `[scoring]` table, or `custom_rule` or
+    /// `pattern_rule`.
+    pub weight: String,
+    pub points: u32,
+    /// What the findings added before their entry in `Scoring::caps`
+    /// lowered it.
+    pub uncapped_points: Option<u32>,
+}
+
+/// The findings each weight scores, for taking suppressed findings out of
+/// the score. Weights whose kind has no finding, such as `reference_cycle`,
+/// are left out and can't be suppressed.
+const WEIGHT_RULES: &[(&str, &[&str])] = &[
+    ("javascript", &["PS002"]),
+    ("auto_action", &["PS005"]),
+    ("open_action_javascript", &["PS003"]),
+    ("object_streams", &["PS019"]),
+    ("suspicious_name", &["PS009"]),
+    ("suspicious_stream_content", &["PS030"]),
+    ("hidden_content", &["PS018"]),
+    ("large_file", &["PS025"]),
+    ("suspicious_metadata", &["PS016"]),
+    ("metadata_date_mismatch", &["PS017"]),
+    ("modified_before_created", &["PS062"]),
+    ("future_metadata_date", &["PS063"]),
+    ("missing_producer", &["PS064"]),
+    ("structure_violation", &["PS023"]),
+    ("unknown_creator", &["PS015"]),
+    ("malicious_creator", &["PS014"]),
+    ("unusual_object", &["PS020"]),
+    ("javascript_object", &["PS002"]),
+    ("object_stream_object", &["PS019"]),
+    ("shellcode", &["PS010"]),
+    ("heap_spray", &["PS011"]),
+    ("split_javascript", &["PS008"]),
+    ("steganography", &["PS013"]),
+    ("compression_bomb", &["PS012"]),
+    ("high_entropy_stream", &["PS039"]),
+    ("embedded_executable", &["PS057"]),
+    ("embedded_archive", &["PS058"]),
+    ("suspicious_named_destinations", &["PS024"]),
+    ("xref_anomaly", &["PS022"]),
+    ("xref_inconsistency", &["PS048", "PS049", "PS050"]),
+    ("escaped_name", &["PS040"]),
+    ("suspicious_url", &["PS051"]),
+    ("encrypted", &["PS041"]),
+    ("undecryptable", &["PS041"]),
+    ("parse_failure", &["PS042"]),
+    ("framing_anomaly", &["PS043", "PS044", "PS045"]),
+    ("polyglot", &["PS061"]),
+    ("shadowed_object", &["PS046", "PS047"]),
+    ("shadowed_action", &["PS047"]),
+    ("form_field_javascript", &["PS007"]),
+    ("keystroke_action", &["PS007"]),
+    ("xfa_form", &["PS052"]),
+    ("xfa_javascript", &["PS053"]),
+    ("xfa_submit", &["PS054"]),
+    ("dynamic_eval", &["PS002"]),
+    ("string_concat_call", &["PS002"]),
+    ("dangerous_js_api", &["PS004"]),
+    (
+        "acrobat_api",
+        &[
+            "PS031", "PS032", "PS033", "PS034", "PS035", "PS036", "PS037",
+        ],
+    ),
+    ("hidden_match", &["PS038"]),
+    ("iife", &["PS002"]),
+    ("launch_action", &["PS006"]),
+    ("go_to_remote_action", &["PS027"]),
+    ("go_to_embedded_action", &["PS027"]),
+    ("submit_form_action", &["PS027"]),
+    ("import_data_action", &["PS027"]),
+    ("embedded_file", &["PS028"]),
+    ("executable_embedded_file", &["PS028"]),
+    ("embedded_pdf", &["PS060"]),
+    ("media_annotation", &["PS055"]),
+    ("media_payload", &["PS056"]),
+    ("yara_match", &["PS029"]),
+    ("virustotal_detection", &["PS059"]),
+    ("custom_rule", &["PS026"]),
+    ("pattern_rule", &["PS009", "PS016", "PS030", "PS038"]),
+];
+
+/// Sums the weight of every finding, capping each kind of finding and then
+/// the total as configured, and says what each kind added. Of a kind with
+/// suppressed findings, only the share its remaining findings make up
+/// counts.
+pub(crate) fn calculate_severity_score(
+    result: &AnalysisResult,
+    scoring: &Scoring,
+) -> (u32, ScoreExplanation) {
+    let weights = &scoring.weights;
+    let count = |items: usize| items as u32;
+    let mut totals: HashMap<&str, u32> = HashMap::new();
+    let mut add = |name: &'static str, points: u32| {
+        let total = totals.entry(name).or_default();
+        *total = total.saturating_add(points);
+    };
+
+    if result.has_javascript {
+        add("javascript", weights.javascript);
+    }
+    if result.has_auto_action {
+        add("auto_action", weights.auto_action);
+    }
+    if result.open_action_js.is_some() {
+        add("open_action_javascript", weights.open_action_javascript);
+    }
+    if result.has_obj_stm {
+        add("object_streams", weights.object_streams);
+    }
+    add(
+        "suspicious_name",
+        count(result.suspicious_names.len()) * weights.suspicious_name,
+    );
+    add(
+        "suspicious_stream_content",
+        count(result.suspicious_stream_content.len()) * weights.suspicious_stream_content,
+    );
+    if result.hidden_content {
+        add("hidden_content", weights.hidden_content);
+    }
+    if result.large_file_size {
+        add("large_file", weights.large_file);
+    }
+    if result.suspicious_metadata {
+        add("suspicious_metadata", weights.suspicious_metadata);
+    }
+    if result.metadata_date_mismatch.is_some() {
+        add("metadata_date_mismatch", weights.metadata_date_mismatch);
+    }
+    for anomaly in &result.metadata_anomalies {
+        match anomaly.kind {
+            MetadataAnomalyKind::ModifiedBeforeCreated => {
+                add("modified_before_created", weights.modified_before_created)
+            }
+            MetadataAnomalyKind::FutureDate => {
+                add("future_metadata_date", weights.future_metadata_date)
+            }
+            MetadataAnomalyKind::MissingProducer => {
+                add("missing_producer", weights.missing_producer)
+            }
+        }
+    }
+    add(
+        "structure_violation",
+        count(result.structure_violations.len()) * weights.structure_violation,
+    );
+    if result.unknown_creator {
+        add("unknown_creator", weights.unknown_creator);
+    }
+    if result.malicious_creator.is_some() {
+        add("malicious_creator", weights.malicious_creator);
+    }
+    add(
+        "unusual_object",
+        count(result.unusual_objects.len()) * weights.unusual_object,
+    );
+    add(
+        "javascript_object",
+        count(result.object_statistics.js_objects) * weights.javascript_object,
+    );
+    add(
+        "object_stream_object",
+        count(result.object_statistics.obj_stm_objects) * weights.object_stream_object,
+    );
+    add(
+        "reference_cycle",
+        count(result.reference_cycles.len()) * weights.reference_cycle,
+    );
+    add(
+        "dangling_references",
+        count(result.dangling_refs.len() / 10) * weights.dangling_references,
+    );
+    add(
+        "shellcode",
+        count(result.shellcode_hints.len()) * weights.shellcode,
+    );
+    add(
+        "heap_spray",
+        count(result.heap_spray_candidates.len()) * weights.heap_spray,
+    );
+    add(
+        "split_javascript",
+        count(result.split_js_candidates.len()) * weights.split_javascript,
+    );
+    add(
+        "steganography",
+        count(result.steganography_hints.len()) * weights.steganography,
+    );
+    add(
+        "compression_bomb",
+        count(result.compression_bomb_hints.len()) * weights.compression_bomb,
+    );
+    add(
+        "high_entropy_stream",
+        count(result.high_entropy_streams.len()) * weights.high_entropy_stream,
+    );
+    let signature_streams = |executable: bool| {
+        let mut streams: Vec<u32> = result
+            .embedded_signatures
+            .iter()
+            .filter(|signature| signature.kind.is_executable() == executable)
+            .map(|signature| signature.object_id)
+            .collect();
+        streams.dedup();
+        count(streams.len())
+    };
+    add(
+        "embedded_executable",
+        signature_streams(true) * weights.embedded_executable,
+    );
+    add(
+        "embedded_archive",
+        signature_streams(false) * weights.embedded_archive,
+    );
+    if let Some(report) = &result.pdfa_conformance {
+        add(
+            "pdfa_violation",
+            count(report.violations.len()) * weights.pdfa_violation,
+        );
+    }
+    if result.named_destinations.is_suspicious() {
+        add(
+            "suspicious_named_destinations",
+            weights.suspicious_named_destinations,
+        );
+    }
+    add(
+        "ratio_finding",
+        count(result.ratio_findings.len()) * weights.ratio_finding,
+    );
+    add(
+        "xref_anomaly",
+        count(result.xref_anomalies.len()) * weights.xref_anomaly,
+    );
+    let consistency = &result.xref_consistency;
+    add(
+        "xref_inconsistency",
+        count(
+            usize::from(consistency.size_mismatch.is_some())
+                + consistency.missing_objects.len()
+                + consistency.unlisted_objects.len(),
+        ) * weights.xref_inconsistency,
+    );
+    if !result.escaped_names.is_empty() {
+        add("escaped_name", weights.escaped_name);
+    }
+    add(
+        "suspicious_url",
+        count(
+            result
+                .urls
+                .iter()
+                .filter(|url| !url.indicators.is_empty())
+                .count(),
+        ) * weights.suspicious_url,
+    );
+    if let Some(encryption) = &result.encryption {
+        add("encrypted", weights.encrypted);
+        if encryption.raw_scan_only {
+            add("undecryptable", weights.undecryptable);
+        }
+    }
+    if result.parse_error.is_some() {
+        add("parse_failure", weights.parse_failure);
+    }
+    add(
+        "framing_anomaly",
+        count(result.framing.anomalies.len()) * weights.framing_anomaly,
+    );
+    add(
+        "polyglot",
+        count(result.framing.polyglots.len()) * weights.polyglot,
+    );
+    let shadowed = &result.revisions.shadowed_objects;
+    add(
+        "shadowed_object",
+        count(shadowed.len()) * weights.shadowed_object,
+    );
+    add(
+        "shadowed_action",
+        count(
+            shadowed
+                .iter()
+                .filter(|object| object.is_sensitive())
+                .count(),
+        ) * weights.shadowed_action,
+    );
+    add(
+        "form_field_javascript",
+        count(
+            result
+                .form_field_actions
+                .iter()
+                .filter(|action| action.has_javascript)
+                .count(),
+        ) * weights.form_field_javascript,
+    );
+    if result
+        .form_field_actions
+        .iter()
+        .any(|action| action.trigger == "K")
+    {
+        add("keystroke_action", weights.keystroke_action);
+    }
+    if let Some(xfa) = &result.xfa {
+        add("xfa_form", weights.xfa_form);
+        add(
+            "xfa_javascript",
+            count(xfa.scripts) * weights.xfa_javascript,
+        );
+        add(
+            "xfa_submit",
+            count(
+                xfa.submit_targets
+                    .iter()
+                    .filter(|submit| submit.external)
+                    .count(),
+            ) * weights.xfa_submit,
+        );
+    }
+    for action in &result.external_actions {
+        match action.kind {
+            ActionKind::Launch => add("launch_action", weights.launch_action),
+            ActionKind::GoToR => add("go_to_remote_action", weights.go_to_remote_action),
+            ActionKind::GoToE => add("go_to_embedded_action", weights.go_to_embedded_action),
+            ActionKind::SubmitForm => add("submit_form_action", weights.submit_form_action),
+            ActionKind::ImportData => add("import_data_action", weights.import_data_action),
+        }
+    }
+    for file in &result.embedded_files {
+        add("embedded_file", weights.embedded_file);
+        if file.executable {
+            add("executable_embedded_file", weights.executable_embedded_file);
+        }
+    }
+    for pdf in &result.embedded_pdfs {
+        add(
+            "embedded_pdf",
+            weights
+                .embedded_pdf
+                .saturating_add(pdf.result.severity_score),
+        );
+    }
+    for annotation in &result.media {
+        add("media_annotation", weights.media_annotation);
+        add(
+            "media_payload",
+            count(
+                annotation
+                    .payloads
+                    .iter()
+                    .filter(|payload| payload.format.is_some())
+                    .count(),
+            ) * weights.media_payload,
+        );
+    }
+    for finding in &result.rule_findings {
+        add("custom_rule", finding.weight);
+    }
+    for found in &result.pattern_matches {
+        add("pattern_rule", found.weight);
+    }
+    add(
+        "yara_match",
+        count(result.yara_matches.len()) * weights.yara_match,
+    );
+    add(
+        "virustotal_detection",
+        count(
+            result
+                .virustotal
+                .iter()
+                .filter(|lookup| lookup.is_detected())
+                .count(),
+        ) * weights.virustotal_detection,
+    );
+    for js_obj in &result.javascript_objects {
+        let ast = &js_obj.ast_findings;
+        add(
+            "dynamic_eval",
+            count(ast.dynamic_eval_patterns) * weights.dynamic_eval,
+        );
+        add(
+            "string_concat_call",
+            count(ast.string_concat_calls) * weights.string_concat_call,
+        );
+        add(
+            "dangerous_js_api",
+            count(js_obj.dangerous_apis.len()) * weights.dangerous_js_api,
+        );
+        if ast.iife_count > 0 {
+            add("iife", weights.iife);
+        }
+        add(
+            "hidden_match",
+            count(js_obj.hidden_matches.len()) * weights.hidden_match,
+        );
+        for call in &ast.acrobat_apis {
+            add(
+                "acrobat_api",
+                call.severity.multiplier() * weights.acrobat_api,
+            );
+        }
+    }
+
+    // A suppressed finding takes its share of its kind's points with it
+    if !result.suppressed_findings.is_empty() {
+        for &(weight, rules) in WEIGHT_RULES {
+            let suppressed = result
+                .suppressed_findings
+                .iter()
+                .filter(|suppressed| rules.contains(&&*suppressed.finding.rule_id))
+                .count();
+            if suppressed == 0 {
+                continue;
+            }
+            let kept = result
+                .findings
+                .iter()
+                .filter(|finding| rules.contains(&&*finding.rule_id))
+                .count();
+            if let Some(total) = totals.get_mut(weight) {
+                *total = (u64::from(*total) * kept as u64 / (kept + suppressed) as u64) as u32;
+            }
+        }
+    }
+
+    let mut contributions: Vec<ScoreContribution> = totals
+        .into_iter()
+        .filter(|&(_, total)| total > 0)
+        .map(|(weight, total)| {
+            let cap = scoring.caps.get(weight).copied().filter(|&cap| cap < total);
+            ScoreContribution {
+                weight: weight.to_string(),
+                points: cap.unwrap_or(total),
+                uncapped_points: cap.map(|_| total),
+            }
+        })
+        .collect();
+    // By name among equals, so that the order doesn't change from run to run
+    contributions.sort_by(|a, b| b.points.cmp(&a.points).then(a.weight.cmp(&b.weight)));
+    let score = contributions
+        .iter()
+        .map(|contribution| contribution.points)
+        .fold(0, u32::saturating_add);
+    let explanation = ScoreExplanation {
+        contributions,
+        uncapped_score: (score > scoring.max_score).then_some(score),
+    };
+    (score.min(scoring.max_score), explanation)
+}
@@ -0,0 +1,72 @@
+use crate::error::SentinelError;
+use aho_corasick::AhoCorasick;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Bytes read from each end of the file.
+const WINDOW_SIZE: u64 = 4096;
+
+/// Byte sequences worth a full analysis, with the indicator reported for each.
+const PATTERNS: [(&[u8], &str); 6] = [
+    (b"/JavaScript", "JavaScript"),
+    (b"/Launch", "Launch action"),
+    (b"eval(", "eval call"),
+    (b"/ObjStm", "object stream"),
+    (b"\x7fELF", "ELF header"),
+    (b"MZ", "MZ header"),
+];
+
+pub struct QuickScreenResult {
+    pub likely_suspicious: bool,
+    pub indicators: Vec<&'static str>,
+}
+
+fn matcher() -> &'static AhoCorasick {
+    static MATCHER: OnceLock<AhoCorasick> = OnceLock::new();
+    MATCHER.get_or_init(|| AhoCorasick::new(PATTERNS.map(|(pattern, _)| pattern)).unwrap())
+}
+
+/// Looks for telltale byte sequences in the first and last 4 KB of a file,
+/// without parsing it. This is a cheap filter for large batches: anything in
+/// compressed streams or the middle of the file is invisible to it, so a
+/// clean screen means "nothing obvious", not "clean". Object streams count
+/// as an indicator because that is where such content tends to hide.
+pub fn quick_screen(path: &Path) -> Result<QuickScreenResult, SentinelError> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+
+    let mut head = Vec::new();
+    let mut tail = Vec::new();
+    if len <= 2 * WINDOW_SIZE {
+        file.read_to_end(&mut head)?;
+    } else {
+        (&mut file).take(WINDOW_SIZE).read_to_end(&mut head)?;
+        file.seek(SeekFrom::End(-(WINDOW_SIZE as i64)))?;
+        file.read_to_end(&mut tail)?;
+    }
+
+    let mut indicators = Vec::new();
+    // Scanned separately so that no match spans the gap between the two
+    for window in [&head, &tail] {
+        for found in matcher().find_iter(window) {
+            let indicator = PATTERNS[found.pattern().as_usize()].1;
+            if !indicators.contains(&indicator) {
+                indicators.push(indicator);
+            }
+        }
+    }
+
+    Ok(QuickScreenResult {
+        likely_suspicious: !indicators.is_empty(),
+        indicators,
+    })
+}
+
+/// Whether a batch should skip `path` without full analysis. A file that
+/// can't be screened is not skipped, so that the full analysis gets to
+/// report the error.
+pub fn skip_after_quick_screen(path: &Path, thorough: bool) -> bool {
+    !thorough && quick_screen(path).is_ok_and(|screen| !screen.likely_suspicious)
+}
@@ -0,0 +1,302 @@
+use crate::FileReport;
+use pdf_sentinel::{Analyzer, SentinelError};
+use serde_json::json;
+use std::fmt::{Display, Write as _};
+use std::io::{self, Cursor, Read};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// How often idle workers check whether Ctrl-C was pressed.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+const JSON: &str = "application/json";
+/// The Prometheus text exposition format.
+const METRICS: &str = "text/plain; version=0.0.4";
+
+/// In the order `/metrics` lists them.
+const CLASSIFICATIONS: [&str; 4] = [
+    "clean",
+    "suspicious",
+    "probable-exploit",
+    "confirmed-malicious",
+];
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+#[derive(Default)]
+struct Metrics {
+    /// Indexed like [`CLASSIFICATIONS`].
+    scans: [AtomicU64; 4],
+    failures: AtomicU64,
+    rejected: AtomicU64,
+    bytes: AtomicU64,
+    duration_nanos: AtomicU64,
+}
+
+impl Metrics {
+    fn render(&self) -> String {
+        let mut out = String::new();
+        let scans: Vec<u64> = self
+            .scans
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect();
+        let failures = self.failures.load(Ordering::Relaxed);
+
+        out.push_str("# HELP pdf_sentinel_scans_total Documents analyzed, by classification.\n");
+        out.push_str("# TYPE pdf_sentinel_scans_total counter\n");
+        for (classification, count) in CLASSIFICATIONS.iter().zip(&scans) {
+            let _ = writeln!(
+                out,
+                "pdf_sentinel_scans_total{{classification=\"{}\"}} {}",
+                classification, count
+            );
+        }
+        let counters = [
+            (
+                "pdf_sentinel_scan_failures_total",
+                "Uploads that could not be parsed as a PDF.",
+                failures,
+            ),
+            (
+                "pdf_sentinel_rejected_requests_total",
+                "Scan requests refused as malformed or too large.",
+                self.rejected.load(Ordering::Relaxed),
+            ),
+            (
+                "pdf_sentinel_scanned_bytes_total",
+                "Bytes of PDF analyzed.",
+                self.bytes.load(Ordering::Relaxed),
+            ),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+        let seconds = self.duration_nanos.load(Ordering::Relaxed) as f64 / 1e9;
+        out.push_str("# HELP pdf_sentinel_scan_duration_seconds Time spent analyzing.\n");
+        out.push_str("# TYPE pdf_sentinel_scan_duration_seconds summary\n");
+        let _ = writeln!(out, "pdf_sentinel_scan_duration_seconds_sum {}", seconds);
+        let _ = writeln!(
+            out,
+            "pdf_sentinel_scan_duration_seconds_count {}",
+            scans.iter().sum::<u64>() + failures
+        );
+        out
+    }
+}
+
+struct State<'a> {
+    analyzer: &'a Analyzer,
+    max_body_size: u64,
+    metrics: Metrics,
+}
+
+/// Serves `POST /scan`, `GET /healthz` and `GET /metrics` on `address` with
+/// `workers` threads until Ctrl-C. Requests already being handled when the
+/// signal arrives are answered before returning.
+///
+/// `/scan` takes the PDF as the raw request body, or as the first file of a
+/// `multipart/form-data` body, and answers with the same JSON record
+/// `--format ndjson` prints for a file.
+pub fn serve(
+    address: &str,
+    analyzer: &Analyzer,
+    max_body_size: u64,
+    workers: usize,
+) -> Result<(), SentinelError> {
+    let server = Server::http(address).map_err(io::Error::other)?;
+    let running = Arc::new(AtomicBool::new(true));
+    let handler_flag = Arc::clone(&running);
+    ctrlc::set_handler(move || handler_flag.store(false, Ordering::SeqCst))
+        .map_err(|err| SentinelError::Io(io::Error::other(err)))?;
+    eprintln!("Listening on {}", address);
+
+    let state = State {
+        analyzer,
+        max_body_size,
+        metrics: Metrics::default(),
+    };
+    std::thread::scope(|scope| {
+        for _ in 0..workers.max(1) {
+            scope.spawn(|| {
+                while running.load(Ordering::SeqCst) {
+                    match server.recv_timeout(POLL_INTERVAL) {
+                        Ok(Some(mut request)) => {
+                            let response = route(&mut request, &state);
+                            if let Err(err) = request.respond(response) {
+                                tracing::warn!(error = %err, "failed to send a response");
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(err) => tracing::error!(error = %err, "failed to accept a request"),
+                    }
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+fn route(request: &mut Request, state: &State) -> HttpResponse {
+    let path = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    match (request.method(), path.as_str()) {
+        (Method::Post, "/scan") => scan(request, state),
+        (Method::Get, "/healthz") => respond(200, JSON, json!({ "status": "ok" }).to_string()),
+        (Method::Get, "/metrics") => respond(200, METRICS, state.metrics.render()),
+        (_, "/scan" | "/healthz" | "/metrics") => error(405, "method not allowed"),
+        _ => error(404, "not found"),
+    }
+}
+
+fn scan(request: &mut Request, state: &State) -> HttpResponse {
+    let rejected = |status, message: &str| {
+        state.metrics.rejected.fetch_add(1, Ordering::Relaxed);
+        error(status, message)
+    };
+    let too_large = format!("the PDF is larger than {} bytes", state.max_body_size);
+    if request
+        .body_length()
+        .is_some_and(|length| length as u64 > state.max_body_size)
+    {
+        return rejected(413, &too_large);
+    }
+    // One byte past the limit tells a body that is too large from one that
+    // is exactly the limit
+    let mut body = Vec::new();
+    let read = request
+        .as_reader()
+        .take(state.max_body_size + 1)
+        .read_to_end(&mut body);
+    if let Err(err) = read {
+        return rejected(400, &err.to_string());
+    }
+    if body.len() as u64 > state.max_body_size {
+        return rejected(413, &too_large);
+    }
+
+    let content_type = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Content-Type"))
+        .map(|header| header.value.as_str().to_string());
+    let (name, data) = match content_type.as_deref().and_then(multipart_boundary) {
+        Some(boundary) => match multipart_file(&body, boundary) {
+            Some((name, data)) => (name, data),
+            None => return rejected(400, "no file in the multipart body"),
+        },
+        None => (None, &body[..]),
+    };
+    if data.is_empty() {
+        return rejected(400, "no PDF in the request body");
+    }
+    let name = name.unwrap_or_else(|| "-".to_string());
+
+    let started = Instant::now();
+    let result = state.analyzer.analyze_bytes(data);
+    let metrics = &state.metrics;
+    metrics
+        .duration_nanos
+        .fetch_add(started.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    metrics
+        .bytes
+        .fetch_add(data.len() as u64, Ordering::Relaxed);
+    match result {
+        Ok(result) => {
+            let classification = result.classification.id();
+            if let Some(index) = CLASSIFICATIONS.iter().position(|&id| id == classification) {
+                metrics.scans[index].fetch_add(1, Ordering::Relaxed);
+            }
+            match serde_json::to_string(&FileReport::new(&name, &result)) {
+                Ok(record) => respond(200, JSON, record),
+                Err(err) => error(500, err),
+            }
+        }
+        Err(err) => {
+            metrics.failures.fetch_add(1, Ordering::Relaxed);
+            error(422, err)
+        }
+    }
+}
+
+/// The boundary of a `multipart/form-data` content type.
+fn multipart_boundary(content_type: &str) -> Option<&str> {
+    let (mime, params) = content_type.split_once(';')?;
+    if !mime.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    params.split(';').find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// The first part of a multipart body that has a file name, or the first
+/// part when none has, with that name.
+fn multipart_file<'b>(body: &'b [u8], boundary: &str) -> Option<(Option<String>, &'b [u8])> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let mut rest = &body[find(body, &delimiter)? + delimiter.len()..];
+    let mut parts = Vec::new();
+    // A delimiter followed by -- closes the body
+    while !rest.starts_with(b"--") {
+        let end = find(rest, &delimiter)?;
+        // The line break before a delimiter is part of it
+        let part = rest[..end].strip_suffix(b"\r\n").unwrap_or(&rest[..end]);
+        let part = part.strip_prefix(b"\r\n").unwrap_or(part);
+        let headers_end = find(part, b"\r\n\r\n")?;
+        let headers = String::from_utf8_lossy(&part[..headers_end]);
+        parts.push((part_filename(&headers), &part[headers_end + 4..]));
+        rest = &rest[end + delimiter.len()..];
+    }
+    let file = parts
+        .iter()
+        .position(|(name, _)| name.is_some())
+        .unwrap_or(0);
+    parts.into_iter().nth(file)
+}
+
+fn part_filename(headers: &str) -> Option<String> {
+    headers
+        .lines()
+        .filter(|line| {
+            line.to_ascii_lowercase()
+                .starts_with("content-disposition:")
+        })
+        .find_map(|line| {
+            line.split(';').find_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                (key.trim() == "filename").then(|| value.trim().trim_matches('"').to_string())
+            })
+        })
+}
+
+fn respond(status: u16, content_type: &str, body: String) -> HttpResponse {
+    let header = Header::from_bytes("Content-Type", content_type).expect("valid header");
+    Response::from_string(body)
+        .with_status_code(status)
+        .with_header(header)
+}
+
+fn error(status: u16, message: impl Display) -> HttpResponse {
+    respond(
+        status,
+        JSON,
+        json!({ "error": message.to_string() }).to_string(),
+    )
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+
+const NOP_SLED_THRESHOLD: usize = 16;
+const REPEATED_PATTERN_THRESHOLD: usize = 32;
+const MAX_PATTERN_LENGTH: usize = 4;
+/// Fewest consecutive `%uXXXX` or `\uXXXX` escapes reported as a payload:
+/// 32 bytes of machine code. Legitimate scripts rarely escape more than a
+/// character or two in a row.
+const MIN_ESCAPED_UNITS: usize = 16;
+
+/// Single-byte fills that show up as padding in ordinary streams.
+const PADDING_BYTES: [u8; 6] = [0x00, 0xFF, b' ', b'\t', b'\r', b'\n'];
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct ShellcodeHeuristicResult {
+    pub nop_sled_detected: bool,
+    pub nop_sled_length: usize,
+    /// Where the longest sled starts in the data checked.
+    pub nop_sled_offset: usize,
+    pub repeated_pattern: Option<Vec<u8>>,
+    pub pattern_run_length: usize,
+    /// Where the repeated pattern's run starts in the data checked.
+    pub pattern_offset: usize,
+    pub escaped_payloads: Vec<EscapedPayload>,
+}
+
+impl ShellcodeHeuristicResult {
+    pub fn is_detected(&self) -> bool {
+        self.nop_sled_detected
+            || self.repeated_pattern.is_some()
+            || !self.escaped_payloads.is_empty()
+    }
+}
+
+/// What a run of Unicode escapes decodes to.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum EscapedPayloadKind {
+    /// A NOP sled, such as `%u9090%u9090...`.
+    NopSled,
+    /// A short pattern repeated, such as the `%u0c0c` blocks heap sprays fill
+    /// memory with.
+    RepeatedBlock,
+    /// Anything else, which at this length is most likely machine code.
+    Encoded,
+}
+
+impl EscapedPayloadKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            EscapedPayloadKind::NopSled => "NOP sled",
+            EscapedPayloadKind::RepeatedBlock => "repeated block",
+            EscapedPayloadKind::Encoded => "encoded code",
+        }
+    }
+}
+
+/// A run of `%uXXXX` (as `unescape` takes) or `\uXXXX` escapes in text.
+#[derive(Deserialize, Serialize)]
+pub struct EscapedPayload {
+    pub kind: EscapedPayloadKind,
+    /// Byte offset of the first escape in the data checked.
+    pub offset: usize,
+    /// Length of the escaped text in bytes.
+    pub length: usize,
+    /// How many bytes the escapes stand for, two per escape.
+    pub decoded_length: usize,
+}
+
+/// Checks raw bytes for NOP sleds and repeated patterns, and, for decoded
+/// streams and scripts that hold their payload as text, runs of Unicode
+/// escapes. Offsets are into `data`, so analysts can go straight to the
+/// payload.
+pub fn check_shellcode_heuristics(data: &[u8]) -> ShellcodeHeuristicResult {
+    let (nop_sled_offset, nop_sled_length) = longest_sled(data);
+    let mut result = ShellcodeHeuristicResult {
+        nop_sled_detected: nop_sled_length >= NOP_SLED_THRESHOLD,
+        nop_sled_length,
+        nop_sled_offset,
+        escaped_payloads: find_escaped_payloads(data),
+        ..Default::default()
+    };
+
+    if let Some((start, period, run_length)) = longest_repeated_run(data) {
+        let pattern = &data[start..start + period];
+        let is_padding =
+            pattern.iter().all(|&b| b == pattern[0]) && PADDING_BYTES.contains(&pattern[0]);
+        if run_length >= REPEATED_PATTERN_THRESHOLD && !is_padding {
+            result.repeated_pattern = Some(pattern.to_vec());
+            result.pattern_run_length = run_length;
+            result.pattern_offset = start;
+        }
+    }
+
+    result
+}
+
+/// Offset and length of the longest run of x86 `NOP` (0x90) or `INT3` (0xCC)
+/// bytes.
+fn longest_sled(data: &[u8]) -> (usize, usize) {
+    let mut longest = (0, 0);
+    let mut current = 0;
+    let mut previous = None;
+
+    for (i, &byte) in data.iter().enumerate() {
+        if (byte == 0x90 || byte == 0xCC) && previous == Some(byte) {
+            current += 1;
+        } else if byte == 0x90 || byte == 0xCC {
+            current = 1;
+        } else {
+            current = 0;
+        }
+        previous = Some(byte);
+        if current > longest.1 {
+            longest = (i + 1 - current, current);
+        }
+    }
+
+    longest
+}
+
+/// Finds runs of at least `MIN_ESCAPED_UNITS` consecutive escapes of one
+/// style, and sorts each by what its little-endian bytes look like.
+fn find_escaped_payloads(data: &[u8]) -> Vec<EscapedPayload> {
+    let mut payloads = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let Some(prefix) = escape_prefix(data, i) else {
+            i += 1;
+            continue;
+        };
+        let start = i;
+        let mut bytes = Vec::new();
+        while let Some(unit) = escaped_unit(data, i, prefix) {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+            i += 6;
+        }
+        if bytes.len() / 2 < MIN_ESCAPED_UNITS {
+            i = start + 1;
+            continue;
+        }
+
+        let kind = if longest_sled(&bytes).1 >= NOP_SLED_THRESHOLD {
+            EscapedPayloadKind::NopSled
+        } else if longest_repeated_run(&bytes)
+            .is_some_and(|(_, _, run_length)| run_length >= REPEATED_PATTERN_THRESHOLD)
+        {
+            EscapedPayloadKind::RepeatedBlock
+        } else {
+            EscapedPayloadKind::Encoded
+        };
+        payloads.push(EscapedPayload {
+            kind,
+            offset: start,
+            length: i - start,
+            decoded_length: bytes.len(),
+        });
+    }
+    payloads
+}
+
+/// `%` or `\` when a `u` escape starts at `at`.
+fn escape_prefix(data: &[u8], at: usize) -> Option<u8> {
+    let prefix = *data.get(at)?;
+    ((prefix == b'%' || prefix == b'\\') && escaped_unit(data, at, prefix).is_some())
+        .then_some(prefix)
+}
+
+fn escaped_unit(data: &[u8], at: usize, prefix: u8) -> Option<u16> {
+    let escape = data.get(at..at + 6)?;
+    if escape[0] != prefix || escape[1] != b'u' {
+        return None;
+    }
+    let digits = std::str::from_utf8(&escape[2..]).ok()?;
+    if !digits.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+        return None;
+    }
+    u16::from_str_radix(digits, 16).ok()
+}
+
+/// Finds the longest span made of a repeated sequence of at most
+/// `MAX_PATTERN_LENGTH` bytes, returning `(start, period, length)`. Shorter
+/// periods win ties so that a run of one byte is not reported as a pair.
+fn longest_repeated_run(data: &[u8]) -> Option<(usize, usize, usize)> {
+    let mut best: Option<(usize, usize, usize)> = None;
+
+    for period in 1..=MAX_PATTERN_LENGTH {
+        if data.len() < period * 2 {
+            break;
+        }
+        let mut run_start = 0;
+        let mut matches = 0;
+        for i in period..=data.len() {
+            if i < data.len() && data[i] == data[i - period] {
+                if matches == 0 {
+                    run_start = i - period;
+                }
+                matches += 1;
+                continue;
+            }
+            let length = matches + period;
+            if matches >= period && best.is_none_or(|(_, _, best_len)| length > best_len) {
+                best = Some((run_start, period, length));
+            }
+            matches = 0;
+        }
+    }
+
+    best
+}
@@ -0,0 +1,113 @@
+use aho_corasick::AhoCorasick;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Signatures reported per stream; a stream full of them is one finding's
+/// worth of evidence.
+const MAX_SIGNATURES_PER_STREAM: usize = 8;
+
+/// Magic numbers of executables and containers, with the kind each marks.
+const SIGNATURES: [(&[u8], SignatureKind); 6] = [
+    (b"MZ", SignatureKind::Pe),
+    (b"\x7fELF", SignatureKind::Elf),
+    (b"PK\x03\x04", SignatureKind::Zip),
+    (b"Rar!\x1a\x07\x00", SignatureKind::Rar),
+    (b"Rar!\x1a\x07\x01\x00", SignatureKind::Rar),
+    (b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1", SignatureKind::OleCfb),
+];
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum SignatureKind {
+    /// A Windows executable: an `MZ` header whose `e_lfanew` leads to a
+    /// `PE\0\0` header.
+    Pe,
+    Elf,
+    Zip,
+    Rar,
+    /// An OLE compound file, the container of legacy Office documents and
+    /// their macros.
+    OleCfb,
+}
+
+impl SignatureKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            SignatureKind::Pe => "PE executable",
+            SignatureKind::Elf => "ELF executable",
+            SignatureKind::Zip => "ZIP archive",
+            SignatureKind::Rar => "RAR archive",
+            SignatureKind::OleCfb => "OLE compound file",
+        }
+    }
+
+    pub fn is_executable(&self) -> bool {
+        matches!(self, SignatureKind::Pe | SignatureKind::Elf)
+    }
+
+    pub fn rule_id(&self) -> &'static str {
+        if self.is_executable() {
+            "PS057"
+        } else {
+            "PS058"
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct EmbeddedSignature {
+    /// The stream holding it.
+    pub object_id: u32,
+    /// Offset in the decoded stream content.
+    pub offset: usize,
+    pub kind: SignatureKind,
+}
+
+fn matcher() -> &'static AhoCorasick {
+    static MATCHER: OnceLock<AhoCorasick> = OnceLock::new();
+    MATCHER.get_or_init(|| AhoCorasick::new(SIGNATURES.map(|(magic, _)| magic)).unwrap())
+}
+
+/// Looks through decoded stream content for executables and archives,
+/// wherever in the stream they start. Payloads are smuggled in image data,
+/// attachments and content streams alike, often after a harmless prefix, so
+/// matching only at offset 0 would miss most of them. `MZ` and `\x7fELF`
+/// only count with a plausible header after them, since two or four bytes
+/// turn up by chance in any large binary stream.
+pub fn find_embedded_signatures<'a>(
+    streams: impl IntoIterator<Item = (u32, &'a [u8])>,
+) -> Vec<EmbeddedSignature> {
+    let mut found = Vec::new();
+    for (object_id, content) in streams {
+        let signatures = matcher()
+            .find_overlapping_iter(content)
+            .map(|hit| (hit.start(), SIGNATURES[hit.pattern().as_usize()].1))
+            .filter(|&(offset, kind)| has_valid_header(&content[offset..], kind))
+            .take(MAX_SIGNATURES_PER_STREAM);
+        for (offset, kind) in signatures {
+            found.push(EmbeddedSignature {
+                object_id,
+                offset,
+                kind,
+            });
+        }
+    }
+    found.sort_by_key(|signature| (signature.object_id, signature.offset));
+    found
+}
+
+fn has_valid_header(data: &[u8], kind: SignatureKind) -> bool {
+    match kind {
+        SignatureKind::Pe => data
+            .get(0x3c..0x40)
+            .map(|e_lfanew| {
+                u32::from_le_bytes([e_lfanew[0], e_lfanew[1], e_lfanew[2], e_lfanew[3]])
+            })
+            .and_then(|e_lfanew| data.get(e_lfanew as usize..)?.get(..4))
+            .is_some_and(|header| header == b"PE\0\0"),
+        // 32 or 64-bit, little or big-endian
+        SignatureKind::Elf => data
+            .get(4..6)
+            .is_some_and(|ident| matches!(ident[0], 1 | 2) && matches!(ident[1], 1 | 2)),
+        SignatureKind::Zip | SignatureKind::Rar | SignatureKind::OleCfb => true,
+    }
+}
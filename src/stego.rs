@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+
+/// Below this many samples the statistic is too noisy to mean anything.
+const MIN_SAMPLES: usize = 1024;
+/// Pairs whose expected count is smaller than this are left out of the test.
+const MIN_EXPECTED: f64 = 5.0;
+const SIGNIFICANCE: f64 = 0.05;
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct SteganographyHint {
+    pub chi_square: f64,
+    pub p_value: f64,
+    pub suspected: bool,
+}
+
+/// Pairs-of-values chi-square test (Westfeld & Pfitzmann) on 8-bit samples.
+///
+/// Overwriting least-significant bits with payload evens out the counts of
+/// each value pair `2k`/`2k + 1`, which in natural images usually differ. The
+/// returned `p_value` is the lower-tail probability of seeing pairs at least
+/// this even, so a small value suggests LSB embedding.
+pub fn check_image_steganography(stream_data: &[u8], color_depth: u8) -> SteganographyHint {
+    if color_depth != 8 || stream_data.len() < MIN_SAMPLES {
+        return SteganographyHint::default();
+    }
+
+    let mut histogram = [0u64; 256];
+    for &byte in stream_data {
+        histogram[byte as usize] += 1;
+    }
+
+    let mut chi_square = 0.0;
+    let mut pairs = 0;
+    for pair in histogram.chunks(2) {
+        let expected = (pair[0] + pair[1]) as f64 / 2.0;
+        if expected < MIN_EXPECTED {
+            continue;
+        }
+        let observed = pair[0] as f64;
+        chi_square += (observed - expected).powi(2) / expected;
+        pairs += 1;
+    }
+
+    if pairs < 2 {
+        return SteganographyHint::default();
+    }
+
+    let degrees_of_freedom = (pairs - 1) as f64;
+    let p_value = chi_square_cdf(chi_square, degrees_of_freedom);
+    SteganographyHint {
+        chi_square,
+        p_value,
+        suspected: p_value < SIGNIFICANCE,
+    }
+}
+
+fn chi_square_cdf(x: f64, degrees_of_freedom: f64) -> f64 {
+    regularized_lower_gamma(degrees_of_freedom / 2.0, x / 2.0)
+}
+
+/// P(a, x), using the series expansion below `a + 1` and a continued
+/// fraction above it.
+fn regularized_lower_gamma(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let log_prefix = a * x.ln() - x - ln_gamma(a);
+
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        let mut n = a;
+        for _ in 0..500 {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+            if term.abs() < sum.abs() * 1e-15 {
+                break;
+            }
+        }
+        (sum * log_prefix.exp()).min(1.0)
+    } else {
+        // Lentz's method for the upper incomplete gamma continued fraction
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..500 {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < 1e-15 {
+                break;
+            }
+        }
+        (1.0 - log_prefix.exp() * h).max(0.0)
+    }
+}
+
+/// Lanczos approximation of ln Γ(x) for x > 0.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.001_208_650_973_866_179,
+        -0.000_005_395_239_384_953,
+    ];
+    let tmp = x + 5.5 - (x + 0.5) * (x + 5.5).ln();
+    let mut series = 1.000_000_000_190_015;
+    let mut y = x;
+    for coefficient in COEFFICIENTS {
+        y += 1.0;
+        series += coefficient / y;
+    }
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
@@ -0,0 +1,216 @@
+use chrono::{SecondsFormat, Utc};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::attachments::EmbeddedFile;
+use crate::classification::ThreatClassification;
+use crate::error::SentinelError;
+use crate::urls::split_url;
+use crate::AnalysisResult;
+
+/// MITRE ATT&CK technique for a user opening a malicious file, which is what
+/// a /Launch action relies on.
+const USER_EXECUTION_TECHNIQUE: &str = "T1204.002";
+
+/// Builds a STIX 2.1 bundle from analysis results, keyed by file name: a
+/// `file` object per document, a `malware-analysis` with its verdict and
+/// severity, the URLs it links to, and, for documents that aren't clean,
+/// `indicator` objects for those URLs and for the hashes of the document and
+/// its payloads.
+///
+/// A bundle carries only `type`, `id` and `objects` in 2.1, so the
+/// `spec_version` and `created` timestamp live on each object inside it.
+pub fn to_stix_bundle(results: &[(&str, &AnalysisResult)]) -> Result<String, SentinelError> {
+    let created = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    let mut objects = Vec::new();
+    let mut attack_pattern_id = None;
+
+    for &(file, result) in results {
+        let file_id = stix_id("file");
+        let mut file_object = json!({
+            "type": "file",
+            "spec_version": "2.1",
+            "id": file_id,
+            "name": file,
+        });
+        if let Some(size) = result.file_size {
+            file_object["size"] = json!(size);
+        }
+        let hashes = [
+            ("MD5", &result.md5),
+            ("SHA-1", &result.sha1),
+            ("SHA-256", &result.sha256),
+        ];
+        for (algorithm, hash) in hashes {
+            if let Some(hash) = hash {
+                file_object["hashes"][algorithm] = json!(hash);
+            }
+        }
+        objects.push(file_object);
+
+        let verdict = analysis_result(&result.classification);
+        objects.push(json!({
+            "type": "malware-analysis",
+            "spec_version": "2.1",
+            "id": stix_id("malware-analysis"),
+            "created": created,
+            "modified": created,
+            "product": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "result_name": result.classification.label(),
+            "result": verdict,
+            "sample_ref": file_id,
+            "x_pdf_sentinel_severity": result.severity.label(),
+            "x_pdf_sentinel_severity_score": result.severity_score,
+            "x_pdf_sentinel_indicators": result.classification.indicators(),
+        }));
+
+        if verdict != "benign" {
+            let indicator_type = match verdict {
+                "malicious" => "malicious-activity",
+                _ => "anomalous-activity",
+            };
+            let mut indicator = |name: String, pattern: String| {
+                objects.push(json!({
+                    "type": "indicator",
+                    "spec_version": "2.1",
+                    "id": stix_id("indicator"),
+                    "created": created,
+                    "modified": created,
+                    "name": name,
+                    "indicator_types": [indicator_type],
+                    "pattern": pattern,
+                    "pattern_type": "stix",
+                    "valid_from": created,
+                }));
+            };
+            if let Some(sha256) = &result.sha256 {
+                indicator(file.to_string(), sha256_pattern(sha256));
+            }
+            for attachment in &result.embedded_files {
+                if let Some(sha256) = &attachment.sha256 {
+                    indicator(
+                        format!("{} embedded in {}", attachment_name(attachment), file),
+                        sha256_pattern(sha256),
+                    );
+                }
+            }
+            for payload in result.media.iter().flat_map(|media| &media.payloads) {
+                if let Some(sha256) = &payload.sha256 {
+                    indicator(
+                        format!("Media payload in object {} of {}", payload.object_id, file),
+                        sha256_pattern(sha256),
+                    );
+                }
+            }
+            for uri in &result.uris {
+                indicator(
+                    format!("URL linked from {}", file),
+                    format!("[url:value = '{}']", escape_pattern(uri)),
+                );
+            }
+        }
+
+        for uri in &result.uris {
+            let url_id = stix_id("url");
+            objects.push(json!({
+                "type": "url",
+                "spec_version": "2.1",
+                "id": url_id,
+                "value": uri,
+            }));
+            objects.push(relationship("related-to", &file_id, &url_id, &created));
+
+            if let Some((scheme, host)) = split_url(uri) {
+                let domain_id = stix_id("domain-name");
+                let traffic_id = stix_id("network-traffic");
+                objects.push(json!({
+                    "type": "domain-name",
+                    "spec_version": "2.1",
+                    "id": domain_id,
+                    "value": host,
+                }));
+                objects.push(json!({
+                    "type": "network-traffic",
+                    "spec_version": "2.1",
+                    "id": traffic_id,
+                    "dst_ref": domain_id,
+                    "protocols": [scheme],
+                }));
+                objects.push(relationship("related-to", &url_id, &traffic_id, &created));
+            }
+        }
+
+        if !result.launch_actions.is_empty() {
+            let pattern_id = attack_pattern_id.get_or_insert_with(|| {
+                let id = stix_id("attack-pattern");
+                objects.push(json!({
+                    "type": "attack-pattern",
+                    "spec_version": "2.1",
+                    "id": id,
+                    "created": created,
+                    "modified": created,
+                    "name": "User Execution: Malicious File",
+                    "external_references": [{
+                        "source_name": "mitre-attack",
+                        "external_id": USER_EXECUTION_TECHNIQUE,
+                        "url": "https://attack.mitre.org/techniques/T1204/002/",
+                    }],
+                }));
+                id
+            });
+            objects.push(relationship("related-to", &file_id, pattern_id, &created));
+        }
+    }
+
+    let bundle = json!({
+        "type": "bundle",
+        "id": stix_id("bundle"),
+        "objects": objects,
+    });
+    Ok(serde_json::to_string_pretty(&bundle)?)
+}
+
+fn sha256_pattern(sha256: &str) -> String {
+    format!("[file:hashes.'SHA-256' = '{}']", escape_pattern(sha256))
+}
+
+fn attachment_name(attachment: &EmbeddedFile) -> String {
+    match &attachment.name {
+        Some(name) => name.clone(),
+        None => format!("Object {}", attachment.object_id),
+    }
+}
+
+/// Escapes a string for a quoted literal in a STIX pattern.
+fn escape_pattern(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+/// Maps onto the STIX `malware-result-ov` vocabulary.
+fn analysis_result(classification: &ThreatClassification) -> &'static str {
+    match classification {
+        ThreatClassification::Clean => "benign",
+        ThreatClassification::Suspicious(_) => "suspicious",
+        ThreatClassification::ProbableExploit(_) | ThreatClassification::ConfirmedMalicious(_) => {
+            "malicious"
+        }
+    }
+}
+
+fn stix_id(object_type: &str) -> String {
+    format!("{}--{}", object_type, Uuid::new_v4())
+}
+
+fn relationship(relationship_type: &str, source: &str, target: &str, created: &str) -> Value {
+    json!({
+        "type": "relationship",
+        "spec_version": "2.1",
+        "id": stix_id("relationship"),
+        "created": created,
+        "modified": created,
+        "relationship_type": relationship_type,
+        "source_ref": source,
+        "target_ref": target,
+    })
+}
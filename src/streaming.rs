@@ -0,0 +1,304 @@
+use flate2::read::ZlibDecoder;
+use md5::Md5;
+use regex::bytes::Regex;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::baseline::baselined_result;
+use crate::classification::classify;
+use crate::encoding::{name_pattern, normalize_pdf_string};
+use crate::encryption::EncryptionInfo;
+use crate::error::SentinelError;
+use crate::findings::collect_findings;
+use crate::framing::check_framing;
+use crate::patterns::PatternTarget;
+use crate::scoring::calculate_severity_score;
+use crate::suppressions::apply_suppressions;
+use crate::xref::count_xref_sections;
+use crate::{known_malicious_result, push_stream_match, to_hex, AnalysisResult, CompiledConfig};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Bytes held back at the end of each chunk so that a header or keyword split
+/// across two reads is still seen whole.
+const OVERLAP: usize = 64;
+/// Streams beyond this size are skipped rather than buffered, so one huge
+/// image can't undo the point of streaming.
+const MAX_INLINE_STREAM: usize = 16 * 1024 * 1024;
+
+/// Single pass over the raw file that never builds the object graph.
+///
+/// References can't be resolved this way, but object headers, action names
+/// (including those packed inside compressed object streams), stream content,
+/// cross-reference sections and the file size can all be checked. It is meant
+/// as a cheap pre-filter before `analyze_pdf_file` on very large inputs.
+pub fn analyze_pdf_streaming(
+    path: &Path,
+    config: &CompiledConfig,
+) -> Result<AnalysisResult, SentinelError> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut md5 = Md5::new();
+    let mut sha1 = Sha1::new();
+    let mut scanner = Scanner::new(config);
+    let mut chunk = vec![0; CHUNK_SIZE];
+    let mut file_size = 0u64;
+
+    loop {
+        let read = file.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        file_size += read as u64;
+        hasher.update(&chunk[..read]);
+        md5.update(&chunk[..read]);
+        sha1.update(&chunk[..read]);
+        scanner.feed(&chunk[..read]);
+    }
+    scanner.finish();
+
+    let sha256 = to_hex(&hasher.finalize());
+    let mut result = if config.config.known_bad_hashes.contains(&sha256) {
+        known_malicious_result(sha256)
+    } else if let Some(entry) = config.config.baseline.find(&sha256) {
+        baselined_result(sha256, entry)
+    } else {
+        scanner.into_result(sha256, file_size, None)
+    };
+    result.md5 = Some(to_hex(&md5.finalize()));
+    result.sha1 = Some(to_hex(&sha1.finalize()));
+    result.file_size = Some(file_size);
+    Ok(result)
+}
+
+/// The same scan over a file already in memory, for encrypted documents that
+/// couldn't be decrypted or loaded. `encryption` is reported with the result.
+pub(crate) fn analyze_pdf_raw(
+    data: &[u8],
+    sha256: String,
+    encryption: EncryptionInfo,
+    config: &CompiledConfig,
+) -> AnalysisResult {
+    let mut scanner = Scanner::new(config);
+    scanner.feed(data);
+    scanner.finish();
+    scanner.result.framing = check_framing(data);
+    scanner.into_result(sha256, data.len() as u64, Some(encryption))
+}
+
+enum State {
+    Objects,
+    /// Inside `stream ... endstream`; `None` once the content outgrew
+    /// `MAX_INLINE_STREAM` and is only being skipped.
+    Stream(Option<Vec<u8>>),
+}
+
+struct Scanner<'a> {
+    config: &'a CompiledConfig,
+    header: Regex,
+    stream_keyword: Regex,
+    javascript: Regex,
+    auto_action: Regex,
+    obj_stm: Regex,
+    buffer: Vec<u8>,
+    state: State,
+    object_ids: HashSet<u32>,
+    /// The last object header seen, which a following stream belongs to.
+    current_object: Option<u32>,
+    result: AnalysisResult,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(config: &'a CompiledConfig) -> Self {
+        Scanner {
+            config,
+            header: Regex::new(r"(?-u)[^0-9](\d+)[\x00\t\n\x0c\r ]+\d+[\x00\t\n\x0c\r ]+obj")
+                .unwrap(),
+            stream_keyword: Regex::new(r"(?-u)[^d]stream(?:\r\n|\r|\n)").unwrap(),
+            javascript: name_regex(&["JavaScript", "JS"]),
+            auto_action: name_regex(&["OpenAction", "AA"]),
+            obj_stm: Regex::new(&format!(
+                r"(?-u){}[\x00\t\n\x0c\r ]*{}\b",
+                name_pattern("Type"),
+                name_pattern("ObjStm")
+            ))
+            .unwrap(),
+            buffer: Vec::new(),
+            state: State::Objects,
+            object_ids: HashSet::new(),
+            current_object: None,
+            result: AnalysisResult::default(),
+        }
+    }
+
+    fn into_result(
+        self,
+        sha256: String,
+        file_size: u64,
+        encryption: Option<EncryptionInfo>,
+    ) -> AnalysisResult {
+        let mut result = self.result;
+        result.sha256 = Some(sha256);
+        #[cfg(feature = "virustotal")]
+        if let Some(virustotal) = &self.config.virustotal {
+            result.virustotal = virustotal.look_up(&result);
+        }
+        let config = &self.config.config;
+        result.encryption = encryption;
+        result.large_file_size = file_size > config.file_size_threshold;
+        result.object_statistics.total_objects = self.object_ids.len();
+        (result.severity_score, result.explanation) =
+            calculate_severity_score(&result, &config.scoring);
+        result.severity = config.scoring.bands.severity(result.severity_score);
+        result.classification = classify(&result);
+        result.findings = collect_findings(&result, None);
+        apply_suppressions(&mut result, config);
+        result
+    }
+
+    fn feed(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+        self.drain(false);
+    }
+
+    fn finish(&mut self) {
+        self.drain(true);
+        // A truncated file can end inside a stream
+        if let State::Stream(Some(content)) = std::mem::replace(&mut self.state, State::Objects) {
+            self.scan_stream(&content);
+        }
+    }
+
+    fn drain(&mut self, at_eof: bool) {
+        loop {
+            let limit = if at_eof {
+                self.buffer.len()
+            } else {
+                self.buffer.len().saturating_sub(OVERLAP)
+            };
+
+            match &mut self.state {
+                State::Objects => {
+                    let stream_start = self
+                        .stream_keyword
+                        .find(&self.buffer)
+                        .filter(|keyword| keyword.start() < limit)
+                        .map(|keyword| (keyword.start(), keyword.end()));
+                    let Some((start, data_start)) = stream_start else {
+                        self.scan_objects(limit);
+                        self.buffer.drain(..limit);
+                        return;
+                    };
+                    self.scan_objects(start);
+                    self.buffer.drain(..data_start);
+                    self.state = State::Stream(Some(Vec::new()));
+                }
+                State::Stream(content) => {
+                    let end = find(&self.buffer, b"endstream");
+                    let take = match end {
+                        Some(end) => end,
+                        None if at_eof => self.buffer.len(),
+                        None => self.buffer.len().saturating_sub(b"endstream".len() - 1),
+                    };
+                    if let Some(buffered) = content {
+                        if buffered.len() + take > MAX_INLINE_STREAM {
+                            tracing::debug!(
+                                limit = MAX_INLINE_STREAM,
+                                "stream too large to buffer, skipping its content"
+                            );
+                            *content = None;
+                        } else {
+                            buffered.extend_from_slice(&self.buffer[..take]);
+                        }
+                    }
+                    let Some(end) = end else {
+                        self.buffer.drain(..take);
+                        return;
+                    };
+                    self.buffer.drain(..end + b"endstream".len());
+                    self.result.object_statistics.stream_objects += 1;
+                    if let State::Stream(Some(content)) =
+                        std::mem::replace(&mut self.state, State::Objects)
+                    {
+                        self.scan_stream(&content);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Scans plain object text for matches that start before `end`; the
+    /// bytes after it are still there for matches that run across the cut.
+    fn scan_objects(&mut self, end: usize) {
+        let text = &self.buffer[..];
+        for captures in self.header.captures_iter(text) {
+            if captures.get(0).map_or(end, |m| m.start()) >= end {
+                break;
+            }
+            if let Some(id) = captures
+                .get(1)
+                .and_then(|id| std::str::from_utf8(id.as_bytes()).ok())
+                .and_then(|id| id.parse().ok())
+            {
+                self.object_ids.insert(id);
+                self.current_object = Some(id);
+            }
+        }
+
+        let obj_stm_objects = count_before(&self.obj_stm, text, end);
+        self.result.object_statistics.obj_stm_objects += obj_stm_objects;
+        self.result.has_obj_stm |= obj_stm_objects > 0;
+        self.result.has_javascript |= count_before(&self.javascript, text, end) > 0;
+        self.result.has_auto_action |= count_before(&self.auto_action, text, end) > 0;
+        self.result.xref_sections += count_xref_sections(&text[..end]);
+    }
+
+    fn scan_stream(&mut self, content: &[u8]) {
+        let mut decompressed = Vec::new();
+        let data = match ZlibDecoder::new(content)
+            .take(MAX_INLINE_STREAM as u64)
+            .read_to_end(&mut decompressed)
+        {
+            Ok(_) => &decompressed[..],
+            Err(_) => content,
+        };
+
+        // Object streams hide action dictionaries behind compression
+        if self.javascript.is_match(data) {
+            self.result.has_javascript = true;
+            self.result.object_statistics.js_objects += 1;
+        }
+        self.result.has_auto_action |= self.auto_action.is_match(data);
+
+        let content = normalize_pdf_string(data);
+        if let (Some(object_id), Some(hit)) = (
+            self.current_object,
+            self.config
+                .suspicious_patterns
+                .find(PatternTarget::Streams, &content),
+        ) {
+            push_stream_match(&mut self.result, object_id, hit);
+        }
+    }
+}
+
+/// Matches any of `names`, however their characters are escaped.
+pub(crate) fn name_regex(names: &[&str]) -> Regex {
+    let alternatives: Vec<String> = names.iter().map(|name| name_pattern(name)).collect();
+    Regex::new(&format!(r"(?-u)(?:{})\b", alternatives.join("|"))).unwrap()
+}
+
+fn count_before(re: &Regex, text: &[u8], end: usize) -> usize {
+    re.find_iter(text)
+        .take_while(|found| found.start() < end)
+        .count()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
@@ -0,0 +1,191 @@
+use crate::resolve;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+const MAX_PAGE_TREE_DEPTH: usize = 64;
+
+#[derive(Deserialize, Serialize)]
+pub struct StructureViolation {
+    pub rule: String,
+    pub object_id: Option<u32>,
+    pub detail: String,
+}
+
+impl StructureViolation {
+    fn new(rule: &str, object_id: Option<u32>, detail: String) -> Self {
+        StructureViolation {
+            rule: rule.to_string(),
+            object_id,
+            detail,
+        }
+    }
+}
+
+/// Checks page tree invariants the PDF specification requires: kids resolve
+/// to page tree nodes, fonts referenced by pages exist, `/Parent` chains end
+/// at the root, and `/Count` matches the pages below each node. Writers get
+/// these right as a matter of course, so violations point at files built by
+/// hand or mangled on purpose.
+pub fn validate_structure(doc: &Document) -> Vec<StructureViolation> {
+    let mut violations = Vec::new();
+
+    let Some(root) = doc
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"Pages").ok())
+        .and_then(|pages| pages.as_reference().ok())
+    else {
+        violations.push(StructureViolation::new(
+            "page-tree",
+            None,
+            "catalog has no /Pages reference".to_string(),
+        ));
+        return violations;
+    };
+
+    let mut visited = HashSet::new();
+    walk_page_tree(doc, root, None, 0, &mut visited, &mut violations);
+    violations
+}
+
+/// Returns the number of pages at or below `id`.
+fn walk_page_tree(
+    doc: &Document,
+    id: ObjectId,
+    inherited_resources: Option<&Dictionary>,
+    depth: usize,
+    visited: &mut HashSet<ObjectId>,
+    violations: &mut Vec<StructureViolation>,
+) -> i64 {
+    if depth > MAX_PAGE_TREE_DEPTH || !visited.insert(id) {
+        violations.push(StructureViolation::new(
+            "page-tree",
+            Some(id.0),
+            "node is reachable more than once or nested too deeply".to_string(),
+        ));
+        return 0;
+    }
+    let Ok(node) = doc.get_dictionary(id) else {
+        violations.push(StructureViolation::new(
+            "kids",
+            Some(id.0),
+            "page tree node is missing or not a dictionary".to_string(),
+        ));
+        return 0;
+    };
+    check_parent_chain(doc, id, violations);
+
+    let resources = node
+        .get(b"Resources")
+        .ok()
+        .and_then(|resources| resolve_dict(doc, resources))
+        .or(inherited_resources);
+
+    match node.get(b"Type").and_then(|kind| kind.as_name()) {
+        Ok(b"Page") => {
+            check_fonts(doc, id, resources, violations);
+            1
+        }
+        Ok(b"Pages") => {
+            let kids = node
+                .get(b"Kids")
+                .and_then(|kids| kids.as_array())
+                .map(|kids| kids.as_slice())
+                .unwrap_or_default();
+            let mut pages = 0;
+            for kid in kids {
+                match kid.as_reference() {
+                    Ok(kid) => {
+                        pages += walk_page_tree(doc, kid, resources, depth + 1, visited, violations)
+                    }
+                    Err(_) => violations.push(StructureViolation::new(
+                        "kids",
+                        Some(id.0),
+                        "/Kids entry is not an indirect reference".to_string(),
+                    )),
+                }
+            }
+
+            match node.get(b"Count").and_then(|count| count.as_i64()) {
+                Ok(count) if count == pages => {}
+                Ok(count) => violations.push(StructureViolation::new(
+                    "count",
+                    Some(id.0),
+                    format!("/Count is {} but {} pages descend from it", count, pages),
+                )),
+                Err(_) => violations.push(StructureViolation::new(
+                    "count",
+                    Some(id.0),
+                    "/Pages node has no /Count".to_string(),
+                )),
+            }
+            pages
+        }
+        _ => {
+            violations.push(StructureViolation::new(
+                "kids",
+                Some(id.0),
+                "page tree node is neither /Page nor /Pages".to_string(),
+            ));
+            0
+        }
+    }
+}
+
+/// Follows `/Parent` upwards; a chain that revisits a node never reaches the
+/// root.
+fn check_parent_chain(doc: &Document, id: ObjectId, violations: &mut Vec<StructureViolation>) {
+    let mut seen = HashSet::from([id]);
+    let mut current = id;
+    while let Some(parent) = doc
+        .get_dictionary(current)
+        .ok()
+        .and_then(|node| node.get(b"Parent").ok())
+        .and_then(|parent| parent.as_reference().ok())
+    {
+        if !seen.insert(parent) || seen.len() > MAX_PAGE_TREE_DEPTH {
+            violations.push(StructureViolation::new(
+                "parent",
+                Some(id.0),
+                format!("/Parent chain loops back to object {}", parent.0),
+            ));
+            return;
+        }
+        current = parent;
+    }
+}
+
+fn check_fonts(
+    doc: &Document,
+    page_id: ObjectId,
+    resources: Option<&Dictionary>,
+    violations: &mut Vec<StructureViolation>,
+) {
+    let Some(fonts) = resources
+        .and_then(|resources| resources.get(b"Font").ok())
+        .and_then(|fonts| resolve_dict(doc, fonts))
+    else {
+        return;
+    };
+
+    for (name, font) in fonts.iter() {
+        if let Ok(font_id) = font.as_reference() {
+            if doc.get_object(font_id).is_err() {
+                violations.push(StructureViolation::new(
+                    "font",
+                    Some(page_id.0),
+                    format!(
+                        "font /{} references missing object {}",
+                        String::from_utf8_lossy(name),
+                        font_id.0
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+fn resolve_dict<'a>(doc: &'a Document, object: &'a Object) -> Option<&'a Dictionary> {
+    resolve(doc, object)?.as_dict().ok()
+}
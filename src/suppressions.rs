@@ -0,0 +1,112 @@
+//! Findings accepted as benign, such as the form validation scripts of a
+//! company's own invoices. A suppression names a rule and optionally the
+//! object it fires on, or the SHA-256 of a file whose findings are all
+//! accepted, or both. Suppressed findings stop counting toward the severity
+//! score but stay in the result, apart from the others, so that what was
+//! waved through can still be reviewed.
+
+use crate::findings::Finding;
+use crate::scoring::calculate_severity_score;
+use crate::{load_config_file, AnalysisResult, Config, SentinelError};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// One entry of `Config::suppressions`. Every field given must match; an
+/// entry with neither `rule` nor `sha256` matches nothing.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Suppression {
+    /// A finding rule id, e.g. `PS002`.
+    pub rule: Option<String>,
+    /// The object the finding is about. Findings about the whole document
+    /// have none, so this only narrows `rule`.
+    pub object: Option<u32>,
+    /// The SHA-256 of the file, in hex.
+    pub sha256: Option<String>,
+    /// Why the finding is benign, shown with it.
+    pub reason: Option<String>,
+}
+
+impl Suppression {
+    pub fn matches(&self, finding: &Finding, sha256: Option<&str>) -> bool {
+        if self.rule.is_none() && self.sha256.is_none() {
+            return false;
+        }
+        let rule = self
+            .rule
+            .as_deref()
+            .is_none_or(|rule| rule.eq_ignore_ascii_case(&finding.rule_id));
+        let object = self
+            .object
+            .is_none_or(|object| finding.object_id == Some(object));
+        let file = self.sha256.as_deref().is_none_or(|expected| {
+            sha256.is_some_and(|sha256| sha256.eq_ignore_ascii_case(expected))
+        });
+        rule && object && file
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct SuppressedFinding {
+    pub finding: Finding,
+    /// The `reason` of the suppression that matched.
+    pub reason: Option<String>,
+}
+
+/// A suppressions file: `[[suppress]]` tables in TOML, or a `suppress`
+/// array in JSON.
+#[derive(Deserialize)]
+struct SuppressionFile {
+    #[serde(default)]
+    suppress: Vec<Suppression>,
+}
+
+/// Reads a suppressions file, e.g. for `--suppressions`. An entry that
+/// could never match, or that gives an object without a rule, is an error
+/// rather than a silently ignored line.
+pub fn load_suppressions(path: &Path) -> Result<Vec<Suppression>, SentinelError> {
+    let file: SuppressionFile = load_config_file(path)?;
+    for (index, suppression) in file.suppress.iter().enumerate() {
+        let problem = if suppression.rule.is_none() && suppression.sha256.is_none() {
+            "needs a rule or a sha256"
+        } else if suppression.object.is_some() && suppression.rule.is_none() {
+            "gives an object but no rule"
+        } else {
+            continue;
+        };
+        return Err(SentinelError::Config(
+            path.to_path_buf(),
+            format!("suppression {} {}", index + 1, problem),
+        ));
+    }
+    Ok(file.suppress)
+}
+
+/// Moves the findings that a suppression matches out of `result.findings`,
+/// then scores the document again without them.
+pub(crate) fn apply_suppressions(result: &mut AnalysisResult, config: &Config) {
+    if config.suppressions.is_empty() {
+        return;
+    }
+    let sha256 = result.sha256.clone();
+    let mut kept = Vec::new();
+    for finding in std::mem::take(&mut result.findings) {
+        let suppression = config
+            .suppressions
+            .iter()
+            .find(|suppression| suppression.matches(&finding, sha256.as_deref()));
+        match suppression {
+            Some(suppression) => result.suppressed_findings.push(SuppressedFinding {
+                finding,
+                reason: suppression.reason.clone(),
+            }),
+            None => kept.push(finding),
+        }
+    }
+    result.findings = kept;
+    if result.suppressed_findings.is_empty() {
+        return;
+    }
+    (result.severity_score, result.explanation) = calculate_severity_score(result, &config.scoring);
+    result.severity = config.scoring.bands.severity(result.severity_score);
+}
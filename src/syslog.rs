@@ -0,0 +1,269 @@
+use chrono::{SecondsFormat, Utc};
+use clap::ValueEnum;
+use pdf_sentinel::classification::ThreatClassification;
+use pdf_sentinel::findings::triggered_rules;
+use pdf_sentinel::scoring::Severity;
+use pdf_sentinel::{AnalysisResult, SentinelError};
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The `user` facility; receivers route on the event format, not on this.
+const FACILITY: u8 = 1;
+
+const VENDOR: &str = env!("CARGO_PKG_NAME");
+const PRODUCT: &str = env!("CARGO_PKG_NAME");
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum SyslogTransport {
+    Udp,
+    /// One event per line
+    Tcp,
+    /// Octet-counted, as RFC 5425 has it; needs the syslog-tls feature
+    #[cfg(feature = "syslog-tls")]
+    Tls,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum EventFormat {
+    /// ArcSight Common Event Format, which Splunk and most SIEMs parse
+    Cef,
+    /// IBM QRadar's Log Event Extended Format
+    Leef,
+}
+
+enum Connection {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+    #[cfg(feature = "syslog-tls")]
+    Tls(Box<native_tls::TlsStream<TcpStream>>),
+}
+
+impl Connection {
+    fn open(address: &str, transport: SyslogTransport) -> io::Result<Self> {
+        let target = resolve(address)?;
+        match transport {
+            SyslogTransport::Udp => {
+                let local = if target.is_ipv4() {
+                    "0.0.0.0:0"
+                } else {
+                    "[::]:0"
+                };
+                let socket = UdpSocket::bind(local)?;
+                socket.connect(target)?;
+                Ok(Connection::Udp(socket))
+            }
+            SyslogTransport::Tcp => Ok(Connection::Tcp(TcpStream::connect_timeout(
+                &target,
+                CONNECT_TIMEOUT,
+            )?)),
+            #[cfg(feature = "syslog-tls")]
+            SyslogTransport::Tls => {
+                let stream = TcpStream::connect_timeout(&target, CONNECT_TIMEOUT)?;
+                let host = address
+                    .rsplit_once(':')
+                    .map_or(address, |(host, _)| host)
+                    .trim_start_matches('[')
+                    .trim_end_matches(']');
+                let stream = native_tls::TlsConnector::new()
+                    .map_err(io::Error::other)?
+                    .connect(host, stream)
+                    .map_err(|err| io::Error::other(err.to_string()))?;
+                Ok(Connection::Tls(Box::new(stream)))
+            }
+        }
+    }
+
+    fn send(&mut self, message: &str) -> io::Result<()> {
+        match self {
+            Connection::Udp(socket) => socket.send(message.as_bytes()).map(|_| ()),
+            Connection::Tcp(stream) => {
+                stream.write_all(format!("{}\n", message).as_bytes())?;
+                stream.flush()
+            }
+            #[cfg(feature = "syslog-tls")]
+            Connection::Tls(stream) => {
+                stream.write_all(format!("{} {}", message.len(), message).as_bytes())?;
+                stream.flush()
+            }
+        }
+    }
+}
+
+fn resolve(address: &str) -> io::Result<SocketAddr> {
+    address.to_socket_addrs()?.next().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} did not resolve to an address", address),
+        )
+    })
+}
+
+/// Sends one RFC 5424 syslog message per analyzed file, carrying a CEF or
+/// LEEF event with the verdict, severity and triggered rule ids.
+pub struct SyslogSink {
+    address: String,
+    transport: SyslogTransport,
+    format: EventFormat,
+    connection: Option<Connection>,
+}
+
+impl SyslogSink {
+    /// Connects right away, so a wrong address fails before any file is
+    /// analyzed.
+    pub fn connect(
+        address: &str,
+        transport: SyslogTransport,
+        format: EventFormat,
+    ) -> Result<Self, SentinelError> {
+        Ok(SyslogSink {
+            address: address.to_string(),
+            transport,
+            format,
+            connection: Some(Connection::open(address, transport)?),
+        })
+    }
+
+    /// Sends the event for one file. A connection the receiver dropped is
+    /// reopened once before giving up.
+    pub fn send(&mut self, file: &str, result: &AnalysisResult) -> Result<(), SentinelError> {
+        let event = match self.format {
+            EventFormat::Cef => cef_event(file, result),
+            EventFormat::Leef => leef_event(file, result),
+        };
+        let message = format!(
+            "<{}>1 {} - {} {} - - {}",
+            FACILITY * 8 + syslog_severity(result),
+            Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            PRODUCT,
+            std::process::id(),
+            event
+        );
+
+        if let Some(connection) = &mut self.connection {
+            if connection.send(&message).is_ok() {
+                return Ok(());
+            }
+        }
+        self.connection = None;
+        let mut connection = Connection::open(&self.address, self.transport)?;
+        connection.send(&message)?;
+        self.connection = Some(connection);
+        Ok(())
+    }
+}
+
+fn cef_event(file: &str, result: &AnalysisResult) -> String {
+    let mut extension = vec![("fname", file.to_string())];
+    if let Some(size) = result.file_size {
+        extension.push(("fsize", size.to_string()));
+    }
+    if let Some(sha256) = &result.sha256 {
+        extension.push(("fileHash", sha256.clone()));
+    }
+    extension.extend([
+        ("cs1Label", "Verdict".to_string()),
+        ("cs1", result.classification.label().to_string()),
+        ("cs2Label", "Rules".to_string()),
+        ("cs2", triggered_rules(&result.findings).join(",")),
+        ("cn1Label", "Severity score".to_string()),
+        ("cn1", result.severity_score.to_string()),
+    ]);
+    let indicators = result.classification.indicators();
+    if !indicators.is_empty() {
+        extension.push(("msg", indicators.join("; ")));
+    }
+
+    let extension: Vec<String> = extension
+        .into_iter()
+        .map(|(key, value)| format!("{}={}", key, escape_cef_value(&value)))
+        .collect();
+    format!(
+        "CEF:0|{}|{}|{}|{}|{}|{}|{}",
+        escape_header(VENDOR),
+        escape_header(PRODUCT),
+        escape_header(VERSION),
+        result.classification.id(),
+        escape_header(result.classification.label()),
+        event_severity(result),
+        extension.join(" ")
+    )
+}
+
+fn leef_event(file: &str, result: &AnalysisResult) -> String {
+    let mut attributes = vec![
+        ("cat", result.classification.label().to_string()),
+        ("sev", event_severity(result).to_string()),
+        ("fileName", file.to_string()),
+    ];
+    if let Some(size) = result.file_size {
+        attributes.push(("fileSize", size.to_string()));
+    }
+    if let Some(sha256) = &result.sha256 {
+        attributes.push(("fileHash", sha256.clone()));
+    }
+    attributes.extend([
+        ("rules", triggered_rules(&result.findings).join(",")),
+        ("severityScore", result.severity_score.to_string()),
+    ]);
+
+    // LEEF 1.0 separates attributes with tabs, so values can't hold any
+    let attributes: Vec<String> = attributes
+        .into_iter()
+        .map(|(key, value)| format!("{}={}", key, value.replace(['\t', '\r', '\n'], " ")))
+        .collect();
+    format!(
+        "LEEF:1.0|{}|{}|{}|{}|{}",
+        escape_header(VENDOR),
+        escape_header(PRODUCT),
+        escape_header(VERSION),
+        result.classification.id(),
+        attributes.join("\t")
+    )
+}
+
+/// CEF and LEEF severity, from 0 to 10; clean files are 0 whatever their
+/// score.
+fn event_severity(result: &AnalysisResult) -> u8 {
+    if matches!(result.classification, ThreatClassification::Clean) {
+        return 0;
+    }
+    match result.severity {
+        Severity::Low => 3,
+        Severity::Medium => 5,
+        Severity::High => 8,
+        Severity::Critical => 10,
+    }
+}
+
+fn syslog_severity(result: &AnalysisResult) -> u8 {
+    if matches!(result.classification, ThreatClassification::Clean) {
+        // Informational
+        return 6;
+    }
+    match result.severity {
+        // Notice
+        Severity::Low => 5,
+        // Warning
+        Severity::Medium => 4,
+        // Error
+        Severity::High => 3,
+        // Critical
+        Severity::Critical => 2,
+    }
+}
+
+fn escape_header(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+fn escape_cef_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('=', "\\=")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n")
+}
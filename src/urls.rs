@@ -0,0 +1,265 @@
+use crate::encoding::{get_name_key, normalize_pdf_string};
+use crate::resolve;
+use lopdf::{Dictionary, Document, Object};
+use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+
+const MAX_LINK_DEPTH: usize = 32;
+
+/// Hosts of URL shortening services, which hide where a link leads.
+const SHORTENERS: [&str; 16] = [
+    "bit.ly",
+    "bitly.com",
+    "tinyurl.com",
+    "goo.gl",
+    "t.co",
+    "ow.ly",
+    "is.gd",
+    "buff.ly",
+    "rebrand.ly",
+    "cutt.ly",
+    "shorturl.at",
+    "rb.gy",
+    "tiny.cc",
+    "t.ly",
+    "s.id",
+    "v.gd",
+];
+
+/// What makes a URL look like phishing or a payload rather than an ordinary
+/// link.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum UrlIndicator {
+    /// The host is an IP address instead of a domain name.
+    IpLiteralHost,
+    /// A label of the host is punycode (`xn--`), as lookalike domains are.
+    Punycode,
+    /// The host is written with non-ASCII characters, such as a Cyrillic `а`
+    /// standing in for a Latin `a`.
+    NonAsciiHost,
+    /// A credential-like `user@` part, as in `https://bank.com@evil.com`,
+    /// makes the real host easy to misread.
+    UserInfo,
+    /// The host is a URL shortener.
+    Shortener,
+    /// A `data:` URI, which carries its content inline.
+    DataUri,
+    /// The link's text names a different host than the one it opens.
+    TextMismatch,
+}
+
+impl UrlIndicator {
+    pub fn description(&self) -> &'static str {
+        match self {
+            UrlIndicator::IpLiteralHost => "IP address host",
+            UrlIndicator::Punycode => "punycode host",
+            UrlIndicator::NonAsciiHost => "non-ASCII host",
+            UrlIndicator::UserInfo => "user info before the host",
+            UrlIndicator::Shortener => "URL shortener",
+            UrlIndicator::DataUri => "data: URI",
+            UrlIndicator::TextMismatch => "link text names another host",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct LinkUrl {
+    pub url: String,
+    /// Lowercased, without user info or port; `None` for URLs without an
+    /// authority, such as `mailto:` and `data:`.
+    pub host: Option<String>,
+    /// Objects holding an action or annotation with this URL.
+    pub object_ids: Vec<u32>,
+    /// What a link annotation shows or describes itself as, from its
+    /// `/Contents`, when it has any.
+    pub link_text: Option<String>,
+    pub indicators: Vec<UrlIndicator>,
+}
+
+/// Extracts every URL from `/URI` actions, whether they are objects of their
+/// own or written inline in a link annotation's `/A` or an `/AA` entry, and
+/// checks each for the tricks phishing links use. URLs are listed once, in
+/// the order first found, with every object they appear in.
+pub fn find_urls(doc: &Document) -> Vec<LinkUrl> {
+    let mut urls = Vec::new();
+    for (id, object) in doc.objects.iter() {
+        collect_urls(doc, id.0, object, None, 0, &mut urls);
+    }
+    for url in &mut urls {
+        url.indicators = url_indicators(&url.url, url.link_text.as_deref());
+    }
+    urls
+}
+
+fn collect_urls(
+    doc: &Document,
+    holder: u32,
+    object: &Object,
+    link_text: Option<&str>,
+    depth: usize,
+    urls: &mut Vec<LinkUrl>,
+) {
+    if depth > MAX_LINK_DEPTH {
+        return;
+    }
+    let dict = match object {
+        Object::Dictionary(dict) => dict,
+        Object::Array(items) => {
+            for item in items {
+                collect_urls(doc, holder, item, link_text, depth + 1, urls);
+            }
+            return;
+        }
+        _ => return,
+    };
+
+    if let Some(url) = get_name_key(dict, b"URI")
+        .and_then(|uri| resolve(doc, uri))
+        .and_then(|uri| uri.as_str().ok())
+    {
+        add_url(urls, normalize_pdf_string(url), holder, link_text);
+    }
+
+    // A link annotation's text applies to the action in its /A, which is
+    // followed even when it is an object of its own
+    let contents = string_entry(doc, dict, b"Contents");
+    let link_text = contents.as_deref().or(link_text);
+    for (key, value) in dict.iter() {
+        let value = match key.as_slice() {
+            b"URI" => continue,
+            b"A" => resolve(doc, value).unwrap_or(value),
+            _ => value,
+        };
+        collect_urls(doc, holder, value, link_text, depth + 1, urls);
+    }
+}
+
+fn add_url(urls: &mut Vec<LinkUrl>, url: String, holder: u32, link_text: Option<&str>) {
+    if let Some(seen) = urls.iter_mut().find(|seen| seen.url == url) {
+        if !seen.object_ids.contains(&holder) {
+            seen.object_ids.push(holder);
+        }
+        if seen.link_text.is_none() {
+            seen.link_text = link_text.map(str::to_string);
+        }
+        return;
+    }
+    urls.push(LinkUrl {
+        host: split_url(&url).map(|(_, host)| host),
+        url,
+        object_ids: vec![holder],
+        link_text: link_text.map(str::to_string),
+        indicators: Vec::new(),
+    });
+}
+
+/// What makes `url` suspicious, given the text of the link that opens it.
+pub(crate) fn url_indicators(url: &str, link_text: Option<&str>) -> Vec<UrlIndicator> {
+    let mut indicators = Vec::new();
+    if url
+        .get(..5)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("data:"))
+    {
+        indicators.push(UrlIndicator::DataUri);
+    }
+    let Some((_, host)) = split_url(url) else {
+        return indicators;
+    };
+    let host = host.as_str();
+
+    if is_ip_literal(host) {
+        indicators.push(UrlIndicator::IpLiteralHost);
+    }
+    if host.split('.').any(|label| label.starts_with("xn--")) {
+        indicators.push(UrlIndicator::Punycode);
+    }
+    if !host.is_ascii() {
+        indicators.push(UrlIndicator::NonAsciiHost);
+    }
+    if has_user_info(url) {
+        indicators.push(UrlIndicator::UserInfo);
+    }
+    if SHORTENERS
+        .iter()
+        .any(|shortener| is_same_or_subdomain(host, shortener))
+    {
+        indicators.push(UrlIndicator::Shortener);
+    }
+    let shown = link_text.and_then(shown_host);
+    if shown.is_some_and(|shown| {
+        !is_same_or_subdomain(host, &shown) && !is_same_or_subdomain(&shown, host)
+    }) {
+        indicators.push(UrlIndicator::TextMismatch);
+    }
+    indicators
+}
+
+/// The scheme and host of a URL with an authority, both lowercased, e.g.
+/// `("https", "example.com")` for `https://user@Example.com:8080/path`.
+pub(crate) fn split_url(url: &str) -> Option<(String, String)> {
+    let (scheme, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#', '\\']).next()?;
+    let host_and_port = authority.rsplit('@').next()?;
+    let host = match host_and_port.strip_prefix('[') {
+        // An IPv6 literal keeps its colons
+        Some(ipv6) => ipv6.split(']').next()?,
+        None => host_and_port.split(':').next()?,
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some((scheme.to_ascii_lowercase(), host.to_lowercase()))
+}
+
+/// Dotted IPv4, IPv6 and the single-number forms browsers also accept, such
+/// as `http://3232235777/` for 192.168.1.1.
+fn is_ip_literal(host: &str) -> bool {
+    host.parse::<Ipv4Addr>().is_ok()
+        || host.contains(':')
+        || (!host.is_empty() && host.bytes().all(|b| b.is_ascii_digit()))
+        || host
+            .strip_prefix("0x")
+            .is_some_and(|hex| !hex.is_empty() && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+fn has_user_info(url: &str) -> bool {
+    url.split_once("://")
+        .and_then(|(_, rest)| rest.split(['/', '?', '#']).next())
+        .is_some_and(|authority| authority.contains('@'))
+}
+
+/// The host a link's text shows, when the text is a URL or a bare domain
+/// such as `www.example.com`.
+fn shown_host(text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.contains(char::is_whitespace) {
+        return None;
+    }
+    if let Some((_, host)) = split_url(text) {
+        return Some(host);
+    }
+    let host = text.split(['/', '?', '#']).next()?.to_lowercase();
+    let labels: Vec<&str> = host.split('.').collect();
+    let domain_like = labels.len() >= 2
+        && labels.iter().all(|label| !label.is_empty())
+        && labels
+            .last()
+            .is_some_and(|tld| tld.chars().all(char::is_alphabetic));
+    domain_like.then_some(host)
+}
+
+/// Whether `host` is `domain` or under it, ignoring a leading `www.` on
+/// either.
+fn is_same_or_subdomain(host: &str, domain: &str) -> bool {
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    let domain = domain.strip_prefix("www.").unwrap_or(domain);
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+fn string_entry(doc: &Document, dict: &Dictionary, key: &[u8]) -> Option<String> {
+    let value = resolve(doc, dict.get(key).ok()?)?;
+    Some(normalize_pdf_string(value.as_str().ok()?))
+}
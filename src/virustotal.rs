@@ -0,0 +1,178 @@
+//! VirusTotal hash lookups, behind the `virustotal` feature.
+//!
+//! Only hashes are sent, never file content. Without the feature the lookup
+//! types still exist, so results keep the same shape, but nothing is ever
+//! looked up.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "virustotal")]
+use crate::AnalysisResult;
+#[cfg(feature = "virustotal")]
+use std::sync::Mutex;
+#[cfg(feature = "virustotal")]
+use std::time::{Duration, Instant};
+
+/// Engines that must flag a hash before it counts as detected; one or two
+/// are often false positives.
+pub const MIN_DETECTIONS: u32 = 3;
+
+#[cfg(feature = "virustotal")]
+const API_URL: &str = "https://www.virustotal.com/api/v3/files/";
+
+#[cfg(feature = "virustotal")]
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What was looked up.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum VtSubject {
+    /// The scanned file itself.
+    File,
+    /// An embedded file or media payload held in stream `id`.
+    Payload(u32),
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct VtLookup {
+    pub subject: VtSubject,
+    pub sha256: String,
+    /// Engines whose last analysis called it malicious; `None` when
+    /// VirusTotal has never seen the hash.
+    pub malicious: Option<u32>,
+    /// Engines that gave a verdict of any kind.
+    pub engines: u32,
+}
+
+impl VtLookup {
+    pub fn is_detected(&self) -> bool {
+        self.malicious
+            .is_some_and(|malicious| malicious >= MIN_DETECTIONS)
+    }
+
+    /// The detection ratio as VirusTotal shows it, e.g. `34/62`.
+    pub fn ratio(&self) -> Option<String> {
+        self.malicious
+            .map(|malicious| format!("{}/{}", malicious, self.engines))
+    }
+}
+
+/// A VirusTotal client that keeps to the API's request rate across every
+/// analysis sharing it.
+#[cfg(feature = "virustotal")]
+pub struct VirusTotal {
+    agent: ureq::Agent,
+    api_key: String,
+    interval: Duration,
+    state: Mutex<ClientState>,
+}
+
+#[cfg(feature = "virustotal")]
+#[derive(Default)]
+struct ClientState {
+    last_request: Option<Instant>,
+    /// Set once the API can't be reached or rejects the key, so later
+    /// lookups are skipped instead of each waiting for a timeout.
+    disabled: bool,
+}
+
+#[cfg(feature = "virustotal")]
+impl VirusTotal {
+    /// `requests_per_minute` is 4 for the public API.
+    pub fn new(api_key: String, requests_per_minute: u32) -> Self {
+        VirusTotal {
+            agent: ureq::AgentBuilder::new()
+                .timeout(REQUEST_TIMEOUT)
+                .user_agent(concat!(
+                    env!("CARGO_PKG_NAME"),
+                    "/",
+                    env!("CARGO_PKG_VERSION")
+                ))
+                .build(),
+            api_key,
+            interval: Duration::from_secs(60) / requests_per_minute.max(1),
+            state: Mutex::new(ClientState::default()),
+        }
+    }
+
+    /// Looks up the file's hash and those of its embedded files and media
+    /// payloads, each once. Hashes that fail to look up are left out.
+    pub fn look_up(&self, result: &AnalysisResult) -> Vec<VtLookup> {
+        let file = result.sha256.iter().map(|sha256| (VtSubject::File, sha256));
+        let attachments = result
+            .embedded_files
+            .iter()
+            .filter_map(|file| Some((VtSubject::Payload(file.object_id), file.sha256.as_ref()?)));
+        let media = result
+            .media
+            .iter()
+            .flat_map(|annotation| &annotation.payloads)
+            .filter_map(|payload| {
+                Some((
+                    VtSubject::Payload(payload.object_id),
+                    payload.sha256.as_ref()?,
+                ))
+            });
+
+        let mut lookups: Vec<VtLookup> = Vec::new();
+        for (subject, sha256) in file.chain(attachments).chain(media) {
+            if lookups.iter().any(|lookup| &lookup.sha256 == sha256) {
+                continue;
+            }
+            if let Some((malicious, engines)) = self.query(sha256) {
+                lookups.push(VtLookup {
+                    subject,
+                    sha256: sha256.clone(),
+                    malicious,
+                    engines,
+                });
+            }
+        }
+        lookups
+    }
+
+    /// The detection counts for `sha256`, waiting first if the last request
+    /// was too recent. `None` when the lookup failed.
+    fn query(&self, sha256: &str) -> Option<(Option<u32>, u32)> {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        if state.disabled {
+            return None;
+        }
+        if let Some(last) = state.last_request {
+            std::thread::sleep(self.interval.saturating_sub(last.elapsed()));
+        }
+        state.last_request = Some(Instant::now());
+
+        let response = self
+            .agent
+            .get(&format!("{}{}", API_URL, sha256))
+            .set("x-apikey", &self.api_key)
+            .call();
+        let body = match response {
+            Ok(response) => response.into_string().ok()?,
+            Err(ureq::Error::Status(404, _)) => return Some((None, 0)),
+            Err(ureq::Error::Status(status @ (401 | 403), _)) => {
+                tracing::warn!(status, "VirusTotal rejected the API key, skipping lookups");
+                state.disabled = true;
+                return None;
+            }
+            Err(ureq::Error::Status(status, _)) => {
+                tracing::warn!(status, %sha256, "VirusTotal lookup failed");
+                return None;
+            }
+            Err(ureq::Error::Transport(err)) => {
+                tracing::warn!(error = %err, "VirusTotal unreachable, skipping lookups");
+                state.disabled = true;
+                return None;
+            }
+        };
+
+        let report: serde_json::Value = serde_json::from_str(&body).ok()?;
+        let stats = &report["data"]["attributes"]["last_analysis_stats"];
+        let count = |key: &str| stats[key].as_u64().unwrap_or(0) as u32;
+        let engines = ["malicious", "suspicious", "undetected", "harmless"]
+            .into_iter()
+            .map(count)
+            .sum();
+        Some((Some(count("malicious")), engines))
+    }
+}
@@ -0,0 +1,70 @@
+use notify::{EventKind, RecursiveMode, Watcher};
+use pdf_sentinel::{AnalysisResult, Analyzer, SentinelError};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+/// Files still being written keep producing modify events; a file is only
+/// analyzed once it has been quiet for this long.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Analyzes every `*.pdf` file created or modified in `dir` until Ctrl-C,
+/// handing each result to `on_result` as it completes. An analysis already
+/// running when the signal arrives is finished and flushed before returning.
+pub fn watch_directory(
+    dir: &Path,
+    analyzer: &Analyzer,
+    mut on_result: impl FnMut(&str, Result<AnalysisResult, SentinelError>) -> Result<(), SentinelError>,
+) -> Result<(), SentinelError> {
+    let running = Arc::new(AtomicBool::new(true));
+    let handler_flag = Arc::clone(&running);
+    ctrlc::set_handler(move || handler_flag.store(false, Ordering::SeqCst))
+        .map_err(|err| SentinelError::Io(std::io::Error::other(err)))?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+
+    while running.load(Ordering::SeqCst) {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths.into_iter().filter(|path| is_pdf(path)) {
+                        pending.insert(path, Instant::now());
+                    }
+                }
+            }
+            Ok(Err(err)) => tracing::error!(error = %err, "watch error"),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+
+        let ready: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, last_event)| last_event.elapsed() >= DEBOUNCE)
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        for path in ready {
+            pending.remove(&path);
+            if !path.is_file() {
+                continue;
+            }
+            on_result(&path.to_string_lossy(), analyzer.analyze_file(&path))?;
+            std::io::stdout().flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+}
@@ -0,0 +1,60 @@
+use crate::FileReport;
+use pdf_sentinel::{AnalysisResult, SentinelError};
+use std::io;
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// POSTs each file's JSON record, as `--format ndjson` prints it, to an
+/// HTTP endpoint such as a SIEM's HTTP event collector.
+pub struct Webhook {
+    agent: ureq::Agent,
+    url: String,
+    /// Sent with every request, e.g. `("Authorization", "Splunk <token>")`.
+    headers: Vec<(String, String)>,
+}
+
+impl Webhook {
+    /// `headers` are `Name: value` pairs.
+    pub fn new(url: &str, headers: &[String]) -> Result<Self, SentinelError> {
+        let headers = headers
+            .iter()
+            .map(|header| match header.split_once(':') {
+                Some((name, value)) if !name.trim().is_empty() => {
+                    Ok((name.trim().to_string(), value.trim().to_string()))
+                }
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("webhook header {:?} is not NAME: VALUE", header),
+                )),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Webhook {
+            agent: ureq::AgentBuilder::new()
+                .timeout(REQUEST_TIMEOUT)
+                .user_agent(concat!(
+                    env!("CARGO_PKG_NAME"),
+                    "/",
+                    env!("CARGO_PKG_VERSION")
+                ))
+                .build(),
+            url: url.to_string(),
+            headers,
+        })
+    }
+
+    pub fn send(&self, file: &str, result: &AnalysisResult) -> Result<(), SentinelError> {
+        let body = serde_json::to_string(&FileReport::new(file, result))?;
+        let mut request = self
+            .agent
+            .post(&self.url)
+            .set("Content-Type", "application/json");
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        request
+            .send_string(&body)
+            .map_err(|err| io::Error::other(err.to_string()))?;
+        Ok(())
+    }
+}
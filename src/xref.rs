@@ -0,0 +1,137 @@
+use lopdf::xref::XrefEntry;
+use lopdf::Document;
+use regex::bytes::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Declared offsets may point at leading whitespace or a line break.
+const OFFSET_TOLERANCE: u64 = 4;
+
+#[derive(Deserialize, Serialize)]
+pub struct XrefAnomaly {
+    pub object_id: u32,
+    pub declared_offset: u64,
+    pub actual_offset: u64,
+}
+
+/// Compares the offsets recorded in the cross-reference data against where
+/// each `N G obj` header actually sits in the file. Objects that lopdf finds
+/// through a different offset than the one a viewer would use ("shadow
+/// objects") show up as mismatches.
+pub fn check_xref_stream_anomalies(doc: &Document, raw: &[u8]) -> Vec<XrefAnomaly> {
+    let actual_offsets = object_header_offsets(raw);
+
+    let mut anomalies = Vec::new();
+    for (&id, entry) in doc.reference_table.entries.iter() {
+        let XrefEntry::Normal { offset, generation } = entry else {
+            continue;
+        };
+        let declared_offset = *offset as u64;
+        let Some(offsets) = actual_offsets.get(&(id, *generation)) else {
+            continue;
+        };
+        if offsets
+            .iter()
+            .all(|actual| actual.abs_diff(declared_offset) > OFFSET_TOLERANCE)
+        {
+            anomalies.push(XrefAnomaly {
+                object_id: id,
+                declared_offset,
+                // Later revisions win, so the last definition is the one a viewer uses
+                actual_offset: *offsets.last().unwrap_or(&0),
+            });
+        }
+    }
+
+    anomalies
+}
+
+/// Where the cross-reference data and the objects in the file disagree
+/// beyond offsets. Writers keep these consistent; a hand-crafted file often
+/// doesn't.
+#[derive(Default, Deserialize, Serialize)]
+pub struct XrefConsistency {
+    /// The trailer's `/Size` when it isn't one more than the highest object
+    /// number in the cross-reference data, with that expected value.
+    pub size_mismatch: Option<SizeMismatch>,
+    /// Objects the cross-reference data places at an offset where no object
+    /// of that number is defined anywhere in the file.
+    pub missing_objects: Vec<u32>,
+    /// Objects defined in the file that the cross-reference data doesn't list,
+    /// which viewers that repair files may still pick up.
+    pub unlisted_objects: Vec<u32>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct SizeMismatch {
+    /// `None` when the trailer has no `/Size`.
+    pub declared: Option<i64>,
+    pub expected: u32,
+}
+
+/// Checks the trailer's `/Size` against the cross-reference data, and the
+/// data's entries against the object headers actually in the file.
+/// Mismatched offsets are [`check_xref_stream_anomalies`]'s to report.
+pub fn check_xref_consistency(doc: &Document, raw: &[u8]) -> XrefConsistency {
+    let entries = &doc.reference_table.entries;
+    let mut consistency = XrefConsistency::default();
+
+    let declared = doc.trailer.get(b"Size").and_then(|size| size.as_i64()).ok();
+    let expected = entries.keys().next_back().map_or(1, |&highest| highest + 1);
+    if declared != Some(i64::from(expected)) {
+        consistency.size_mismatch = Some(SizeMismatch { declared, expected });
+    }
+
+    let actual_offsets = object_header_offsets(raw);
+    for (&id, entry) in entries.iter() {
+        if let XrefEntry::Normal { generation, .. } = entry {
+            if !actual_offsets.contains_key(&(id, *generation)) {
+                consistency.missing_objects.push(id);
+            }
+        }
+    }
+    let mut unlisted: Vec<u32> = actual_offsets
+        .keys()
+        .map(|&(id, _)| id)
+        .filter(|id| !entries.contains_key(id))
+        .collect();
+    unlisted.sort_unstable();
+    unlisted.dedup();
+    consistency.unlisted_objects = unlisted;
+
+    consistency
+}
+
+/// Where each `N G obj` header sits in the file, by object number and
+/// generation, in file order.
+fn object_header_offsets(raw: &[u8]) -> HashMap<(u32, u16), Vec<u64>> {
+    let header = Regex::new(r"(?-u)(\d+)[\x00\t\n\x0c\r ]+(\d+)[\x00\t\n\x0c\r ]+obj").unwrap();
+
+    let mut actual_offsets: HashMap<(u32, u16), Vec<u64>> = HashMap::new();
+    for captures in header.captures_iter(raw) {
+        let (Some(id), Some(generation)) = (captures.get(1), captures.get(2)) else {
+            continue;
+        };
+        let id = std::str::from_utf8(id.as_bytes())
+            .ok()
+            .and_then(|s| s.parse().ok());
+        let generation = std::str::from_utf8(generation.as_bytes())
+            .ok()
+            .and_then(|s| s.parse().ok());
+        if let (Some(id), Some(generation)) = (id, generation) {
+            actual_offsets
+                .entry((id, generation))
+                .or_default()
+                .push(captures.get(0).map_or(0, |m| m.start() as u64));
+        }
+    }
+    actual_offsets
+}
+
+/// Counts cross-reference sections, both classic `xref` tables and
+/// `/Type /XRef` streams. More than one means the file has incremental updates.
+pub fn count_xref_sections(raw: &[u8]) -> usize {
+    let table = Regex::new(r"(?-u)(^|[^a-z])xref[\x00\t\n\x0c\r ]").unwrap();
+    let stream = Regex::new(r"(?-u)/Type[\x00\t\n\x0c\r ]*/XRef\b").unwrap();
+    table.find_iter(raw).count() + stream.find_iter(raw).count()
+}
@@ -0,0 +1,110 @@
+//! YARA scanning of decoded streams and scripts, behind the `yara` feature.
+//!
+//! The feature links libyara, which must be installed to build with it.
+//! Without the feature the match types still exist, so results keep the same
+//! shape, but nothing is ever scanned.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "yara")]
+use crate::error::SentinelError;
+#[cfg(feature = "yara")]
+use crate::filters::StreamCache;
+#[cfg(feature = "yara")]
+use crate::{JavaScriptObject, JsSource};
+#[cfg(feature = "yara")]
+use std::path::Path;
+
+/// Seconds a single scan may take before libyara gives up on it.
+#[cfg(feature = "yara")]
+const SCAN_TIMEOUT_SECS: i32 = 10;
+
+/// What a rule matched against.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum YaraTarget {
+    /// The decoded content of a stream.
+    Stream,
+    /// A script that was not a stream, e.g. an inline `/JS` string.
+    JavaScript,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct YaraMatch {
+    pub rule: String,
+    /// The rule file's name without its extension.
+    pub namespace: String,
+    pub tags: Vec<String>,
+    pub object_id: u32,
+    pub target: YaraTarget,
+}
+
+/// A compiled set of YARA rules.
+#[cfg(feature = "yara")]
+pub struct YaraRules {
+    rules: yara::Rules,
+}
+
+#[cfg(feature = "yara")]
+impl YaraRules {
+    /// Compiles every `.yar` and `.yara` file in `dir`, each in a namespace
+    /// named after the file, so that rules in different files may share names.
+    pub fn load_dir(dir: &Path) -> Result<Self, SentinelError> {
+        let mut compiler = yara::Compiler::new().map_err(yara::Error::from)?;
+        let mut files = 0;
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let is_rule_file = path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| extension == "yar" || extension == "yara");
+            if !is_rule_file {
+                continue;
+            }
+            let namespace = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            compiler = compiler.add_rules_file_with_namespace(&path, &namespace)?;
+            files += 1;
+        }
+
+        let rules = compiler.compile_rules().map_err(yara::Error::from)?;
+        tracing::debug!(dir = %dir.display(), files, "compiled YARA rules");
+        Ok(YaraRules { rules })
+    }
+
+    /// Runs the rules over every decoded stream and every script that isn't
+    /// read from a stream. Matches are ordered by object.
+    pub fn scan(&self, cache: &StreamCache, scripts: &[JavaScriptObject]) -> Vec<YaraMatch> {
+        let mut targets: Vec<(u32, YaraTarget, &[u8])> = cache
+            .iter()
+            .map(|(id, content)| (id, YaraTarget::Stream, content))
+            .collect();
+        targets.extend(
+            scripts
+                .iter()
+                .filter(|js| !matches!(js.source, JsSource::Stream | JsSource::XfaScript))
+                .map(|js| (js.id, YaraTarget::JavaScript, js.content.as_bytes())),
+        );
+        targets.sort_by_key(|(id, _, _)| *id);
+
+        let mut matches = Vec::new();
+        for (object_id, target, content) in targets {
+            let rules = match self.rules.scan_mem(content, SCAN_TIMEOUT_SECS) {
+                Ok(rules) => rules,
+                Err(err) => {
+                    tracing::warn!(object_id, error = %err, "YARA scan failed");
+                    continue;
+                }
+            };
+            matches.extend(rules.into_iter().map(|rule| YaraMatch {
+                rule: rule.identifier.to_string(),
+                namespace: rule.namespace.to_string(),
+                tags: rule.tags.iter().map(|tag| tag.to_string()).collect(),
+                object_id,
+                target,
+            }));
+        }
+        matches
+    }
+}